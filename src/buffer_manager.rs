@@ -1,28 +1,54 @@
 use std::{
+    io,
     ops::{Deref, DerefMut},
-    sync::{Arc, Mutex, RwLock},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
 };
 use thiserror::Error;
 
 use crate::{
-    file_manager::{BlockId, FileManager, Page},
+    file_manager::{BlockId, FileManagerTrait, Page},
     log_manager::LogManager,
 };
 
-pub const MAX_TIME: i32 = 10000;
+pub const MAX_TIME: Duration = Duration::from_millis(10000);
 
 #[derive(Error, Debug)]
 pub enum BufferAbortError {
     #[error("Buffer Abort Error")]
     BufferAbortError,
+    // 追い出すバッファの書き出しや、ブロックの読み込みに失敗した
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferInfo {
+    pub block_id: Option<BlockId>,
+    pub pins: i32,
+    pub txnum: i32,
+    pub is_dirty: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BufferStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
 }
 
 pub struct Buffer {
+    file_manager: Arc<Mutex<dyn FileManagerTrait>>,
+    log_manager: Arc<Mutex<LogManager>>,
     contents: Page,
     block_id: Option<BlockId>,
     pins: i32,
     txnum: i32,
-    last_save_numbder: i32,
+    last_save_numbder: i64,
+    referenced: bool,
 }
 
 impl Deref for Buffer {
@@ -39,14 +65,20 @@ impl DerefMut for Buffer {
 }
 
 impl Buffer {
-    fn new(block_size: usize) -> Buffer {
-        let contents = Page::new(block_size);
+    fn new(
+        file_manager: Arc<Mutex<dyn FileManagerTrait>>,
+        log_manager: Arc<Mutex<LogManager>>,
+    ) -> Buffer {
+        let contents = Page::new(file_manager.lock().unwrap().block_size());
         Buffer {
+            file_manager,
+            log_manager,
             contents,
             block_id: None,
             pins: 0,
             txnum: -1,
             last_save_numbder: -1,
+            referenced: false,
         }
     }
 
@@ -57,7 +89,7 @@ impl Buffer {
         }
     }
 
-    pub fn set_modified(&mut self, txnum: i32, last_save_number: i32) {
+    pub fn set_modified(&mut self, txnum: i32, last_save_number: i64) {
         self.txnum = txnum;
         if last_save_number >= 0 {
             self.last_save_numbder = last_save_number
@@ -72,16 +104,39 @@ impl Buffer {
         self.txnum
     }
 
-    fn assign_to_back(&mut self, block_id: BlockId) {
-        self.flush();
+    pub fn last_save_number(&self) -> i64 {
+        self.last_save_numbder
+    }
+
+    fn assign_to_back(&mut self, block_id: BlockId) -> io::Result<()> {
+        self.flush()?;
         self.block_id = Some(block_id);
         self.pins = 0;
+        Ok(())
     }
 
-    fn flush(&mut self) {}
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.txnum < 0 {
+            return Ok(());
+        }
+        if let Some(block_id) = &self.block_id {
+            self.log_manager
+                .lock()
+                .unwrap()
+                .flush_with(self.last_save_numbder)?;
+            self.file_manager
+                .lock()
+                .unwrap()
+                .write(block_id, &mut self.contents)?;
+        }
+        self.txnum = -1;
+        self.last_save_numbder = -1;
+        Ok(())
+    }
 
     fn pin(&mut self) {
         self.pins += 1;
+        self.referenced = true;
     }
 
     fn unpin(&mut self) {
@@ -90,33 +145,68 @@ impl Buffer {
 }
 
 pub struct BufferManager {
-    file_manager: Arc<Mutex<FileManager>>,
+    file_manager: Arc<Mutex<dyn FileManagerTrait>>,
     log_manager: Arc<Mutex<LogManager>>,
     buffer_pool: Vec<Arc<RwLock<Buffer>>>,
     num_available: i32,
+    clock_hand: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    // unpinでバッファが空いたことをpinで待っているスレッドに知らせる
+    buffer_available: Arc<Condvar>,
 }
 
 impl BufferManager {
     pub fn new(
-        file_manager: Arc<Mutex<FileManager>>,
+        file_manager: Arc<Mutex<dyn FileManagerTrait>>,
         log_manager: Arc<Mutex<LogManager>>,
         num_buffers: i32,
     ) -> BufferManager {
-        let block_size = file_manager.lock().unwrap().block_size;
         BufferManager {
             file_manager: Arc::clone(&file_manager),
             log_manager: Arc::clone(&log_manager),
             buffer_pool: (0..num_buffers)
-                .map(|_| Arc::new(RwLock::new(Buffer::new(block_size))))
+                .map(|_| {
+                    Arc::new(RwLock::new(Buffer::new(
+                        Arc::clone(&file_manager),
+                        Arc::clone(&log_manager),
+                    )))
+                })
                 .collect(),
             num_available: num_buffers,
+            clock_hand: 0,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            buffer_available: Arc::new(Condvar::new()),
         }
     }
 
+    // num_availableはpin/unpinのたびに更新されるだけなので、各バッファのpin状態と
+    // 同時に読まれる保証はない。BufferManagerをlockしている間だけ正確な値になる
     pub fn available(&self) -> i32 {
         self.num_available
     }
 
+    pub fn stats(&self) -> BufferStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let total = hits + misses;
+        BufferStats {
+            hits,
+            misses,
+            hit_rate: if total == 0 {
+                0.0
+            } else {
+                hits as f64 / total as f64
+            },
+        }
+    }
+
+    pub fn reset_stats(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
     pub fn flush_all(&mut self, txnum: i32) {
         for buffer in self.buffer_pool.iter() {
             let mut buffer = buffer.write().unwrap();
@@ -127,61 +217,179 @@ impl BufferManager {
                         .unwrap()
                         .flush_with(buffer.last_save_numbder)
                         .unwrap();
+                    debug_assert!(
+                        buffer.last_save_numbder
+                            <= self.log_manager.lock().unwrap().last_flushed_lsn(),
+                        "write-ahead log invariant violated: {:?} would be written before its log record",
+                        buffer.block_id
+                    );
                     self.file_manager
                         .lock()
                         .unwrap()
                         .write(&buffer.block_id.clone().unwrap(), &mut buffer)
                         .unwrap();
-                    buffer.txnum -= 1;
+                    buffer.txnum = -1;
+                    buffer.last_save_numbder = -1;
                 }
             }
         }
     }
 
+    // どのトランザクションが変更したかによらず、変更されたバッファをすべて書き出す
+    pub fn flush_all_modified(&mut self) -> io::Result<()> {
+        for buffer in self.buffer_pool.iter() {
+            buffer.write().unwrap().flush()?;
+        }
+        Ok(())
+    }
+
+    // ファイル名の変更に合わせて、old_filenameのバッファを書き出してからnew_filenameのブロックとして付け替える。
+    // new_filenameの古いバッファが残っていれば、pinされていない限り捨てる
+    pub fn rename_file(&mut self, old_filename: &str, new_filename: &str) -> io::Result<()> {
+        for buffer in self.buffer_pool.iter() {
+            let mut buffer = buffer.write().unwrap();
+            let Some(block_id) = buffer.block_id.clone() else {
+                continue;
+            };
+            if block_id.filename == new_filename && !buffer.is_pinned() {
+                buffer.flush()?;
+                buffer.block_id = None;
+            } else if block_id.filename == old_filename {
+                buffer.flush()?;
+                buffer.block_id = Some(BlockId {
+                    filename: new_filename.to_string(),
+                    block_number: block_id.block_number,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn dump_pool(&self) -> Vec<BufferInfo> {
+        self.buffer_pool
+            .iter()
+            .map(|buffer| {
+                let buffer = buffer.read().unwrap();
+                BufferInfo {
+                    block_id: buffer.block_id.clone(),
+                    pins: buffer.pins,
+                    txnum: buffer.txnum,
+                    is_dirty: buffer.txnum >= 0,
+                }
+            })
+            .collect()
+    }
+
+    pub fn dump_pool_to_string(&self) -> String {
+        let mut out = format!(
+            "{:>4} {:<32} {:>6} {:>6} {:>5}\n",
+            "#", "block", "pins", "txnum", "dirty"
+        );
+        for (i, info) in self.dump_pool().iter().enumerate() {
+            let block = match &info.block_id {
+                Some(block_id) => format!("{}:{}", block_id.filename, block_id.block_number),
+                None => "-".to_string(),
+            };
+            out += &format!(
+                "{:>4} {:<32} {:>6} {:>6} {:>5}\n",
+                i, block, info.pins, info.txnum, info.is_dirty
+            );
+        }
+        out
+    }
+
     pub fn unpin(&mut self, buffer: Arc<RwLock<Buffer>>) {
         buffer.write().unwrap().unpin();
         if !buffer.write().unwrap().is_pinned() {
             self.num_available += 1;
+            self.buffer_available.notify_one();
         }
     }
 
-    pub fn pin(&mut self, block_id: &BlockId) -> Result<Arc<RwLock<Buffer>>, BufferAbortError> {
-        self.try_to_pin(block_id)
-            .ok_or(BufferAbortError::BufferAbortError)
+    // 空きバッファがなければ、他のスレッドがunpinするまでbuffer_managerのlockを外して
+    // MAX_TIMEまで待つ
+    pub fn pin(
+        buffer_manager: &Mutex<BufferManager>,
+        block_id: &BlockId,
+    ) -> Result<Arc<RwLock<Buffer>>, BufferAbortError> {
+        let start = Instant::now();
+        let mut locked_buffer_manager = buffer_manager.lock().unwrap();
+        let buffer_available = Arc::clone(&locked_buffer_manager.buffer_available);
+        // 待ってやり直しても1回のpinとして数えるよう、ヒット・ミスは最初の試行だけで数える
+        let mut record_stats = true;
+        loop {
+            if let Some(buffer) = locked_buffer_manager.try_to_pin(block_id, record_stats)? {
+                return Ok(buffer);
+            }
+            record_stats = false;
+            let elapsed = start.elapsed();
+            if elapsed >= MAX_TIME {
+                return Err(BufferAbortError::BufferAbortError);
+            }
+            locked_buffer_manager = buffer_available
+                .wait_timeout(locked_buffer_manager, MAX_TIME - elapsed)
+                .unwrap()
+                .0;
+        }
     }
 
-    // TODO: fn wait_to_long(self) {}
+    // 空きバッファがなければ待たずにエラーを返す
+    pub fn try_pin(&mut self, block_id: &BlockId) -> Result<Arc<RwLock<Buffer>>, BufferAbortError> {
+        self.try_to_pin(block_id, true)?
+            .ok_or(BufferAbortError::BufferAbortError)
+    }
 
-    fn try_to_pin(&mut self, block_id: &BlockId) -> Option<Arc<RwLock<Buffer>>> {
-        if let Some(buffer) = self.find_assignable_block(block_id) {
-            if !buffer.write().unwrap().is_pinned() {
-                self.num_available -= 1;
-            }
-            buffer.write().unwrap().pin();
-            Some(buffer)
-        } else {
-            None
+    fn try_to_pin(
+        &mut self,
+        block_id: &BlockId,
+        record_stats: bool,
+    ) -> io::Result<Option<Arc<RwLock<Buffer>>>> {
+        let Some(buffer) = self.find_assignable_block(block_id, record_stats)? else {
+            return Ok(None);
+        };
+        if !buffer.write().unwrap().is_pinned() {
+            self.num_available -= 1;
         }
+        buffer.write().unwrap().pin();
+        Ok(Some(buffer))
     }
 
-    fn find_assignable_block(&self, block_id: &BlockId) -> Option<Arc<RwLock<Buffer>>> {
-        self.find_existing_buffer(block_id)
-            .or_else(|| match self.choose_unpinned_buffer() {
-                Some(buffer) => {
-                    buffer.write().unwrap().assign_to_back(block_id.clone());
-                    self.file_manager
-                        .lock()
-                        .unwrap()
-                        .read(&block_id, &mut buffer.write().unwrap().contents)
-                        .unwrap();
-                    Some(buffer)
-                }
-                None => None,
-            })
+    fn find_assignable_block(
+        &mut self,
+        block_id: &BlockId,
+        record_stats: bool,
+    ) -> io::Result<Option<Arc<RwLock<Buffer>>>> {
+        if let Some(buffer) = self.find_existing_buffer(block_id) {
+            if record_stats {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+            }
+            return Ok(Some(buffer));
+        }
+        if record_stats {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        let Some(buffer) = self.choose_unpinned_buffer() else {
+            return Ok(None);
+        };
+        let mut locked_buffer = buffer.write().unwrap();
+        locked_buffer.assign_to_back(block_id.clone())?;
+        let read = self
+            .file_manager
+            .lock()
+            .unwrap()
+            .read(block_id, &mut locked_buffer.contents);
+        if let Err(e) = read {
+            // 読めなかった内容を次のpinでキャッシュとして返さないよう、割り当てを外す
+            locked_buffer.block_id = None;
+            return Err(e);
+        }
+        drop(locked_buffer);
+        Ok(Some(buffer))
     }
 
     fn find_existing_buffer(&self, target_block_id: &BlockId) -> Option<Arc<RwLock<Buffer>>> {
-        self.buffer_pool
+        let buffer = self
+            .buffer_pool
             .iter()
             .find(|buffer| {
                 if let Some(block_id) = buffer.write().unwrap().block_id.clone() {
@@ -190,25 +398,41 @@ impl BufferManager {
                     false
                 }
             })
-            .and_then(|v| Some(v.clone()))
+            .and_then(|v| Some(v.clone()));
+        buffer
     }
 
-    fn choose_unpinned_buffer(&self) -> Option<Arc<RwLock<Buffer>>> {
-        self.buffer_pool
-            .iter()
-            .find(|buffer| !buffer.write().unwrap().is_pinned())
-            .and_then(|v| Some(v.clone()))
+    // clock (second-chance) 方式: 参照ビットが立っていれば落として次へ進み、
+    // 参照ビットの落ちた未pinのバッファを追い出す
+    fn choose_unpinned_buffer(&mut self) -> Option<Arc<RwLock<Buffer>>> {
+        let num_buffers = self.buffer_pool.len();
+        for _ in 0..2 * num_buffers {
+            let buffer = Arc::clone(&self.buffer_pool[self.clock_hand]);
+            self.clock_hand = (self.clock_hand + 1) % num_buffers;
+            let mut locked_buffer = buffer.write().unwrap();
+            if locked_buffer.is_pinned() {
+                continue;
+            }
+            if locked_buffer.referenced {
+                locked_buffer.referenced = false;
+                continue;
+            }
+            drop(locked_buffer);
+            return Some(buffer);
+        }
+        None
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::file_manager::{Codec, FileManager};
     use tempfile::Builder;
 
     fn create_block_id(i: i32) -> BlockId {
         let directory = "./data";
-        let tempfile = Builder::new().tempfile_in(directory.to_string()).unwrap();
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
         BlockId {
             filename: tempfile
                 .path()
@@ -224,14 +448,13 @@ mod tests {
     #[test]
     fn buffer_manager() {
         let directory = "./data";
-        let log_tempfile = Builder::new().tempfile_in(directory.to_string()).unwrap();
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
         let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
-        let log_file_manager = FileManager::new(directory.to_string());
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
         let log_manager = Arc::new(Mutex::new(
-            LogManager::new(log_file_manager, log_filename.to_string()).unwrap(),
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
         ));
-
-        let file_manager = Arc::new(Mutex::new(FileManager::new(directory.to_string())));
         let mut buffer_manager =
             BufferManager::new(Arc::clone(&file_manager), Arc::clone(&log_manager), 3);
 
@@ -241,23 +464,356 @@ mod tests {
         let block_id_2 = create_block_id(2);
         let block_id_3 = create_block_id(3);
 
-        assert!(buffer_manager.pin(&block_id_0).is_ok());
+        assert!(buffer_manager.try_pin(&block_id_0).is_ok());
 
-        buffer.insert(0, buffer_manager.pin(&block_id_0).unwrap());
-        buffer.insert(1, buffer_manager.pin(&block_id_1).unwrap());
-        buffer.insert(2, buffer_manager.pin(&block_id_2).unwrap());
+        buffer.insert(0, buffer_manager.try_pin(&block_id_0).unwrap());
+        buffer.insert(1, buffer_manager.try_pin(&block_id_1).unwrap());
+        buffer.insert(2, buffer_manager.try_pin(&block_id_2).unwrap());
 
         buffer_manager.unpin(buffer.get(1).unwrap().clone());
 
-        buffer.insert(3, buffer_manager.pin(&block_id_0).unwrap());
-        buffer.insert(4, buffer_manager.pin(&block_id_1).unwrap());
+        buffer.insert(3, buffer_manager.try_pin(&block_id_0).unwrap());
+        buffer.insert(4, buffer_manager.try_pin(&block_id_1).unwrap());
 
         assert_eq!(buffer_manager.available(), 0);
 
-        assert!(buffer_manager.pin(&block_id_3).is_err());
+        assert!(buffer_manager.try_pin(&block_id_3).is_err());
 
         buffer_manager.unpin(buffer.get(2).unwrap().clone());
 
-        buffer.insert(5, buffer_manager.pin(&block_id_3).unwrap());
+        buffer.insert(5, buffer_manager.try_pin(&block_id_3).unwrap());
+    }
+
+    #[test]
+    fn dump_pool() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+        let mut buffer_manager =
+            BufferManager::new(Arc::clone(&file_manager), Arc::clone(&log_manager), 3);
+
+        let block_id_0 = create_block_id(0);
+        let block_id_1 = create_block_id(1);
+
+        let buffer0 = buffer_manager.try_pin(&block_id_0).unwrap();
+        buffer_manager.try_pin(&block_id_0).unwrap();
+        buffer_manager.try_pin(&block_id_1).unwrap();
+        buffer0.write().unwrap().set_modified(7, -1);
+
+        let dump = buffer_manager.dump_pool();
+        assert_eq!(dump.len(), 3);
+        assert_eq!(
+            dump[0],
+            BufferInfo {
+                block_id: Some(block_id_0),
+                pins: 2,
+                txnum: 7,
+                is_dirty: true,
+            }
+        );
+        assert_eq!(
+            dump[1],
+            BufferInfo {
+                block_id: Some(block_id_1),
+                pins: 1,
+                txnum: -1,
+                is_dirty: false,
+            }
+        );
+        assert_eq!(
+            dump[2],
+            BufferInfo {
+                block_id: None,
+                pins: 0,
+                txnum: -1,
+                is_dirty: false,
+            }
+        );
+        assert_eq!(buffer_manager.dump_pool_to_string().lines().count(), 4);
+    }
+
+    #[test]
+    fn pin_waits_for_unpin() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            3,
+        )));
+
+        let buffers: Vec<Arc<RwLock<Buffer>>> = (0..3)
+            .map(|i| BufferManager::pin(&buffer_manager, &create_block_id(i)).unwrap())
+            .collect();
+        assert_eq!(buffer_manager.lock().unwrap().available(), 0);
+
+        // 4つ目のpinは空きバッファができるまで待つ
+        let handle = {
+            let buffer_manager = Arc::clone(&buffer_manager);
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                let buffer = BufferManager::pin(&buffer_manager, &create_block_id(3));
+                (buffer.is_ok(), start.elapsed())
+            })
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        buffer_manager
+            .lock()
+            .unwrap()
+            .unpin(Arc::clone(&buffers[0]));
+
+        let (pinned, elapsed) = handle.join().unwrap();
+        assert!(pinned);
+        assert!(elapsed >= Duration::from_millis(50));
+        assert!(elapsed < MAX_TIME);
+        assert_eq!(buffer_manager.lock().unwrap().available(), 0);
+    }
+
+    #[test]
+    fn blocking_pin_counts_one_miss() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            1,
+        )));
+
+        let buffer = BufferManager::pin(&buffer_manager, &create_block_id(0)).unwrap();
+        buffer_manager.lock().unwrap().reset_stats();
+
+        // 他のスレッドのunpinで何度起こされても、待っている間のやり直しはミスに数えない
+        let handle = {
+            let buffer_manager = Arc::clone(&buffer_manager);
+            std::thread::spawn(move || BufferManager::pin(&buffer_manager, &create_block_id(1)))
+        };
+        for _ in 0..5 {
+            std::thread::sleep(Duration::from_millis(20));
+            let locked_buffer_manager = buffer_manager.lock().unwrap();
+            locked_buffer_manager.buffer_available.notify_all();
+        }
+        buffer_manager.lock().unwrap().unpin(buffer);
+        assert!(handle.join().unwrap().is_ok());
+
+        let stats = buffer_manager.lock().unwrap().stats();
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hits, 0);
+    }
+
+    #[test]
+    fn pin_returns_read_error() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let block_id = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+
+        // lz4で書いたブロックを、zstdのFileManagerを使うバッファから読む
+        let mut lz4 = FileManager::new(directory.to_string());
+        lz4.codec = Codec::Lz4;
+        let mut page = Page::new(lz4.block_size);
+        lz4.write(&block_id, &mut page).unwrap();
+
+        let mut zstd = FileManager::new(directory.to_string());
+        zstd.codec = Codec::Zstd;
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> = Arc::new(Mutex::new(zstd));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            3,
+        )));
+
+        for _ in 0..2 {
+            let err = BufferManager::pin(&buffer_manager, &block_id)
+                .err()
+                .unwrap();
+            assert!(matches!(err, BufferAbortError::Io(_)));
+            assert!(err.to_string().contains("Lz4"));
+        }
+        let mut locked_buffer_manager = buffer_manager.lock().unwrap();
+        assert!(matches!(
+            locked_buffer_manager.try_pin(&block_id),
+            Err(BufferAbortError::Io(_))
+        ));
+        // 読めなかったブロックはどのバッファにも残らない
+        assert_eq!(locked_buffer_manager.available(), 3);
+        assert!(locked_buffer_manager
+            .dump_pool()
+            .iter()
+            .all(|info| info.block_id.is_none()));
+    }
+
+    #[test]
+    fn flush() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+        let mut buffer_manager =
+            BufferManager::new(Arc::clone(&file_manager), Arc::clone(&log_manager), 3);
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let block_id = BlockId {
+            filename: tempfile
+                .path()
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string(),
+            block_number: 0,
+        };
+
+        let buffer = buffer_manager.try_pin(&block_id).unwrap();
+        {
+            let mut locked_buffer = buffer.write().unwrap();
+            locked_buffer.set_int(80, 345).unwrap();
+            locked_buffer.set_modified(1, -1);
+            locked_buffer.flush().unwrap();
+            assert_eq!(locked_buffer.modifying_tx(), -1);
+        }
+
+        let mut page = Page::new(file_manager.lock().unwrap().block_size());
+        file_manager
+            .lock()
+            .unwrap()
+            .read(&block_id, &mut page)
+            .unwrap();
+        assert_eq!(page.get_int(80).unwrap(), 345);
+    }
+
+    #[test]
+    fn flush_all() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+        let mut buffer_manager =
+            BufferManager::new(Arc::clone(&file_manager), Arc::clone(&log_manager), 3);
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let block_id = BlockId {
+            filename: tempfile
+                .path()
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string(),
+            block_number: 0,
+        };
+        let read_from_disk = || {
+            let mut page = Page::new(file_manager.lock().unwrap().block_size());
+            file_manager
+                .lock()
+                .unwrap()
+                .read(&block_id, &mut page)
+                .unwrap();
+            page.get_int(80).unwrap()
+        };
+
+        let buffer = buffer_manager.try_pin(&block_id).unwrap();
+        buffer.write().unwrap().set_int(80, 1).unwrap();
+        buffer.write().unwrap().set_modified(1, -1);
+        buffer_manager.flush_all(1);
+        assert_eq!(buffer.read().unwrap().modifying_tx(), -1);
+        assert_eq!(read_from_disk(), 1);
+
+        buffer.write().unwrap().set_int(80, 2).unwrap();
+        buffer.write().unwrap().set_modified(2, -1);
+        buffer_manager.flush_all(1);
+        buffer_manager.flush_all(-1);
+        assert_eq!(read_from_disk(), 1);
+
+        buffer_manager.flush_all(2);
+        assert_eq!(buffer.read().unwrap().modifying_tx(), -1);
+        assert_eq!(read_from_disk(), 2);
+    }
+
+    #[test]
+    fn hit_rate() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+        let mut buffer_manager =
+            BufferManager::new(Arc::clone(&file_manager), Arc::clone(&log_manager), 4);
+
+        // プールより小さいワーキングセットなら、ウォームアップ後はすべてヒットする
+        let working_set: Vec<BlockId> = (0..3).map(create_block_id).collect();
+        for _ in 0..100 {
+            for block_id in working_set.iter() {
+                let buffer = buffer_manager.try_pin(block_id).unwrap();
+                buffer_manager.unpin(buffer);
+            }
+        }
+        let stats = buffer_manager.stats();
+        assert_eq!(stats.misses, 3);
+        assert_eq!(stats.hits, 297);
+        assert_eq!(stats.hit_rate, 0.99);
+
+        // 新しいブロックはワーキングセットを追い出さずに空きバッファへ入る
+        let other = create_block_id(9);
+        let buffer = buffer_manager.try_pin(&other).unwrap();
+        buffer_manager.unpin(buffer);
+        for block_id in working_set.iter() {
+            let buffer = buffer_manager.try_pin(block_id).unwrap();
+            buffer_manager.unpin(buffer);
+        }
+        assert_eq!(buffer_manager.stats().misses, 4);
+
+        // 同じブロックを繰り返し読むとヒット数だけが増える
+        buffer_manager.reset_stats();
+        assert_eq!(
+            buffer_manager.stats(),
+            BufferStats {
+                hits: 0,
+                misses: 0,
+                hit_rate: 0.0
+            }
+        );
+        for i in 1..=5 {
+            let buffer = buffer_manager.try_pin(&other).unwrap();
+            buffer_manager.unpin(buffer);
+            assert_eq!(buffer_manager.stats().hits, i);
+        }
+        assert_eq!(buffer_manager.stats().misses, 0);
+        assert_eq!(buffer_manager.stats().hit_rate, 1.0);
     }
 }
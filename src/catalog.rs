@@ -0,0 +1,4 @@
+pub mod index_manager;
+pub mod statistics_manager;
+pub mod table_manager;
+pub mod view_manager;
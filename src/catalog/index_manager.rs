@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context};
+
+use crate::index::btree::BTreeIndex;
+use crate::index::Index;
+use crate::record_manager::layout::Layout;
+use crate::record_manager::scan::{Scan, UpdateScan};
+use crate::record_manager::schema::Schema;
+use crate::record_manager::table_scan::TableScan;
+use crate::transaction::transaction::Transaction;
+
+use super::statistics_manager::{StatInfo, StatisticsManager};
+use super::table_manager::{check_name, TableManager, MAX_NAME};
+
+pub const INDEX_CATALOG: &str = "index_catalog";
+
+// 索引1つ分の情報。プランナがコストを見積もるのにも使う
+pub struct IndexInfo {
+    index_name: String,
+    field_name: String,
+    index_layout: Arc<Layout>,
+    // 索引を張ったテーブルの統計
+    stat_info: StatInfo,
+    block_size: usize,
+}
+
+impl IndexInfo {
+    pub fn new(
+        index_name: String,
+        field_name: String,
+        table_schema: &Schema,
+        stat_info: StatInfo,
+        block_size: usize,
+    ) -> anyhow::Result<Self> {
+        let key_info = *table_schema
+            .info(&field_name)
+            .with_context(|| format!("field {} not found", field_name))?;
+        Ok(Self {
+            index_name,
+            field_name,
+            index_layout: Arc::new(BTreeIndex::leaf_layout(key_info)),
+            stat_info,
+            block_size,
+        })
+    }
+
+    pub fn index_name(&self) -> &str {
+        &self.index_name
+    }
+
+    pub fn field_name(&self) -> &str {
+        &self.field_name
+    }
+
+    pub fn open(&self, transaction: &Arc<Mutex<Transaction>>) -> anyhow::Result<BTreeIndex> {
+        BTreeIndex::new(
+            Arc::clone(transaction),
+            &self.index_name,
+            Arc::clone(&self.index_layout),
+        )
+    }
+
+    // 1つのキーで引けるレコード数の見積もり。
+    // TablePlanと同じく、フィールドの値の種類はレコード数の1/3程度とみなす
+    pub fn records_output(&self) -> i32 {
+        self.stat_info.num_records / (1 + self.stat_info.num_records / 3)
+    }
+
+    // 1つのキーを索引で探すときに読むブロック数の見積もり
+    pub fn blocks_accessed(&self) -> i32 {
+        let records_per_block = (self.block_size / self.index_layout.slot_size()) as i32;
+        let num_blocks = self.stat_info.num_records / records_per_block.max(1);
+        BTreeIndex::search_cost(num_blocks, records_per_block)
+    }
+}
+
+// 索引の定義をindex_catalogに保存する
+pub struct IndexManager {
+    layout: Arc<Layout>,
+    table_manager: Arc<TableManager>,
+    statistics_manager: Arc<StatisticsManager>,
+}
+
+impl IndexManager {
+    pub fn new(
+        is_new: bool,
+        table_manager: Arc<TableManager>,
+        statistics_manager: Arc<StatisticsManager>,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Self> {
+        let mut schema = Schema::new();
+        schema.add_string_field("index_name".to_string(), MAX_NAME)?;
+        schema.add_string_field("table_name".to_string(), MAX_NAME)?;
+        schema.add_string_field("field_name".to_string(), MAX_NAME)?;
+        if is_new {
+            table_manager.create_table(INDEX_CATALOG, &schema, transaction)?;
+        }
+        Ok(Self {
+            layout: Arc::new(Layout::from(schema)),
+            table_manager,
+            statistics_manager,
+        })
+    }
+
+    // 索引を登録し、テーブルにすでにあるレコードをすべて索引に入れる
+    pub fn create_index(
+        &self,
+        index_name: &str,
+        table_name: &str,
+        field_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<()> {
+        check_name(index_name)?;
+        let table_layout = Arc::new(self.table_manager.get_layout(table_name, transaction)?);
+        if !table_layout.schema().has_field(field_name) {
+            bail!("field {} not found in table {}", field_name, table_name);
+        }
+        if self.has_index(index_name, transaction)? {
+            bail!("index {} already exists", index_name);
+        }
+
+        let mut index_catalog = self.index_catalog(transaction)?;
+        index_catalog.insert()?;
+        index_catalog.set_string("index_name", index_name.to_string())?;
+        index_catalog.set_string("table_name", table_name.to_string())?;
+        index_catalog.set_string("field_name", field_name.to_string())?;
+        index_catalog.close()?;
+
+        let stat_info =
+            self.statistics_manager
+                .get_stat_info(table_name, &table_layout, transaction)?;
+        let index_info = IndexInfo::new(
+            index_name.to_string(),
+            field_name.to_string(),
+            table_layout.schema(),
+            stat_info,
+            transaction.lock().unwrap().block_size(),
+        )?;
+        let mut index = index_info.open(transaction)?;
+        let mut table_scan = TableScan::new(Arc::clone(transaction), table_name, table_layout)?;
+        while table_scan.next()? {
+            index.insert(table_scan.get_val(field_name)?, table_scan.get_rid()?)?;
+        }
+        table_scan.close()?;
+        index.close()
+    }
+
+    pub fn drop_index(
+        &self,
+        index_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<()> {
+        let mut index_catalog = self.index_catalog(transaction)?;
+        let mut found = false;
+        while index_catalog.next()? {
+            if index_catalog.get_string("index_name")? == index_name {
+                index_catalog.delete()?;
+                found = true;
+                break;
+            }
+        }
+        index_catalog.close()?;
+        if !found {
+            bail!("index {} not found", index_name);
+        }
+
+        for filename in BTreeIndex::filenames(index_name) {
+            transaction.lock().unwrap().delete_file(&filename)?;
+        }
+        Ok(())
+    }
+
+    // テーブル名の変更に合わせて、index_catalogのtable_nameを書き換える。索引のファイルはそのまま使える
+    pub fn rename_table(
+        &self,
+        old_name: &str,
+        new_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<()> {
+        let mut index_catalog = self.index_catalog(transaction)?;
+        while index_catalog.next()? {
+            if index_catalog.get_string("table_name")? == old_name {
+                index_catalog.set_string("table_name", new_name.to_string())?;
+            }
+        }
+        index_catalog.close()
+    }
+
+    // table_nameの索引を、索引を張ったフィールド名をキーにして返す
+    pub fn get_index_info(
+        &self,
+        table_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<HashMap<String, IndexInfo>> {
+        let mut indexes = vec![];
+        let mut index_catalog = self.index_catalog(transaction)?;
+        while index_catalog.next()? {
+            if index_catalog.get_string("table_name")? == table_name {
+                indexes.push((
+                    index_catalog.get_string("index_name")?,
+                    index_catalog.get_string("field_name")?,
+                ));
+            }
+        }
+        index_catalog.close()?;
+        if indexes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let table_layout = Arc::new(self.table_manager.get_layout(table_name, transaction)?);
+        let stat_info =
+            self.statistics_manager
+                .get_stat_info(table_name, &table_layout, transaction)?;
+        let block_size = transaction.lock().unwrap().block_size();
+        let mut result = HashMap::new();
+        for (index_name, field_name) in indexes {
+            let index_info = IndexInfo::new(
+                index_name,
+                field_name.clone(),
+                table_layout.schema(),
+                stat_info,
+                block_size,
+            )?;
+            result.insert(field_name, index_info);
+        }
+        Ok(result)
+    }
+
+    fn has_index(
+        &self,
+        index_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<bool> {
+        let mut index_catalog = self.index_catalog(transaction)?;
+        let mut found = false;
+        while index_catalog.next()? {
+            if index_catalog.get_string("index_name")? == index_name {
+                found = true;
+                break;
+            }
+        }
+        index_catalog.close()?;
+        Ok(found)
+    }
+
+    fn index_catalog(&self, transaction: &Arc<Mutex<Transaction>>) -> anyhow::Result<TableScan> {
+        TableScan::new(
+            Arc::clone(transaction),
+            INDEX_CATALOG,
+            Arc::clone(&self.layout),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::record_manager::constant::Constant;
+
+    #[test]
+    fn index_manager() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let dir = tempdir.path().join("db");
+        let config = || MyDbConfig {
+            dir: dir.to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 10).unwrap();
+        let layout = Arc::new(Layout::from(schema.clone()));
+
+        let db = MyDb::open(config()).unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        db.table_manager()
+            .create_table("users", &schema, &transaction)
+            .unwrap();
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), "users", Arc::clone(&layout)).unwrap();
+        for n in 0..50 {
+            table_scan.insert().unwrap();
+            table_scan.set_int("id", n).unwrap();
+            table_scan.set_string("name", format!("user{}", n)).unwrap();
+        }
+        table_scan.close().unwrap();
+
+        let index_manager = db.index_manager();
+        index_manager
+            .create_index("users_id", "users", "id", &transaction)
+            .unwrap();
+        assert!(index_manager
+            .create_index("users_id", "users", "name", &transaction)
+            .is_err());
+        assert!(index_manager
+            .create_index("users_age", "users", "age", &transaction)
+            .is_err());
+        transaction.lock().unwrap().commit().unwrap();
+        drop(db);
+
+        // 開き直しても索引の定義が残っていて、既存のレコードを引ける
+        let db = MyDb::open(config()).unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        let index_manager = db.index_manager();
+        let indexes = index_manager.get_index_info("users", &transaction).unwrap();
+        assert_eq!(indexes.len(), 1);
+        let index_info = &indexes["id"];
+        assert_eq!(index_info.index_name(), "users_id");
+        assert_eq!(index_info.field_name(), "id");
+        assert!(index_info.blocks_accessed() >= 1);
+
+        let mut index = index_info.open(&transaction).unwrap();
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), "users", Arc::clone(&layout)).unwrap();
+        index.before_first(Constant::Int(7)).unwrap();
+        assert!(index.next().unwrap());
+        table_scan
+            .move_to_rid(index.get_data_rid().unwrap())
+            .unwrap();
+        assert_eq!(table_scan.get_string("name").unwrap(), "user7");
+        assert!(!index.next().unwrap());
+        index.close().unwrap();
+        table_scan.close().unwrap();
+
+        index_manager.drop_index("users_id", &transaction).unwrap();
+        assert!(index_manager
+            .get_index_info("users", &transaction)
+            .unwrap()
+            .is_empty());
+        assert!(!dir.join("users_id.leaf").exists());
+        assert!(!dir.join("users_id.dir").exists());
+        assert!(index_manager.drop_index("users_id", &transaction).is_err());
+        // コミットでバッファを書き出しても、ファイルは作り直されない
+        transaction.lock().unwrap().commit().unwrap();
+        assert!(!dir.join("users_id.leaf").exists());
+    }
+}
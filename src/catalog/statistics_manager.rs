@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::record_manager::layout::Layout;
+use crate::record_manager::scan::{Scan, UpdateScan};
+use crate::record_manager::table_scan::TableScan;
+use crate::transaction::transaction::Transaction;
+
+use super::table_manager::TableManager;
+
+// この回数get_stat_infoを呼ぶごとに、すべてのテーブルの統計を取り直す
+const REFRESH_INTERVAL: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatInfo {
+    pub num_blocks: i32,
+    pub num_records: i32,
+}
+
+impl StatInfo {
+    // reduction_factor件に1件を選ぶとき、選ばれたレコードが集まっていれば読むブロック数
+    pub fn blocks_accessed_for_select(&self, reduction_factor: i32) -> i32 {
+        let reduction_factor = reduction_factor.max(1);
+        (self.num_blocks + reduction_factor - 1) / reduction_factor
+    }
+
+    // reduction_factor件に1件を選ぶときに出力されるレコード数
+    pub fn records_output(&self, reduction_factor: i32) -> i32 {
+        self.num_records / reduction_factor.max(1)
+    }
+}
+
+struct Statistics {
+    table_stats: HashMap<String, StatInfo>,
+    num_calls: usize,
+}
+
+// テーブルの大きさの見積もり。最初に聞かれたときに数えてメモリに置いておく
+pub struct StatisticsManager {
+    table_manager: Arc<TableManager>,
+    statistics: Mutex<Statistics>,
+}
+
+impl StatisticsManager {
+    pub fn new(
+        table_manager: Arc<TableManager>,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Self> {
+        let statistics_manager = Self {
+            table_manager,
+            statistics: Mutex::new(Statistics {
+                table_stats: HashMap::new(),
+                num_calls: 0,
+            }),
+        };
+        statistics_manager.refresh_statistics(transaction)?;
+        Ok(statistics_manager)
+    }
+
+    pub fn get_stat_info(
+        &self,
+        table_name: &str,
+        layout: &Arc<Layout>,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<StatInfo> {
+        let mut statistics = self.statistics.lock().unwrap();
+        statistics.num_calls += 1;
+        if statistics.num_calls > REFRESH_INTERVAL {
+            self.refresh(&mut statistics, transaction)?;
+        }
+        if let Some(stat_info) = statistics.table_stats.get(table_name) {
+            return Ok(*stat_info);
+        }
+        let stat_info = calc_table_stats(table_name, layout, transaction)?;
+        statistics
+            .table_stats
+            .insert(table_name.to_string(), stat_info);
+        Ok(stat_info)
+    }
+
+    pub fn refresh_statistics(&self, transaction: &Arc<Mutex<Transaction>>) -> anyhow::Result<()> {
+        let mut statistics = self.statistics.lock().unwrap();
+        self.refresh(&mut statistics, transaction)
+    }
+
+    fn refresh(
+        &self,
+        statistics: &mut Statistics,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<()> {
+        statistics.table_stats.clear();
+        statistics.num_calls = 0;
+        for table_name in self.table_manager.table_names(transaction)? {
+            let layout = Arc::new(self.table_manager.get_layout(&table_name, transaction)?);
+            let stat_info = calc_table_stats(&table_name, &layout, transaction)?;
+            statistics.table_stats.insert(table_name, stat_info);
+        }
+        Ok(())
+    }
+}
+
+// レコードのある最後のブロックまでをテーブルの大きさとする
+fn calc_table_stats(
+    table_name: &str,
+    layout: &Arc<Layout>,
+    transaction: &Arc<Mutex<Transaction>>,
+) -> anyhow::Result<StatInfo> {
+    let mut table_scan = TableScan::new(Arc::clone(transaction), table_name, Arc::clone(layout))?;
+    let mut num_records = 0;
+    let mut num_blocks = 0;
+    while table_scan.next()? {
+        num_records += 1;
+        num_blocks = table_scan.get_rid()?.block_number + 1;
+    }
+    table_scan.close()?;
+    Ok(StatInfo {
+        num_blocks,
+        num_records,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::record_manager::schema::Schema;
+
+    #[test]
+    fn stat_info() {
+        let stat_info = StatInfo {
+            num_blocks: 10,
+            num_records: 300,
+        };
+        assert_eq!(stat_info.blocks_accessed_for_select(1), 10);
+        assert_eq!(stat_info.blocks_accessed_for_select(3), 4);
+        assert_eq!(stat_info.records_output(1), 300);
+        assert_eq!(stat_info.records_output(30), 10);
+        assert_eq!(stat_info.records_output(0), 300);
+    }
+
+    #[test]
+    fn statistics_manager() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            block_size: 400,
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 10).unwrap();
+        let layout = Arc::new(Layout::from(schema.clone()));
+        db.table_manager()
+            .create_table("users", &schema, &transaction)
+            .unwrap();
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), "users", Arc::clone(&layout)).unwrap();
+        for n in 0..100 {
+            table_scan.insert().unwrap();
+            table_scan.set_int("id", n).unwrap();
+            table_scan.set_string("name", format!("user{}", n)).unwrap();
+        }
+
+        let statistics_manager = db.statistics_manager();
+        let stat_info = statistics_manager
+            .get_stat_info("users", &layout, &transaction)
+            .unwrap();
+        assert_eq!(stat_info.num_records, 100);
+        assert!(stat_info.num_blocks > 1);
+
+        table_scan.before_first().unwrap();
+        while table_scan.next().unwrap() {
+            if table_scan.get_int("id").unwrap() < 40 {
+                table_scan.delete().unwrap();
+            }
+        }
+        table_scan.close().unwrap();
+
+        // 取り直すまではメモリに置いた値を返す
+        let cached = statistics_manager
+            .get_stat_info("users", &layout, &transaction)
+            .unwrap();
+        assert_eq!(cached.num_records, 100);
+        for _ in 0..REFRESH_INTERVAL {
+            statistics_manager
+                .get_stat_info("users", &layout, &transaction)
+                .unwrap();
+        }
+        let refreshed = statistics_manager
+            .get_stat_info("users", &layout, &transaction)
+            .unwrap();
+        assert_eq!(refreshed.num_records, 60);
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
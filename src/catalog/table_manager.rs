@@ -0,0 +1,368 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::bail;
+
+use crate::record_manager::layout::Layout;
+use crate::record_manager::scan::{Scan, UpdateScan};
+use crate::record_manager::schema::{schema, FieldInfo, Schema, OPTIONAL_FLAG};
+use crate::record_manager::table_scan::TableScan;
+use crate::transaction::transaction::Transaction;
+
+// テーブル名とフィールド名の最大長
+pub const MAX_NAME: usize = 16;
+
+pub const TABLE_CATALOG: &str = "table_catalog";
+pub const FIELD_CATALOG: &str = "field_catalog";
+
+// テーブルのスキーマをtable_catalogとfield_catalogに保存する
+pub struct TableManager {
+    table_catalog_layout: Arc<Layout>,
+    field_catalog_layout: Arc<Layout>,
+}
+
+impl TableManager {
+    // is_newなら、カタログ自身もカタログに登録する
+    pub fn new(is_new: bool, transaction: &Arc<Mutex<Transaction>>) -> anyhow::Result<Self> {
+        let table_catalog_schema = schema!["table_name" => String(MAX_NAME)]?;
+        let field_catalog_schema = schema![
+            "table_name" => String(MAX_NAME),
+            "field_name" => String(MAX_NAME),
+            "type" => Int,
+            "length" => Int,
+        ]?;
+
+        let table_manager = Self {
+            table_catalog_layout: Arc::new(Layout::from(table_catalog_schema.clone())),
+            field_catalog_layout: Arc::new(Layout::from(field_catalog_schema.clone())),
+        };
+        if is_new {
+            table_manager.create_table(TABLE_CATALOG, &table_catalog_schema, transaction)?;
+            table_manager.create_table(FIELD_CATALOG, &field_catalog_schema, transaction)?;
+        }
+        Ok(table_manager)
+    }
+
+    pub fn create_table(
+        &self,
+        table_name: &str,
+        schema: &Schema,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<()> {
+        check_name(table_name)?;
+        for field_name in schema.fields() {
+            check_name(field_name)?;
+        }
+        if self.has_table(table_name, transaction)? {
+            bail!("table {} already exists", table_name);
+        }
+
+        let mut table_catalog = self.table_catalog(transaction)?;
+        table_catalog.insert()?;
+        table_catalog.set_string("table_name", table_name.to_string())?;
+        table_catalog.close()?;
+
+        let mut field_catalog = self.field_catalog(transaction)?;
+        for field_name in schema.fields() {
+            let info = schema.info(field_name).unwrap();
+            let length = match info {
+                FieldInfo::String(length) => *length,
+                _ => 0,
+            };
+            field_catalog.insert()?;
+            field_catalog.set_string("table_name", table_name.to_string())?;
+            field_catalog.set_string("field_name", field_name.clone())?;
+            field_catalog.set_int("type", schema.type_code(field_name).unwrap() as i32)?;
+            field_catalog.set_int("length", length as i32)?;
+        }
+        field_catalog.close()?;
+
+        // テーブルのファイルがなければ、最初のブロックを追加してフォーマットする
+        let layout = Arc::new(Layout::from(schema.clone()));
+        TableScan::new(Arc::clone(transaction), table_name, layout)?.close()
+    }
+
+    pub fn get_layout(
+        &self,
+        table_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Layout> {
+        if !self.has_table(table_name, transaction)? {
+            bail!("table {} not found", table_name);
+        }
+
+        // 挿入は常に後ろのスロットへ進むので、走査順はcreate_tableで登録した順と同じ
+        let mut schema = Schema::new();
+        let mut field_catalog = self.field_catalog(transaction)?;
+        while field_catalog.next()? {
+            if field_catalog.get_string("table_name")? != table_name {
+                continue;
+            }
+            let field_name = field_catalog.get_string("field_name")?;
+            let code = field_catalog.get_int("type")? as u8;
+            let info = FieldInfo::from_type_code(
+                code & !OPTIONAL_FLAG,
+                field_catalog.get_int("length")? as usize,
+            )?;
+            if code & OPTIONAL_FLAG != 0 {
+                schema.add_optional_field(field_name, info)?;
+            } else {
+                schema.add_field(field_name, info)?;
+            }
+        }
+        field_catalog.close()?;
+        Ok(Layout::from(schema))
+    }
+
+    // field_catalogの名前だけを書き換える。位置と型は変わらないので、テーブルのデータはそのまま読める
+    pub fn rename_field(
+        &self,
+        table_name: &str,
+        old_name: &str,
+        new_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<()> {
+        check_name(new_name)?;
+        let mut schema = self.get_layout(table_name, transaction)?.schema().clone();
+        schema.rename_field(old_name, new_name)?;
+
+        let mut field_catalog = self.field_catalog(transaction)?;
+        while field_catalog.next()? {
+            if field_catalog.get_string("table_name")? == table_name
+                && field_catalog.get_string("field_name")? == old_name
+            {
+                field_catalog.set_string("field_name", new_name.to_string())?;
+            }
+        }
+        field_catalog.close()
+    }
+
+    // table_catalogとfield_catalogのテーブル名を書き換える。データのファイル名は呼び出し側で変える
+    pub fn rename_table(
+        &self,
+        old_name: &str,
+        new_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<()> {
+        check_name(new_name)?;
+        if !self.has_table(old_name, transaction)? {
+            bail!("table {} not found", old_name);
+        }
+        if self.has_table(new_name, transaction)? {
+            bail!("table {} already exists", new_name);
+        }
+
+        for mut catalog in [
+            self.table_catalog(transaction)?,
+            self.field_catalog(transaction)?,
+        ] {
+            while catalog.next()? {
+                if catalog.get_string("table_name")? == old_name {
+                    catalog.set_string("table_name", new_name.to_string())?;
+                }
+            }
+            catalog.close()?;
+        }
+        Ok(())
+    }
+
+    pub fn drop_table(
+        &self,
+        table_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<()> {
+        if !self.has_table(table_name, transaction)? {
+            bail!("table {} not found", table_name);
+        }
+
+        for mut catalog in [
+            self.table_catalog(transaction)?,
+            self.field_catalog(transaction)?,
+        ] {
+            while catalog.next()? {
+                if catalog.get_string("table_name")? == table_name {
+                    catalog.delete()?;
+                }
+            }
+            catalog.close()?;
+        }
+        Ok(())
+    }
+
+    pub fn has_table(
+        &self,
+        table_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<bool> {
+        let mut table_catalog = self.table_catalog(transaction)?;
+        let mut found = false;
+        while table_catalog.next()? {
+            if table_catalog.get_string("table_name")? == table_name {
+                found = true;
+                break;
+            }
+        }
+        table_catalog.close()?;
+        Ok(found)
+    }
+
+    pub fn table_names(
+        &self,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut table_catalog = self.table_catalog(transaction)?;
+        let mut table_names = vec![];
+        while table_catalog.next()? {
+            table_names.push(table_catalog.get_string("table_name")?);
+        }
+        table_catalog.close()?;
+        Ok(table_names)
+    }
+
+    fn table_catalog(&self, transaction: &Arc<Mutex<Transaction>>) -> anyhow::Result<TableScan> {
+        TableScan::new(
+            Arc::clone(transaction),
+            TABLE_CATALOG,
+            Arc::clone(&self.table_catalog_layout),
+        )
+    }
+
+    fn field_catalog(&self, transaction: &Arc<Mutex<Transaction>>) -> anyhow::Result<TableScan> {
+        TableScan::new(
+            Arc::clone(transaction),
+            FIELD_CATALOG,
+            Arc::clone(&self.field_catalog_layout),
+        )
+    }
+}
+
+pub fn check_name(name: &str) -> anyhow::Result<()> {
+    if name.len() > MAX_NAME {
+        bail!("name {} is longer than {} bytes", name, MAX_NAME);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::buffer_manager::BufferManager;
+    use crate::file_manager::{FileManager, FileManagerTrait};
+    use crate::log_manager::LogManager;
+    use crate::transaction::lock_table::LockTable;
+
+    #[test]
+    fn table_manager() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let directory = tempdir.path().to_str().unwrap().to_string();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            8,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let transaction = Arc::new(Mutex::new(Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        )));
+
+        let table_manager = TableManager::new(true, &transaction).unwrap();
+        // カタログ自身も登録されている
+        let layout = table_manager
+            .get_layout(FIELD_CATALOG, &transaction)
+            .unwrap();
+        assert_eq!(
+            layout.schema().fields(),
+            &vec!["table_name", "field_name", "type", "length"]
+        );
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 9).unwrap();
+        schema.add_bool_field("active".to_string()).unwrap();
+        schema.add_float_field("score".to_string()).unwrap();
+        schema
+            .add_optional_field("nickname".to_string(), FieldInfo::String(9))
+            .unwrap();
+        table_manager
+            .create_table("users", &schema, &transaction)
+            .unwrap();
+        assert_eq!(
+            transaction
+                .lock()
+                .unwrap()
+                .size("users.tbl".to_string())
+                .unwrap(),
+            1
+        );
+        assert!(table_manager
+            .create_table("users", &schema, &transaction)
+            .is_err());
+        assert!(table_manager
+            .create_table("a_very_long_table_name", &schema, &transaction)
+            .is_err());
+
+        let layout = table_manager.get_layout("users", &transaction).unwrap();
+        let expected = Layout::from(schema.clone());
+        assert_eq!(layout.schema().fields(), expected.schema().fields());
+        for field_name in schema.fields() {
+            assert_eq!(layout.schema().info(field_name), schema.info(field_name));
+            assert_eq!(
+                layout.schema().is_optional(field_name),
+                schema.is_optional(field_name)
+            );
+            assert_eq!(layout.offset(field_name), expected.offset(field_name));
+        }
+        assert_eq!(layout.slot_size(), expected.slot_size());
+
+        // 名前を変えてもフィールドの並びと位置は変わらない
+        table_manager
+            .rename_field("users", "name", "full_name", &transaction)
+            .unwrap();
+        let renamed = table_manager.get_layout("users", &transaction).unwrap();
+        assert_eq!(
+            renamed.schema().fields(),
+            &vec!["id", "full_name", "active", "score", "nickname"]
+        );
+        assert_eq!(renamed.offset("full_name"), layout.offset("name"));
+        assert_eq!(renamed.slot_size(), layout.slot_size());
+        assert!(table_manager
+            .rename_field("users", "name", "other", &transaction)
+            .is_err());
+        assert!(table_manager
+            .rename_field("users", "id", "active", &transaction)
+            .is_err());
+        assert!(table_manager
+            .rename_field("users", "id", "a_very_long_field_name", &transaction)
+            .is_err());
+        assert!(table_manager
+            .rename_field("missing", "id", "other", &transaction)
+            .is_err());
+        assert_eq!(
+            table_manager
+                .get_layout("users", &transaction)
+                .unwrap()
+                .schema()
+                .fields(),
+            renamed.schema().fields()
+        );
+
+        table_manager.drop_table("users", &transaction).unwrap();
+        assert!(!table_manager.has_table("users", &transaction).unwrap());
+        assert!(table_manager.get_layout("users", &transaction).is_err());
+        assert!(table_manager.drop_table("users", &transaction).is_err());
+        assert!(table_manager
+            .has_table(TABLE_CATALOG, &transaction)
+            .unwrap());
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
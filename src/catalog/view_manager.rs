@@ -0,0 +1,227 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::bail;
+
+use crate::record_manager::layout::Layout;
+use crate::record_manager::scan::{Scan, UpdateScan};
+use crate::record_manager::schema::Schema;
+use crate::record_manager::table_scan::TableScan;
+use crate::transaction::transaction::Transaction;
+
+use super::table_manager::{check_name, TableManager, MAX_NAME};
+
+// ビューの定義に使えるSQLの最大長
+pub const MAX_VIEW_DEF: usize = 256;
+
+pub const VIEW_CATALOG: &str = "view_catalog";
+
+// ビューの定義をSQLのままview_catalogに保存する
+pub struct ViewManager {
+    layout: Arc<Layout>,
+    table_manager: Arc<TableManager>,
+}
+
+impl ViewManager {
+    pub fn new(
+        is_new: bool,
+        table_manager: Arc<TableManager>,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Self> {
+        let mut schema = Schema::new();
+        schema.add_string_field("view_name".to_string(), MAX_NAME)?;
+        schema.add_string_field("view_def".to_string(), MAX_VIEW_DEF)?;
+        if is_new {
+            table_manager.create_table(VIEW_CATALOG, &schema, transaction)?;
+        }
+        Ok(Self {
+            layout: Arc::new(Layout::from(schema)),
+            table_manager,
+        })
+    }
+
+    pub fn create_view(
+        &self,
+        view_name: &str,
+        view_def: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<()> {
+        check_name(view_name)?;
+        if view_def.len() > MAX_VIEW_DEF {
+            bail!(
+                "definition of view {} is longer than {} bytes",
+                view_name,
+                MAX_VIEW_DEF
+            );
+        }
+        // FROM句ではテーブルと同じ名前空間で引くので、テーブル名とも重ならないようにする
+        if self.table_manager.has_table(view_name, transaction)? {
+            bail!("table {} already exists", view_name);
+        }
+        if self.find_view(view_name, transaction)?.is_some() {
+            bail!("view {} already exists", view_name);
+        }
+
+        let mut view_catalog = self.view_catalog(transaction)?;
+        view_catalog.insert()?;
+        view_catalog.set_string("view_name", view_name.to_string())?;
+        view_catalog.set_string("view_def", view_def.to_string())?;
+        view_catalog.close()
+    }
+
+    pub fn get_view_definition(
+        &self,
+        view_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<String> {
+        match self.find_view(view_name, transaction)? {
+            Some(view_def) => Ok(view_def),
+            None => bail!("view {} not found", view_name),
+        }
+    }
+
+    pub fn has_view(
+        &self,
+        view_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<bool> {
+        Ok(self.find_view(view_name, transaction)?.is_some())
+    }
+
+    fn find_view(
+        &self,
+        view_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Option<String>> {
+        let mut view_catalog = self.view_catalog(transaction)?;
+        let mut view_def = None;
+        while view_catalog.next()? {
+            if view_catalog.get_string("view_name")? == view_name {
+                view_def = Some(view_catalog.get_string("view_def")?);
+                break;
+            }
+        }
+        view_catalog.close()?;
+        Ok(view_def)
+    }
+
+    fn view_catalog(&self, transaction: &Arc<Mutex<Transaction>>) -> anyhow::Result<TableScan> {
+        TableScan::new(
+            Arc::clone(transaction),
+            VIEW_CATALOG,
+            Arc::clone(&self.layout),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::planner::basic_update_planner::BasicUpdatePlanner;
+    use crate::planner::heuristic_query_planner::HeuristicQueryPlanner;
+    use crate::planner::{QueryPlanner, UpdatePlanner};
+    use crate::sql::parser::{Parser, Statement};
+
+    #[test]
+    fn view_manager() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let dir = tempdir.path().join("db");
+        let config = || MyDbConfig {
+            dir: dir.to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+
+        let db = MyDb::open(config()).unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        let planner =
+            BasicUpdatePlanner::new(db.table_manager(), db.view_manager(), db.index_manager());
+        let execute = |sql: &str| -> anyhow::Result<i32> {
+            match Parser::new(sql)?.statement()? {
+                Statement::CreateTable(data) => planner.execute_create_table(&data, &transaction),
+                Statement::CreateView(data) => planner.execute_create_view(&data, &transaction),
+                Statement::Insert(data) => planner.execute_insert(&data, &transaction),
+                _ => bail!("unexpected statement"),
+            }
+        };
+        execute("create table student (sid int, sname varchar(10), majorid int)").unwrap();
+        for (sid, sname, majorid) in [(1, "joe", 10), (2, "amy", 20), (3, "max", 10)] {
+            execute(&format!(
+                "insert into student values ({}, '{}', {})",
+                sid, sname, majorid
+            ))
+            .unwrap();
+        }
+        execute("create view compsci as select sid, sname from student where majorid = 10")
+            .unwrap();
+        // 同じ名前、存在しない参照先、長すぎる定義は登録しない
+        assert!(execute("create view compsci as select sid from student").is_err());
+        assert!(execute("create view student as select sid from student").is_err());
+        assert!(execute("create view v as select a from missing").is_err());
+        let view_manager = db.view_manager();
+        assert!(view_manager
+            .create_view("long", &"x".repeat(MAX_VIEW_DEF + 1), &transaction)
+            .is_err());
+        transaction.lock().unwrap().commit().unwrap();
+        drop(db);
+
+        // 開き直してもビューの定義が残っている
+        let db = MyDb::open(config()).unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        let view_manager = db.view_manager();
+        assert_eq!(
+            view_manager
+                .get_view_definition("compsci", &transaction)
+                .unwrap(),
+            "select sid, sname from student where majorid = 10"
+        );
+        assert!(view_manager.has_view("compsci", &transaction).unwrap());
+        assert!(!view_manager.has_view("student", &transaction).unwrap());
+        assert!(view_manager
+            .get_view_definition("missing", &transaction)
+            .is_err());
+
+        // ビューをFROM句に書くと、定義のクエリを展開して実行する
+        let planner = HeuristicQueryPlanner::new(
+            db.table_manager(),
+            db.view_manager(),
+            db.index_manager(),
+            db.statistics_manager(),
+        );
+        let query = Parser::new("select sname from compsci where sid = 3")
+            .unwrap()
+            .query()
+            .unwrap();
+        let mut scan = planner
+            .create_plan(&query, &transaction)
+            .unwrap()
+            .open()
+            .unwrap();
+        assert!(scan.next().unwrap());
+        assert_eq!(scan.get_string("sname").unwrap(), "max");
+        assert!(!scan.next().unwrap());
+        scan.close().unwrap();
+
+        let query = Parser::new("select sname from compsci")
+            .unwrap()
+            .query()
+            .unwrap();
+        let plan = planner.create_plan(&query, &transaction).unwrap();
+        let mut scan = plan.open().unwrap();
+        let mut names = vec![];
+        while scan.next().unwrap() {
+            names.push(scan.get_string("sname").unwrap());
+        }
+        scan.close().unwrap();
+        names.sort();
+        assert_eq!(names, vec!["joe", "max"]);
+        // ビューに含まれないフィールドは参照できない
+        let query = Parser::new("select majorid from compsci")
+            .unwrap()
+            .query()
+            .unwrap();
+        assert!(planner.create_plan(&query, &transaction).is_err());
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
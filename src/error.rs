@@ -6,3 +6,21 @@ pub struct LockAbortError {
     #[from]
     source: anyhow::Error,
 }
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SchemaError {
+    #[error("field {0} is already defined")]
+    DuplicateField(String),
+    #[error("field {0} is not defined")]
+    UnknownField(String),
+    #[error("default value does not fit field {0}")]
+    InvalidDefault(String),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("syntax error at line {line}, column {column}: {message}")]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
@@ -1,13 +1,20 @@
-use std::cell::RefCell;
 use std::collections::HashMap;
 
 use std::collections::hash_map::Entry;
-use std::fs::{metadata, File, OpenOptions};
+use std::fs::{remove_file, rename, File, OpenOptions};
 use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
-use std::rc::Rc;
 
 pub const PAGE_SIZE: usize = 4096;
 pub const INTGER_BYTES: usize = 4;
+pub const LONG_BYTES: usize = 8;
+pub const BOOL_BYTES: usize = 1;
+pub const FLOAT_BYTES: usize = 8;
+pub const MAX_VARINT_BYTES: usize = 10;
+pub const CODEC_HEADER_BYTES: usize = 2 * INTGER_BYTES;
+// 圧縮したブロックの先頭のintは、上位3バイトが"MDB"、下位1バイトがcodec
+const CODEC_MAGIC: i32 = 0x4D44_4200;
+const CODEC_MASK: i32 = 0xFF;
+const ZSTD_LEVEL: i32 = 3;
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub struct BlockId {
@@ -22,9 +29,19 @@ impl BlockId {
             block_number,
         }
     }
+
+    // ファイル単位のロックに使う番兵
+    pub fn dummy_for_file(filename: &str) -> Self {
+        Self::new(filename, -1)
+    }
+
+    pub fn is_dummy(&self) -> bool {
+        self.block_number == -1
+    }
 }
 
-#[derive(Debug)]
+// 複製はカーソルの位置も引き継ぐ
+#[derive(Debug, Clone)]
 pub struct Page {
     cursor: Cursor<Vec<u8>>,
 }
@@ -32,7 +49,7 @@ pub struct Page {
 impl Page {
     pub fn new(block_size: usize) -> Self {
         Page {
-            cursor: Cursor::new(Vec::with_capacity(block_size)),
+            cursor: Cursor::new(vec![0; block_size]),
         }
     }
 
@@ -50,16 +67,127 @@ impl Page {
         Ok(())
     }
 
+    pub fn get_long(&mut self, offset: usize) -> io::Result<i64> {
+        self.cursor.seek(SeekFrom::Start(offset as u64))?;
+        let ret: &mut [u8; LONG_BYTES] = &mut [0; LONG_BYTES];
+        self.cursor.read_exact(ret)?;
+        Ok(i64::from_be_bytes(*ret))
+    }
+
+    pub fn set_long(&mut self, offset: usize, value: i64) -> io::Result<()> {
+        self.cursor.seek(SeekFrom::Start(offset as u64))?;
+        let data = i64::to_be_bytes(value);
+        self.cursor.write_all(&data)?;
+        Ok(())
+    }
+
+    pub fn get_float(&mut self, offset: usize) -> io::Result<f64> {
+        self.cursor.seek(SeekFrom::Start(offset as u64))?;
+        let ret: &mut [u8; FLOAT_BYTES] = &mut [0; FLOAT_BYTES];
+        self.cursor.read_exact(ret)?;
+        Ok(f64::from_be_bytes(*ret))
+    }
+
+    pub fn set_float(&mut self, offset: usize, value: f64) -> io::Result<()> {
+        self.cursor.seek(SeekFrom::Start(offset as u64))?;
+        let data = f64::to_be_bytes(value);
+        self.cursor.write_all(&data)?;
+        Ok(())
+    }
+
+    pub fn get_bool(&mut self, offset: usize) -> io::Result<bool> {
+        self.cursor.seek(SeekFrom::Start(offset as u64))?;
+        let ret: &mut [u8; BOOL_BYTES] = &mut [0; BOOL_BYTES];
+        self.cursor.read_exact(ret)?;
+        match ret[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid bool byte {} at offset {}", b, offset),
+            )),
+        }
+    }
+
+    pub fn set_bool(&mut self, offset: usize, value: bool) -> io::Result<()> {
+        self.cursor.seek(SeekFrom::Start(offset as u64))?;
+        self.cursor.write_all(&[value as u8])?;
+        Ok(())
+    }
+
+    pub fn get_byte(&mut self, offset: usize) -> io::Result<u8> {
+        self.cursor.seek(SeekFrom::Start(offset as u64))?;
+        let ret: &mut [u8; 1] = &mut [0; 1];
+        self.cursor.read_exact(ret)?;
+        Ok(ret[0])
+    }
+
+    pub fn set_byte(&mut self, offset: usize, value: u8) -> io::Result<()> {
+        self.cursor.seek(SeekFrom::Start(offset as u64))?;
+        self.cursor.write_all(&[value])?;
+        Ok(())
+    }
+
+    // zigzag符号化したLEB128で、絶対値の小さい整数ほど少ないバイト数になる
+    pub fn get_varint(&mut self, offset: usize) -> io::Result<(i64, usize)> {
+        self.cursor.seek(SeekFrom::Start(offset as u64))?;
+        let mut encoded: u64 = 0;
+        let mut size = 0;
+        loop {
+            let byte: &mut [u8; 1] = &mut [0; 1];
+            self.cursor.read_exact(byte)?;
+            encoded |= ((byte[0] & 0x7f) as u64) << (7 * size);
+            size += 1;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+            if size == MAX_VARINT_BYTES {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("varint at offset {} is too long", offset),
+                ));
+            }
+        }
+        let value = (encoded >> 1) as i64 ^ -((encoded & 1) as i64);
+        Ok((value, size))
+    }
+
+    pub fn set_varint(&mut self, offset: usize, value: i64) -> io::Result<usize> {
+        self.cursor.seek(SeekFrom::Start(offset as u64))?;
+        let mut encoded = ((value << 1) ^ (value >> 63)) as u64;
+        let mut size = 0;
+        loop {
+            let byte = (encoded & 0x7f) as u8;
+            encoded >>= 7;
+            size += 1;
+            if encoded == 0 {
+                self.cursor.write_all(&[byte])?;
+                return Ok(size);
+            }
+            self.cursor.write_all(&[byte | 0x80])?;
+        }
+    }
+
+    pub fn varint_size(value: i64) -> usize {
+        let encoded = ((value << 1) ^ (value >> 63)) as u64;
+        let bits = 64 - encoded.leading_zeros() as usize;
+        bits.max(1).div_ceil(7)
+    }
+
+    // 長さの後ろに本体を置く。get_intを読んだ後のカーソルの位置に頼らず、本体の位置へ移動する
     pub fn get_bytes(&mut self, offset: usize) -> io::Result<Box<[u8]>> {
         let length = self.get_int(offset)?;
         let mut data = vec![0; length as usize].into_boxed_slice();
+        self.cursor
+            .seek(SeekFrom::Start((offset + INTGER_BYTES) as u64))?;
         self.cursor.read_exact(data.as_mut())?;
         Ok(data)
     }
 
     pub fn set_bytes(&mut self, offset: usize, value: &[u8]) -> io::Result<()> {
-        self.cursor.seek(SeekFrom::Start(offset as u64))?;
         self.set_int(offset, value.len() as i32)?;
+        self.cursor
+            .seek(SeekFrom::Start((offset + INTGER_BYTES) as u64))?;
         self.cursor.write_all(value)?;
         Ok(())
     }
@@ -78,11 +206,43 @@ impl Page {
         INTGER_BYTES + strlen
     }
 
+    // otherの内容で置き換える。大きさが違えばotherに合わせる
+    pub fn copy_from(&mut self, other: &Page) {
+        self.cursor.get_mut().clone_from(other.cursor.get_ref());
+    }
+
     pub fn contents(&mut self) -> &mut Vec<u8> {
         self.cursor.get_mut()
     }
 }
 
+// Read/Write/Seekは現在のカーソルの位置から読み書きする。get_intなどもカーソルを動かす。
+// ページの大きさは変えないので、末尾を越えては書き込まない
+impl Read for Page {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.cursor.read(buf)
+    }
+}
+
+impl Write for Page {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let position = self.cursor.position() as usize;
+        let remaining = self.cursor.get_ref().len().saturating_sub(position);
+        let size = buf.len().min(remaining);
+        self.cursor.write(&buf[..size])
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for Page {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.cursor.seek(pos)
+    }
+}
+
 impl From<Box<[u8]>> for Page {
     fn from(buf: Box<[u8]>) -> Self {
         Page {
@@ -91,39 +251,138 @@ impl From<Box<[u8]>> for Page {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    None = 0,
+    Lz4,
+    Zstd,
+}
+
+impl TryFrom<i32> for Codec {
+    type Error = io::Error;
+    fn try_from(v: i32) -> io::Result<Self> {
+        match v {
+            0 => Ok(Codec::None),
+            1 => Ok(Codec::Lz4),
+            2 => Ok(Codec::Zstd),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown codec {v}"),
+            )),
+        }
+    }
+}
+
+impl From<Codec> for i32 {
+    fn from(c: Codec) -> Self {
+        match c {
+            Codec::None => 0,
+            Codec::Lz4 => 1,
+            Codec::Zstd => 2,
+        }
+    }
+}
+
+impl Codec {
+    fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Codec::None => Ok(data.to_vec()),
+            Codec::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+            Codec::Zstd => zstd::bulk::compress(data, ZSTD_LEVEL),
+        }
+    }
+
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Codec::None => Ok(data.to_vec()),
+            Codec::Lz4 => lz4_flex::decompress_size_prepended(data)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Codec::Zstd => zstd::stream::decode_all(data),
+        }
+    }
+}
+
+pub struct FileManagerOptions {
+    pub block_size: usize,
+    pub max_open_files: usize,
+    pub codec: Codec,
+    pub fsync_on_write: bool,
+    pub full_durability: bool,
+}
+
+impl Default for FileManagerOptions {
+    fn default() -> Self {
+        Self {
+            block_size: PAGE_SIZE,
+            max_open_files: usize::MAX,
+            codec: Codec::None,
+            fsync_on_write: false,
+            full_durability: false,
+        }
+    }
+}
+
+// ブロック単位でファイルを読み書きする。ディスクに置くFileManagerと、メモリ上に置くMemoryFileManagerがある
+pub trait FileManagerTrait: Send {
+    fn block_size(&self) -> usize;
+    // ファイル末尾より後ろのブロックは0で埋めて返す
+    fn read(&mut self, block_id: &BlockId, page: &mut Page) -> io::Result<()>;
+    fn write(&mut self, block_id: &BlockId, page: &mut Page) -> io::Result<()>;
+    fn append_new_block(&mut self, filename: &str) -> io::Result<BlockId>;
+    fn length(&mut self, filename: &str) -> anyhow::Result<i32>;
+    fn last_block_num(&mut self, filename: &str) -> io::Result<i32>;
+    fn rename_file(&mut self, old_filename: &str, new_filename: &str) -> io::Result<()>;
+    fn delete_file(&mut self, filename: &str) -> io::Result<()>;
+    fn fsync_directory(&self) -> io::Result<()>;
+    fn sync_all(&self) -> io::Result<()>;
+    fn sync_file(&self, filename: &str) -> io::Result<()>;
+}
+
 pub struct FileManager {
     pub directory: String,
     pub block_size: usize,
-    pub open_files: Rc<RefCell<HashMap<String, File>>>,
+    pub max_open_files: usize,
+    pub codec: Codec,
+    pub fsync_on_write: bool,
+    pub full_durability: bool,
+    pub open_files: HashMap<String, File>,
 }
 
 impl FileManager {
     pub fn new(directory: String) -> Self {
+        Self::new_with_options(directory, Default::default())
+    }
+
+    pub fn new_with_options(directory: String, options: FileManagerOptions) -> Self {
         FileManager {
             directory,
-            block_size: PAGE_SIZE,
-            open_files: Rc::new(RefCell::new(HashMap::new())),
+            block_size: options.block_size,
+            max_open_files: options.max_open_files,
+            codec: options.codec,
+            fsync_on_write: options.fsync_on_write,
+            full_durability: options.full_durability,
+            open_files: HashMap::new(),
         }
     }
 
-    pub fn write(&mut self, block_id: &BlockId, page: &mut Page) -> io::Result<()> {
-        let block_size = self.block_size as i32;
-        let mut file = self.get_file(&block_id.filename)?;
-        file.seek(SeekFrom::Start((block_id.block_number * block_size) as u64))?;
-        file.write_all(page.contents())?;
-        Ok(())
+    pub fn block_number_for_offset(&self, byte_offset: u64) -> i32 {
+        (byte_offset / self.block_size as u64) as i32
     }
 
-    pub fn read(&mut self, block_id: &BlockId, page: &mut Page) -> io::Result<()> {
-        let block_size = self.block_size as i32;
-        let mut file = self.get_file(&block_id.filename)?;
-        file.seek(SeekFrom::Start((block_id.block_number * block_size) as u64))?;
-        file.read_to_end(page.contents())?;
-        Ok(())
+    pub fn byte_offset_for_block(&self, block_number: i32) -> u64 {
+        block_number as u64 * self.block_size as u64
     }
 
-    fn get_file(&mut self, filename: &String) -> io::Result<File> {
-        let file = match self.open_files.borrow_mut().entry(filename.to_string()) {
+    fn get_file(&mut self, filename: &str) -> io::Result<File> {
+        if !self.open_files.contains_key(filename) && self.open_files.len() >= self.max_open_files {
+            // 閉じた後はsync_allの対象にならないので、ここで同期しておく
+            if let Some(victim) = self.open_files.keys().next().cloned() {
+                if let Some(file) = self.open_files.remove(&victim) {
+                    file.sync_all()?;
+                }
+            }
+        }
+        let file = match self.open_files.entry(filename.to_string()) {
             Entry::Occupied(o) => o.into_mut().try_clone()?,
             Entry::Vacant(v) => {
                 let new_file = OpenOptions::new()
@@ -136,32 +395,277 @@ impl FileManager {
         };
         Ok(file)
     }
+}
+
+impl FileManagerTrait for FileManager {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn write(&mut self, block_id: &BlockId, page: &mut Page) -> io::Result<()> {
+        let mut file = self.get_file(&block_id.filename)?;
+        file.seek(SeekFrom::Start(
+            self.byte_offset_for_block(block_id.block_number),
+        ))?;
+        if self.codec == Codec::None {
+            file.write_all(page.contents())?;
+        } else {
+            // 圧縮したブロックの先頭8バイトにはmagicとcodec、圧縮後のサイズが格納されている
+            let compressed = self.codec.compress(page.contents())?;
+            if CODEC_HEADER_BYTES + compressed.len() > self.block_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "{:?} compressed to {} bytes, which does not fit in a {} byte block",
+                        block_id,
+                        compressed.len(),
+                        self.block_size
+                    ),
+                ));
+            }
+            let mut header = Page::new(CODEC_HEADER_BYTES);
+            header.set_int(0, CODEC_MAGIC | i32::from(self.codec))?;
+            header.set_int(INTGER_BYTES, compressed.len() as i32)?;
+            file.write_all(header.contents())?;
+            file.write_all(&compressed)?;
+        }
+        if self.fsync_on_write {
+            file.sync_all()?;
+        }
+        Ok(())
+    }
 
-    pub fn length(&mut self, filename: &String) -> anyhow::Result<i32> {
-        let _ = self.get_file(filename)?;
-        let s = metadata(filename).unwrap();
-        return Ok((s.len() / (self.block_size as u64)) as i32);
+    fn read(&mut self, block_id: &BlockId, page: &mut Page) -> io::Result<()> {
+        let file = self.get_file(&block_id.filename)?;
+        let mut file = file.take(self.block_size as u64);
+        file.get_mut().seek(SeekFrom::Start(
+            self.byte_offset_for_block(block_id.block_number),
+        ))?;
+        // ファイル末尾より後ろの部分は0で埋める
+        let mut buf = Vec::with_capacity(self.block_size);
+        file.read_to_end(&mut buf)?;
+        buf.resize(self.block_size, 0);
+        if self.codec == Codec::None {
+            *page.contents() = buf;
+            return Ok(());
+        }
+
+        if buf.iter().all(|b| *b == 0) {
+            // まだ書き込まれていないブロック
+            *page.contents() = buf;
+            return Ok(());
+        }
+        let mut block = Page::from(buf.into_boxed_slice());
+        let tag = block.get_int(0)?;
+        if tag & !CODEC_MASK != CODEC_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} is not a compressed block", block_id),
+            ));
+        }
+        let codec = Codec::try_from(tag & CODEC_MASK)?;
+        if codec != self.codec {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{:?} was written with {:?}, but the file manager uses {:?}",
+                    block_id, codec, self.codec
+                ),
+            ));
+        }
+        let length = block.get_int(INTGER_BYTES)? as usize;
+        let compressed = block
+            .contents()
+            .get(CODEC_HEADER_BYTES..CODEC_HEADER_BYTES + length)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{:?} has a corrupted codec header", block_id),
+                )
+            })?;
+        let mut contents = codec.decompress(compressed)?;
+        contents.resize(self.block_size, 0);
+        *page.contents() = contents;
+        Ok(())
     }
 
-    pub fn append_new_block(&mut self, filename: &String) -> io::Result<BlockId> {
-        let block_size = self.block_size;
+    fn length(&mut self, filename: &str) -> anyhow::Result<i32> {
+        Ok(self.last_block_num(filename)?)
+    }
 
+    fn append_new_block(&mut self, filename: &str) -> io::Result<BlockId> {
         let new_block_num = self.last_block_num(filename)?;
         let new_block = BlockId {
             filename: filename.to_string(),
-            block_number: new_block_num as i32,
+            block_number: new_block_num,
         };
-        let buf: Vec<u8> = Vec::with_capacity(block_size);
+        let buf: Vec<u8> = vec![0; self.block_size];
 
         let mut file = self.get_file(filename)?;
-        file.seek(SeekFrom::Start((new_block_num * block_size) as u64))?;
+        file.seek(SeekFrom::Start(
+            self.byte_offset_for_block(new_block.block_number),
+        ))?;
         file.write_all(&buf)?;
+        if self.full_durability {
+            self.fsync_directory()?;
+        }
         Ok(new_block)
     }
 
-    pub fn last_block_num(&mut self, filename: &String) -> io::Result<usize> {
+    fn rename_file(&mut self, old_filename: &str, new_filename: &str) -> io::Result<()> {
+        if let Some(file) = self.open_files.remove(old_filename) {
+            file.sync_all()?;
+        }
+        self.open_files.remove(new_filename);
+        rename(
+            format!("{}/{old_filename}", self.directory),
+            format!("{}/{new_filename}", self.directory),
+        )?;
+        if self.full_durability {
+            self.fsync_directory()?;
+        }
+        Ok(())
+    }
+
+    // ファイルがなければ何もしない
+    fn delete_file(&mut self, filename: &str) -> io::Result<()> {
+        self.open_files.remove(filename);
+        match remove_file(format!("{}/{filename}", self.directory)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    // Windowsではディレクトリをファイルとして開けないので何もしない
+    #[cfg(windows)]
+    fn fsync_directory(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(windows))]
+    fn fsync_directory(&self) -> io::Result<()> {
+        File::open(&self.directory)?.sync_all()
+    }
+
+    // 開いているファイルをすべてディスクに同期する
+    fn sync_all(&self) -> io::Result<()> {
+        for file in self.open_files.values() {
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    // 閉じたファイルは閉じるときに同期しているので何もしない
+    fn sync_file(&self, filename: &str) -> io::Result<()> {
+        match self.open_files.get(filename) {
+            Some(file) => file.sync_all(),
+            None => Ok(()),
+        }
+    }
+
+    // ファイルのブロック数。BlockId::block_numberと同じくi32で数える
+    fn last_block_num(&mut self, filename: &str) -> io::Result<i32> {
         let file = self.get_file(filename)?;
-        Ok(file.metadata().unwrap().len() as usize / self.block_size)
+        Ok(self.block_number_for_offset(file.metadata()?.len()))
+    }
+}
+
+// ファイルの中身をすべてメモリ上に持ち、ディスクには何も書かない。テストで使う
+pub struct MemoryFileManager {
+    pub block_size: usize,
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryFileManager {
+    pub fn new(block_size: usize) -> Self {
+        MemoryFileManager {
+            block_size,
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn byte_offset_for_block(&self, block_number: i32) -> usize {
+        block_number as usize * self.block_size
+    }
+}
+
+impl FileManagerTrait for MemoryFileManager {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn read(&mut self, block_id: &BlockId, page: &mut Page) -> io::Result<()> {
+        let offset = self.byte_offset_for_block(block_id.block_number);
+        let file = self.files.entry(block_id.filename.clone()).or_default();
+        let mut buf = vec![0; self.block_size];
+        if offset < file.len() {
+            let end = file.len().min(offset + self.block_size);
+            buf[..end - offset].copy_from_slice(&file[offset..end]);
+        }
+        *page.contents() = buf;
+        Ok(())
+    }
+
+    fn write(&mut self, block_id: &BlockId, page: &mut Page) -> io::Result<()> {
+        let offset = self.byte_offset_for_block(block_id.block_number);
+        let file = self.files.entry(block_id.filename.clone()).or_default();
+        let contents = page.contents();
+        if file.len() < offset + contents.len() {
+            file.resize(offset + contents.len(), 0);
+        }
+        file[offset..offset + contents.len()].copy_from_slice(contents);
+        Ok(())
+    }
+
+    fn append_new_block(&mut self, filename: &str) -> io::Result<BlockId> {
+        let new_block_num = self.last_block_num(filename)?;
+        let end = self.byte_offset_for_block(new_block_num + 1);
+        self.files
+            .entry(filename.to_string())
+            .or_default()
+            .resize(end, 0);
+        Ok(BlockId {
+            filename: filename.to_string(),
+            block_number: new_block_num,
+        })
+    }
+
+    fn length(&mut self, filename: &str) -> anyhow::Result<i32> {
+        Ok(self.last_block_num(filename)?)
+    }
+
+    fn last_block_num(&mut self, filename: &str) -> io::Result<i32> {
+        let len = self.files.get(filename).map_or(0, |file| file.len());
+        Ok((len / self.block_size) as i32)
+    }
+
+    fn rename_file(&mut self, old_filename: &str, new_filename: &str) -> io::Result<()> {
+        let file = self.files.remove(old_filename).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found", old_filename),
+            )
+        })?;
+        self.files.insert(new_filename.to_string(), file);
+        Ok(())
+    }
+
+    // ファイルがなければ何もしない
+    fn delete_file(&mut self, filename: &str) -> io::Result<()> {
+        self.files.remove(filename);
+        Ok(())
+    }
+
+    fn fsync_directory(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn sync_all(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn sync_file(&self, _filename: &str) -> io::Result<()> {
+        Ok(())
     }
 }
 
@@ -170,6 +674,67 @@ mod tests {
     use super::*;
     use tempfile::Builder;
 
+    #[test]
+    fn file_manager_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<FileManager>();
+        assert_send::<std::sync::Arc<std::sync::Mutex<FileManager>>>();
+        assert_send::<std::sync::Arc<std::sync::Mutex<dyn FileManagerTrait>>>();
+    }
+
+    #[test]
+    fn memory() {
+        let mut file_manager = MemoryFileManager::new(400);
+        let filename = "T.tbl".to_string();
+        assert_eq!(file_manager.length(&filename).unwrap(), 0);
+
+        let block_id = file_manager.append_new_block(&filename).unwrap();
+        assert_eq!(block_id.block_number, 0);
+        assert_eq!(
+            file_manager
+                .append_new_block(&filename)
+                .unwrap()
+                .block_number,
+            1
+        );
+        assert_eq!(file_manager.length(&filename).unwrap(), 2);
+
+        let mut page = Page::new(file_manager.block_size());
+        page.set_string(100, "abc".to_string()).unwrap();
+        page.set_int(396, 345).unwrap();
+        let block_id3 = BlockId {
+            filename: filename.clone(),
+            block_number: 3,
+        };
+        file_manager.write(&block_id3, &mut page).unwrap();
+        assert_eq!(file_manager.last_block_num(&filename).unwrap(), 4);
+
+        let mut read = Page::new(file_manager.block_size());
+        file_manager.read(&block_id3, &mut read).unwrap();
+        assert_eq!(read.get_string(100).unwrap(), "abc");
+        assert_eq!(read.get_int(396).unwrap(), 345);
+
+        // 書いていないブロックもファイル末尾より後ろも0で埋まっている
+        for block_number in [2, 10] {
+            let block_id = BlockId {
+                filename: filename.clone(),
+                block_number,
+            };
+            file_manager.read(&block_id, &mut read).unwrap();
+            assert_eq!(read.contents(), &vec![0; 400]);
+        }
+        assert_eq!(file_manager.last_block_num(&filename).unwrap(), 4);
+
+        file_manager.rename_file(&filename, "U.tbl").unwrap();
+        assert_eq!(file_manager.length(&filename).unwrap(), 0);
+        assert_eq!(file_manager.length("U.tbl").unwrap(), 4);
+        assert!(file_manager.rename_file(&filename, "U.tbl").is_err());
+        file_manager.delete_file("U.tbl").unwrap();
+        file_manager.delete_file("U.tbl").unwrap();
+        assert_eq!(file_manager.length("U.tbl").unwrap(), 0);
+        file_manager.sync_all().unwrap();
+    }
+
     #[test]
     fn disk() {
         let directory = "./data";
@@ -230,8 +795,437 @@ mod tests {
         );
 
         file_manager2.read(&block_id2, &mut page3).unwrap();
-        assert!(page3.get_string(str_position).is_err());
+        assert_eq!(page3.get_string(str_position).unwrap(), "");
 
         drop(tempfile)
     }
+
+    #[test]
+    fn read_single_block() {
+        let directory = "./data";
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let mut file_manager = FileManager::new(directory.to_string());
+
+        let mut page0 = Page::new(file_manager.block_size);
+        page0.set_int(0, 100).unwrap();
+        file_manager
+            .write(&BlockId::new(filename, 0), &mut page0)
+            .unwrap();
+        let mut page1 = Page::new(file_manager.block_size);
+        page1.set_int(0, 200).unwrap();
+        file_manager
+            .write(&BlockId::new(filename, 1), &mut page1)
+            .unwrap();
+
+        let mut page = Page::new(file_manager.block_size);
+        file_manager
+            .read(&BlockId::new(filename, 0), &mut page)
+            .unwrap();
+        assert_eq!(page.contents().len(), file_manager.block_size);
+        assert_eq!(page.get_int(0).unwrap(), 100);
+        assert!(page.get_int(file_manager.block_size).is_err());
+
+        file_manager
+            .read(&BlockId::new(filename, 1), &mut page)
+            .unwrap();
+        assert_eq!(page.contents().len(), file_manager.block_size);
+        assert_eq!(page.get_int(0).unwrap(), 200);
+    }
+
+    #[test]
+    fn bytes() {
+        let mut page = Page::new(PAGE_SIZE);
+        page.set_bytes(100, &[1, 2, 3, 4, 5]).unwrap();
+        page.set_bytes(0, &[9, 8, 7]).unwrap();
+        // 別の位置を読み書きした後でも、指定した位置の値を返す
+        assert_eq!(page.get_bytes(100).unwrap().as_ref(), &[1, 2, 3, 4, 5]);
+        assert_eq!(page.get_bytes(0).unwrap().as_ref(), &[9, 8, 7]);
+        page.get_long(200).unwrap();
+        assert_eq!(page.get_bytes(100).unwrap().as_ref(), &[1, 2, 3, 4, 5]);
+
+        page.set_string(100, "abc".to_string()).unwrap();
+        assert_eq!(page.get_string(100).unwrap(), "abc");
+        assert!(page.get_bytes(PAGE_SIZE - 2).is_err());
+    }
+
+    #[test]
+    fn clone_and_copy_from() {
+        let mut page = Page::new(PAGE_SIZE);
+        page.set_int(0, 42).unwrap();
+        page.set_string(100, "before".to_string()).unwrap();
+        let mut cloned = page.clone();
+        page.set_int(0, 7).unwrap();
+        page.set_string(100, "after".to_string()).unwrap();
+        assert_eq!(cloned.get_int(0).unwrap(), 42);
+        assert_eq!(cloned.get_string(100).unwrap(), "before");
+
+        let mut small = Page::new(16);
+        small.copy_from(&page);
+        assert_eq!(small.contents().len(), PAGE_SIZE);
+        assert_eq!(small.get_int(0).unwrap(), 7);
+        assert_eq!(small.get_string(100).unwrap(), "after");
+        cloned.copy_from(&Page::new(8));
+        assert_eq!(cloned.contents(), &vec![0; 8]);
+    }
+
+    #[test]
+    fn read_write_seek() {
+        #[derive(Debug, PartialEq)]
+        struct Entry {
+            id: u32,
+            name: String,
+            score: f64,
+        }
+
+        impl Entry {
+            fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+                writer.write_all(&self.id.to_le_bytes())?;
+                writer.write_all(&(self.name.len() as u32).to_le_bytes())?;
+                writer.write_all(self.name.as_bytes())?;
+                writer.write_all(&self.score.to_le_bytes())
+            }
+
+            fn decode(reader: &mut impl Read) -> io::Result<Self> {
+                let mut buf = [0; 4];
+                reader.read_exact(&mut buf)?;
+                let id = u32::from_le_bytes(buf);
+                reader.read_exact(&mut buf)?;
+                let mut name = vec![0; u32::from_le_bytes(buf) as usize];
+                reader.read_exact(&mut name)?;
+                let mut score = [0; 8];
+                reader.read_exact(&mut score)?;
+                Ok(Self {
+                    id,
+                    name: String::from_utf8(name).unwrap(),
+                    score: f64::from_le_bytes(score),
+                })
+            }
+        }
+
+        let entries = [
+            Entry {
+                id: 1,
+                name: "alice".to_string(),
+                score: 1.5,
+            },
+            Entry {
+                id: 2,
+                name: "bob".to_string(),
+                score: -0.25,
+            },
+        ];
+        let mut page = Page::new(64);
+        page.seek(SeekFrom::Start(10)).unwrap();
+        for entry in &entries {
+            entry.encode(&mut page).unwrap();
+        }
+        let end = page.stream_position().unwrap();
+        assert_eq!(page.contents().len(), 64);
+
+        page.seek(SeekFrom::Start(10)).unwrap();
+        for entry in &entries {
+            assert_eq!(&Entry::decode(&mut page).unwrap(), entry);
+        }
+        assert_eq!(page.stream_position().unwrap(), end);
+
+        // 型付きのアクセサと同じバイト列を読み書きする
+        page.seek(SeekFrom::Start(0)).unwrap();
+        page.write_all(&7i32.to_be_bytes()).unwrap();
+        assert_eq!(page.get_int(0).unwrap(), 7);
+
+        // 末尾を越えて書き込もうとするとエラーになり、ページは大きくならない
+        page.seek(SeekFrom::End(-2)).unwrap();
+        assert_eq!(page.write(&[1, 2, 3]).unwrap(), 2);
+        assert_eq!(page.write(&[4]).unwrap(), 0);
+        page.seek(SeekFrom::End(-2)).unwrap();
+        assert!(page.write_all(&[1, 2, 3]).is_err());
+        assert_eq!(page.contents().len(), 64);
+        let mut rest = vec![];
+        page.seek(SeekFrom::End(-2)).unwrap();
+        page.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, vec![1, 2]);
+    }
+
+    #[test]
+    fn long() {
+        let mut page = Page::new(PAGE_SIZE);
+        let samples = [i64::MIN, -1, 0, 1, i32::MAX as i64 + 1, i64::MAX];
+        for (i, value) in samples.iter().enumerate() {
+            page.set_long(i * LONG_BYTES + 3, *value).unwrap();
+        }
+        for (i, value) in samples.iter().enumerate() {
+            assert_eq!(page.get_long(i * LONG_BYTES + 3).unwrap(), *value);
+        }
+    }
+
+    #[test]
+    fn float() {
+        let mut page = Page::new(PAGE_SIZE);
+        let samples = [
+            0.0,
+            -0.0,
+            1.5,
+            f64::MIN_POSITIVE / 2.0,
+            f64::MAX,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN,
+        ];
+        for (i, value) in samples.iter().enumerate() {
+            page.set_float(i * FLOAT_BYTES + 1, *value).unwrap();
+        }
+        for (i, value) in samples.iter().enumerate() {
+            let actual = page.get_float(i * FLOAT_BYTES + 1).unwrap();
+            assert_eq!(actual.to_bits(), value.to_bits());
+        }
+        assert!(page.get_float(FLOAT_BYTES * 3 + 1).unwrap().is_subnormal());
+        assert!(page.get_float(FLOAT_BYTES * 7 + 1).unwrap().is_nan());
+    }
+
+    #[test]
+    fn bool() {
+        let mut page = Page::new(PAGE_SIZE);
+        page.set_bool(0, true).unwrap();
+        page.set_bool(1, false).unwrap();
+        assert!(page.get_bool(0).unwrap());
+        assert!(!page.get_bool(1).unwrap());
+
+        page.set_int(8, 2).unwrap();
+        assert!(page.get_bool(11).is_err());
+    }
+
+    #[test]
+    fn dummy_block() {
+        let dummy = BlockId::dummy_for_file("testfile");
+        assert_eq!(dummy.filename, "testfile");
+        assert!(dummy.is_dummy());
+        assert!(!BlockId::new("testfile", 0).is_dummy());
+    }
+
+    #[test]
+    fn varint() {
+        let mut page = Page::new(PAGE_SIZE);
+        let samples = [
+            (0, 1),
+            (1, 1),
+            (-1, 1),
+            (63, 1),
+            (-64, 1),
+            (64, 2),
+            (8191, 2),
+            (8192, 3),
+            (i32::MAX as i64, 5),
+            (i64::MAX, MAX_VARINT_BYTES),
+            (i64::MIN, MAX_VARINT_BYTES),
+        ];
+
+        let mut offset = 10;
+        for (value, size) in samples {
+            assert_eq!(Page::varint_size(value), size);
+            assert_eq!(page.set_varint(offset, value).unwrap(), size);
+            offset += size;
+        }
+
+        let mut offset = 10;
+        for (value, size) in samples {
+            assert_eq!(page.get_varint(offset).unwrap(), (value, size));
+            offset += size;
+        }
+    }
+
+    #[test]
+    fn block_offset_conversion() {
+        let file_manager = FileManager::new("./data".to_string());
+        let block_size = file_manager.block_size as u64;
+
+        for block_number in [0, 1, 2, 1000] {
+            let offset = file_manager.byte_offset_for_block(block_number);
+            assert_eq!(offset, block_number as u64 * block_size);
+            assert_eq!(file_manager.block_number_for_offset(offset), block_number);
+        }
+        assert_eq!(file_manager.block_number_for_offset(block_size - 1), 0);
+        assert_eq!(file_manager.block_number_for_offset(block_size + 1), 1);
+        assert_eq!(file_manager.block_number_for_offset(3 * block_size - 1), 2);
+    }
+
+    #[test]
+    fn rename_file() {
+        let directory = "./data";
+        let tempdir = Builder::new().tempdir_in(directory).unwrap();
+        let mut file_manager = FileManager::new(tempdir.path().to_str().unwrap().to_string());
+
+        let mut page1 = Page::new(file_manager.block_size);
+        page1.set_int(0, 345).unwrap();
+        file_manager
+            .write(&BlockId::new("old", 0), &mut page1)
+            .unwrap();
+
+        file_manager.rename_file("old", "new").unwrap();
+        assert!(!tempdir.path().join("old").exists());
+
+        let mut page2 = Page::new(file_manager.block_size);
+        file_manager
+            .read(&BlockId::new("new", 0), &mut page2)
+            .unwrap();
+        assert_eq!(page2.get_int(0).unwrap(), 345);
+    }
+
+    #[test]
+    fn fsync_directory() {
+        let directory = "./data";
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+
+        let mut file_manager = FileManager::new(directory.to_string());
+        file_manager.full_durability = true;
+        file_manager.append_new_block(filename).unwrap();
+        assert!(file_manager.fsync_directory().is_ok());
+    }
+
+    #[test]
+    fn sync() {
+        let directory = "./data";
+        let tempdir = Builder::new().tempdir_in(directory).unwrap();
+        let mut file_manager = FileManager::new_with_options(
+            tempdir.path().to_str().unwrap().to_string(),
+            FileManagerOptions {
+                max_open_files: 2,
+                ..Default::default()
+            },
+        );
+
+        for (i, filename) in ["file1", "file2", "file3"].iter().enumerate() {
+            let mut page = Page::new(file_manager.block_size);
+            page.set_int(0, i as i32).unwrap();
+            file_manager
+                .write(&BlockId::new(filename, 0), &mut page)
+                .unwrap();
+        }
+        assert_eq!(file_manager.open_files.len(), 2);
+        assert!(file_manager.sync_all().is_ok());
+        for filename in ["file1", "file2", "file3", "missing"] {
+            assert!(file_manager.sync_file(filename).is_ok());
+        }
+    }
+
+    #[test]
+    fn new_with_options() {
+        let directory = "./data";
+        let tempdir = Builder::new().tempdir_in(directory).unwrap();
+        let mut file_manager = FileManager::new_with_options(
+            tempdir.path().to_str().unwrap().to_string(),
+            FileManagerOptions {
+                block_size: 512,
+                max_open_files: 1,
+                codec: Codec::Lz4,
+                fsync_on_write: true,
+                full_durability: true,
+            },
+        );
+
+        for (i, filename) in ["file1", "file2", "file3"].iter().enumerate() {
+            let mut page = Page::new(file_manager.block_size);
+            page.set_int(0, i as i32).unwrap();
+            file_manager
+                .write(&BlockId::new(filename, 1), &mut page)
+                .unwrap();
+            assert_eq!(file_manager.open_files.len(), 1);
+        }
+
+        for (i, filename) in ["file1", "file2", "file3"].iter().enumerate() {
+            let mut page = Page::new(file_manager.block_size);
+            file_manager
+                .read(&BlockId::new(filename, 1), &mut page)
+                .unwrap();
+            assert_eq!(page.get_int(0).unwrap(), i as i32);
+            assert_eq!(file_manager.last_block_num(filename).unwrap(), 1);
+        }
+    }
+
+    #[test]
+    fn codec() {
+        let directory = "./data";
+        for codec in [Codec::None, Codec::Lz4, Codec::Zstd] {
+            let tempfile = Builder::new().tempfile_in(directory).unwrap();
+            let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+
+            let mut file_manager = FileManager::new(directory.to_string());
+            file_manager.codec = codec;
+
+            let block_id = BlockId::new(filename, 1);
+            let text = "compressible text ".repeat(100);
+
+            let mut page1 = Page::new(file_manager.block_size);
+            page1.set_string(200, text.clone()).unwrap();
+            page1.set_int(0, 345).unwrap();
+            file_manager.write(&block_id, &mut page1).unwrap();
+
+            let mut page2 = Page::new(file_manager.block_size);
+            file_manager.read(&block_id, &mut page2).unwrap();
+            assert_eq!(page2.get_int(0).unwrap(), 345);
+            assert_eq!(page2.get_string(200).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn codec_mismatch() {
+        let directory = "./data";
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let block_size = PAGE_SIZE;
+
+        // 圧縮せずに書いたブロック。先頭のintが0でも、0埋めのブロックとしては読まない
+        let mut raw = FileManager::new(directory.to_string());
+        let mut page = Page::new(block_size);
+        page.set_int(100, 7).unwrap();
+        raw.write(&BlockId::new(filename, 0), &mut page).unwrap();
+
+        let mut lz4 = FileManager::new(directory.to_string());
+        lz4.codec = Codec::Lz4;
+        let mut page = Page::new(block_size);
+        let err = lz4.read(&BlockId::new(filename, 0), &mut page).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // 別のcodecで書いたブロックは読めない
+        lz4.write(&BlockId::new(filename, 1), &mut page).unwrap();
+        let mut zstd = FileManager::new(directory.to_string());
+        zstd.codec = Codec::Zstd;
+        let err = zstd
+            .read(&BlockId::new(filename, 1), &mut page)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("Lz4"));
+
+        // ファイル末尾より後ろのブロックは0で埋めて返す
+        page.set_int(0, 1).unwrap();
+        zstd.read(&BlockId::new(filename, 5), &mut page).unwrap();
+        assert_eq!(page.contents(), &vec![0; block_size]);
+    }
+
+    #[test]
+    fn codec_overflow() {
+        let directory = "./data";
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+
+        let mut file_manager = FileManager::new(directory.to_string());
+        file_manager.codec = Codec::Lz4;
+
+        // 圧縮できないデータはブロックに収まらない
+        let mut seed: u32 = 2463534242;
+        let data: Vec<u8> = (0..file_manager.block_size)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        let mut page = Page::from(data.into_boxed_slice());
+        let err = file_manager
+            .write(&BlockId::new(filename, 0), &mut page)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
 }
@@ -0,0 +1,16 @@
+pub mod btree;
+pub mod hash_index;
+
+use crate::record_manager::constant::Constant;
+use crate::record_manager::rid::Rid;
+
+// 検索キーからレコードのRIDを引く索引
+pub trait Index {
+    // search_keyと等しいキーを持つ最初のエントリの手前に移動する
+    fn before_first(&mut self, search_key: Constant) -> anyhow::Result<()>;
+    fn next(&mut self) -> anyhow::Result<bool>;
+    fn get_data_rid(&self) -> anyhow::Result<Rid>;
+    fn insert(&mut self, key: Constant, rid: Rid) -> anyhow::Result<()>;
+    fn delete(&mut self, key: Constant, rid: Rid) -> anyhow::Result<()>;
+    fn close(&mut self) -> anyhow::Result<()>;
+}
@@ -0,0 +1,870 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+
+use crate::file_manager::{BlockId, INTGER_BYTES};
+use crate::record_manager::constant::Constant;
+use crate::record_manager::layout::Layout;
+use crate::record_manager::rid::Rid;
+use crate::record_manager::schema::{FieldInfo, Schema};
+use crate::transaction::transaction::{LogMode, Transaction};
+
+use super::Index;
+
+// ページの先頭にはフラグとレコード数(int)が入り、その後ろにLayoutのスロットが並ぶ
+const FLAG_OFFSET: usize = 0;
+const NUM_RECS_OFFSET: usize = INTGER_BYTES;
+const HEADER_BYTES: usize = 2 * INTGER_BYTES;
+
+// リーフのフラグ。オーバーフローブロックがなければ-1、あればそのブロック番号
+const NO_OVERFLOW: i32 = -1;
+
+const BLOCK_FIELD: &str = "block";
+const ID_FIELD: &str = "id";
+const DATA_VAL_FIELD: &str = "dataval";
+
+// ディレクトリとリーフで共通のページ。レコードはキーの昇順に詰めて並べる
+struct BTPage {
+    transaction: Arc<Mutex<Transaction>>,
+    block_id: BlockId,
+    layout: Arc<Layout>,
+}
+
+impl BTPage {
+    fn new(
+        transaction: Arc<Mutex<Transaction>>,
+        block_id: BlockId,
+        layout: Arc<Layout>,
+    ) -> anyhow::Result<Self> {
+        transaction.lock().unwrap().pin(&block_id)?;
+        Ok(Self {
+            transaction,
+            block_id,
+            layout,
+        })
+    }
+
+    // search_keyより小さいキーを持つ最後のスロット
+    fn find_slot_before(&self, search_key: &Constant) -> anyhow::Result<Option<usize>> {
+        let num_recs = self.num_recs()?;
+        let mut slot = 0;
+        while slot < num_recs && self.data_val(slot)? < *search_key {
+            slot += 1;
+        }
+        Ok(slot.checked_sub(1))
+    }
+
+    fn close(&self) -> anyhow::Result<()> {
+        self.transaction.lock().unwrap().unpin(&self.block_id)
+    }
+
+    // もう1レコード入れる余地がない
+    fn is_full(&self) -> anyhow::Result<bool> {
+        Ok(self.slot_pos(self.num_recs()? + 1) > self.transaction.lock().unwrap().block_size())
+    }
+
+    // split_pos以降のレコードを新しいブロックへ移し、そのブロックを返す
+    fn split(&self, split_pos: usize, flag: i32) -> anyhow::Result<BlockId> {
+        let new_block_id = self.append_new(flag)?;
+        let new_page = BTPage::new(
+            Arc::clone(&self.transaction),
+            new_block_id.clone(),
+            Arc::clone(&self.layout),
+        )?;
+        self.transfer_records(split_pos, &new_page)?;
+        new_page.set_flag(flag)?;
+        new_page.close()?;
+        Ok(new_block_id)
+    }
+
+    fn data_val(&self, slot: usize) -> anyhow::Result<Constant> {
+        self.get_val(slot, DATA_VAL_FIELD)
+    }
+
+    fn flag(&self) -> anyhow::Result<i32> {
+        self.transaction
+            .lock()
+            .unwrap()
+            .get_int(&self.block_id, FLAG_OFFSET as i32)
+    }
+
+    fn set_flag(&self, flag: i32) -> anyhow::Result<()> {
+        self.transaction.lock().unwrap().set_int(
+            &self.block_id,
+            FLAG_OFFSET as i32,
+            flag,
+            LogMode::Log,
+        )
+    }
+
+    fn append_new(&self, flag: i32) -> anyhow::Result<BlockId> {
+        let block_id = self
+            .transaction
+            .lock()
+            .unwrap()
+            .append(&self.block_id.filename)?;
+        self.transaction.lock().unwrap().pin(&block_id)?;
+        self.format(&block_id, flag)?;
+        self.transaction.lock().unwrap().unpin(&block_id)?;
+        Ok(block_id)
+    }
+
+    // 新しいブロックを空のページにして、全スロットを初期値で埋める
+    fn format(&self, block_id: &BlockId, flag: i32) -> anyhow::Result<()> {
+        let mut transaction = self.transaction.lock().unwrap();
+        transaction.set_int(block_id, FLAG_OFFSET as i32, flag, LogMode::NoLog)?;
+        transaction.set_int(block_id, NUM_RECS_OFFSET as i32, 0, LogMode::NoLog)?;
+        let block_size = transaction.block_size();
+        let mut pos = HEADER_BYTES;
+        while pos + self.layout.slot_size() <= block_size {
+            for field_name in self.layout.schema().fields() {
+                let offset = (pos + self.layout.offset(field_name).unwrap()) as i32;
+                let info = self.layout.schema().info(field_name).unwrap();
+                transaction.set_val(
+                    block_id,
+                    offset,
+                    info,
+                    self.layout.encoding(),
+                    info.default_value(),
+                    LogMode::NoLog,
+                )?;
+            }
+            pos += self.layout.slot_size();
+        }
+        Ok(())
+    }
+
+    fn child_num(&self, slot: usize) -> anyhow::Result<i32> {
+        self.get_int(slot, BLOCK_FIELD)
+    }
+
+    fn insert_dir(&self, slot: usize, val: Constant, block_number: i32) -> anyhow::Result<()> {
+        self.insert(slot)?;
+        self.set_val(slot, DATA_VAL_FIELD, val)?;
+        self.set_int(slot, BLOCK_FIELD, block_number)
+    }
+
+    fn data_rid(&self, slot: usize) -> anyhow::Result<Rid> {
+        Ok(Rid::new(
+            self.get_int(slot, BLOCK_FIELD)?,
+            self.get_int(slot, ID_FIELD)? as usize,
+        ))
+    }
+
+    fn insert_leaf(&self, slot: usize, val: Constant, rid: Rid) -> anyhow::Result<()> {
+        self.insert(slot)?;
+        self.set_val(slot, DATA_VAL_FIELD, val)?;
+        self.set_int(slot, BLOCK_FIELD, rid.block_number)?;
+        self.set_int(slot, ID_FIELD, rid.slot_id as i32)
+    }
+
+    // slotより後ろのレコードを1つずつ前に詰める
+    fn delete(&self, slot: usize) -> anyhow::Result<()> {
+        let num_recs = self.num_recs()?;
+        for i in slot + 1..num_recs {
+            self.copy_record(i, i - 1)?;
+        }
+        self.set_num_recs(num_recs - 1)
+    }
+
+    fn num_recs(&self) -> anyhow::Result<usize> {
+        Ok(self
+            .transaction
+            .lock()
+            .unwrap()
+            .get_int(&self.block_id, NUM_RECS_OFFSET as i32)? as usize)
+    }
+
+    fn get_int(&self, slot: usize, field_name: &str) -> anyhow::Result<i32> {
+        let offset = self.field_pos(slot, field_name)?;
+        self.transaction
+            .lock()
+            .unwrap()
+            .get_int(&self.block_id, offset)
+    }
+
+    fn get_val(&self, slot: usize, field_name: &str) -> anyhow::Result<Constant> {
+        let offset = self.field_pos(slot, field_name)?;
+        let mut transaction = self.transaction.lock().unwrap();
+        transaction.get_val(
+            &self.block_id,
+            offset,
+            self.layout.schema().info(field_name).unwrap(),
+            self.layout.encoding(),
+        )
+    }
+
+    fn set_int(&self, slot: usize, field_name: &str, val: i32) -> anyhow::Result<()> {
+        let offset = self.field_pos(slot, field_name)?;
+        self.transaction
+            .lock()
+            .unwrap()
+            .set_int(&self.block_id, offset, val, LogMode::Log)
+    }
+
+    fn set_val(&self, slot: usize, field_name: &str, val: Constant) -> anyhow::Result<()> {
+        let offset = self.field_pos(slot, field_name)?;
+        let mut transaction = self.transaction.lock().unwrap();
+        transaction.set_val(
+            &self.block_id,
+            offset,
+            self.layout.schema().info(field_name).unwrap(),
+            self.layout.encoding(),
+            val,
+            LogMode::Log,
+        )
+    }
+
+    fn set_num_recs(&self, num_recs: usize) -> anyhow::Result<()> {
+        self.transaction.lock().unwrap().set_int(
+            &self.block_id,
+            NUM_RECS_OFFSET as i32,
+            num_recs as i32,
+            LogMode::Log,
+        )
+    }
+
+    // slot以降のレコードを1つずつ後ろにずらして空きを作る
+    fn insert(&self, slot: usize) -> anyhow::Result<()> {
+        let num_recs = self.num_recs()?;
+        for i in (slot + 1..=num_recs).rev() {
+            self.copy_record(i - 1, i)?;
+        }
+        self.set_num_recs(num_recs + 1)
+    }
+
+    fn copy_record(&self, from: usize, to: usize) -> anyhow::Result<()> {
+        for field_name in self.layout.schema().fields() {
+            self.set_val(to, field_name, self.get_val(from, field_name)?)?;
+        }
+        Ok(())
+    }
+
+    fn transfer_records(&self, slot: usize, dest: &BTPage) -> anyhow::Result<()> {
+        let mut dest_slot = 0;
+        while slot < self.num_recs()? {
+            dest.insert(dest_slot)?;
+            for field_name in self.layout.schema().fields() {
+                dest.set_val(dest_slot, field_name, self.get_val(slot, field_name)?)?;
+            }
+            self.delete(slot)?;
+            dest_slot += 1;
+        }
+        Ok(())
+    }
+
+    fn field_pos(&self, slot: usize, field_name: &str) -> anyhow::Result<i32> {
+        let offset = self
+            .layout
+            .offset(field_name)
+            .with_context(|| format!("unknown field {}", field_name))?;
+        Ok((self.slot_pos(slot) + offset) as i32)
+    }
+
+    fn slot_pos(&self, slot: usize) -> usize {
+        HEADER_BYTES + slot * self.layout.slot_size()
+    }
+}
+
+// 子ページの分割で親に追加するエントリ。block_numberのページはdata_val以上のキーを持つ
+#[derive(Debug, Clone, PartialEq)]
+struct DirEntry {
+    data_val: Constant,
+    block_number: i32,
+}
+
+// ディレクトリのページ。フラグはリーフからの高さで、0なら子はリーフ
+struct BTreeDir {
+    transaction: Arc<Mutex<Transaction>>,
+    layout: Arc<Layout>,
+    contents: BTPage,
+    filename: String,
+}
+
+impl BTreeDir {
+    fn new(
+        transaction: Arc<Mutex<Transaction>>,
+        block_id: BlockId,
+        layout: Arc<Layout>,
+    ) -> anyhow::Result<Self> {
+        let filename = block_id.filename.clone();
+        let contents = BTPage::new(Arc::clone(&transaction), block_id, Arc::clone(&layout))?;
+        Ok(Self {
+            transaction,
+            layout,
+            contents,
+            filename,
+        })
+    }
+
+    fn close(&self) -> anyhow::Result<()> {
+        self.contents.close()
+    }
+
+    // search_keyを持ちうるリーフのブロック番号
+    fn search(&mut self, search_key: &Constant) -> anyhow::Result<i32> {
+        let mut child_block_id = self.find_child_block(search_key)?;
+        while self.contents.flag()? > 0 {
+            self.contents.close()?;
+            self.contents = BTPage::new(
+                Arc::clone(&self.transaction),
+                child_block_id,
+                Arc::clone(&self.layout),
+            )?;
+            child_block_id = self.find_child_block(search_key)?;
+        }
+        Ok(child_block_id.block_number)
+    }
+
+    // ルートの中身を新しいブロックへ移し、元のルートと分割された兄弟を子に持つようにする
+    fn make_new_root(&self, entry: DirEntry) -> anyhow::Result<()> {
+        let first_val = self.contents.data_val(0)?;
+        let level = self.contents.flag()?;
+        let new_block_id = self.contents.split(0, level)?;
+        let old_root = DirEntry {
+            data_val: first_val,
+            block_number: new_block_id.block_number,
+        };
+        self.insert_entry(old_root)?;
+        self.insert_entry(entry)?;
+        self.contents.set_flag(level + 1)
+    }
+
+    fn insert(&self, entry: DirEntry) -> anyhow::Result<Option<DirEntry>> {
+        if self.contents.flag()? == 0 {
+            return self.insert_entry(entry);
+        }
+        let child_block_id = self.find_child_block(&entry.data_val)?;
+        let child = BTreeDir::new(
+            Arc::clone(&self.transaction),
+            child_block_id,
+            Arc::clone(&self.layout),
+        )?;
+        let child_entry = child.insert(entry)?;
+        child.close()?;
+        match child_entry {
+            Some(child_entry) => self.insert_entry(child_entry),
+            None => Ok(None),
+        }
+    }
+
+    fn insert_entry(&self, entry: DirEntry) -> anyhow::Result<Option<DirEntry>> {
+        let new_slot = self
+            .contents
+            .find_slot_before(&entry.data_val)?
+            .map_or(0, |slot| slot + 1);
+        self.contents
+            .insert_dir(new_slot, entry.data_val, entry.block_number)?;
+        if !self.contents.is_full()? {
+            return Ok(None);
+        }
+        let level = self.contents.flag()?;
+        let split_pos = self.contents.num_recs()? / 2;
+        let split_val = self.contents.data_val(split_pos)?;
+        let new_block_id = self.contents.split(split_pos, level)?;
+        Ok(Some(DirEntry {
+            data_val: split_val,
+            block_number: new_block_id.block_number,
+        }))
+    }
+
+    fn find_child_block(&self, search_key: &Constant) -> anyhow::Result<BlockId> {
+        let mut slot = self.contents.find_slot_before(search_key)?;
+        let next_slot = slot.map_or(0, |slot| slot + 1);
+        if next_slot < self.contents.num_recs()?
+            && self.contents.data_val(next_slot)? == *search_key
+        {
+            slot = Some(next_slot);
+        }
+        // 先頭のエントリはキーの最小値なので、どのキーもいずれかの子に入る
+        let block_number = self.contents.child_num(slot.unwrap_or(0))?;
+        Ok(BlockId {
+            filename: self.filename.clone(),
+            block_number,
+        })
+    }
+}
+
+// リーフのページ。同じキーがページに収まらなければオーバーフローブロックにつなぐ
+struct BTreeLeaf {
+    transaction: Arc<Mutex<Transaction>>,
+    layout: Arc<Layout>,
+    search_key: Constant,
+    contents: BTPage,
+    current_slot: Option<usize>,
+    filename: String,
+}
+
+impl BTreeLeaf {
+    fn new(
+        transaction: Arc<Mutex<Transaction>>,
+        block_id: BlockId,
+        layout: Arc<Layout>,
+        search_key: Constant,
+    ) -> anyhow::Result<Self> {
+        let filename = block_id.filename.clone();
+        let contents = BTPage::new(Arc::clone(&transaction), block_id, Arc::clone(&layout))?;
+        let current_slot = contents.find_slot_before(&search_key)?;
+        Ok(Self {
+            transaction,
+            layout,
+            search_key,
+            contents,
+            current_slot,
+            filename,
+        })
+    }
+
+    fn close(&self) -> anyhow::Result<()> {
+        self.contents.close()
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        loop {
+            let slot = self.current_slot.map_or(0, |slot| slot + 1);
+            self.current_slot = Some(slot);
+            if slot < self.contents.num_recs()? && self.contents.data_val(slot)? == self.search_key
+            {
+                return Ok(true);
+            }
+            if !self.move_to_overflow()? {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn data_rid(&self) -> anyhow::Result<Rid> {
+        let slot = self
+            .current_slot
+            .with_context(|| format!("index leaf {} has no current entry", self.filename))?;
+        self.contents.data_rid(slot)
+    }
+
+    fn delete(&mut self, rid: Rid) -> anyhow::Result<bool> {
+        while self.next()? {
+            if self.data_rid()? == rid {
+                self.contents.delete(self.current_slot.unwrap())?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // 現在位置の後ろにエントリを加え、ページを分割したら親に追加するエントリを返す
+    fn insert(&mut self, rid: Rid) -> anyhow::Result<Option<DirEntry>> {
+        // オーバーフローしているページより小さいキーは、既存のレコードを全部新しいブロックに移してから入れる
+        if self.contents.flag()? >= 0
+            && self.contents.num_recs()? > 0
+            && self.contents.data_val(0)? > self.search_key
+        {
+            let first_val = self.contents.data_val(0)?;
+            let new_block_id = self.contents.split(0, self.contents.flag()?)?;
+            self.current_slot = Some(0);
+            self.contents.set_flag(NO_OVERFLOW)?;
+            self.contents.insert_leaf(0, self.search_key.clone(), rid)?;
+            return Ok(Some(DirEntry {
+                data_val: first_val,
+                block_number: new_block_id.block_number,
+            }));
+        }
+
+        let slot = self.current_slot.map_or(0, |slot| slot + 1);
+        self.current_slot = Some(slot);
+        self.contents
+            .insert_leaf(slot, self.search_key.clone(), rid)?;
+        if !self.contents.is_full()? {
+            return Ok(None);
+        }
+
+        let num_recs = self.contents.num_recs()?;
+        let first_key = self.contents.data_val(0)?;
+        let last_key = self.contents.data_val(num_recs - 1)?;
+        if last_key == first_key {
+            // すべて同じキーなので、先頭以外をオーバーフローブロックへ移す
+            let new_block_id = self.contents.split(1, self.contents.flag()?)?;
+            self.contents.set_flag(new_block_id.block_number)?;
+            return Ok(None);
+        }
+
+        // 同じキーが2つのページにまたがらない位置で分割する
+        let mut split_pos = num_recs / 2;
+        let mut split_key = self.contents.data_val(split_pos)?;
+        if split_key == first_key {
+            while self.contents.data_val(split_pos)? == split_key {
+                split_pos += 1;
+            }
+            split_key = self.contents.data_val(split_pos)?;
+        } else {
+            while self.contents.data_val(split_pos - 1)? == split_key {
+                split_pos -= 1;
+            }
+        }
+        let new_block_id = self.contents.split(split_pos, NO_OVERFLOW)?;
+        Ok(Some(DirEntry {
+            data_val: split_key,
+            block_number: new_block_id.block_number,
+        }))
+    }
+
+    // search_keyのオーバーフローブロックがあればそこへ移る
+    fn move_to_overflow(&mut self) -> anyhow::Result<bool> {
+        let flag = self.contents.flag()?;
+        if flag < 0 {
+            return Ok(false);
+        }
+        if self.contents.num_recs()? > 0 && self.contents.data_val(0)? != self.search_key {
+            return Ok(false);
+        }
+        self.contents.close()?;
+        let block_id = BlockId {
+            filename: self.filename.clone(),
+            block_number: flag,
+        };
+        self.contents = BTPage::new(
+            Arc::clone(&self.transaction),
+            block_id,
+            Arc::clone(&self.layout),
+        )?;
+        self.current_slot = None;
+        Ok(true)
+    }
+}
+
+// ディレクトリを<name>.dir、リーフを<name>.leafに置くB木の索引
+pub struct BTreeIndex {
+    transaction: Arc<Mutex<Transaction>>,
+    dir_layout: Arc<Layout>,
+    leaf_layout: Arc<Layout>,
+    leaf_filename: String,
+    root_block_id: BlockId,
+    leaf: Option<BTreeLeaf>,
+}
+
+impl BTreeIndex {
+    pub fn new(
+        transaction: Arc<Mutex<Transaction>>,
+        index_name: &str,
+        leaf_layout: Arc<Layout>,
+    ) -> anyhow::Result<Self> {
+        let key_info = *leaf_layout
+            .schema()
+            .info(DATA_VAL_FIELD)
+            .with_context(|| format!("index {} has no {} field", index_name, DATA_VAL_FIELD))?;
+
+        let [leaf_filename, dir_filename] = Self::filenames(index_name);
+        if transaction.lock().unwrap().size(leaf_filename.clone())? == 0 {
+            let block_id = transaction.lock().unwrap().append(&leaf_filename)?;
+            let node = BTPage::new(
+                Arc::clone(&transaction),
+                block_id.clone(),
+                Arc::clone(&leaf_layout),
+            )?;
+            node.format(&block_id, NO_OVERFLOW)?;
+            node.close()?;
+        }
+
+        let mut dir_schema = Schema::new();
+        dir_schema.add_int_field(BLOCK_FIELD.to_string())?;
+        dir_schema.add_field(DATA_VAL_FIELD.to_string(), key_info)?;
+        let dir_layout = Arc::new(Layout::from(dir_schema));
+
+        let root_block_id = BlockId {
+            filename: dir_filename.clone(),
+            block_number: 0,
+        };
+        if transaction.lock().unwrap().size(dir_filename.clone())? == 0 {
+            transaction.lock().unwrap().append(&dir_filename)?;
+            let node = BTPage::new(
+                Arc::clone(&transaction),
+                root_block_id.clone(),
+                Arc::clone(&dir_layout),
+            )?;
+            node.format(&root_block_id, 0)?;
+            // どのキーも最初のリーフに入るよう、キーの最小値のエントリを置く
+            node.insert_dir(0, Self::min_value(key_info), 0)?;
+            node.close()?;
+        }
+
+        Ok(Self {
+            transaction,
+            dir_layout,
+            leaf_layout,
+            leaf_filename,
+            root_block_id,
+            leaf: None,
+        })
+    }
+
+    // リーフとディレクトリのファイル名
+    pub fn filenames(index_name: &str) -> [String; 2] {
+        [
+            format!("{}.leaf", index_name),
+            format!("{}.dir", index_name),
+        ]
+    }
+
+    // 1つのキーを探すのに読むブロック数の見積もり。ディレクトリの高さ+リーフ
+    pub fn search_cost(num_blocks: i32, records_per_block: i32) -> i32 {
+        if num_blocks <= 1 || records_per_block <= 1 {
+            return 1;
+        }
+        1 + ((num_blocks as f64).ln() / (records_per_block as f64).ln()) as i32
+    }
+
+    // キーの型がkey_infoのリーフのLayout
+    pub fn leaf_layout(key_info: FieldInfo) -> Layout {
+        let mut schema = Schema::new();
+        schema.add_int_field(BLOCK_FIELD.to_string()).unwrap();
+        schema.add_int_field(ID_FIELD.to_string()).unwrap();
+        schema
+            .add_field(DATA_VAL_FIELD.to_string(), key_info)
+            .unwrap();
+        Layout::from(schema)
+    }
+
+    fn min_value(key_info: FieldInfo) -> Constant {
+        match key_info {
+            FieldInfo::Int => Constant::Int(i32::MIN),
+            FieldInfo::String(_) => Constant::String(String::new()),
+            FieldInfo::Bool => Constant::Bool(false),
+            FieldInfo::Float => Constant::Float(f64::NEG_INFINITY),
+            FieldInfo::Long => Constant::Long(i64::MIN),
+            FieldInfo::Timestamp => Constant::Timestamp(i64::MIN),
+        }
+    }
+
+    fn leaf(&mut self) -> anyhow::Result<&mut BTreeLeaf> {
+        self.leaf
+            .as_mut()
+            .context("before_first has not been called on the index")
+    }
+}
+
+impl Index for BTreeIndex {
+    fn before_first(&mut self, search_key: Constant) -> anyhow::Result<()> {
+        self.close()?;
+        let mut root = BTreeDir::new(
+            Arc::clone(&self.transaction),
+            self.root_block_id.clone(),
+            Arc::clone(&self.dir_layout),
+        )?;
+        let block_number = root.search(&search_key)?;
+        root.close()?;
+        let leaf_block_id = BlockId {
+            filename: self.leaf_filename.clone(),
+            block_number,
+        };
+        self.leaf = Some(BTreeLeaf::new(
+            Arc::clone(&self.transaction),
+            leaf_block_id,
+            Arc::clone(&self.leaf_layout),
+            search_key,
+        )?);
+        Ok(())
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        self.leaf()?.next()
+    }
+
+    fn get_data_rid(&self) -> anyhow::Result<Rid> {
+        self.leaf
+            .as_ref()
+            .context("before_first has not been called on the index")?
+            .data_rid()
+    }
+
+    fn insert(&mut self, key: Constant, rid: Rid) -> anyhow::Result<()> {
+        self.before_first(key)?;
+        let entry = self.leaf()?.insert(rid)?;
+        self.close()?;
+        let Some(entry) = entry else {
+            return Ok(());
+        };
+        let root = BTreeDir::new(
+            Arc::clone(&self.transaction),
+            self.root_block_id.clone(),
+            Arc::clone(&self.dir_layout),
+        )?;
+        if let Some(root_entry) = root.insert(entry)? {
+            root.make_new_root(root_entry)?;
+        }
+        root.close()
+    }
+
+    fn delete(&mut self, key: Constant, rid: Rid) -> anyhow::Result<()> {
+        self.before_first(key)?;
+        self.leaf()?.delete(rid)?;
+        self.close()
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        if let Some(leaf) = self.leaf.take() {
+            leaf.close()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::buffer_manager::BufferManager;
+    use crate::file_manager::{FileManager, FileManagerOptions, FileManagerTrait};
+    use crate::log_manager::LogManager;
+    use crate::transaction::lock_table::LockTable;
+
+    // リーフに4エントリしか入らない小さなブロックで分割を起こしやすくする
+    fn setup(key_info: FieldInfo) -> (Arc<Mutex<Transaction>>, BTreeIndex, tempfile::TempDir) {
+        let leaf_layout = Arc::new(BTreeIndex::leaf_layout(key_info));
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let log_file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(log_file_manager, log_filename.to_string()).unwrap(),
+        ));
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new_with_options(
+                directory.to_string(),
+                FileManagerOptions {
+                    block_size: HEADER_BYTES + 4 * leaf_layout.slot_size(),
+                    ..Default::default()
+                },
+            )));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            16,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let transaction = Arc::new(Mutex::new(Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        )));
+
+        let index_tempdir = Builder::new().tempdir_in(directory).unwrap();
+        let index_name = format!(
+            "{}/I",
+            index_tempdir.path().file_name().unwrap().to_str().unwrap()
+        );
+        let index = BTreeIndex::new(Arc::clone(&transaction), &index_name, leaf_layout).unwrap();
+        (transaction, index, index_tempdir)
+    }
+
+    fn lookup(index: &mut BTreeIndex, key: Constant) -> Vec<Rid> {
+        let mut rids = vec![];
+        index.before_first(key).unwrap();
+        while index.next().unwrap() {
+            rids.push(index.get_data_rid().unwrap());
+        }
+        index.close().unwrap();
+        rids
+    }
+
+    #[test]
+    fn insert_and_lookup() {
+        let (transaction, mut index, _index_tempdir) = setup(FieldInfo::Int);
+
+        // 順番を入れ替えて挿入する
+        for n in 0..200 {
+            let key = (n * 37) % 200;
+            index
+                .insert(Constant::Int(key), Rid::new(key, key as usize))
+                .unwrap();
+        }
+        // リーフもディレクトリも分割されている
+        let leaf_filename = index.leaf_filename.clone();
+        let dir_filename = index.root_block_id.filename.clone();
+        assert!(transaction.lock().unwrap().size(leaf_filename).unwrap() > 1);
+        assert!(transaction.lock().unwrap().size(dir_filename).unwrap() > 1);
+
+        for key in 0..200 {
+            assert_eq!(
+                lookup(&mut index, Constant::Int(key)),
+                vec![Rid::new(key, key as usize)]
+            );
+        }
+        assert!(lookup(&mut index, Constant::Int(200)).is_empty());
+        assert!(lookup(&mut index, Constant::Int(-1)).is_empty());
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let (transaction, mut index, _index_tempdir) = setup(FieldInfo::Int);
+
+        // 1ページに収まらない数の同じキーはオーバーフローブロックにつながる
+        for n in 0..30 {
+            index.insert(Constant::Int(n), Rid::new(n, 0)).unwrap();
+            index
+                .insert(Constant::Int(10), Rid::new(100, n as usize))
+                .unwrap();
+        }
+
+        // 続けてnextを呼ぶと、同じキーのエントリがすべて返る
+        let rids: HashSet<Rid> = lookup(&mut index, Constant::Int(10)).into_iter().collect();
+        let mut expected: HashSet<Rid> = (0..30).map(|n| Rid::new(100, n)).collect();
+        expected.insert(Rid::new(10, 0));
+        assert_eq!(rids, expected);
+
+        for n in (0..30).filter(|n| *n != 10) {
+            assert_eq!(lookup(&mut index, Constant::Int(n)), vec![Rid::new(n, 0)]);
+        }
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn delete() {
+        let (transaction, mut index, _index_tempdir) = setup(FieldInfo::Int);
+
+        for n in 0..100 {
+            index.insert(Constant::Int(n), Rid::new(n, 0)).unwrap();
+        }
+        for n in (0..100).step_by(2) {
+            index.delete(Constant::Int(n), Rid::new(n, 0)).unwrap();
+        }
+        // RIDが一致しなければ消えない
+        index.delete(Constant::Int(1), Rid::new(1, 1)).unwrap();
+
+        for n in 0..100 {
+            let rids = lookup(&mut index, Constant::Int(n));
+            if n % 2 == 0 {
+                assert!(rids.is_empty());
+            } else {
+                assert_eq!(rids, vec![Rid::new(n, 0)]);
+            }
+        }
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn string_keys() {
+        let (transaction, mut index, _index_tempdir) = setup(FieldInfo::String(8));
+
+        for n in (0..100).rev() {
+            index
+                .insert(Constant::from(format!("key{:03}", n)), Rid::new(n, 0))
+                .unwrap();
+        }
+        for n in 0..100 {
+            assert_eq!(
+                lookup(&mut index, Constant::from(format!("key{:03}", n))),
+                vec![Rid::new(n, 0)]
+            );
+        }
+        assert!(lookup(&mut index, Constant::from("missing")).is_empty());
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
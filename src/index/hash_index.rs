@@ -0,0 +1,278 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context};
+
+use crate::record_manager::constant::Constant;
+use crate::record_manager::layout::Layout;
+use crate::record_manager::rid::Rid;
+use crate::record_manager::scan::{Scan, UpdateScan};
+use crate::record_manager::schema::{FieldInfo, Schema};
+use crate::record_manager::table_scan::TableScan;
+use crate::transaction::transaction::Transaction;
+
+use super::Index;
+
+pub const NUM_BUCKETS: usize = 100;
+
+const BLOCK_FIELD: &str = "block";
+const ID_FIELD: &str = "id";
+const DATA_VAL_FIELD: &str = "dataval";
+
+// キーのハッシュ値でバケットを選ぶ静的ハッシュの索引。等号での検索にしか使えない。
+// バケットiは<name>i.idxに置く
+pub struct HashIndex {
+    transaction: Arc<Mutex<Transaction>>,
+    index_name: String,
+    layout: Arc<Layout>,
+    num_buckets: usize,
+    search_key: Option<Constant>,
+    table_scan: Option<TableScan>,
+    current_rid: Option<Rid>,
+}
+
+impl HashIndex {
+    pub fn new(
+        transaction: Arc<Mutex<Transaction>>,
+        index_name: &str,
+        layout: Arc<Layout>,
+    ) -> anyhow::Result<Self> {
+        Self::new_with_buckets(transaction, index_name, layout, NUM_BUCKETS)
+    }
+
+    pub fn new_with_buckets(
+        transaction: Arc<Mutex<Transaction>>,
+        index_name: &str,
+        layout: Arc<Layout>,
+        num_buckets: usize,
+    ) -> anyhow::Result<Self> {
+        if num_buckets == 0 {
+            bail!("index {} needs at least one bucket", index_name);
+        }
+        layout
+            .schema()
+            .info(DATA_VAL_FIELD)
+            .with_context(|| format!("index {} has no {} field", index_name, DATA_VAL_FIELD))?;
+        Ok(Self {
+            transaction,
+            index_name: index_name.to_string(),
+            layout,
+            num_buckets,
+            search_key: None,
+            table_scan: None,
+            current_rid: None,
+        })
+    }
+
+    // キーの型がkey_infoのバケットのLayout
+    pub fn layout(key_info: FieldInfo) -> Layout {
+        let mut schema = Schema::new();
+        schema.add_int_field(BLOCK_FIELD.to_string()).unwrap();
+        schema.add_int_field(ID_FIELD.to_string()).unwrap();
+        schema
+            .add_field(DATA_VAL_FIELD.to_string(), key_info)
+            .unwrap();
+        Layout::from(schema)
+    }
+
+    // 1つのキーを探すのに読むブロック数の見積もり。1つのバケットをすべて読む
+    pub fn search_cost(num_blocks: i32, num_buckets: usize) -> i32 {
+        (num_blocks / num_buckets as i32).max(1)
+    }
+
+    pub fn bucket_filename(&self, bucket: usize) -> String {
+        format!("{}{}.idx", self.index_name, bucket)
+    }
+
+    fn bucket(&self, key: &Constant) -> usize {
+        (Self::hash(key) % self.num_buckets as u64) as usize
+    }
+
+    // ファイルに残る値なので、実行ごとに変わりうる標準のハッシュ関数は使わずFNV-1aで計算する
+    fn hash(key: &Constant) -> u64 {
+        let bytes = match key {
+            Constant::Int(v) => v.to_le_bytes().to_vec(),
+            Constant::String(v) => v.as_bytes().to_vec(),
+            Constant::Bool(v) => vec![*v as u8],
+            // -0.0 == 0.0なので同じバケットに入れる
+            Constant::Float(v) => (if *v == 0.0 { 0.0f64 } else { *v })
+                .to_bits()
+                .to_le_bytes()
+                .to_vec(),
+            Constant::Long(v) | Constant::Timestamp(v) => v.to_le_bytes().to_vec(),
+        };
+        bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    fn table_scan(&mut self) -> anyhow::Result<&mut TableScan> {
+        self.table_scan
+            .as_mut()
+            .context("before_first has not been called on the index")
+    }
+}
+
+impl Index for HashIndex {
+    fn before_first(&mut self, search_key: Constant) -> anyhow::Result<()> {
+        self.close()?;
+        let filename = self.bucket_filename(self.bucket(&search_key));
+        self.table_scan = Some(TableScan::new_with_filename(
+            Arc::clone(&self.transaction),
+            filename,
+            Arc::clone(&self.layout),
+        )?);
+        self.search_key = Some(search_key);
+        Ok(())
+    }
+
+    // バケットには他のキーも入っているので、検索キーと等しいものだけを返す
+    fn next(&mut self) -> anyhow::Result<bool> {
+        let search_key = self
+            .search_key
+            .clone()
+            .context("before_first has not been called on the index")?;
+        self.current_rid = None;
+        let table_scan = self.table_scan()?;
+        while table_scan.next()? {
+            if table_scan.get_val(DATA_VAL_FIELD)? == search_key {
+                let block_number = table_scan.get_int(BLOCK_FIELD)?;
+                let slot_id = table_scan.get_int(ID_FIELD)? as usize;
+                self.current_rid = Some(Rid::new(block_number, slot_id));
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn get_data_rid(&self) -> anyhow::Result<Rid> {
+        self.current_rid.context("index has no current entry")
+    }
+
+    fn insert(&mut self, key: Constant, rid: Rid) -> anyhow::Result<()> {
+        self.before_first(key.clone())?;
+        let table_scan = self.table_scan()?;
+        table_scan.insert()?;
+        table_scan.set_int(BLOCK_FIELD, rid.block_number)?;
+        table_scan.set_int(ID_FIELD, rid.slot_id as i32)?;
+        table_scan.set_val(DATA_VAL_FIELD, key)?;
+        self.close()
+    }
+
+    fn delete(&mut self, key: Constant, rid: Rid) -> anyhow::Result<()> {
+        self.before_first(key)?;
+        while self.next()? {
+            if self.get_data_rid()? == rid {
+                self.table_scan()?.delete()?;
+                break;
+            }
+        }
+        self.close()
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.search_key = None;
+        self.current_rid = None;
+        if let Some(mut table_scan) = self.table_scan.take() {
+            table_scan.close()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+
+    fn lookup(index: &mut HashIndex, key: Constant) -> Vec<Rid> {
+        let mut rids = vec![];
+        index.before_first(key).unwrap();
+        while index.next().unwrap() {
+            rids.push(index.get_data_rid().unwrap());
+        }
+        index.close().unwrap();
+        rids
+    }
+
+    #[test]
+    fn insert_and_lookup() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        let layout = Arc::new(HashIndex::layout(FieldInfo::Int));
+        let mut index = HashIndex::new(Arc::clone(&transaction), "I", layout).unwrap();
+
+        // 順番を入れ替えて挿入する
+        for n in 0..1000 {
+            let key = (n * 37) % 1000;
+            index
+                .insert(Constant::Int(key), Rid::new(key / 10, (key % 10) as usize))
+                .unwrap();
+        }
+        for key in 0..1000 {
+            assert_eq!(
+                lookup(&mut index, Constant::Int(key)),
+                vec![Rid::new(key / 10, (key % 10) as usize)]
+            );
+        }
+        assert!(lookup(&mut index, Constant::Int(1000)).is_empty());
+        assert!(lookup(&mut index, Constant::Int(-1)).is_empty());
+
+        // キーは複数のバケットに散らばる
+        let buckets: HashSet<usize> = (0..1000).map(|n| index.bucket(&Constant::Int(n))).collect();
+        assert!(buckets.len() > NUM_BUCKETS / 2);
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn duplicate_keys_and_delete() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        let layout = Arc::new(HashIndex::layout(FieldInfo::String(8)));
+        let mut index =
+            HashIndex::new_with_buckets(Arc::clone(&transaction), "I", layout, 4).unwrap();
+
+        for n in 0..30 {
+            index
+                .insert(Constant::from(format!("key{}", n % 3)), Rid::new(n, 0))
+                .unwrap();
+        }
+        // RIDが一致しなければ消えない
+        index
+            .delete(Constant::from("key0"), Rid::new(1, 0))
+            .unwrap();
+        for n in (0..30).step_by(2) {
+            index
+                .delete(Constant::from(format!("key{}", n % 3)), Rid::new(n, 0))
+                .unwrap();
+        }
+
+        for k in 0..3 {
+            let rids: HashSet<Rid> = lookup(&mut index, Constant::from(format!("key{}", k)))
+                .into_iter()
+                .collect();
+            let expected: HashSet<Rid> = (0..30)
+                .filter(|n| n % 3 == k && n % 2 == 1)
+                .map(|n| Rid::new(n, 0))
+                .collect();
+            assert_eq!(rids, expected);
+        }
+        assert!(lookup(&mut index, Constant::from("missing")).is_empty());
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
@@ -1,5 +1,11 @@
 mod buffer_manager;
+mod catalog;
 mod error;
 mod file_manager;
+mod index;
 mod log_manager;
+mod mydb;
+mod planner;
+mod record_manager;
+mod sql;
 mod transaction;
@@ -1,64 +1,199 @@
 use std::{
     io,
-    sync::{Arc, Mutex},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
 };
 
-use crate::file_manager::{BlockId, FileManager, Page, INTGER_BYTES};
+use crate::file_manager::{BlockId, FileManagerTrait, Page, INTGER_BYTES, LONG_BYTES};
+
+// ブロックの先頭には、境界の位置とそのブロックの最初のレコードのLSNを置く
+const FIRST_LSN_OFFSET: usize = INTGER_BYTES;
+const LOG_HEADER_BYTES: usize = INTGER_BYTES + LONG_BYTES;
 
 pub struct LogManager {
-    file_manager: Arc<Mutex<FileManager>>,
+    file_manager: Arc<Mutex<dyn FileManagerTrait>>,
     log_file: String,
     log_page: Page,
     current_block: BlockId,
-    latest_log_sequence_number: i32,
-    last_saved_log_sequence_number: i32,
+    latest_log_sequence_number: i64,
+    last_saved_log_sequence_number: i64,
+    // (block_number, そのブロックに書き込んだ最初のLSN)
+    block_lsns: Vec<(i32, i64)>,
+    // group commitが有効なら、バックグラウンドスレッドがこの間隔でflushする
+    group_commit_interval: Option<Duration>,
+    // バックグラウンドスレッドが動いているか。enable_group_commitで二重に起動しないために使う
+    group_commit_running: bool,
+    // バックグラウンドスレッドのflushが失敗したときのエラー
+    group_commit_error: Option<io::Error>,
+    flushed: Arc<Condvar>,
 }
 
 impl LogManager {
-    pub fn new(mut file_manager: FileManager, log_file: String) -> io::Result<Self> {
-        let log_size = file_manager.last_block_num(&log_file)?;
-        let mut log_page = Page::new(file_manager.block_size);
+    pub fn new(
+        file_manager: Arc<Mutex<dyn FileManagerTrait>>,
+        log_file: String,
+    ) -> io::Result<Self> {
+        let mut locked_file_manager = file_manager.lock().unwrap();
+        let log_size = locked_file_manager.last_block_num(&log_file)?;
+        let block_size = locked_file_manager.block_size();
+        let mut log_page = Page::new(block_size);
+        let mut block_lsns = vec![];
+        let mut latest_lsn = 1;
 
         let current_block = if log_size == 0 {
-            let block_id = file_manager.append_new_block(&log_file)?;
-            log_page.set_int(0, file_manager.block_size as i32)?;
-            file_manager.write(&block_id, &mut log_page)?;
+            let block_id = locked_file_manager.append_new_block(&log_file)?;
+            log_page.set_int(0, block_size as i32)?;
+            locked_file_manager.write(&block_id, &mut log_page)?;
             block_id
         } else {
+            // 各ブロックの最初のLSNとレコード数から、前回の続きのLSNを求める
+            for block_number in 0..log_size {
+                log_page = Page::new(block_size);
+                let block_id = BlockId {
+                    filename: log_file.clone(),
+                    block_number,
+                };
+                locked_file_manager.read(&block_id, &mut log_page)?;
+                let count = Self::record_count(&mut log_page, block_size)?;
+                if count > 0 {
+                    let first_lsn = log_page.get_long(FIRST_LSN_OFFSET)?;
+                    block_lsns.push((block_number, first_lsn));
+                    latest_lsn = first_lsn + count as i64 - 1;
+                }
+            }
             BlockId {
                 filename: log_file.clone(),
-                block_number: (log_size - 1) as i32,
+                block_number: log_size - 1,
             }
         };
+        drop(locked_file_manager);
         Ok(LogManager {
-            file_manager: Arc::new(Mutex::new(file_manager)),
+            file_manager,
             log_file,
             log_page,
             current_block,
-            latest_log_sequence_number: 1,
-            last_saved_log_sequence_number: 1,
+            latest_log_sequence_number: latest_lsn,
+            last_saved_log_sequence_number: latest_lsn,
+            block_lsns,
+            group_commit_interval: None,
+            group_commit_running: false,
+            group_commit_error: None,
+            flushed: Arc::new(Condvar::new()),
         })
     }
 
-    pub fn flush_with(&mut self, lsn: i32) -> io::Result<()> {
+    pub fn flush_with(&mut self, lsn: i64) -> io::Result<()> {
         if lsn > self.last_saved_log_sequence_number {
             self.flush()?;
         }
         Ok(())
     }
 
+    // 有効にすると、flush_with_groupはすぐには書き込まず、バックグラウンドスレッドが
+    // intervalごとにまとめてflushするのを待つ
+    pub fn enable_group_commit(log_manager: &Arc<Mutex<LogManager>>, interval: Duration) {
+        let mut locked_log_manager = log_manager.lock().unwrap();
+        locked_log_manager.group_commit_interval = Some(interval);
+        locked_log_manager.group_commit_error = None;
+        // 無効にした直後でも、前のスレッドがまだ動いていればそれを使い続ける
+        if locked_log_manager.group_commit_running {
+            return;
+        }
+        locked_log_manager.group_commit_running = true;
+        drop(locked_log_manager);
+
+        // LogManagerが破棄されたらスレッドも終わる
+        let log_manager = Arc::downgrade(log_manager);
+        thread::spawn(move || loop {
+            let interval = match log_manager.upgrade() {
+                Some(log_manager) => {
+                    let mut locked_log_manager = log_manager.lock().unwrap();
+                    match locked_log_manager.group_commit_interval {
+                        Some(interval) => interval,
+                        None => {
+                            locked_log_manager.group_commit_running = false;
+                            return;
+                        }
+                    }
+                }
+                None => return,
+            };
+            thread::sleep(interval);
+            let log_manager = match log_manager.upgrade() {
+                Some(log_manager) => log_manager,
+                None => return,
+            };
+            let mut locked_log_manager = log_manager.lock().unwrap();
+            if locked_log_manager.latest_log_sequence_number
+                > locked_log_manager.last_saved_log_sequence_number
+            {
+                // 失敗したらエラーを残してgroup commitをやめる。待っている側は自分でflushし直す
+                if let Err(e) = locked_log_manager.flush() {
+                    locked_log_manager.group_commit_error = Some(e);
+                    locked_log_manager.group_commit_interval = None;
+                    locked_log_manager.group_commit_running = false;
+                    locked_log_manager.flushed.notify_all();
+                    return;
+                }
+            }
+            locked_log_manager.flushed.notify_all();
+        });
+    }
+
+    pub fn disable_group_commit(&mut self) -> io::Result<()> {
+        self.group_commit_interval = None;
+        self.flush()?;
+        self.flushed.notify_all();
+        Ok(())
+    }
+
+    // バックグラウンドスレッドのflushが失敗して、group commitが止まっていればそのエラー
+    pub fn group_commit_error(&self) -> Option<&io::Error> {
+        self.group_commit_error.as_ref()
+    }
+
+    // group commitが無効ならflush_withと同じ。有効ならlsnまでflushされるのを待つ。
+    // バックグラウンドスレッドが2回分の間隔を過ぎてもflushしなければ、待たずに自分でflushする
+    pub fn flush_with_group(log_manager: &Mutex<LogManager>, lsn: i64) -> io::Result<()> {
+        let mut locked_log_manager = log_manager.lock().unwrap();
+        let flushed = Arc::clone(&locked_log_manager.flushed);
+        while lsn > locked_log_manager.last_saved_log_sequence_number {
+            let interval = match locked_log_manager.group_commit_interval {
+                Some(interval) => interval,
+                None => return locked_log_manager.flush_with(lsn),
+            };
+            let (guard, result) = flushed
+                .wait_timeout(locked_log_manager, interval * 2)
+                .unwrap();
+            locked_log_manager = guard;
+            if result.timed_out() {
+                return locked_log_manager.flush_with(lsn);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn last_flushed_lsn(&self) -> i64 {
+        self.last_saved_log_sequence_number
+    }
+
+    pub fn latest_lsn(&self) -> i64 {
+        self.latest_log_sequence_number
+    }
+
     pub fn iterator(&mut self) -> io::Result<LogIterator> {
         self.flush().unwrap();
         LogIterator::new(self.file_manager.clone(), self.current_block.clone())
     }
 
-    pub fn append_record(&mut self, log_record: &[u8]) -> io::Result<i32> {
+    pub fn append_record(&mut self, log_record: &[u8]) -> io::Result<i64> {
         //
         let mut boundary = self.get_boundary();
         let record_size = log_record.len();
         let bytes_needed = record_size + INTGER_BYTES;
 
-        if (boundary - bytes_needed as i32) < (INTGER_BYTES as i32) {
+        if (boundary - bytes_needed as i32) < (LOG_HEADER_BYTES as i32) {
             self.flush()?;
             self.current_block = self.append_new_block()?;
             boundary = self.get_boundary();
@@ -68,12 +203,149 @@ impl LogManager {
         self.log_page.set_bytes(record_pos, log_record)?;
         self.log_page.set_int(0, record_pos as i32)?;
 
-        self.last_saved_log_sequence_number += 1;
-        Ok(self.last_saved_log_sequence_number)
+        self.latest_log_sequence_number += 1;
+        let block_number = self.current_block.block_number;
+        if self.block_lsns.last().map(|(b, _)| *b) != Some(block_number) {
+            // 開き直したときにLSNを振り直せるよう、ブロックの最初のLSNを残しておく
+            self.log_page
+                .set_long(FIRST_LSN_OFFSET, self.latest_log_sequence_number)?;
+            self.block_lsns
+                .push((block_number, self.latest_log_sequence_number));
+        }
+        Ok(self.latest_log_sequence_number)
+    }
+
+    pub fn read_record_at_lsn(&mut self, lsn: i64) -> io::Result<Box<[u8]>> {
+        let not_found = || {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("lsn {} is not in the log", lsn),
+            )
+        };
+        if lsn > self.latest_log_sequence_number {
+            return Err(not_found());
+        }
+        let index = self
+            .block_lsns
+            .iter()
+            .rposition(|(_, first_lsn)| *first_lsn <= lsn)
+            .ok_or_else(not_found)?;
+        let (block_number, _) = self.block_lsns[index];
+        // ブロック内のレコードは新しい順に並んでいる
+        let last_lsn = match self.block_lsns.get(index + 1) {
+            Some((_, next_lsn)) => next_lsn - 1,
+            None => self.latest_log_sequence_number,
+        };
+
+        self.flush()?;
+        let block_id = BlockId {
+            filename: self.log_file.clone(),
+            block_number,
+        };
+        LogIterator::new(self.file_manager.clone(), block_id)?
+            .nth((last_lsn - lsn) as usize)
+            .ok_or_else(not_found)
+    }
+
+    // checkpoint_lsnより前のレコードだけを含むブロックを捨てて、ログファイルを作り直す。
+    // 作り直している間はfile_managerのlockを持ち続ける
+    pub fn compact(&mut self, checkpoint_lsn: i64) -> io::Result<()> {
+        self.flush()?;
+        let index = match self
+            .block_lsns
+            .iter()
+            .rposition(|(_, first_lsn)| *first_lsn <= checkpoint_lsn)
+        {
+            Some(index) => index,
+            // ログに残っているどのレコードよりも前なので、捨てるものはない
+            None => return Ok(()),
+        };
+        let (first_block, _) = self.block_lsns[index];
+        let last_lsn_in_first_block = match self.block_lsns.get(index + 1) {
+            Some((_, next_lsn)) => next_lsn - 1,
+            None => self.latest_log_sequence_number,
+        };
+
+        let mut locked_file_manager = self.file_manager.lock().unwrap();
+        let block_size = locked_file_manager.block_size();
+        let compact_file = format!("{}.compact", self.log_file);
+        locked_file_manager.delete_file(&compact_file)?;
+
+        let mut page = Page::new(block_size);
+        for block_number in first_block..=self.current_block.block_number {
+            page = Page::new(block_size);
+            locked_file_manager.read(
+                &BlockId {
+                    filename: self.log_file.clone(),
+                    block_number,
+                },
+                &mut page,
+            )?;
+            if block_number == first_block {
+                let keep = (last_lsn_in_first_block - checkpoint_lsn + 1) as usize;
+                page = Self::drop_older_records(page, keep, checkpoint_lsn, block_size)?;
+            }
+            locked_file_manager.write(
+                &BlockId {
+                    filename: compact_file.clone(),
+                    block_number: block_number - first_block,
+                },
+                &mut page,
+            )?;
+        }
+        locked_file_manager.rename_file(&compact_file, &self.log_file)?;
+        drop(locked_file_manager);
+
+        self.log_page = page;
+        self.current_block = BlockId {
+            filename: self.log_file.clone(),
+            block_number: self.current_block.block_number - first_block,
+        };
+        self.block_lsns = self.block_lsns[index..]
+            .iter()
+            .map(|(block_number, first_lsn)| (block_number - first_block, *first_lsn))
+            .collect();
+        self.block_lsns[0].1 = checkpoint_lsn;
+        Ok(())
+    }
+
+    // ブロック内のレコードは新しい順に並んでいるので、新しい方からkeep個を残して末尾に詰め直す。
+    // 残した中で最も古いレコードのLSNがfirst_lsnになる
+    fn drop_older_records(
+        mut page: Page,
+        keep: usize,
+        first_lsn: i64,
+        block_size: usize,
+    ) -> io::Result<Page> {
+        let boundary = page.get_int(0)? as usize;
+        let mut pos = boundary;
+        for _ in 0..keep {
+            let record = page.get_bytes(pos)?;
+            pos += INTGER_BYTES + record.len();
+        }
+        let kept = page.contents()[boundary..pos].to_vec();
+
+        let mut compacted = Page::new(block_size);
+        let new_boundary = block_size - kept.len();
+        compacted.contents()[new_boundary..].copy_from_slice(&kept);
+        compacted.set_int(0, new_boundary as i32)?;
+        compacted.set_long(FIRST_LSN_OFFSET, first_lsn)?;
+        Ok(compacted)
+    }
+
+    fn record_count(page: &mut Page, block_size: usize) -> io::Result<usize> {
+        let mut pos = page.get_int(0)? as usize;
+        let mut count = 0;
+        while pos < block_size {
+            let record = page.get_bytes(pos)?;
+            pos += INTGER_BYTES + record.len();
+            count += 1;
+        }
+        Ok(count)
     }
 
     fn append_new_block(&mut self) -> io::Result<BlockId> {
-        self.log_page = Page::new(self.file_manager.lock().unwrap().block_size);
+        self.log_page = Page::new(self.file_manager.lock().unwrap().block_size());
         let block_id = self
             .file_manager
             .lock()
@@ -94,22 +366,24 @@ impl LogManager {
 
     fn set_boundary(&mut self) {
         self.log_page
-            .set_int(0, self.file_manager.lock().unwrap().block_size as i32)
+            .set_int(0, self.file_manager.lock().unwrap().block_size() as i32)
             .expect("set boundary")
     }
 
+    // コミットの永続性はログにかかっているので、書き込んだらログファイルを同期する。
+    // データファイルはログから復旧できるのでここでは同期しない
     fn flush(&mut self) -> io::Result<()> {
-        self.file_manager
-            .lock()
-            .unwrap()
-            .write(&mut self.current_block, &mut self.log_page)?;
+        let mut file_manager = self.file_manager.lock().unwrap();
+        file_manager.write(&mut self.current_block, &mut self.log_page)?;
+        file_manager.sync_file(&self.log_file)?;
+        drop(file_manager);
         self.last_saved_log_sequence_number = self.latest_log_sequence_number;
         Ok(())
     }
 }
 
 pub struct LogIterator {
-    file_manager: Arc<Mutex<FileManager>>,
+    file_manager: Arc<Mutex<dyn FileManagerTrait>>,
     block_id: BlockId,
     page: Page,
     current_pos: usize,
@@ -117,8 +391,11 @@ pub struct LogIterator {
 }
 
 impl LogIterator {
-    pub fn new(file_manager: Arc<Mutex<FileManager>>, block_id: BlockId) -> io::Result<Self> {
-        let buf: Vec<u8> = Vec::with_capacity(file_manager.lock().unwrap().block_size);
+    pub fn new(
+        file_manager: Arc<Mutex<dyn FileManagerTrait>>,
+        block_id: BlockId,
+    ) -> io::Result<Self> {
+        let buf: Vec<u8> = Vec::with_capacity(file_manager.lock().unwrap().block_size());
         let mut log_itertor = LogIterator {
             file_manager,
             block_id: block_id.clone(),
@@ -132,7 +409,7 @@ impl LogIterator {
     }
 
     fn move_to_block(&mut self, block_id: &BlockId) -> io::Result<()> {
-        self.page = Page::new(self.file_manager.lock().unwrap().block_size);
+        self.page = Page::new(self.file_manager.lock().unwrap().block_size());
         self.file_manager
             .lock()
             .unwrap()
@@ -147,13 +424,13 @@ impl Iterator for LogIterator {
     type Item = Box<[u8]>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current_pos >= self.file_manager.lock().unwrap().block_size
+        if self.current_pos >= self.file_manager.lock().unwrap().block_size()
             && self.block_id.block_number <= 0
         {
             return None;
         }
 
-        if self.current_pos == self.file_manager.lock().unwrap().block_size {
+        if self.current_pos == self.file_manager.lock().unwrap().block_size() {
             let block_id = BlockId {
                 filename: self.block_id.filename.clone(),
                 block_number: self.block_id.block_number - 1,
@@ -170,6 +447,9 @@ impl Iterator for LogIterator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::file_manager::{FileManager, FileManagerOptions, MemoryFileManager};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
     use tempfile::Builder;
 
     #[test]
@@ -177,7 +457,8 @@ mod tests {
         let directory = "./data";
         let tempfile = Builder::new().tempfile_in(directory).unwrap();
         let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
-        let file_manager = FileManager::new(directory.to_string());
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
         let mut log_manager = LogManager::new(file_manager, filename.to_string()).unwrap();
 
         for n in 0..35 {
@@ -203,4 +484,421 @@ mod tests {
             page.contents().to_owned()
         }
     }
+
+    #[test]
+    fn read_record_at_lsn() {
+        let directory = "./data";
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let mut log_manager = LogManager::new(file_manager, filename.to_string()).unwrap();
+
+        let records: Vec<(i64, String)> = (0..50)
+            .map(|n| {
+                let record = format!("{:0>200}", n);
+                let mut page = Page::new(Page::max_length(record.len()));
+                page.set_string(0, record.clone()).unwrap();
+                let lsn = log_manager.append_record(page.contents()).unwrap();
+                (lsn, record)
+            })
+            .collect();
+        assert!(log_manager.block_lsns.len() > 1);
+
+        for i in [0, 1, 17, 18, 19, 20, 21, 33, 48, 49] {
+            let (lsn, expected) = &records[i];
+            let record = log_manager.read_record_at_lsn(*lsn).unwrap();
+            assert_eq!(&Page::from(record).get_string(0).unwrap(), expected);
+        }
+
+        let latest = records.last().unwrap().0;
+        let err = log_manager.read_record_at_lsn(latest + 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn iterator_reads_block_zero() {
+        let directory = "./data";
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let block_size = file_manager.lock().unwrap().block_size();
+        let mut log_manager = LogManager::new(file_manager, filename.to_string()).unwrap();
+
+        // 1レコード = 4バイトの長さ + 1017バイト、ヘッダの12バイトを除いたブロックをちょうど埋める
+        let record_size = 1017;
+        let count = (block_size - LOG_HEADER_BYTES) / (INTGER_BYTES + record_size);
+        assert_eq!(
+            count * (INTGER_BYTES + record_size),
+            block_size - LOG_HEADER_BYTES
+        );
+        for n in 0..count {
+            log_manager
+                .append_record(&vec![n as u8; record_size])
+                .unwrap();
+        }
+        assert_eq!(log_manager.current_block.block_number, 0);
+
+        let records: Vec<Box<[u8]>> = log_manager.iterator().unwrap().collect();
+        assert_eq!(records.len(), count);
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(record.to_vec(), vec![(count - 1 - i) as u8; record_size]);
+        }
+
+        // 次のレコードはブロック1に入るが、ブロック0のレコードも全て読める
+        log_manager
+            .append_record(&vec![count as u8; record_size])
+            .unwrap();
+        assert_eq!(log_manager.current_block.block_number, 1);
+        assert_eq!(log_manager.iterator().unwrap().count(), count + 1);
+    }
+
+    #[test]
+    fn compact() {
+        let directory = "./data";
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let mut log_manager =
+            LogManager::new(Arc::clone(&file_manager), filename.to_string()).unwrap();
+
+        let lsns: Vec<i64> = (0..1000)
+            .map(|n: i32| log_manager.append_record(&n.to_be_bytes()).unwrap())
+            .collect();
+        let checkpoint = 600;
+        let checkpoint_lsn = lsns[checkpoint];
+        let blocks_before = file_manager
+            .lock()
+            .unwrap()
+            .last_block_num(filename)
+            .unwrap();
+
+        log_manager.compact(checkpoint_lsn).unwrap();
+
+        let blocks_after = file_manager
+            .lock()
+            .unwrap()
+            .last_block_num(filename)
+            .unwrap();
+        assert!(blocks_after < blocks_before);
+        assert!(!Path::new(directory)
+            .join(format!("{}.compact", filename))
+            .exists());
+
+        // checkpoint以降のレコードだけが新しい順に読める
+        let records: Vec<i32> = log_manager
+            .iterator()
+            .unwrap()
+            .map(|record| Page::from(record).get_int(0).unwrap())
+            .collect();
+        assert_eq!(records, (checkpoint as i32..1000).rev().collect::<Vec<_>>());
+
+        // compact後も追記とLSNでの読み出しができる
+        let lsn = log_manager.append_record(&1000_i32.to_be_bytes()).unwrap();
+        assert_eq!(lsn, lsns[999] + 1);
+        for (n, lsn) in [(600, lsns[600]), (999, lsns[999]), (1000, lsn)] {
+            let record = log_manager.read_record_at_lsn(lsn).unwrap();
+            assert_eq!(Page::from(record).get_int(0).unwrap(), n);
+        }
+        assert_eq!(log_manager.iterator().unwrap().count(), 401);
+        drop(log_manager);
+
+        // 開き直しても、compact後のLSNで読み出せて、さらにcompactできる
+        let mut log_manager =
+            LogManager::new(Arc::clone(&file_manager), filename.to_string()).unwrap();
+        assert_eq!(log_manager.latest_lsn(), lsn);
+        for (n, lsn) in [(600, lsns[600]), (800, lsns[800]), (1000, lsn)] {
+            let record = log_manager.read_record_at_lsn(lsn).unwrap();
+            assert_eq!(Page::from(record).get_int(0).unwrap(), n);
+        }
+        assert_eq!(
+            log_manager
+                .read_record_at_lsn(lsns[599])
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+        log_manager.compact(lsns[800]).unwrap();
+        assert_eq!(log_manager.iterator().unwrap().count(), 201);
+        drop(log_manager);
+
+        let mut log_manager =
+            LogManager::new(Arc::clone(&file_manager), filename.to_string()).unwrap();
+        let record = log_manager.read_record_at_lsn(lsns[800]).unwrap();
+        assert_eq!(Page::from(record).get_int(0).unwrap(), 800);
+        assert_eq!(log_manager.append_record(&[0; 4]).unwrap(), lsn + 1);
+    }
+
+    #[test]
+    fn group_commit() {
+        let directory = "./data";
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(file_manager, filename.to_string()).unwrap(),
+        ));
+        LogManager::enable_group_commit(&log_manager, Duration::from_millis(50));
+
+        // 追記しただけではflushされない
+        let lsn = log_manager.lock().unwrap().append_record(&[0; 4]).unwrap();
+        assert!(log_manager.lock().unwrap().last_flushed_lsn() < lsn);
+
+        // 複数のスレッドのflushがまとめて書き込まれる
+        let handles: Vec<_> = (0..10)
+            .map(|n: i32| {
+                let log_manager = Arc::clone(&log_manager);
+                thread::spawn(move || {
+                    let lsn = log_manager
+                        .lock()
+                        .unwrap()
+                        .append_record(&n.to_be_bytes())
+                        .unwrap();
+                    LogManager::flush_with_group(&log_manager, lsn).unwrap();
+                    assert!(log_manager.lock().unwrap().last_flushed_lsn() >= lsn);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // 無効にすると、その場でflushする
+        let mut locked_log_manager = log_manager.lock().unwrap();
+        locked_log_manager.disable_group_commit().unwrap();
+        let lsn = locked_log_manager.append_record(&[0; 4]).unwrap();
+        drop(locked_log_manager);
+        LogManager::flush_with_group(&log_manager, lsn).unwrap();
+        assert_eq!(log_manager.lock().unwrap().last_flushed_lsn(), lsn);
+        assert_eq!(log_manager.lock().unwrap().iterator().unwrap().count(), 12);
+    }
+
+    // failがtrueの間は書き込みに失敗するFileManager
+    struct FailingFileManager {
+        inner: MemoryFileManager,
+        fail: Arc<AtomicBool>,
+    }
+
+    impl FileManagerTrait for FailingFileManager {
+        fn block_size(&self) -> usize {
+            self.inner.block_size()
+        }
+
+        fn read(&mut self, block_id: &BlockId, page: &mut Page) -> io::Result<()> {
+            self.inner.read(block_id, page)
+        }
+
+        fn write(&mut self, block_id: &BlockId, page: &mut Page) -> io::Result<()> {
+            if self.fail.load(Ordering::SeqCst) {
+                return Err(io::Error::other("disk is full"));
+            }
+            self.inner.write(block_id, page)
+        }
+
+        fn append_new_block(&mut self, filename: &str) -> io::Result<BlockId> {
+            self.inner.append_new_block(filename)
+        }
+
+        fn length(&mut self, filename: &str) -> anyhow::Result<i32> {
+            self.inner.length(filename)
+        }
+
+        fn last_block_num(&mut self, filename: &str) -> io::Result<i32> {
+            self.inner.last_block_num(filename)
+        }
+
+        fn rename_file(&mut self, old_filename: &str, new_filename: &str) -> io::Result<()> {
+            self.inner.rename_file(old_filename, new_filename)
+        }
+
+        fn delete_file(&mut self, filename: &str) -> io::Result<()> {
+            self.inner.delete_file(filename)
+        }
+
+        fn fsync_directory(&self) -> io::Result<()> {
+            self.inner.fsync_directory()
+        }
+
+        fn sync_all(&self) -> io::Result<()> {
+            self.inner.sync_all()
+        }
+
+        fn sync_file(&self, filename: &str) -> io::Result<()> {
+            self.inner.sync_file(filename)
+        }
+    }
+
+    #[test]
+    fn group_commit_error() {
+        let fail = Arc::new(AtomicBool::new(false));
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FailingFileManager {
+                inner: MemoryFileManager::new(400),
+                fail: Arc::clone(&fail),
+            }));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(file_manager, "logfile".to_string()).unwrap(),
+        ));
+        LogManager::enable_group_commit(&log_manager, Duration::from_millis(10));
+
+        // バックグラウンドスレッドのflushが失敗しても、panicせずにエラーが残る
+        fail.store(true, Ordering::SeqCst);
+        let lsn = log_manager.lock().unwrap().append_record(&[0; 4]).unwrap();
+        assert!(LogManager::flush_with_group(&log_manager, lsn).is_err());
+        let locked_log_manager = log_manager.lock().unwrap();
+        assert_eq!(
+            locked_log_manager.group_commit_error().unwrap().to_string(),
+            "disk is full"
+        );
+        assert!(!locked_log_manager.group_commit_running);
+        drop(locked_log_manager);
+
+        // 書き込めるようになれば、待っていた側が自分でflushできる
+        fail.store(false, Ordering::SeqCst);
+        LogManager::flush_with_group(&log_manager, lsn).unwrap();
+        assert_eq!(log_manager.lock().unwrap().last_flushed_lsn(), lsn);
+
+        // 有効にし直すとエラーは消え、スレッドが再び動く
+        LogManager::enable_group_commit(&log_manager, Duration::from_millis(10));
+        let lsn = log_manager.lock().unwrap().append_record(&[0; 4]).unwrap();
+        LogManager::flush_with_group(&log_manager, lsn).unwrap();
+        let locked_log_manager = log_manager.lock().unwrap();
+        assert!(locked_log_manager.group_commit_error().is_none());
+        assert!(locked_log_manager.group_commit_running);
+        assert!(locked_log_manager.last_flushed_lsn() >= lsn);
+    }
+
+    #[test]
+    fn group_commit_timeout() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(400)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(file_manager, "logfile".to_string()).unwrap(),
+        ));
+        LogManager::enable_group_commit(&log_manager, Duration::from_millis(10));
+
+        // 無効にした直後に有効にし直しても、スレッドは増えない
+        log_manager.lock().unwrap().disable_group_commit().unwrap();
+        LogManager::enable_group_commit(&log_manager, Duration::from_millis(10));
+        assert!(log_manager.lock().unwrap().group_commit_running);
+        thread::sleep(Duration::from_millis(50));
+        assert!(log_manager.lock().unwrap().group_commit_running);
+
+        // スレッドが止まっていても、待ちきれなければ自分でflushする
+        let log_manager = Mutex::new(
+            LogManager::new(
+                Arc::new(Mutex::new(MemoryFileManager::new(400))),
+                "logfile".to_string(),
+            )
+            .unwrap(),
+        );
+        let mut locked_log_manager = log_manager.lock().unwrap();
+        locked_log_manager.group_commit_interval = Some(Duration::from_millis(10));
+        locked_log_manager.group_commit_running = true;
+        let lsn = locked_log_manager.append_record(&[0; 4]).unwrap();
+        drop(locked_log_manager);
+        LogManager::flush_with_group(&log_manager, lsn).unwrap();
+        assert_eq!(log_manager.lock().unwrap().last_flushed_lsn(), lsn);
+    }
+
+    #[test]
+    fn lsn_past_i32_max() {
+        let directory = "./data";
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let mut log_manager = LogManager::new(file_manager, filename.to_string()).unwrap();
+
+        // 20億件書き込む代わりにLSNを直前まで進めておく
+        log_manager.latest_log_sequence_number = i32::MAX as i64 - 1;
+        let lsns: Vec<i64> = (0..3)
+            .map(|n| log_manager.append_record(&[n; 8]).unwrap())
+            .collect();
+        assert_eq!(
+            lsns,
+            vec![i32::MAX as i64, i32::MAX as i64 + 1, i32::MAX as i64 + 2]
+        );
+
+        log_manager.flush_with(i32::MAX as i64 + 1).unwrap();
+        assert_eq!(log_manager.last_flushed_lsn(), i32::MAX as i64 + 2);
+        let record = log_manager.read_record_at_lsn(i32::MAX as i64 + 1).unwrap();
+        assert_eq!(record.to_vec(), vec![1; 8]);
+    }
+
+    #[test]
+    fn reopen() {
+        let directory = "./data";
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+
+        let mut log_manager =
+            LogManager::new(Arc::clone(&file_manager), filename.to_string()).unwrap();
+        let first_lsn = log_manager.append_record(&[1; 8]).unwrap();
+        let second_lsn = log_manager.append_record(&[2; 8]).unwrap();
+        log_manager.iterator().unwrap();
+        drop(log_manager);
+
+        // 開き直したら、最後のブロックの続きに前回の続きのLSNで追記する
+        let mut log_manager =
+            LogManager::new(Arc::clone(&file_manager), filename.to_string()).unwrap();
+        assert_eq!(log_manager.latest_lsn(), second_lsn);
+        assert_eq!(log_manager.last_flushed_lsn(), second_lsn);
+        assert_eq!(log_manager.append_record(&[3; 8]).unwrap(), second_lsn + 1);
+        let record = log_manager.read_record_at_lsn(first_lsn).unwrap();
+        assert_eq!(record.to_vec(), vec![1; 8]);
+        let records: Vec<Vec<u8>> = log_manager
+            .iterator()
+            .unwrap()
+            .map(|record| record.to_vec())
+            .collect();
+        assert_eq!(records, vec![vec![3; 8], vec![2; 8], vec![1; 8]]);
+    }
+
+    #[test]
+    fn reopen_across_blocks() {
+        let directory = "./data";
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        // 1ブロックにレコードが数個しか入らない大きさにする
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new_with_options(
+                directory.to_string(),
+                FileManagerOptions {
+                    block_size: 64,
+                    ..Default::default()
+                },
+            )));
+
+        let mut log_manager =
+            LogManager::new(Arc::clone(&file_manager), filename.to_string()).unwrap();
+        let mut lsns: Vec<i64> = (0..20)
+            .map(|n| log_manager.append_record(&[n; 8]).unwrap())
+            .collect();
+        log_manager.iterator().unwrap();
+        drop(log_manager);
+
+        // 2回目は、途中まで埋まった最後のブロックの空きから書き始める
+        let mut log_manager =
+            LogManager::new(Arc::clone(&file_manager), filename.to_string()).unwrap();
+        lsns.extend((20..40).map(|n| log_manager.append_record(&[n; 8]).unwrap()));
+        assert_eq!(lsns, (lsns[0]..lsns[0] + 40).collect::<Vec<i64>>());
+        // 前回書いたブロックのレコードもLSNで引ける
+        for n in [0, 7, 19, 20, 39] {
+            let record = log_manager.read_record_at_lsn(lsns[n]).unwrap();
+            assert_eq!(record.to_vec(), vec![n as u8; 8]);
+        }
+        let records: Vec<Vec<u8>> = log_manager
+            .iterator()
+            .unwrap()
+            .map(|record| record.to_vec())
+            .collect();
+        let expected: Vec<Vec<u8>> = (0..40).rev().map(|n| vec![n; 8]).collect();
+        assert_eq!(records, expected);
+    }
 }
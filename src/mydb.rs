@@ -0,0 +1,706 @@
+use std::fs::create_dir_all;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::bail;
+
+use crate::buffer_manager::{BufferInfo, BufferManager};
+use crate::catalog::index_manager::IndexManager;
+use crate::catalog::statistics_manager::StatisticsManager;
+use crate::catalog::table_manager::TableManager;
+use crate::catalog::view_manager::ViewManager;
+use crate::file_manager::{FileManager, FileManagerOptions, FileManagerTrait, PAGE_SIZE};
+use crate::log_manager::LogManager;
+use crate::planner::basic_update_planner::BasicUpdatePlanner;
+use crate::planner::heuristic_query_planner::HeuristicQueryPlanner;
+use crate::planner::{QueryPlanner, UpdatePlanner};
+use crate::record_manager::scan::Scan;
+use crate::sql::parser::{Parser, Statement};
+use crate::transaction::lock_table::LockTable;
+use crate::transaction::transaction::Transaction;
+
+pub struct MyDbConfig {
+    pub dir: String,
+    pub log_file: String,
+    pub block_size: usize,
+    pub buffer_pool_size: usize,
+}
+
+impl Default for MyDbConfig {
+    fn default() -> Self {
+        Self {
+            dir: "data".to_string(),
+            log_file: "logfile".to_string(),
+            block_size: PAGE_SIZE,
+            buffer_pool_size: 8,
+        }
+    }
+}
+
+// デバッグ用に取る、データベースの内部状態のスナップショット
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+    pub buffer_pool: Vec<BufferInfo>,
+}
+
+pub struct MyDb {
+    file_manager: Arc<Mutex<dyn FileManagerTrait>>,
+    log_manager: Arc<Mutex<LogManager>>,
+    buffer_manager: Arc<Mutex<BufferManager>>,
+    lock_table: Arc<LockTable>,
+    table_manager: Arc<TableManager>,
+    statistics_manager: Arc<StatisticsManager>,
+    index_manager: Arc<IndexManager>,
+    view_manager: Arc<ViewManager>,
+    // new_transactionで作られ、まだ破棄されていないトランザクションの数
+    active_count: Arc<AtomicUsize>,
+}
+
+impl MyDb {
+    pub fn open(config: MyDbConfig) -> anyhow::Result<Self> {
+        let is_new = !Path::new(&config.dir).exists();
+        create_dir_all(&config.dir)?;
+
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new_with_options(
+                config.dir,
+                FileManagerOptions {
+                    block_size: config.block_size,
+                    ..Default::default()
+                },
+            )));
+        let log_manager = Arc::new(Mutex::new(LogManager::new(
+            Arc::clone(&file_manager),
+            config.log_file,
+        )?));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            config.buffer_pool_size as i32,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let transaction = Arc::new(Mutex::new(Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        )));
+
+        // 既存のデータベースを開くときは、前回の未完了トランザクションを取り消す
+        if !is_new {
+            transaction.lock().unwrap().recover()?;
+        }
+        let table_manager = Arc::new(TableManager::new(is_new, &transaction)?);
+        let statistics_manager = Arc::new(StatisticsManager::new(
+            Arc::clone(&table_manager),
+            &transaction,
+        )?);
+        let index_manager = Arc::new(IndexManager::new(
+            is_new,
+            Arc::clone(&table_manager),
+            Arc::clone(&statistics_manager),
+            &transaction,
+        )?);
+        let view_manager = Arc::new(ViewManager::new(
+            is_new,
+            Arc::clone(&table_manager),
+            &transaction,
+        )?);
+        transaction.lock().unwrap().commit()?;
+
+        Ok(Self {
+            file_manager,
+            log_manager,
+            buffer_manager,
+            lock_table,
+            table_manager,
+            statistics_manager,
+            index_manager,
+            view_manager,
+            active_count: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn new_transaction(&self) -> Transaction {
+        Transaction::new(
+            Arc::clone(&self.file_manager),
+            Arc::clone(&self.log_manager),
+            Arc::clone(&self.buffer_manager),
+            Arc::clone(&self.lock_table),
+        )
+        .with_active_count(&self.active_count)
+    }
+
+    pub fn active_transaction_count(&self) -> usize {
+        self.active_count.load(Ordering::SeqCst)
+    }
+
+    // コミット時のログのflushをinterval_msごとにまとめる
+    pub fn enable_group_commit(&self, interval_ms: u64) {
+        LogManager::enable_group_commit(&self.log_manager, Duration::from_millis(interval_ms));
+    }
+
+    // 変更されたバッファとログをすべて書き出し、開いているファイルをディスクに同期する
+    pub fn shutdown(&mut self) -> anyhow::Result<()> {
+        self.buffer_manager.lock().unwrap().flush_all_modified()?;
+        let mut log_manager = self.log_manager.lock().unwrap();
+        let latest_lsn = log_manager.latest_lsn();
+        log_manager.flush_with(latest_lsn)?;
+        drop(log_manager);
+
+        let file_manager = self.file_manager.lock().unwrap();
+        file_manager.sync_all()?;
+        file_manager.fsync_directory()?;
+        Ok(())
+    }
+
+    // SELECT文を実行し、結果を読むScanを返す
+    pub fn execute_query(
+        &self,
+        sql: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Box<dyn Scan>> {
+        let query = Parser::new(sql)?.query()?;
+        let planner = HeuristicQueryPlanner::new(
+            self.table_manager(),
+            self.view_manager(),
+            self.index_manager(),
+            self.statistics_manager(),
+        );
+        planner.create_plan(&query, transaction)?.open()
+    }
+
+    // SELECT以外の文を実行し、変更したレコード数を返す。CREATE文は0を返す
+    pub fn execute_update(
+        &self,
+        sql: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32> {
+        let planner = BasicUpdatePlanner::new(
+            self.table_manager(),
+            self.view_manager(),
+            self.index_manager(),
+        );
+        match Parser::new(sql)?.statement()? {
+            Statement::Query(_) => bail!("use execute_query for select statements"),
+            Statement::Insert(data) => planner.execute_insert(&data, transaction),
+            Statement::Delete(data) => planner.execute_delete(&data, transaction),
+            Statement::Update(data) => planner.execute_update(&data, transaction),
+            Statement::CreateTable(data) => planner.execute_create_table(&data, transaction),
+            Statement::CreateView(data) => planner.execute_create_view(&data, transaction),
+            Statement::CreateIndex(data) => planner.execute_create_index(&data, transaction),
+        }
+    }
+
+    // カタログのテーブル名と索引の登録先を書き換え、データのファイル名を変える。
+    // どれもtransactionの中で行うので、rollbackすれば元の名前に戻る
+    pub fn rename_table(
+        &self,
+        old_name: &str,
+        new_name: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<()> {
+        self.table_manager
+            .rename_table(old_name, new_name, transaction)?;
+        self.index_manager
+            .rename_table(old_name, new_name, transaction)?;
+        transaction
+            .lock()
+            .unwrap()
+            .rename_file(&format!("{}.tbl", old_name), &format!("{}.tbl", new_name))
+    }
+
+    pub fn diagnostics(&self) -> Diagnostics {
+        Diagnostics {
+            buffer_pool: self.buffer_manager.lock().unwrap().dump_pool(),
+        }
+    }
+
+    pub fn table_manager(&self) -> Arc<TableManager> {
+        Arc::clone(&self.table_manager)
+    }
+
+    pub fn statistics_manager(&self) -> Arc<StatisticsManager> {
+        Arc::clone(&self.statistics_manager)
+    }
+
+    pub fn index_manager(&self) -> Arc<IndexManager> {
+        Arc::clone(&self.index_manager)
+    }
+
+    pub fn view_manager(&self) -> Arc<ViewManager> {
+        Arc::clone(&self.view_manager)
+    }
+
+    pub fn file_manager(&self) -> Arc<Mutex<dyn FileManagerTrait>> {
+        Arc::clone(&self.file_manager)
+    }
+
+    pub fn log_manager(&self) -> Arc<Mutex<LogManager>> {
+        Arc::clone(&self.log_manager)
+    }
+
+    pub fn buffer_manager(&self) -> Arc<Mutex<BufferManager>> {
+        Arc::clone(&self.buffer_manager)
+    }
+}
+
+// Dropはエラーを返せないので、shutdownに失敗したら表示だけする
+impl Drop for MyDb {
+    fn drop(&mut self) {
+        if let Err(e) = self.shutdown() {
+            eprintln!("failed to shut down the database: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::file_manager::{BlockId, Page};
+    use crate::record_manager::schema::Schema;
+    use crate::transaction::transaction::{total_transaction_count, LogMode};
+
+    #[test]
+    fn open() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let dir = |name: &str| tempdir.path().join(name).to_str().unwrap().to_string();
+
+        let db1 = MyDb::open(MyDbConfig {
+            dir: dir("db1"),
+            block_size: 400,
+            buffer_pool_size: 3,
+            ..Default::default()
+        })
+        .unwrap();
+        let db2 = MyDb::open(MyDbConfig {
+            dir: dir("db2"),
+            log_file: "db2.log".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(db1.file_manager().lock().unwrap().block_size(), 400);
+        assert_eq!(db1.buffer_manager().lock().unwrap().available(), 3);
+        assert!(Path::new(&dir("db1")).join("logfile").exists());
+        assert!(Path::new(&dir("db2")).join("db2.log").exists());
+
+        // 同じファイル名でも、別のディレクトリのデータベースは干渉しない
+        let block = BlockId {
+            filename: "T.tbl".to_string(),
+            block_number: 0,
+        };
+        for (db, value) in [(&db1, 1), (&db2, 2)] {
+            let mut transaction = db.new_transaction();
+            transaction.pin(&block).unwrap();
+            transaction.set_int(&block, 0, value, LogMode::Log).unwrap();
+            transaction.commit().unwrap();
+        }
+        for (db, value) in [(&db1, 1), (&db2, 2)] {
+            let mut transaction = db.new_transaction();
+            transaction.pin(&block).unwrap();
+            assert_eq!(transaction.get_int(&block, 0).unwrap(), value);
+            transaction.commit().unwrap();
+        }
+        drop(db1);
+
+        // 既存のディレクトリを開き直しても、コミット済みのデータは残る
+        let db1 = MyDb::open(MyDbConfig {
+            dir: dir("db1"),
+            block_size: 400,
+            buffer_pool_size: 3,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut transaction = db1.new_transaction();
+        transaction.pin(&block).unwrap();
+        assert_eq!(transaction.get_int(&block, 0).unwrap(), 1);
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn shutdown() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let dir = tempdir.path().join("db");
+        let config = || MyDbConfig {
+            dir: dir.to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        let block = BlockId {
+            filename: "T.tbl".to_string(),
+            block_number: 0,
+        };
+
+        let mut db = MyDb::open(config()).unwrap();
+        let mut transaction = db.new_transaction();
+        transaction.pin(&block).unwrap();
+        transaction.set_int(&block, 0, 123, LogMode::Log).unwrap();
+        // ログに残さない変更も、バッファを書き出せばファイルに残る
+        transaction.set_int(&block, 4, 456, LogMode::NoLog).unwrap();
+        transaction.unpin(&block).unwrap();
+        let buffer_manager = db.buffer_manager();
+        assert!(buffer_manager
+            .lock()
+            .unwrap()
+            .dump_pool()
+            .iter()
+            .any(|info| info.is_dirty));
+
+        db.shutdown().unwrap();
+        assert!(buffer_manager
+            .lock()
+            .unwrap()
+            .dump_pool()
+            .iter()
+            .all(|info| !info.is_dirty));
+        transaction.commit().unwrap();
+        let mut page = Page::new(PAGE_SIZE);
+        db.file_manager()
+            .lock()
+            .unwrap()
+            .read(&block, &mut page)
+            .unwrap();
+        assert_eq!(page.get_int(0).unwrap(), 123);
+        assert_eq!(page.get_int(4).unwrap(), 456);
+        drop(db);
+
+        let db = MyDb::open(config()).unwrap();
+        let mut transaction = db.new_transaction();
+        transaction.pin(&block).unwrap();
+        assert_eq!(transaction.get_int(&block, 0).unwrap(), 123);
+        assert_eq!(transaction.get_int(&block, 4).unwrap(), 456);
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn group_commit() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            buffer_pool_size: 100,
+            ..Default::default()
+        })
+        .unwrap();
+        db.enable_group_commit(1);
+
+        let blocks: Vec<BlockId> = (0..100)
+            .map(|i| BlockId {
+                filename: "T.tbl".to_string(),
+                block_number: i,
+            })
+            .collect();
+        std::thread::scope(|scope| {
+            for (i, block) in blocks.iter().enumerate() {
+                let db = &db;
+                scope.spawn(move || {
+                    let mut transaction = db.new_transaction();
+                    transaction.pin(block).unwrap();
+                    transaction
+                        .set_int(block, 0, i as i32, LogMode::Log)
+                        .unwrap();
+                    transaction.commit().unwrap();
+                });
+            }
+        });
+
+        let last_lsn = db.log_manager().lock().unwrap().latest_lsn();
+        assert!(db.log_manager().lock().unwrap().last_flushed_lsn() >= last_lsn);
+        let mut transaction = db.new_transaction();
+        for (i, block) in blocks.iter().enumerate() {
+            transaction.pin(block).unwrap();
+            assert_eq!(transaction.get_int(block, 0).unwrap(), i as i32);
+        }
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn active_transaction_count() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(db.active_transaction_count(), 0);
+
+        let total = total_transaction_count();
+        let mut tx1 = db.new_transaction();
+        let tx2 = Arc::new(Mutex::new(db.new_transaction()));
+        assert_eq!(db.active_transaction_count(), 2);
+        assert!(total_transaction_count() >= total + 2);
+
+        // commitしても、破棄されるまでは数える
+        tx1.commit().unwrap();
+        assert_eq!(db.active_transaction_count(), 2);
+        drop(tx1);
+        assert_eq!(db.active_transaction_count(), 1);
+
+        // commitせずに破棄されたトランザクションも数えなくなる
+        drop(tx2);
+        assert_eq!(db.active_transaction_count(), 0);
+
+        for _ in 0..3 {
+            db.new_transaction().commit().unwrap();
+        }
+        assert_eq!(db.active_transaction_count(), 0);
+    }
+
+    #[test]
+    fn diagnostics() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            buffer_pool_size: 3,
+            ..Default::default()
+        })
+        .unwrap();
+        db.buffer_manager()
+            .lock()
+            .unwrap()
+            .flush_all_modified()
+            .unwrap();
+        let diagnostics = db.diagnostics();
+        assert_eq!(diagnostics.buffer_pool.len(), 3);
+        assert!(diagnostics
+            .buffer_pool
+            .iter()
+            .all(|info| info.pins == 0 && !info.is_dirty));
+
+        // pinして書き込んだブロックは、pinの数と変更したトランザクションとともに見える
+        let block = BlockId {
+            filename: "T.tbl".to_string(),
+            block_number: 0,
+        };
+        let mut transaction = db.new_transaction();
+        transaction.pin(&block).unwrap();
+        transaction.set_int(&block, 0, 1, LogMode::Log).unwrap();
+        let info = db
+            .diagnostics()
+            .buffer_pool
+            .into_iter()
+            .find(|info| info.block_id.as_ref() == Some(&block))
+            .unwrap();
+        assert_eq!(info.pins, 1);
+        assert!(info.txnum >= 0);
+        assert!(info.is_dirty);
+
+        // コミットすると書き出され、pinも外れる
+        transaction.commit().unwrap();
+        let info = db
+            .diagnostics()
+            .buffer_pool
+            .into_iter()
+            .find(|info| info.block_id.as_ref() == Some(&block))
+            .unwrap();
+        assert_eq!(info.pins, 0);
+        assert!(!info.is_dirty);
+    }
+
+    #[test]
+    fn catalog() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let config = || MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 20).unwrap();
+        schema.add_float_field("score".to_string()).unwrap();
+
+        let db = MyDb::open(config()).unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        db.table_manager()
+            .create_table("users", &schema, &transaction)
+            .unwrap();
+        let layout = db
+            .table_manager()
+            .get_layout("users", &transaction)
+            .unwrap();
+        transaction.lock().unwrap().commit().unwrap();
+        drop(db);
+
+        // 開き直しても同じLayoutが得られる
+        let db = MyDb::open(config()).unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        let reopened = db
+            .table_manager()
+            .get_layout("users", &transaction)
+            .unwrap();
+        assert_eq!(reopened.schema().fields(), layout.schema().fields());
+        for field_name in layout.schema().fields() {
+            assert_eq!(
+                reopened.schema().info(field_name),
+                layout.schema().info(field_name)
+            );
+            assert_eq!(reopened.offset(field_name), layout.offset(field_name));
+        }
+        assert_eq!(reopened.slot_size(), layout.slot_size());
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn execute_query_and_update() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        assert_eq!(
+            db.execute_update(
+                "create table student (sid int, sname varchar(10), gradyear int)",
+                &transaction
+            )
+            .unwrap(),
+            0
+        );
+        for (sid, sname, gradyear) in [(1, "joe", 2021), (2, "amy", 2020), (3, "max", 2021)] {
+            let sql = format!(
+                "insert into student values ({}, '{}', {})",
+                sid, sname, gradyear
+            );
+            assert_eq!(db.execute_update(&sql, &transaction).unwrap(), 1);
+        }
+        assert_eq!(
+            db.execute_update(
+                "update student set gradyear = 2022 where gradyear = 2021",
+                &transaction
+            )
+            .unwrap(),
+            2
+        );
+        assert_eq!(
+            db.execute_update("delete from student where sname = 'amy'", &transaction)
+                .unwrap(),
+            1
+        );
+
+        let mut scan = db
+            .execute_query(
+                "select sid, sname from student where gradyear = 2022",
+                &transaction,
+            )
+            .unwrap();
+        let mut rows = vec![];
+        while scan.next().unwrap() {
+            rows.push((
+                scan.get_int("sid").unwrap(),
+                scan.get_string("sname").unwrap(),
+            ));
+            assert!(!scan.has_field("gradyear"));
+        }
+        scan.close().unwrap();
+        rows.sort();
+        assert_eq!(rows, vec![(1, "joe".to_string()), (3, "max".to_string())]);
+
+        // 構文の誤りも、存在しないテーブルも、エラーとして返す
+        assert!(db
+            .execute_query("select from student", &transaction)
+            .is_err());
+        assert!(db
+            .execute_query("select sid from missing", &transaction)
+            .is_err());
+        assert!(db
+            .execute_update("select sid from student", &transaction)
+            .is_err());
+        assert!(db
+            .execute_update("insert into missing values (1)", &transaction)
+            .is_err());
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn rename_table() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let dir = tempdir.path().join("db");
+        let config = || MyDbConfig {
+            dir: dir.to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        let sids = |db: &MyDb, table_name: &str, transaction: &Arc<Mutex<Transaction>>| {
+            let sql = format!("select sid from {}", table_name);
+            let mut scan = db.execute_query(&sql, transaction)?;
+            let mut sids = vec![];
+            while scan.next()? {
+                sids.push(scan.get_int("sid")?);
+            }
+            scan.close()?;
+            sids.sort();
+            anyhow::Ok(sids)
+        };
+
+        let db = MyDb::open(config()).unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        db.execute_update(
+            "create table student (sid int, sname varchar(10))",
+            &transaction,
+        )
+        .unwrap();
+        db.execute_update("create index sid_idx on student (sid)", &transaction)
+            .unwrap();
+        for sid in 1..=3 {
+            let sql = format!("insert into student values ({}, 's{}')", sid, sid);
+            db.execute_update(&sql, &transaction).unwrap();
+        }
+        transaction.lock().unwrap().commit().unwrap();
+
+        // 名前を変えても、データも索引も移さずにそのまま読める
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        db.rename_table("student", "pupil", &transaction).unwrap();
+        assert_eq!(sids(&db, "pupil", &transaction).unwrap(), vec![1, 2, 3]);
+        assert!(sids(&db, "student", &transaction).is_err());
+        let index_info = db
+            .index_manager()
+            .get_index_info("pupil", &transaction)
+            .unwrap();
+        assert_eq!(index_info["sid"].index_name(), "sid_idx");
+        assert!(db
+            .index_manager()
+            .get_index_info("student", &transaction)
+            .unwrap()
+            .is_empty());
+        // 存在しないテーブルや、すでにある名前への変更はエラー
+        assert!(db.rename_table("missing", "other", &transaction).is_err());
+        assert!(db
+            .rename_table("pupil", "table_catalog", &transaction)
+            .is_err());
+        transaction.lock().unwrap().commit().unwrap();
+        assert!(dir.join("pupil.tbl").exists());
+        assert!(!dir.join("student.tbl").exists());
+
+        // rollbackすると、カタログもファイル名も元に戻る
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        db.rename_table("pupil", "learner", &transaction).unwrap();
+        db.execute_update("insert into learner values (4, 's4')", &transaction)
+            .unwrap();
+        assert_eq!(
+            sids(&db, "learner", &transaction).unwrap(),
+            vec![1, 2, 3, 4]
+        );
+        assert!(dir.join("learner.tbl").exists());
+        transaction.lock().unwrap().rollback().unwrap();
+        assert!(dir.join("pupil.tbl").exists());
+        assert!(!dir.join("learner.tbl").exists());
+        drop(db);
+
+        let db = MyDb::open(config()).unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        assert_eq!(sids(&db, "pupil", &transaction).unwrap(), vec![1, 2, 3]);
+        assert!(sids(&db, "learner", &transaction).is_err());
+        assert_eq!(
+            db.index_manager()
+                .get_index_info("pupil", &transaction)
+                .unwrap()
+                .len(),
+            1
+        );
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
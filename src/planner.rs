@@ -0,0 +1,62 @@
+use std::sync::{Arc, Mutex};
+
+use crate::sql::parser::{
+    CreateIndexData, CreateTableData, CreateViewData, DeleteData, InsertData, QueryData, UpdateData,
+};
+use crate::transaction::transaction::Transaction;
+
+use self::plan::Plan;
+
+pub mod basic_query_planner;
+pub mod basic_update_planner;
+pub mod heuristic_query_planner;
+pub mod index_join_plan;
+pub mod index_select_plan;
+pub mod plan;
+pub mod product_plan;
+pub mod project_plan;
+pub mod select_plan;
+pub mod table_plan;
+
+// SELECT文からPlanを組み立てる
+pub trait QueryPlanner {
+    fn create_plan(
+        &self,
+        query: &QueryData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Box<dyn Plan>>;
+}
+
+// 更新系の文を実行し、変更したレコード数を返す。定義を作る文は0を返す
+pub trait UpdatePlanner {
+    fn execute_insert(
+        &self,
+        data: &InsertData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32>;
+    fn execute_delete(
+        &self,
+        data: &DeleteData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32>;
+    fn execute_update(
+        &self,
+        data: &UpdateData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32>;
+    fn execute_create_table(
+        &self,
+        data: &CreateTableData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32>;
+    fn execute_create_view(
+        &self,
+        data: &CreateViewData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32>;
+    fn execute_create_index(
+        &self,
+        data: &CreateIndexData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32>;
+}
@@ -0,0 +1,187 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::bail;
+
+use crate::catalog::statistics_manager::StatisticsManager;
+use crate::catalog::table_manager::TableManager;
+use crate::sql::parser::QueryData;
+use crate::transaction::transaction::Transaction;
+
+use super::plan::Plan;
+use super::product_plan::ProductPlan;
+use super::project_plan::ProjectPlan;
+use super::select_plan::SelectPlan;
+use super::table_plan::TablePlan;
+use super::QueryPlanner;
+
+// FROM句の順にテーブルの直積を取り、WHERE句で選んでから射影する。結合の順序は考えない
+pub struct BasicQueryPlanner {
+    table_manager: Arc<TableManager>,
+    statistics_manager: Arc<StatisticsManager>,
+}
+
+impl BasicQueryPlanner {
+    pub fn new(
+        table_manager: Arc<TableManager>,
+        statistics_manager: Arc<StatisticsManager>,
+    ) -> Self {
+        Self {
+            table_manager,
+            statistics_manager,
+        }
+    }
+}
+
+impl QueryPlanner for BasicQueryPlanner {
+    fn create_plan(
+        &self,
+        query: &QueryData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Box<dyn Plan>> {
+        let mut plan: Option<Box<dyn Plan>> = None;
+        for table_name in &query.tables {
+            let table_plan = Box::new(TablePlan::new(
+                table_name,
+                &self.table_manager,
+                &self.statistics_manager,
+                transaction,
+            )?);
+            plan = Some(match plan {
+                Some(plan) => Box::new(ProductPlan::new(plan, table_plan)?),
+                None => table_plan,
+            });
+        }
+        let Some(plan) = plan else {
+            bail!("query has no tables");
+        };
+
+        let plan = Box::new(SelectPlan::new(plan, query.predicate.clone()));
+        match &query.fields {
+            Some(fields) => Ok(Box::new(ProjectPlan::new(plan, fields)?)),
+            None => Ok(plan),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::record_manager::layout::Layout;
+    use crate::record_manager::scan::{Scan, UpdateScan};
+    use crate::record_manager::schema::Schema;
+    use crate::record_manager::table_scan::TableScan;
+    use crate::sql::parser::Parser;
+
+    #[test]
+    fn two_table_query() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        let mut student = Schema::new();
+        student.add_int_field("sid".to_string()).unwrap();
+        student.add_string_field("sname".to_string(), 10).unwrap();
+        student.add_int_field("majorid".to_string()).unwrap();
+        let mut dept = Schema::new();
+        dept.add_int_field("did".to_string()).unwrap();
+        dept.add_string_field("dname".to_string(), 10).unwrap();
+        let table_manager = db.table_manager();
+        table_manager
+            .create_table("student", &student, &transaction)
+            .unwrap();
+        table_manager
+            .create_table("dept", &dept, &transaction)
+            .unwrap();
+
+        let mut table_scan = TableScan::new(
+            Arc::clone(&transaction),
+            "student",
+            Arc::new(Layout::from(student)),
+        )
+        .unwrap();
+        for (sid, sname, majorid) in [(1, "joe", 10), (2, "amy", 20), (3, "max", 10)] {
+            table_scan.insert().unwrap();
+            table_scan.set_int("sid", sid).unwrap();
+            table_scan.set_string("sname", sname.to_string()).unwrap();
+            table_scan.set_int("majorid", majorid).unwrap();
+        }
+        table_scan.close().unwrap();
+        let mut table_scan = TableScan::new(
+            Arc::clone(&transaction),
+            "dept",
+            Arc::new(Layout::from(dept)),
+        )
+        .unwrap();
+        for (did, dname) in [(10, "compsci"), (20, "math"), (30, "drama")] {
+            table_scan.insert().unwrap();
+            table_scan.set_int("did", did).unwrap();
+            table_scan.set_string("dname", dname.to_string()).unwrap();
+        }
+        table_scan.close().unwrap();
+
+        let planner = BasicQueryPlanner::new(table_manager, db.statistics_manager());
+        let query = Parser::new("select sname, dname from student, dept where majorid = did")
+            .unwrap()
+            .query()
+            .unwrap();
+        let plan = planner.create_plan(&query, &transaction).unwrap();
+        assert_eq!(
+            plan.schema().fields(),
+            &vec!["sname".to_string(), "dname".to_string()]
+        );
+
+        let mut scan = plan.open().unwrap();
+        let mut rows = vec![];
+        while scan.next().unwrap() {
+            rows.push((
+                scan.get_string("sname").unwrap(),
+                scan.get_string("dname").unwrap(),
+            ));
+        }
+        assert!(scan.get_int("sid").is_err());
+        scan.close().unwrap();
+        rows.sort();
+        assert_eq!(
+            rows,
+            vec![
+                ("amy".to_string(), "math".to_string()),
+                ("joe".to_string(), "compsci".to_string()),
+                ("max".to_string(), "compsci".to_string()),
+            ]
+        );
+
+        let query = Parser::new("select sname from student, dept where dname = 'art'")
+            .unwrap()
+            .query()
+            .unwrap();
+        let mut scan = planner
+            .create_plan(&query, &transaction)
+            .unwrap()
+            .open()
+            .unwrap();
+        assert!(!scan.next().unwrap());
+        scan.close().unwrap();
+
+        // 同じ名前のフィールドを持つテーブルどうしは直積にできない
+        let query = Parser::new("select sname from student, student")
+            .unwrap()
+            .query()
+            .unwrap();
+        let error = planner.create_plan(&query, &transaction).err().unwrap();
+        assert!(format!("{:#}", error).contains("fields sid, sname, majorid appear on both sides"));
+
+        let query = Parser::new("select sname from nothing")
+            .unwrap()
+            .query()
+            .unwrap();
+        assert!(planner.create_plan(&query, &transaction).is_err());
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
@@ -0,0 +1,334 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context};
+
+use crate::catalog::index_manager::IndexManager;
+use crate::catalog::table_manager::TableManager;
+use crate::catalog::view_manager::ViewManager;
+use crate::record_manager::constant::Constant;
+use crate::record_manager::scan::{Scan, UpdateScan};
+use crate::record_manager::schema::{FieldInfo, Schema};
+use crate::record_manager::select_scan::SelectScan;
+use crate::record_manager::table_scan::TableScan;
+use crate::sql::parser::{
+    CreateIndexData, CreateTableData, CreateViewData, DeleteData, InsertData, UpdateData,
+};
+use crate::transaction::transaction::Transaction;
+
+use super::UpdatePlanner;
+
+// テーブルを先頭から走査して更新する。索引は更新しない
+pub struct BasicUpdatePlanner {
+    table_manager: Arc<TableManager>,
+    view_manager: Arc<ViewManager>,
+    index_manager: Arc<IndexManager>,
+}
+
+impl BasicUpdatePlanner {
+    pub fn new(
+        table_manager: Arc<TableManager>,
+        view_manager: Arc<ViewManager>,
+        index_manager: Arc<IndexManager>,
+    ) -> Self {
+        Self {
+            table_manager,
+            view_manager,
+            index_manager,
+        }
+    }
+}
+
+impl UpdatePlanner for BasicUpdatePlanner {
+    fn execute_insert(
+        &self,
+        data: &InsertData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32> {
+        let layout = self
+            .table_manager
+            .get_layout(&data.table_name, transaction)?;
+        let fields = match &data.fields {
+            Some(fields) => fields.clone(),
+            None => layout.schema().fields().clone(),
+        };
+        if fields.len() != data.values.len() {
+            bail!(
+                "expected {} values, found {}",
+                fields.len(),
+                data.values.len()
+            );
+        }
+        for (field, value) in fields.iter().zip(&data.values) {
+            check_type(layout.schema(), field, value)?;
+        }
+
+        let mut table_scan =
+            TableScan::new(Arc::clone(transaction), &data.table_name, Arc::new(layout))?;
+        table_scan.insert()?;
+        for (field, value) in fields.iter().zip(&data.values) {
+            table_scan.set_val(field, value.clone())?;
+        }
+        table_scan.close()?;
+        Ok(1)
+    }
+
+    fn execute_delete(
+        &self,
+        data: &DeleteData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32> {
+        let layout = Arc::new(
+            self.table_manager
+                .get_layout(&data.table_name, transaction)?,
+        );
+        let table_scan = TableScan::new(Arc::clone(transaction), &data.table_name, layout)?;
+        let mut scan = SelectScan::new(table_scan, data.predicate.clone());
+        let mut count = 0;
+        while scan.next()? {
+            scan.delete()?;
+            count += 1;
+        }
+        scan.close()?;
+        Ok(count)
+    }
+
+    fn execute_update(
+        &self,
+        data: &UpdateData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32> {
+        let layout = Arc::new(
+            self.table_manager
+                .get_layout(&data.table_name, transaction)?,
+        );
+        if !layout.schema().has_field(&data.field_name) {
+            bail!(
+                "field {} not found in table {}",
+                data.field_name,
+                data.table_name
+            );
+        }
+        let table_scan = TableScan::new(
+            Arc::clone(transaction),
+            &data.table_name,
+            Arc::clone(&layout),
+        )?;
+        let mut scan = SelectScan::new(table_scan, data.predicate.clone());
+        let mut count = 0;
+        while scan.next()? {
+            let value = data.new_value.evaluate(&mut scan)?;
+            check_type(layout.schema(), &data.field_name, &value)?;
+            scan.set_val(&data.field_name, value)?;
+            count += 1;
+        }
+        scan.close()?;
+        Ok(count)
+    }
+
+    fn execute_create_table(
+        &self,
+        data: &CreateTableData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32> {
+        self.table_manager
+            .create_table(&data.table_name, &data.schema, transaction)?;
+        Ok(0)
+    }
+
+    fn execute_create_view(
+        &self,
+        data: &CreateViewData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32> {
+        // 参照先が先にあることを求めれば、ビューが循環して参照し合うことはない
+        for table_name in &data.query.tables {
+            if !self.table_manager.has_table(table_name, transaction)?
+                && !self.view_manager.has_view(table_name, transaction)?
+            {
+                bail!("table or view {} not found", table_name);
+            }
+        }
+        self.view_manager
+            .create_view(&data.view_name, &data.view_def(), transaction)?;
+        Ok(0)
+    }
+
+    fn execute_create_index(
+        &self,
+        data: &CreateIndexData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32> {
+        self.index_manager.create_index(
+            &data.index_name,
+            &data.table_name,
+            &data.field_name,
+            transaction,
+        )?;
+        Ok(0)
+    }
+}
+
+// RecordPageは型を確かめずに書き込むので、書く前にフィールドの定義と照らし合わせる
+fn check_type(schema: &Schema, field_name: &str, value: &Constant) -> anyhow::Result<()> {
+    let info = schema
+        .info(field_name)
+        .with_context(|| format!("field {} not found", field_name))?;
+    match (info, value) {
+        (FieldInfo::Int, Constant::Int(_))
+        | (FieldInfo::Bool, Constant::Bool(_))
+        | (FieldInfo::Float, Constant::Float(_))
+        | (FieldInfo::Long, Constant::Long(_))
+        | (FieldInfo::Timestamp, Constant::Timestamp(_)) => Ok(()),
+        (FieldInfo::String(length), Constant::String(value)) => {
+            if value.chars().count() > *length {
+                bail!(
+                    "{} is too long for field {} (max {} characters)",
+                    value,
+                    field_name,
+                    length
+                );
+            }
+            Ok(())
+        }
+        _ => bail!("{} cannot be stored in field {}", value, field_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::planner::basic_query_planner::BasicQueryPlanner;
+    use crate::planner::QueryPlanner;
+    use crate::sql::parser::{Parser, Statement};
+
+    fn execute(
+        planner: &BasicUpdatePlanner,
+        sql: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<i32> {
+        match Parser::new(sql)?.statement()? {
+            Statement::Insert(data) => planner.execute_insert(&data, transaction),
+            Statement::Delete(data) => planner.execute_delete(&data, transaction),
+            Statement::Update(data) => planner.execute_update(&data, transaction),
+            Statement::CreateTable(data) => planner.execute_create_table(&data, transaction),
+            Statement::CreateView(data) => planner.execute_create_view(&data, transaction),
+            Statement::CreateIndex(data) => planner.execute_create_index(&data, transaction),
+            Statement::Query(_) => bail!("not an update statement"),
+        }
+    }
+
+    fn select(
+        planner: &BasicQueryPlanner,
+        sql: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> Vec<(i32, String)> {
+        let query = Parser::new(sql).unwrap().query().unwrap();
+        let mut scan = planner
+            .create_plan(&query, transaction)
+            .unwrap()
+            .open()
+            .unwrap();
+        let mut rows = vec![];
+        while scan.next().unwrap() {
+            rows.push((
+                scan.get_int("id").unwrap(),
+                scan.get_string("name").unwrap(),
+            ));
+        }
+        scan.close().unwrap();
+        rows
+    }
+
+    #[test]
+    fn basic_update_planner() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        let planner =
+            BasicUpdatePlanner::new(db.table_manager(), db.view_manager(), db.index_manager());
+        let query_planner = BasicQueryPlanner::new(db.table_manager(), db.statistics_manager());
+
+        assert_eq!(
+            execute(
+                &planner,
+                "create table t (id int, name varchar(10))",
+                &transaction
+            )
+            .unwrap(),
+            0
+        );
+        assert_eq!(
+            execute(&planner, "insert into t values (1, 'Alice')", &transaction).unwrap(),
+            1
+        );
+        assert_eq!(
+            execute(
+                &planner,
+                "insert into t (name, id) values ('Bob', 2)",
+                &transaction
+            )
+            .unwrap(),
+            1
+        );
+        assert_eq!(
+            select(&query_planner, "select * from t", &transaction),
+            vec![(1, "Alice".to_string()), (2, "Bob".to_string())]
+        );
+
+        assert_eq!(
+            execute(
+                &planner,
+                "update t set name = 'Carol' where id = 2",
+                &transaction
+            )
+            .unwrap(),
+            1
+        );
+        assert_eq!(
+            execute(&planner, "update t set id = 3 where id = 9", &transaction).unwrap(),
+            0
+        );
+        assert_eq!(
+            execute(&planner, "delete from t where id = 1", &transaction).unwrap(),
+            1
+        );
+        assert_eq!(
+            select(&query_planner, "select * from t", &transaction),
+            vec![(2, "Carol".to_string())]
+        );
+
+        // 型や長さの合わない値、足りない値は書き込まない
+        assert!(execute(&planner, "insert into t values (1)", &transaction).is_err());
+        assert!(execute(&planner, "insert into t values ('x', 'y')", &transaction).is_err());
+        assert!(execute(
+            &planner,
+            "insert into t values (1, 'abcdefghijk')",
+            &transaction
+        )
+        .is_err());
+        assert!(execute(&planner, "update t set id = 'x'", &transaction).is_err());
+        assert!(execute(&planner, "update t set age = 1", &transaction).is_err());
+        assert!(execute(&planner, "insert into u values (1)", &transaction).is_err());
+        assert_eq!(
+            select(&query_planner, "select * from t", &transaction),
+            vec![(2, "Carol".to_string())]
+        );
+
+        execute(&planner, "create index t_id on t (id)", &transaction).unwrap();
+        assert_eq!(
+            db.index_manager()
+                .get_index_info("t", &transaction)
+                .unwrap()
+                .len(),
+            1
+        );
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
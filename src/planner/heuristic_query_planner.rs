@@ -0,0 +1,434 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::bail;
+
+use crate::catalog::index_manager::IndexInfo;
+use crate::catalog::index_manager::IndexManager;
+use crate::catalog::statistics_manager::StatisticsManager;
+use crate::catalog::table_manager::TableManager;
+use crate::catalog::view_manager::ViewManager;
+use crate::record_manager::predicate::Predicate;
+use crate::record_manager::schema::Schema;
+use crate::sql::parser::{Parser, QueryData};
+use crate::transaction::transaction::Transaction;
+
+use super::index_join_plan::IndexJoinPlan;
+use super::index_select_plan::IndexSelectPlan;
+use super::plan::Plan;
+use super::product_plan::ProductPlan;
+use super::project_plan::ProjectPlan;
+use super::select_plan::SelectPlan;
+use super::table_plan::TablePlan;
+use super::QueryPlanner;
+
+// 絞り込んだ後のレコード数が少ないテーブルから順に結合する。
+// 直積の中間結果が大きくなるのを避けるための貪欲法で、最適な順序になるとは限らない。
+// 索引を張ったフィールドが定数と比べられていれば、テーブル全体を読まずに索引で引く。
+// 結合するフィールドに索引があれば、直積ではなく索引で相手のレコードを引く
+pub struct HeuristicQueryPlanner {
+    table_manager: Arc<TableManager>,
+    view_manager: Arc<ViewManager>,
+    index_manager: Arc<IndexManager>,
+    statistics_manager: Arc<StatisticsManager>,
+}
+
+impl HeuristicQueryPlanner {
+    pub fn new(
+        table_manager: Arc<TableManager>,
+        view_manager: Arc<ViewManager>,
+        index_manager: Arc<IndexManager>,
+        statistics_manager: Arc<StatisticsManager>,
+    ) -> Self {
+        Self {
+            table_manager,
+            view_manager,
+            index_manager,
+            statistics_manager,
+        }
+    }
+
+    // FROM句の名前と、単体で評価できるTermで絞り込んだPlanを、結合する順に並べる
+    fn join_order(
+        &self,
+        query: &QueryData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Vec<(String, Box<dyn Plan>)>> {
+        let mut remaining = vec![];
+        for table_name in &query.tables {
+            let plan = self.table_or_view_plan(table_name, &query.predicate, transaction)?;
+            let predicate = query.predicate.select_sub_pred(plan.schema());
+            let plan: Box<dyn Plan> = Box::new(SelectPlan::new(plan, predicate));
+            remaining.push((table_name.clone(), plan));
+        }
+
+        let mut order = vec![];
+        while !remaining.is_empty() {
+            let mut lowest = 0;
+            for (i, (_, plan)) in remaining.iter().enumerate() {
+                if plan.estimated_records_output() < remaining[lowest].1.estimated_records_output()
+                {
+                    lowest = i;
+                }
+            }
+            order.push(remaining.remove(lowest));
+        }
+        Ok(order)
+    }
+
+    // table_nameがテーブルで、outer_schemaのフィールドと等しいとされるフィールドに索引があれば、
+    // そのテーブルのPlanと索引、結合に使うouter側のフィールド名を返す
+    fn index_join(
+        &self,
+        table_name: &str,
+        outer_schema: &Schema,
+        query: &QueryData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Option<(TablePlan, IndexInfo, String)>> {
+        if self.view_manager.has_view(table_name, transaction)? {
+            return Ok(None);
+        }
+        let mut indexes: Vec<_> = self
+            .index_manager
+            .get_index_info(table_name, transaction)?
+            .into_iter()
+            .collect();
+        indexes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (field_name, index_info) in indexes {
+            let Some(join_field) = query.predicate.equates_with_field(&field_name) else {
+                continue;
+            };
+            if outer_schema.has_field(join_field) {
+                let table_plan = TablePlan::new(
+                    table_name,
+                    &self.table_manager,
+                    &self.statistics_manager,
+                    transaction,
+                )?;
+                return Ok(Some((table_plan, index_info, join_field.to_string())));
+            }
+        }
+        Ok(None)
+    }
+
+    // ビューなら定義のSQLから組み立て直す
+    fn table_or_view_plan(
+        &self,
+        name: &str,
+        predicate: &Predicate,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Box<dyn Plan>> {
+        if self.view_manager.has_view(name, transaction)? {
+            let view_def = self.view_manager.get_view_definition(name, transaction)?;
+            let view_query = Parser::new(&view_def)?.query()?;
+            return self.create_plan(&view_query, transaction);
+        }
+        let table_plan = TablePlan::new(
+            name,
+            &self.table_manager,
+            &self.statistics_manager,
+            transaction,
+        )?;
+
+        // 毎回同じプランになるよう、フィールド名の順に索引を試す
+        let mut indexes: Vec<_> = self
+            .index_manager
+            .get_index_info(name, transaction)?
+            .into_iter()
+            .collect();
+        indexes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (field_name, index_info) in indexes {
+            if let Some(search_key) = predicate.equates_with_constant(&field_name) {
+                return Ok(Box::new(IndexSelectPlan::new(
+                    table_plan,
+                    index_info,
+                    search_key.clone(),
+                    transaction,
+                )));
+            }
+        }
+        Ok(Box::new(table_plan))
+    }
+}
+
+impl QueryPlanner for HeuristicQueryPlanner {
+    fn create_plan(
+        &self,
+        query: &QueryData,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Box<dyn Plan>> {
+        let mut plan: Option<Box<dyn Plan>> = None;
+        for (table_name, next_plan) in self.join_order(query, transaction)? {
+            plan = Some(match plan {
+                Some(plan) => {
+                    // 結合して初めて評価できるTermをすぐに適用する
+                    let join_predicate = query
+                        .predicate
+                        .join_sub_pred(plan.schema(), next_plan.schema())?;
+                    match self.index_join(&table_name, plan.schema(), query, transaction)? {
+                        Some((table_plan, index_info, join_field)) => {
+                            // next_planを使わないので、next_planで絞り込んでいたTermも結合した後に適用する
+                            let mut predicate = query.predicate.select_sub_pred(next_plan.schema());
+                            predicate.conjoin_with(join_predicate);
+                            let index_join_plan = Box::new(IndexJoinPlan::new(
+                                plan,
+                                table_plan,
+                                index_info,
+                                &join_field,
+                                transaction,
+                            )?);
+                            Box::new(SelectPlan::new(index_join_plan, predicate))
+                        }
+                        None => {
+                            let product_plan = Box::new(ProductPlan::new(plan, next_plan)?);
+                            Box::new(SelectPlan::new(product_plan, join_predicate))
+                        }
+                    }
+                }
+                None => next_plan,
+            });
+        }
+        let Some(plan) = plan else {
+            bail!("query has no tables");
+        };
+
+        match &query.fields {
+            Some(fields) => Ok(Box::new(ProjectPlan::new(plan, fields)?)),
+            None => Ok(plan),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::planner::basic_query_planner::BasicQueryPlanner;
+    use crate::planner::basic_update_planner::BasicUpdatePlanner;
+    use crate::planner::UpdatePlanner;
+    use crate::sql::parser::Statement;
+
+    #[test]
+    fn heuristic_query_planner() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        // small 10件、mid 100件、big 1000件。midはsmallを、bigはmidを参照する
+        let update_planner =
+            BasicUpdatePlanner::new(db.table_manager(), db.view_manager(), db.index_manager());
+        let execute = |sql: &str| {
+            let mut parser = Parser::new(sql).unwrap();
+            if sql.starts_with("create") {
+                let data = parser.create_table().unwrap();
+                update_planner
+                    .execute_create_table(&data, &transaction)
+                    .unwrap();
+            } else {
+                let data = parser.insert().unwrap();
+                update_planner.execute_insert(&data, &transaction).unwrap();
+            }
+        };
+        execute("create table small (sid int, sname varchar(10))");
+        execute("create table mid (mid int, msid int)");
+        execute("create table big (bid int, bmid int)");
+        for n in 0..10 {
+            execute(&format!("insert into small values ({}, 's{}')", n, n));
+        }
+        for n in 0..100 {
+            execute(&format!("insert into mid values ({}, {})", n, n % 10));
+        }
+        for n in 0..1000 {
+            execute(&format!("insert into big values ({}, {})", n, n % 100));
+        }
+
+        let planner = HeuristicQueryPlanner::new(
+            db.table_manager(),
+            db.view_manager(),
+            db.index_manager(),
+            db.statistics_manager(),
+        );
+        let query = Parser::new("select bid from big, mid, small where bmid = mid and msid = sid")
+            .unwrap()
+            .query()
+            .unwrap();
+        let order: Vec<String> = planner
+            .join_order(&query, &transaction)
+            .unwrap()
+            .into_iter()
+            .map(|(table_name, _)| table_name)
+            .collect();
+        assert_eq!(order, vec!["small", "mid", "big"]);
+
+        // FROM句の順に直積を取るよりも読むブロックが少ない
+        let heuristic_plan = planner.create_plan(&query, &transaction).unwrap();
+        let basic_plan = BasicQueryPlanner::new(db.table_manager(), db.statistics_manager())
+            .create_plan(&query, &transaction)
+            .unwrap();
+        assert!(
+            heuristic_plan.estimated_blocks_accessed() < basic_plan.estimated_blocks_accessed()
+        );
+
+        let query = Parser::new(
+            "select bid, sname from big, mid, small where bmid = mid and msid = sid and sname = 's3'",
+        )
+        .unwrap()
+        .query()
+        .unwrap();
+        let mut scan = planner
+            .create_plan(&query, &transaction)
+            .unwrap()
+            .open()
+            .unwrap();
+        let mut bids = vec![];
+        while scan.next().unwrap() {
+            assert_eq!(scan.get_string("sname").unwrap(), "s3");
+            bids.push(scan.get_int("bid").unwrap());
+        }
+        scan.close().unwrap();
+        bids.sort();
+        assert_eq!(bids, (0..1000).filter(|n| n % 10 == 3).collect::<Vec<_>>());
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn index_select() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            block_size: 400,
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        let update_planner =
+            BasicUpdatePlanner::new(db.table_manager(), db.view_manager(), db.index_manager());
+        let data = Parser::new("create table emp (id int, dept int)")
+            .unwrap()
+            .create_table()
+            .unwrap();
+        update_planner
+            .execute_create_table(&data, &transaction)
+            .unwrap();
+        for n in 0..1000 {
+            let data = Parser::new(&format!("insert into emp values ({}, {})", n, n % 100))
+                .unwrap()
+                .insert()
+                .unwrap();
+            update_planner.execute_insert(&data, &transaction).unwrap();
+        }
+
+        let planner = HeuristicQueryPlanner::new(
+            db.table_manager(),
+            db.view_manager(),
+            db.index_manager(),
+            db.statistics_manager(),
+        );
+        let query = Parser::new("select id from emp where dept = 42")
+            .unwrap()
+            .query()
+            .unwrap();
+        let table_scan_blocks = planner
+            .create_plan(&query, &transaction)
+            .unwrap()
+            .estimated_blocks_accessed();
+
+        let data = Parser::new("create index emp_dept on emp (dept)")
+            .unwrap()
+            .create_index()
+            .unwrap();
+        update_planner
+            .execute_create_index(&data, &transaction)
+            .unwrap();
+        let plan = planner.create_plan(&query, &transaction).unwrap();
+        assert!(plan.estimated_blocks_accessed() < table_scan_blocks);
+
+        let mut scan = plan.open().unwrap();
+        let mut ids = vec![];
+        while scan.next().unwrap() {
+            ids.push(scan.get_int("id").unwrap());
+        }
+        scan.close().unwrap();
+        ids.sort();
+        assert_eq!(ids, (0..10).map(|n| n * 100 + 42).collect::<Vec<_>>());
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn index_join() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            block_size: 400,
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        let update_planner =
+            BasicUpdatePlanner::new(db.table_manager(), db.view_manager(), db.index_manager());
+        let execute = |sql: &str| {
+            match Parser::new(sql).unwrap().statement().unwrap() {
+                Statement::CreateTable(data) => update_planner
+                    .execute_create_table(&data, &transaction)
+                    .unwrap(),
+                Statement::CreateIndex(data) => update_planner
+                    .execute_create_index(&data, &transaction)
+                    .unwrap(),
+                Statement::Insert(data) => {
+                    update_planner.execute_insert(&data, &transaction).unwrap()
+                }
+                _ => unreachable!(),
+            };
+        };
+        execute("create table parent (pid int, pname varchar(10))");
+        execute("create table child (cid int, cpid int)");
+        for pid in 0..100 {
+            execute(&format!("insert into parent values ({}, 'p{}')", pid, pid));
+        }
+        for cid in 0..1000 {
+            execute(&format!(
+                "insert into child values ({}, {})",
+                cid,
+                cid % 100
+            ));
+        }
+
+        let planner = HeuristicQueryPlanner::new(
+            db.table_manager(),
+            db.view_manager(),
+            db.index_manager(),
+            db.statistics_manager(),
+        );
+        let query =
+            Parser::new("select pname, cid from parent, child where pid = cpid and pname = 'p3'")
+                .unwrap()
+                .query()
+                .unwrap();
+        let product_blocks = planner
+            .create_plan(&query, &transaction)
+            .unwrap()
+            .estimated_blocks_accessed();
+
+        execute("create index child_cpid on child (cpid)");
+        let plan = planner.create_plan(&query, &transaction).unwrap();
+        assert!(plan.estimated_blocks_accessed() < product_blocks);
+
+        let mut scan = plan.open().unwrap();
+        let mut cids = vec![];
+        while scan.next().unwrap() {
+            assert_eq!(scan.get_string("pname").unwrap(), "p3");
+            cids.push(scan.get_int("cid").unwrap());
+        }
+        scan.close().unwrap();
+        cids.sort();
+        assert_eq!(cids, (0..10).map(|n| n * 100 + 3).collect::<Vec<_>>());
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::{Arc, Mutex};
+
+use crate::catalog::index_manager::IndexInfo;
+use crate::record_manager::index_join_scan::IndexJoinScan;
+use crate::record_manager::scan::Scan;
+use crate::record_manager::schema::Schema;
+use crate::transaction::transaction::Transaction;
+
+use super::plan::{Plan, ScanStatistics};
+use super::table_plan::TablePlan;
+
+// outerのjoin_fieldと、innerの索引を張ったフィールドが等しいレコードの組を索引で引く
+pub struct IndexJoinPlan {
+    transaction: Arc<Mutex<Transaction>>,
+    outer: Box<dyn Plan>,
+    inner: TablePlan,
+    index_info: IndexInfo,
+    join_field: String,
+    schema: Schema,
+}
+
+impl IndexJoinPlan {
+    pub fn new(
+        outer: Box<dyn Plan>,
+        inner: TablePlan,
+        index_info: IndexInfo,
+        join_field: &str,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Self> {
+        let mut schema = Schema::new();
+        schema.add_all(outer.schema())?;
+        schema.add_all(inner.schema())?;
+        Ok(Self {
+            transaction: Arc::clone(transaction),
+            outer,
+            inner,
+            index_info,
+            join_field: join_field.to_string(),
+            schema,
+        })
+    }
+}
+
+impl Plan for IndexJoinPlan {
+    fn open(&self) -> anyhow::Result<Box<dyn Scan>> {
+        let outer = self.outer.open()?;
+        let index = self.index_info.open(&self.transaction)?;
+        let inner_table = self.inner.open_table_scan()?;
+        Ok(Box::new(IndexJoinScan::new(
+            outer,
+            index,
+            inner_table,
+            &self.join_field,
+        )?))
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+}
+
+impl ScanStatistics for IndexJoinPlan {
+    // outerを1回読み、outerのレコードごとに索引を探し、一致したレコードごとに1ブロック読む
+    fn estimated_blocks_accessed(&self) -> i32 {
+        self.outer
+            .estimated_blocks_accessed()
+            .saturating_add(
+                self.outer
+                    .estimated_records_output()
+                    .saturating_mul(self.index_info.blocks_accessed()),
+            )
+            .saturating_add(self.estimated_records_output())
+    }
+
+    fn estimated_records_output(&self) -> i32 {
+        self.outer
+            .estimated_records_output()
+            .saturating_mul(self.index_info.records_output())
+    }
+
+    fn distinct_values(&self, field_name: &str) -> i32 {
+        if self.outer.schema().has_field(field_name) {
+            self.outer.distinct_values(field_name)
+        } else {
+            self.inner.distinct_values(field_name)
+        }
+    }
+}
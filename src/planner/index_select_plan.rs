@@ -0,0 +1,71 @@
+use std::sync::{Arc, Mutex};
+
+use crate::catalog::index_manager::IndexInfo;
+use crate::record_manager::constant::Constant;
+use crate::record_manager::index_select_scan::IndexSelectScan;
+use crate::record_manager::scan::Scan;
+use crate::record_manager::schema::Schema;
+use crate::transaction::transaction::Transaction;
+
+use super::plan::{Plan, ScanStatistics};
+use super::table_plan::TablePlan;
+
+// 索引を張ったフィールド = 定数 のレコードだけを索引で引く
+pub struct IndexSelectPlan {
+    transaction: Arc<Mutex<Transaction>>,
+    table_plan: TablePlan,
+    index_info: IndexInfo,
+    search_key: Constant,
+}
+
+impl IndexSelectPlan {
+    pub fn new(
+        table_plan: TablePlan,
+        index_info: IndexInfo,
+        search_key: Constant,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> Self {
+        Self {
+            transaction: Arc::clone(transaction),
+            table_plan,
+            index_info,
+            search_key,
+        }
+    }
+}
+
+impl Plan for IndexSelectPlan {
+    fn open(&self) -> anyhow::Result<Box<dyn Scan>> {
+        let table_scan = self.table_plan.open_table_scan()?;
+        let index = self.index_info.open(&self.transaction)?;
+        Ok(Box::new(IndexSelectScan::new(
+            index,
+            table_scan,
+            self.search_key.clone(),
+        )?))
+    }
+
+    fn schema(&self) -> &Schema {
+        self.table_plan.schema()
+    }
+}
+
+impl ScanStatistics for IndexSelectPlan {
+    // 索引を探すブロックに加えて、一致したレコードごとに1ブロック読む
+    fn estimated_blocks_accessed(&self) -> i32 {
+        self.index_info
+            .blocks_accessed()
+            .saturating_add(self.estimated_records_output())
+    }
+
+    fn estimated_records_output(&self) -> i32 {
+        self.index_info.records_output()
+    }
+
+    fn distinct_values(&self, field_name: &str) -> i32 {
+        if field_name == self.index_info.field_name() {
+            return 1;
+        }
+        self.table_plan.distinct_values(field_name)
+    }
+}
@@ -0,0 +1,188 @@
+use crate::record_manager::scan::Scan;
+use crate::record_manager::schema::Schema;
+
+// プランのコストの見積もり。ヒューリスティックなプランナーが結合の順や索引を選ぶのに使う
+pub trait ScanStatistics {
+    // Scanを最後まで読むときにアクセスするブロック数
+    fn estimated_blocks_accessed(&self) -> i32;
+    fn estimated_records_output(&self) -> i32;
+    // 出力のfield_nameに現れる値の種類の数
+    fn distinct_values(&self, field_name: &str) -> i32;
+}
+
+// クエリの実行方法を表す木。openでScanを作り、ScanStatisticsでコストの見積もりを返す
+pub trait Plan: ScanStatistics {
+    fn open(&self) -> anyhow::Result<Box<dyn Scan>>;
+    fn schema(&self) -> &Schema;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::planner::basic_update_planner::BasicUpdatePlanner;
+    use crate::planner::index_join_plan::IndexJoinPlan;
+    use crate::planner::index_select_plan::IndexSelectPlan;
+    use crate::planner::product_plan::ProductPlan;
+    use crate::planner::project_plan::ProjectPlan;
+    use crate::planner::select_plan::SelectPlan;
+    use crate::planner::table_plan::TablePlan;
+    use crate::planner::UpdatePlanner;
+    use crate::record_manager::constant::Constant;
+    use crate::record_manager::predicate::{Expression, Operator, Predicate, Term};
+    use crate::sql::parser::{Parser, Statement};
+
+    #[test]
+    fn scan_statistics() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            block_size: 400,
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        // student 300件、dept 10件。majoridはdidを参照する
+        let update_planner =
+            BasicUpdatePlanner::new(db.table_manager(), db.view_manager(), db.index_manager());
+        let execute = |sql: &str| {
+            match Parser::new(sql).unwrap().statement().unwrap() {
+                Statement::CreateTable(data) => update_planner
+                    .execute_create_table(&data, &transaction)
+                    .unwrap(),
+                Statement::CreateIndex(data) => update_planner
+                    .execute_create_index(&data, &transaction)
+                    .unwrap(),
+                Statement::Insert(data) => {
+                    update_planner.execute_insert(&data, &transaction).unwrap()
+                }
+                _ => unreachable!(),
+            };
+        };
+        execute("create table student (sid int, majorid int)");
+        execute("create table dept (did int, dname varchar(10))");
+        for sid in 0..300 {
+            execute(&format!(
+                "insert into student values ({}, {})",
+                sid,
+                sid % 10
+            ));
+        }
+        for did in 0..10 {
+            execute(&format!("insert into dept values ({}, 'd{}')", did, did));
+        }
+        execute("create index student_majorid on student (majorid)");
+
+        let table_plan = |table_name: &str| {
+            TablePlan::new(
+                table_name,
+                &db.table_manager(),
+                &db.statistics_manager(),
+                &transaction,
+            )
+            .unwrap()
+        };
+        let size = |table_name: &str| {
+            transaction
+                .lock()
+                .unwrap()
+                .size(format!("{}.tbl", table_name))
+                .unwrap()
+        };
+
+        // TablePlanは統計情報の値をそのまま返す
+        let student = table_plan("student");
+        let dept = table_plan("dept");
+        assert_eq!(student.estimated_records_output(), 300);
+        assert_eq!(student.estimated_blocks_accessed(), size("student"));
+        assert_eq!(student.distinct_values("sid"), 101);
+        assert_eq!(dept.estimated_records_output(), 10);
+        assert_eq!(dept.estimated_blocks_accessed(), size("dept"));
+
+        // SelectPlanはreduction factorで割る
+        let sid_is_5 = Term::new(
+            Expression::field("sid"),
+            Operator::Eq,
+            Expression::constant(5),
+        );
+        let select = SelectPlan::new(
+            Box::new(table_plan("student")),
+            Predicate::new().with_term(sid_is_5),
+        );
+        assert_eq!(select.estimated_records_output(), 300 / 101);
+        assert_eq!(
+            select.estimated_blocks_accessed(),
+            student.estimated_blocks_accessed()
+        );
+        assert_eq!(select.distinct_values("sid"), 1);
+        assert_eq!(select.distinct_values("majorid"), 101);
+
+        // ProductPlanは両側を掛け合わせる
+        let product = ProductPlan::new(
+            Box::new(table_plan("student")),
+            Box::new(table_plan("dept")),
+        )
+        .unwrap();
+        assert_eq!(product.estimated_records_output(), 300 * 10);
+        assert_eq!(
+            product.estimated_blocks_accessed(),
+            size("student") + 300 * size("dept")
+        );
+        assert_eq!(product.distinct_values("sid"), 101);
+        assert_eq!(product.distinct_values("dname"), 4);
+
+        // ProjectPlanは見積もりを変えない
+        let project =
+            ProjectPlan::new(Box::new(table_plan("student")), &["sid".to_string()]).unwrap();
+        assert_eq!(project.estimated_records_output(), 300);
+        assert_eq!(project.estimated_blocks_accessed(), size("student"));
+        assert_eq!(project.distinct_values("sid"), 101);
+
+        // IndexSelectPlanはテーブルのレコード数を値の種類の数で割る
+        let index_info = db
+            .index_manager()
+            .get_index_info("student", &transaction)
+            .unwrap()
+            .remove("majorid")
+            .unwrap();
+        let index_select = IndexSelectPlan::new(
+            table_plan("student"),
+            index_info,
+            Constant::from(3),
+            &transaction,
+        );
+        let per_key = 300 / student.distinct_values("majorid");
+        assert_eq!(index_select.estimated_records_output(), per_key);
+        assert!(index_select.estimated_blocks_accessed() > per_key);
+        assert!(index_select.estimated_blocks_accessed() < student.estimated_blocks_accessed());
+        assert_eq!(index_select.distinct_values("majorid"), 1);
+        assert_eq!(index_select.distinct_values("sid"), 101);
+
+        // IndexJoinPlanはouterのレコードごとに索引で引く
+        let index_info = db
+            .index_manager()
+            .get_index_info("student", &transaction)
+            .unwrap()
+            .remove("majorid")
+            .unwrap();
+        let index_join = IndexJoinPlan::new(
+            Box::new(table_plan("dept")),
+            table_plan("student"),
+            index_info,
+            "did",
+            &transaction,
+        )
+        .unwrap();
+        assert_eq!(index_join.estimated_records_output(), 10 * per_key);
+        assert!(index_join.estimated_blocks_accessed() < product.estimated_blocks_accessed());
+        assert_eq!(index_join.distinct_values("dname"), 4);
+        assert_eq!(index_join.distinct_values("sid"), 101);
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::bail;
+
+use crate::record_manager::product_scan::ProductScan;
+use crate::record_manager::scan::Scan;
+use crate::record_manager::schema::Schema;
+
+use super::plan::{Plan, ScanStatistics};
+
+pub struct ProductPlan {
+    plan1: Box<dyn Plan>,
+    plan2: Box<dyn Plan>,
+    schema: Schema,
+}
+
+impl ProductPlan {
+    pub fn new(plan1: Box<dyn Plan>, plan2: Box<dyn Plan>) -> anyhow::Result<Self> {
+        // ProductScanは同じ名前のフィールドをscan1から読むので、重なりがあれば組み立てない
+        let shared = plan1.schema().intersection(plan2.schema());
+        if !shared.is_empty() {
+            bail!(
+                "fields {} appear on both sides of the product",
+                shared.join(", ")
+            );
+        }
+        let mut schema = Schema::new();
+        schema.add_all(plan1.schema())?;
+        schema.add_all(plan2.schema())?;
+        Ok(Self {
+            plan1,
+            plan2,
+            schema,
+        })
+    }
+}
+
+impl Plan for ProductPlan {
+    fn open(&self) -> anyhow::Result<Box<dyn Scan>> {
+        let scan1 = self.plan1.open()?;
+        let scan2 = self.plan2.open()?;
+        Ok(Box::new(ProductScan::new(scan1, scan2)?))
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+}
+
+impl ScanStatistics for ProductPlan {
+    // plan1を1回、plan1のレコードごとにplan2を1回読む
+    fn estimated_blocks_accessed(&self) -> i32 {
+        self.plan1.estimated_blocks_accessed().saturating_add(
+            self.plan1
+                .estimated_records_output()
+                .saturating_mul(self.plan2.estimated_blocks_accessed()),
+        )
+    }
+
+    fn estimated_records_output(&self) -> i32 {
+        self.plan1
+            .estimated_records_output()
+            .saturating_mul(self.plan2.estimated_records_output())
+    }
+
+    fn distinct_values(&self, field_name: &str) -> i32 {
+        if self.plan1.schema().has_field(field_name) {
+            self.plan1.distinct_values(field_name)
+        } else {
+            self.plan2.distinct_values(field_name)
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::bail;
+
+use crate::record_manager::project_scan::ProjectScan;
+use crate::record_manager::scan::Scan;
+use crate::record_manager::schema::Schema;
+
+use super::plan::{Plan, ScanStatistics};
+
+pub struct ProjectPlan {
+    inner: Box<dyn Plan>,
+    schema: Schema,
+}
+
+impl ProjectPlan {
+    pub fn new(inner: Box<dyn Plan>, fields: &[String]) -> anyhow::Result<Self> {
+        let mut schema = Schema::new();
+        for field in fields {
+            if !inner.schema().has_field(field) {
+                bail!("field {} not found", field);
+            }
+            schema.add(field.clone(), inner.schema())?;
+        }
+        Ok(Self { inner, schema })
+    }
+}
+
+impl Plan for ProjectPlan {
+    fn open(&self) -> anyhow::Result<Box<dyn Scan>> {
+        let inner = self.inner.open()?;
+        Ok(Box::new(ProjectScan::new(
+            inner,
+            self.schema.fields().clone(),
+        )))
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+}
+
+impl ScanStatistics for ProjectPlan {
+    fn estimated_blocks_accessed(&self) -> i32 {
+        self.inner.estimated_blocks_accessed()
+    }
+
+    fn estimated_records_output(&self) -> i32 {
+        self.inner.estimated_records_output()
+    }
+
+    fn distinct_values(&self, field_name: &str) -> i32 {
+        self.inner.distinct_values(field_name)
+    }
+}
@@ -0,0 +1,49 @@
+use crate::record_manager::predicate::Predicate;
+use crate::record_manager::scan::Scan;
+use crate::record_manager::schema::Schema;
+use crate::record_manager::select_scan::SelectScan;
+
+use super::plan::{Plan, ScanStatistics};
+
+pub struct SelectPlan {
+    inner: Box<dyn Plan>,
+    predicate: Predicate,
+}
+
+impl SelectPlan {
+    pub fn new(inner: Box<dyn Plan>, predicate: Predicate) -> Self {
+        Self { inner, predicate }
+    }
+}
+
+impl Plan for SelectPlan {
+    fn open(&self) -> anyhow::Result<Box<dyn Scan>> {
+        let inner = self.inner.open()?;
+        Ok(Box::new(SelectScan::new(inner, self.predicate.clone())))
+    }
+
+    fn schema(&self) -> &Schema {
+        self.inner.schema()
+    }
+}
+
+impl ScanStatistics for SelectPlan {
+    fn estimated_blocks_accessed(&self) -> i32 {
+        self.inner.estimated_blocks_accessed()
+    }
+
+    fn estimated_records_output(&self) -> i32 {
+        self.inner.estimated_records_output() / self.predicate.reduction_factor(self.inner.as_ref())
+    }
+
+    fn distinct_values(&self, field_name: &str) -> i32 {
+        if self.predicate.equates_with_constant(field_name).is_some() {
+            return 1;
+        }
+        let distinct_values = self.inner.distinct_values(field_name);
+        match self.predicate.equates_with_field(field_name) {
+            Some(other) => distinct_values.min(self.inner.distinct_values(other)),
+            None => distinct_values,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::{Arc, Mutex};
+
+use crate::catalog::statistics_manager::{StatInfo, StatisticsManager};
+use crate::catalog::table_manager::TableManager;
+use crate::record_manager::layout::Layout;
+use crate::record_manager::scan::Scan;
+use crate::record_manager::schema::Schema;
+use crate::record_manager::table_scan::TableScan;
+use crate::transaction::transaction::Transaction;
+
+use super::plan::{Plan, ScanStatistics};
+
+pub struct TablePlan {
+    transaction: Arc<Mutex<Transaction>>,
+    table_name: String,
+    layout: Arc<Layout>,
+    stat_info: StatInfo,
+}
+
+impl TablePlan {
+    pub fn new(
+        table_name: &str,
+        table_manager: &TableManager,
+        statistics_manager: &StatisticsManager,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Self> {
+        let layout = Arc::new(table_manager.get_layout(table_name, transaction)?);
+        let stat_info = statistics_manager.get_stat_info(table_name, &layout, transaction)?;
+        Ok(Self {
+            transaction: Arc::clone(transaction),
+            table_name: table_name.to_string(),
+            layout,
+            stat_info,
+        })
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    // RIDで移動したいときのために、Box<dyn Scan>ではなくTableScanのまま開く
+    pub fn open_table_scan(&self) -> anyhow::Result<TableScan> {
+        TableScan::new(
+            Arc::clone(&self.transaction),
+            &self.table_name,
+            Arc::clone(&self.layout),
+        )
+    }
+}
+
+impl Plan for TablePlan {
+    fn open(&self) -> anyhow::Result<Box<dyn Scan>> {
+        Ok(Box::new(self.open_table_scan()?))
+    }
+
+    fn schema(&self) -> &Schema {
+        self.layout.schema()
+    }
+}
+
+impl ScanStatistics for TablePlan {
+    fn estimated_blocks_accessed(&self) -> i32 {
+        self.stat_info.num_blocks
+    }
+
+    fn estimated_records_output(&self) -> i32 {
+        self.stat_info.num_records
+    }
+
+    // 値の分布は記録していないので、レコード数の1/3程度とみなす
+    fn distinct_values(&self, _field_name: &str) -> i32 {
+        1 + self.stat_info.num_records / 3
+    }
+}
@@ -0,0 +1,18 @@
+pub mod aggregation_fn;
+pub mod aggregation_scan;
+pub mod constant;
+pub mod distinct_scan;
+pub mod index_join_scan;
+pub mod index_select_scan;
+pub mod layout;
+pub mod limit_scan;
+pub mod predicate;
+pub mod product_scan;
+pub mod project_scan;
+pub mod record_page;
+pub mod rid;
+pub mod scan;
+pub mod schema;
+pub mod select_scan;
+pub mod sort_scan;
+pub mod table_scan;
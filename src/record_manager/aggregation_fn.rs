@@ -0,0 +1,268 @@
+use anyhow::{bail, Context};
+
+use super::constant::Constant;
+use super::scan::Scan;
+
+// グループのレコードを1件ずつ受け取って集計する関数。
+// process_firstでグループの最初のレコードを受け取り、それまでの集計を捨てる
+pub trait AggregationFn: Send {
+    fn process_first(&mut self, scan: &mut dyn Scan) -> anyhow::Result<()>;
+    fn process_next(&mut self, scan: &mut dyn Scan) -> anyhow::Result<()>;
+    // 集計結果のフィールド名
+    fn field_name(&self) -> &str;
+    fn value(&self) -> Constant;
+    // グループごとに別々に集計するための、同じ種類の関数の複製
+    fn boxed_clone(&self) -> Box<dyn AggregationFn>;
+}
+
+// COUNT(field)。NULLがないので、COUNT(*)と同じくグループのレコード数になる
+#[derive(Debug, Clone)]
+pub struct CountFn {
+    field_name: String,
+    count: i32,
+}
+
+impl CountFn {
+    pub fn new(field_name: &str) -> Self {
+        Self {
+            field_name: format!("countof{}", field_name),
+            count: 0,
+        }
+    }
+
+    // COUNT(*)
+    pub fn all() -> Self {
+        Self {
+            field_name: "count".to_string(),
+            count: 0,
+        }
+    }
+}
+
+impl AggregationFn for CountFn {
+    fn process_first(&mut self, _scan: &mut dyn Scan) -> anyhow::Result<()> {
+        self.count = 1;
+        Ok(())
+    }
+
+    fn process_next(&mut self, _scan: &mut dyn Scan) -> anyhow::Result<()> {
+        self.count = self
+            .count
+            .checked_add(1)
+            .context("count overflows an int")?;
+        Ok(())
+    }
+
+    fn field_name(&self) -> &str {
+        &self.field_name
+    }
+
+    fn value(&self) -> Constant {
+        Constant::Int(self.count)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn AggregationFn> {
+        Box::new(self.clone())
+    }
+}
+
+// intの合計はint、floatの合計はfloatになる
+#[derive(Debug, Clone)]
+pub struct SumFn {
+    source_field: String,
+    field_name: String,
+    sum: Constant,
+}
+
+impl SumFn {
+    pub fn new(field_name: &str) -> Self {
+        Self {
+            source_field: field_name.to_string(),
+            field_name: format!("sumof{}", field_name),
+            sum: Constant::Int(0),
+        }
+    }
+}
+
+impl AggregationFn for SumFn {
+    fn process_first(&mut self, scan: &mut dyn Scan) -> anyhow::Result<()> {
+        self.sum = match scan.get_val(&self.source_field)? {
+            value @ (Constant::Int(_) | Constant::Float(_) | Constant::Long(_)) => value,
+            value => bail!("cannot sum {} of {}", value, self.source_field),
+        };
+        Ok(())
+    }
+
+    fn process_next(&mut self, scan: &mut dyn Scan) -> anyhow::Result<()> {
+        self.sum = match (&self.sum, scan.get_val(&self.source_field)?) {
+            (Constant::Int(sum), Constant::Int(value)) => Constant::Int(
+                sum.checked_add(value)
+                    .with_context(|| format!("sum of {} overflows an int", self.source_field))?,
+            ),
+            (Constant::Float(sum), Constant::Float(value)) => Constant::Float(sum + value),
+            (Constant::Long(sum), Constant::Long(value)) => Constant::Long(
+                sum.checked_add(value)
+                    .with_context(|| format!("sum of {} overflows a long", self.source_field))?,
+            ),
+            (_, value) => bail!("cannot sum {} of {}", value, self.source_field),
+        };
+        Ok(())
+    }
+
+    fn field_name(&self) -> &str {
+        &self.field_name
+    }
+
+    fn value(&self) -> Constant {
+        self.sum.clone()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn AggregationFn> {
+        Box::new(self.clone())
+    }
+}
+
+// intの平均も小数になるのでfloatで返す
+#[derive(Debug, Clone)]
+pub struct AvgFn {
+    source_field: String,
+    field_name: String,
+    sum: f64,
+    count: usize,
+}
+
+impl AvgFn {
+    pub fn new(field_name: &str) -> Self {
+        Self {
+            source_field: field_name.to_string(),
+            field_name: format!("avgof{}", field_name),
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn number(&self, scan: &mut dyn Scan) -> anyhow::Result<f64> {
+        match scan.get_val(&self.source_field)? {
+            Constant::Int(value) => Ok(value as f64),
+            Constant::Float(value) => Ok(value),
+            Constant::Long(value) => Ok(value as f64),
+            value => bail!("cannot average {} of {}", value, self.source_field),
+        }
+    }
+}
+
+impl AggregationFn for AvgFn {
+    fn process_first(&mut self, scan: &mut dyn Scan) -> anyhow::Result<()> {
+        self.sum = self.number(scan)?;
+        self.count = 1;
+        Ok(())
+    }
+
+    fn process_next(&mut self, scan: &mut dyn Scan) -> anyhow::Result<()> {
+        self.sum += self.number(scan)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn field_name(&self) -> &str {
+        &self.field_name
+    }
+
+    fn value(&self) -> Constant {
+        Constant::Float(self.sum / self.count.max(1) as f64)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn AggregationFn> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MinFn {
+    source_field: String,
+    field_name: String,
+    min: Option<Constant>,
+}
+
+impl MinFn {
+    pub fn new(field_name: &str) -> Self {
+        Self {
+            source_field: field_name.to_string(),
+            field_name: format!("minof{}", field_name),
+            min: None,
+        }
+    }
+}
+
+impl AggregationFn for MinFn {
+    fn process_first(&mut self, scan: &mut dyn Scan) -> anyhow::Result<()> {
+        self.min = Some(scan.get_val(&self.source_field)?);
+        Ok(())
+    }
+
+    fn process_next(&mut self, scan: &mut dyn Scan) -> anyhow::Result<()> {
+        let value = scan.get_val(&self.source_field)?;
+        if self.min.as_ref().is_none_or(|min| value < *min) {
+            self.min = Some(value);
+        }
+        Ok(())
+    }
+
+    fn field_name(&self) -> &str {
+        &self.field_name
+    }
+
+    // レコードを1件も受け取っていなければ0を返す
+    fn value(&self) -> Constant {
+        self.min.clone().unwrap_or(Constant::Int(0))
+    }
+
+    fn boxed_clone(&self) -> Box<dyn AggregationFn> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MaxFn {
+    source_field: String,
+    field_name: String,
+    max: Option<Constant>,
+}
+
+impl MaxFn {
+    pub fn new(field_name: &str) -> Self {
+        Self {
+            source_field: field_name.to_string(),
+            field_name: format!("maxof{}", field_name),
+            max: None,
+        }
+    }
+}
+
+impl AggregationFn for MaxFn {
+    fn process_first(&mut self, scan: &mut dyn Scan) -> anyhow::Result<()> {
+        self.max = Some(scan.get_val(&self.source_field)?);
+        Ok(())
+    }
+
+    fn process_next(&mut self, scan: &mut dyn Scan) -> anyhow::Result<()> {
+        let value = scan.get_val(&self.source_field)?;
+        if self.max.as_ref().is_none_or(|max| value > *max) {
+            self.max = Some(value);
+        }
+        Ok(())
+    }
+
+    fn field_name(&self) -> &str {
+        &self.field_name
+    }
+
+    // レコードを1件も受け取っていなければ0を返す
+    fn value(&self) -> Constant {
+        self.max.clone().unwrap_or(Constant::Int(0))
+    }
+
+    fn boxed_clone(&self) -> Box<dyn AggregationFn> {
+        Box::new(self.clone())
+    }
+}
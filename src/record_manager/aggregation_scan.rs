@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context};
+
+use super::aggregation_fn::AggregationFn;
+use super::constant::Constant;
+use super::scan::Scan;
+
+// group_fieldsの値が等しいレコードごとに集計関数を適用し、1グループ1レコードで返す。
+// 最初のbefore_firstでinnerを読み切り、結果をメモリ上に持つので、グループ数はmax_groupsまでに制限する。
+// group_fieldsが空ならinner全体を1グループとして集計する
+pub struct AggregationScan<S: Scan> {
+    inner: S,
+    group_fields: Vec<String>,
+    aggregation_fns: Vec<Box<dyn AggregationFn>>,
+    max_groups: usize,
+    // 集計結果。Noneならまだinnerを読んでいない
+    groups: Option<Vec<HashMap<String, Constant>>>,
+    // 現在のグループ。before_firstの直後はNone
+    current: Option<usize>,
+}
+
+impl<S: Scan> AggregationScan<S> {
+    pub fn new(
+        inner: S,
+        group_fields: Vec<String>,
+        aggregation_fns: Vec<Box<dyn AggregationFn>>,
+        max_groups: usize,
+    ) -> anyhow::Result<Self> {
+        for field_name in &group_fields {
+            if !inner.has_field(field_name) {
+                bail!("group by field {} not found", field_name);
+            }
+        }
+        Ok(Self {
+            inner,
+            group_fields,
+            aggregation_fns,
+            max_groups,
+            groups: None,
+            current: None,
+        })
+    }
+
+    fn aggregate(&mut self) -> anyhow::Result<Vec<HashMap<String, Constant>>> {
+        // グループの値をDebug表記にしたものから、keysとstatesの位置を引く
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        let mut keys: Vec<Vec<Constant>> = vec![];
+        let mut states: Vec<Vec<Box<dyn AggregationFn>>> = vec![];
+
+        self.inner.before_first()?;
+        while self.inner.next()? {
+            let mut key = vec![];
+            for field_name in &self.group_fields {
+                key.push(self.inner.get_val(field_name)?);
+            }
+            match positions.get(&format!("{:?}", key)) {
+                Some(&position) => {
+                    for aggregation_fn in states[position].iter_mut() {
+                        aggregation_fn.process_next(&mut self.inner)?;
+                    }
+                }
+                None => {
+                    if keys.len() >= self.max_groups {
+                        bail!("aggregation exceeds {} groups", self.max_groups);
+                    }
+                    let mut fns: Vec<Box<dyn AggregationFn>> = self
+                        .aggregation_fns
+                        .iter()
+                        .map(|aggregation_fn| aggregation_fn.boxed_clone())
+                        .collect();
+                    for aggregation_fn in fns.iter_mut() {
+                        aggregation_fn.process_first(&mut self.inner)?;
+                    }
+                    positions.insert(format!("{:?}", key), keys.len());
+                    keys.push(key);
+                    states.push(fns);
+                }
+            }
+        }
+        // 集計し終えたら、innerを読み直すことはない
+        self.inner.close()?;
+
+        Ok(keys
+            .into_iter()
+            .zip(states)
+            .map(|(key, fns)| {
+                let mut group: HashMap<String, Constant> =
+                    self.group_fields.iter().cloned().zip(key).collect();
+                for aggregation_fn in fns {
+                    group.insert(
+                        aggregation_fn.field_name().to_string(),
+                        aggregation_fn.value(),
+                    );
+                }
+                group
+            })
+            .collect())
+    }
+
+    fn current_group(&self) -> anyhow::Result<&HashMap<String, Constant>> {
+        let current = self
+            .current
+            .context("aggregation scan has no current record")?;
+        self.groups
+            .as_ref()
+            .and_then(|groups| groups.get(current))
+            .context("aggregation scan has no current record")
+    }
+}
+
+impl<S: Scan> Scan for AggregationScan<S> {
+    fn before_first(&mut self) -> anyhow::Result<()> {
+        if self.groups.is_none() {
+            self.groups = Some(self.aggregate()?);
+        }
+        self.current = None;
+        Ok(())
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        if self.groups.is_none() {
+            self.before_first()?;
+        }
+        let num_groups = self.groups.as_ref().map_or(0, |groups| groups.len());
+        let next = self.current.map_or(0, |current| current + 1);
+        // 最後のグループに留まり、続けてnextを呼んでもfalseを返す
+        if next >= num_groups {
+            return Ok(false);
+        }
+        self.current = Some(next);
+        Ok(true)
+    }
+
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+        match self.get_val(field_name)? {
+            Constant::Int(value) => Ok(value),
+            value => bail!("field {} is not an int: {}", field_name, value),
+        }
+    }
+
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+        match self.get_val(field_name)? {
+            Constant::String(value) => Ok(value),
+            value => bail!("field {} is not a string: {}", field_name, value),
+        }
+    }
+
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+        match self.get_val(field_name)? {
+            Constant::Bool(value) => Ok(value),
+            value => bail!("field {} is not a bool: {}", field_name, value),
+        }
+    }
+
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+        match self.get_val(field_name)? {
+            Constant::Float(value) => Ok(value),
+            value => bail!("field {} is not a float: {}", field_name, value),
+        }
+    }
+
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+        match self.get_val(field_name)? {
+            Constant::Long(value) | Constant::Timestamp(value) => Ok(value),
+            value => bail!("field {} is not a long: {}", field_name, value),
+        }
+    }
+
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+        self.current_group()?
+            .get(field_name)
+            .cloned()
+            .with_context(|| format!("field {} not found", field_name))
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.group_fields.iter().any(|field| field == field_name)
+            || self
+                .aggregation_fns
+                .iter()
+                .any(|aggregation_fn| aggregation_fn.field_name() == field_name)
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.current = None;
+        self.inner.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::record_manager::aggregation_fn::{AvgFn, CountFn, MaxFn, MinFn, SumFn};
+    use crate::record_manager::layout::Layout;
+    use crate::record_manager::scan::UpdateScan;
+    use crate::record_manager::schema::Schema;
+    use crate::record_manager::table_scan::TableScan;
+
+    #[test]
+    fn aggregation_scan() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        // category は c0〜c3、price は 0〜99
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("category".to_string(), 4).unwrap();
+        schema.add_int_field("price".to_string()).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), "item", Arc::clone(&layout)).unwrap();
+        for n in 0..1000 {
+            table_scan.insert().unwrap();
+            table_scan.set_int("id", n).unwrap();
+            table_scan
+                .set_string("category", format!("c{}", n % 4))
+                .unwrap();
+            table_scan.set_int("price", n % 100).unwrap();
+        }
+        table_scan.close().unwrap();
+        let open =
+            || TableScan::new(Arc::clone(&transaction), "item", Arc::clone(&layout)).unwrap();
+        let fns = || -> Vec<Box<dyn AggregationFn>> {
+            vec![
+                Box::new(CountFn::all()),
+                Box::new(SumFn::new("price")),
+                Box::new(AvgFn::new("price")),
+                Box::new(MinFn::new("price")),
+                Box::new(MaxFn::new("price")),
+            ]
+        };
+
+        // GROUP BYなし
+        let mut scan = AggregationScan::new(open(), vec![], fns(), 10).unwrap();
+        assert!(scan.next().unwrap());
+        assert_eq!(scan.get_int("count").unwrap(), 1000);
+        assert_eq!(scan.get_int("sumofprice").unwrap(), 49500);
+        assert_eq!(scan.get_float("avgofprice").unwrap(), 49.5);
+        assert_eq!(scan.get_int("minofprice").unwrap(), 0);
+        assert_eq!(scan.get_int("maxofprice").unwrap(), 99);
+        assert!(!scan.next().unwrap());
+        assert!(!scan.next().unwrap());
+        scan.close().unwrap();
+
+        // categoryごと。category c{k} の price は k, k+4, ..., 96+k が10回ずつ
+        let mut scan =
+            AggregationScan::new(open(), vec!["category".to_string()], fns(), 10).unwrap();
+        for _ in 0..2 {
+            scan.before_first().unwrap();
+            let mut categories = vec![];
+            while scan.next().unwrap() {
+                let category = scan.get_string("category").unwrap();
+                let k: i32 = category[1..].parse().unwrap();
+                assert_eq!(scan.get_int("count").unwrap(), 250);
+                assert_eq!(scan.get_int("sumofprice").unwrap(), 10 * (1200 + 25 * k));
+                assert_eq!(scan.get_float("avgofprice").unwrap(), 48.0 + k as f64);
+                assert_eq!(scan.get_int("minofprice").unwrap(), k);
+                assert_eq!(scan.get_int("maxofprice").unwrap(), 96 + k);
+                assert!(scan.has_field("category"));
+                assert!(!scan.has_field("price"));
+                assert!(scan.get_int("price").is_err());
+                categories.push(category);
+            }
+            categories.sort();
+            assert_eq!(categories, vec!["c0", "c1", "c2", "c3"]);
+        }
+        scan.close().unwrap();
+
+        // グループがmax_groupsを超えるとエラーになる
+        let mut scan = AggregationScan::new(open(), vec!["price".to_string()], fns(), 10).unwrap();
+        assert!(scan.before_first().is_err());
+        scan.close().unwrap();
+        assert!(AggregationScan::new(open(), vec!["missing".to_string()], fns(), 10).is_err());
+
+        // 文字列は合計できない
+        let mut scan =
+            AggregationScan::new(open(), vec![], vec![Box::new(SumFn::new("category"))], 10)
+                .unwrap();
+        assert!(scan.next().is_err());
+        scan.close().unwrap();
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
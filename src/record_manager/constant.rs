@@ -0,0 +1,101 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// フィールドの値。比較できるのは同じ型どうしだけ
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i32),
+    String(String),
+    Bool(bool),
+    Float(f64),
+    Long(i64),
+    // Unixエポックからのマイクロ秒
+    Timestamp(i64),
+}
+
+impl PartialOrd for Constant {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Constant::Int(a), Constant::Int(b)) => a.partial_cmp(b),
+            (Constant::String(a), Constant::String(b)) => a.partial_cmp(b),
+            (Constant::Bool(a), Constant::Bool(b)) => a.partial_cmp(b),
+            (Constant::Float(a), Constant::Float(b)) => a.partial_cmp(b),
+            (Constant::Long(a), Constant::Long(b)) => a.partial_cmp(b),
+            (Constant::Timestamp(a), Constant::Timestamp(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Int(v) => write!(f, "{}", v),
+            // SQLの文字列リテラルと同じく、'は''と書く
+            Constant::String(v) => write!(f, "'{}'", v.replace('\'', "''")),
+            Constant::Bool(v) => write!(f, "{}", v),
+            Constant::Float(v) => write!(f, "{}", v),
+            Constant::Long(v) => write!(f, "{}", v),
+            Constant::Timestamp(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl From<i32> for Constant {
+    fn from(v: i32) -> Self {
+        Constant::Int(v)
+    }
+}
+
+impl From<String> for Constant {
+    fn from(v: String) -> Self {
+        Constant::String(v)
+    }
+}
+
+impl From<&str> for Constant {
+    fn from(v: &str) -> Self {
+        Constant::String(v.to_string())
+    }
+}
+
+impl From<bool> for Constant {
+    fn from(v: bool) -> Self {
+        Constant::Bool(v)
+    }
+}
+
+impl From<f64> for Constant {
+    fn from(v: f64) -> Self {
+        Constant::Float(v)
+    }
+}
+
+impl From<i64> for Constant {
+    fn from(v: i64) -> Self {
+        Constant::Long(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare() {
+        assert!(Constant::from(1) < Constant::from(2));
+        assert!(Constant::from("abc") < Constant::from("abd"));
+        assert_eq!(Constant::from(1.5), Constant::Float(1.5));
+        assert!(Constant::from(i64::MIN) < Constant::from(i64::MAX));
+        assert_eq!(Constant::from(1_i64).partial_cmp(&Constant::from(1)), None);
+        assert!(Constant::Timestamp(0) < Constant::Timestamp(1));
+        assert_eq!(
+            Constant::Timestamp(1).partial_cmp(&Constant::from(1_i64)),
+            None
+        );
+        assert_eq!(Constant::from(1).partial_cmp(&Constant::from("1")), None);
+        assert_ne!(Constant::from(1), Constant::from("1"));
+        assert_eq!(Constant::from("x").to_string(), "'x'");
+        assert_eq!(Constant::from("it's").to_string(), "'it''s'");
+    }
+}
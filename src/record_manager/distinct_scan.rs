@@ -0,0 +1,160 @@
+use std::sync::{Arc, Mutex};
+
+use crate::transaction::transaction::Transaction;
+
+use super::constant::Constant;
+use super::scan::Scan;
+use super::schema::Schema;
+use super::sort_scan::{SortKey, SortScan};
+
+// schemaのすべてのフィールドで並べ替え、同じ値の組が続けば最初の1件だけを返す
+pub struct DistinctScan<S: Scan> {
+    inner: SortScan<S>,
+    fields: Vec<String>,
+    // 直前に返したレコードの値
+    previous: Option<Vec<Constant>>,
+}
+
+impl<S: Scan> DistinctScan<S> {
+    pub fn new(
+        inner: S,
+        schema: &Schema,
+        transaction: Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Self> {
+        let fields = schema.fields().clone();
+        let sort_keys = fields.iter().map(|field| SortKey::asc(field)).collect();
+        Ok(Self {
+            inner: SortScan::new(inner, schema, sort_keys, transaction)?,
+            fields,
+            previous: None,
+        })
+    }
+
+    fn current_values(&mut self) -> anyhow::Result<Vec<Constant>> {
+        let mut values = vec![];
+        for field in &self.fields {
+            values.push(self.inner.get_val(field)?);
+        }
+        Ok(values)
+    }
+}
+
+impl<S: Scan> Scan for DistinctScan<S> {
+    fn before_first(&mut self) -> anyhow::Result<()> {
+        self.previous = None;
+        self.inner.before_first()
+    }
+
+    // 並べ替えてあるので、同じ値の組は隣り合う
+    fn next(&mut self) -> anyhow::Result<bool> {
+        while self.inner.next()? {
+            let values = self.current_values()?;
+            if self.previous.as_ref() != Some(&values) {
+                self.previous = Some(values);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+        self.inner.get_int(field_name)
+    }
+
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+        self.inner.get_string(field_name)
+    }
+
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+        self.inner.get_bool(field_name)
+    }
+
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+        self.inner.get_float(field_name)
+    }
+
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+        self.inner.get_long(field_name)
+    }
+
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+        self.inner.get_val(field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.inner.has_field(field_name)
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.inner.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::record_manager::layout::Layout;
+    use crate::record_manager::project_scan::ProjectScan;
+    use crate::record_manager::scan::{assert_scan_exhausts, UpdateScan};
+    use crate::record_manager::table_scan::TableScan;
+
+    #[test]
+    fn distinct_scan() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            block_size: 400,
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("key".to_string(), 4).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), "T", Arc::clone(&layout)).unwrap();
+        let mut seed: u32 = 2463534242;
+        for n in 0..500 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            table_scan.insert().unwrap();
+            table_scan.set_int("id", n).unwrap();
+            table_scan
+                .set_string("key", format!("k{}", seed % 10))
+                .unwrap();
+        }
+        table_scan.close().unwrap();
+
+        // keyだけに射影すると10種類になる
+        let mut key_schema = Schema::new();
+        key_schema.add_string_field("key".to_string(), 4).unwrap();
+        let table_scan =
+            TableScan::new(Arc::clone(&transaction), "T", Arc::clone(&layout)).unwrap();
+        let project_scan = ProjectScan::new(table_scan, vec!["key".to_string()]);
+        let mut scan =
+            DistinctScan::new(project_scan, &key_schema, Arc::clone(&transaction)).unwrap();
+        let mut keys = vec![];
+        while scan.next().unwrap() {
+            keys.push(scan.get_string("key").unwrap());
+        }
+        assert_eq!(keys, (0..10).map(|n| format!("k{}", n)).collect::<Vec<_>>());
+        assert_scan_exhausts(&mut scan, 10);
+        scan.close().unwrap();
+
+        // idも含めればすべて異なる
+        let table_scan =
+            TableScan::new(Arc::clone(&transaction), "T", Arc::clone(&layout)).unwrap();
+        let mut scan =
+            DistinctScan::new(table_scan, layout.schema(), Arc::clone(&transaction)).unwrap();
+        assert_scan_exhausts(&mut scan, 500);
+        scan.close().unwrap();
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
@@ -0,0 +1,339 @@
+use crate::index::Index;
+
+use super::constant::Constant;
+use super::scan::{Scan, UpdateScan};
+
+// outerの各レコードのjoin_fieldの値で索引を引き、inner_tableの一致するレコードだけと組み合わせる。
+// inner_tableはRIDで移動するのでUpdateScanでなければならない
+pub struct IndexJoinScan<O: Scan, I: Index, T: UpdateScan> {
+    outer: O,
+    index: I,
+    inner_table: T,
+    join_field: String,
+    // outerが現在のレコードを指しているか
+    has_outer: bool,
+}
+
+impl<O: Scan, I: Index, T: UpdateScan> IndexJoinScan<O, I, T> {
+    pub fn new(outer: O, index: I, inner_table: T, join_field: &str) -> anyhow::Result<Self> {
+        let mut scan = Self {
+            outer,
+            index,
+            inner_table,
+            join_field: join_field.to_string(),
+            has_outer: false,
+        };
+        scan.before_first()?;
+        Ok(scan)
+    }
+
+    fn reset_index(&mut self) -> anyhow::Result<()> {
+        let search_key = self.outer.get_val(&self.join_field)?;
+        self.index.before_first(search_key)
+    }
+}
+
+impl<O: Scan, I: Index, T: UpdateScan> Scan for IndexJoinScan<O, I, T> {
+    fn before_first(&mut self) -> anyhow::Result<()> {
+        self.outer.before_first()?;
+        self.has_outer = self.outer.next()?;
+        if self.has_outer {
+            self.reset_index()?;
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        while self.has_outer {
+            if self.index.next()? {
+                let rid = self.index.get_data_rid()?;
+                self.inner_table.move_to_rid(rid)?;
+                return Ok(true);
+            }
+            self.has_outer = self.outer.next()?;
+            if self.has_outer {
+                self.reset_index()?;
+            }
+        }
+        Ok(false)
+    }
+
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+        if self.outer.has_field(field_name) {
+            self.outer.get_int(field_name)
+        } else {
+            self.inner_table.get_int(field_name)
+        }
+    }
+
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+        if self.outer.has_field(field_name) {
+            self.outer.get_string(field_name)
+        } else {
+            self.inner_table.get_string(field_name)
+        }
+    }
+
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+        if self.outer.has_field(field_name) {
+            self.outer.get_bool(field_name)
+        } else {
+            self.inner_table.get_bool(field_name)
+        }
+    }
+
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+        if self.outer.has_field(field_name) {
+            self.outer.get_float(field_name)
+        } else {
+            self.inner_table.get_float(field_name)
+        }
+    }
+
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+        if self.outer.has_field(field_name) {
+            self.outer.get_long(field_name)
+        } else {
+            self.inner_table.get_long(field_name)
+        }
+    }
+
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+        if self.outer.has_field(field_name) {
+            self.outer.get_val(field_name)
+        } else {
+            self.inner_table.get_val(field_name)
+        }
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.outer.has_field(field_name) || self.inner_table.has_field(field_name)
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.outer.close()?;
+        self.index.close()?;
+        self.inner_table.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::record_manager::layout::Layout;
+    use crate::record_manager::predicate::{Expression, Operator, Predicate, Term};
+    use crate::record_manager::rid::Rid;
+    use crate::record_manager::schema::Schema;
+    use crate::record_manager::select_scan::SelectScan;
+    use crate::record_manager::table_scan::TableScan;
+    use crate::transaction::transaction::Transaction;
+
+    // move_to_ridで移動したブロックを記録する
+    struct RecordingScan {
+        inner: TableScan,
+        blocks: HashSet<i32>,
+    }
+
+    impl Scan for RecordingScan {
+        fn before_first(&mut self) -> anyhow::Result<()> {
+            self.inner.before_first()
+        }
+
+        fn next(&mut self) -> anyhow::Result<bool> {
+            self.inner.next()
+        }
+
+        fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+            self.inner.get_int(field_name)
+        }
+
+        fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+            self.inner.get_string(field_name)
+        }
+
+        fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+            self.inner.get_bool(field_name)
+        }
+
+        fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+            self.inner.get_float(field_name)
+        }
+
+        fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+            self.inner.get_long(field_name)
+        }
+
+        fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+            self.inner.get_val(field_name)
+        }
+
+        fn has_field(&self, field_name: &str) -> bool {
+            self.inner.has_field(field_name)
+        }
+
+        fn close(&mut self) -> anyhow::Result<()> {
+            self.inner.close()
+        }
+    }
+
+    impl UpdateScan for RecordingScan {
+        fn set_int(&mut self, field_name: &str, val: i32) -> anyhow::Result<()> {
+            self.inner.set_int(field_name, val)
+        }
+
+        fn set_string(&mut self, field_name: &str, val: String) -> anyhow::Result<()> {
+            self.inner.set_string(field_name, val)
+        }
+
+        fn set_bool(&mut self, field_name: &str, val: bool) -> anyhow::Result<()> {
+            self.inner.set_bool(field_name, val)
+        }
+
+        fn set_float(&mut self, field_name: &str, val: f64) -> anyhow::Result<()> {
+            self.inner.set_float(field_name, val)
+        }
+
+        fn set_long(&mut self, field_name: &str, val: i64) -> anyhow::Result<()> {
+            self.inner.set_long(field_name, val)
+        }
+
+        fn insert(&mut self) -> anyhow::Result<Rid> {
+            self.inner.insert()
+        }
+
+        fn delete(&mut self) -> anyhow::Result<()> {
+            self.inner.delete()
+        }
+
+        fn get_rid(&self) -> anyhow::Result<Rid> {
+            self.inner.get_rid()
+        }
+
+        fn move_to_rid(&mut self, rid: Rid) -> anyhow::Result<()> {
+            self.blocks.insert(rid.block_number);
+            self.inner.move_to_rid(rid)
+        }
+    }
+
+    fn create_table(
+        db: &MyDb,
+        table_name: &str,
+        schema: &Schema,
+        transaction: &Arc<Mutex<Transaction>>,
+    ) -> Arc<Layout> {
+        let table_manager = db.table_manager();
+        table_manager
+            .create_table(table_name, schema, transaction)
+            .unwrap();
+        Arc::new(table_manager.get_layout(table_name, transaction).unwrap())
+    }
+
+    #[test]
+    fn index_join_scan() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            block_size: 400,
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        let mut schema = Schema::new();
+        schema.add_int_field("pid".to_string()).unwrap();
+        schema.add_string_field("pname".to_string(), 10).unwrap();
+        let parent_layout = create_table(&db, "parent", &schema, &transaction);
+        let mut scan =
+            TableScan::new(Arc::clone(&transaction), "parent", parent_layout.clone()).unwrap();
+        for pid in 0..100 {
+            scan.insert().unwrap();
+            scan.set_int("pid", pid).unwrap();
+            scan.set_string("pname", format!("p{}", pid)).unwrap();
+        }
+        scan.close().unwrap();
+
+        // 同じ親を持つ子は連続したスロットに並ぶ
+        let mut schema = Schema::new();
+        schema.add_int_field("cid".to_string()).unwrap();
+        schema.add_int_field("cpid".to_string()).unwrap();
+        let child_layout = create_table(&db, "child", &schema, &transaction);
+        let mut scan =
+            TableScan::new(Arc::clone(&transaction), "child", child_layout.clone()).unwrap();
+        for cid in 0..1000 {
+            scan.insert().unwrap();
+            scan.set_int("cid", cid).unwrap();
+            scan.set_int("cpid", cid / 10).unwrap();
+        }
+        scan.close().unwrap();
+        let child_blocks = transaction
+            .lock()
+            .unwrap()
+            .size("child.tbl".to_string())
+            .unwrap();
+
+        let index_manager = db.index_manager();
+        index_manager
+            .create_index("child_cpid", "child", "cpid", &transaction)
+            .unwrap();
+        let index = index_manager
+            .get_index_info("child", &transaction)
+            .unwrap()
+            .remove("cpid")
+            .unwrap()
+            .open(&transaction)
+            .unwrap();
+
+        // pid < 10 の親だけを外側にする
+        let outer = SelectScan::new(
+            TableScan::new(Arc::clone(&transaction), "parent", parent_layout).unwrap(),
+            Predicate::new().with_term(Term::new(
+                Expression::field("pid"),
+                Operator::Lt,
+                Expression::constant(10),
+            )),
+        );
+        let inner_table = RecordingScan {
+            inner: TableScan::new(Arc::clone(&transaction), "child", child_layout.clone()).unwrap(),
+            blocks: HashSet::new(),
+        };
+        let mut scan = IndexJoinScan::new(outer, index, inner_table, "pid").unwrap();
+        let mut pairs = vec![];
+        while scan.next().unwrap() {
+            let pid = scan.get_int("pid").unwrap();
+            assert_eq!(scan.get_string("pname").unwrap(), format!("p{}", pid));
+            assert_eq!(scan.get_int("cpid").unwrap(), pid);
+            pairs.push((pid, scan.get_int("cid").unwrap()));
+        }
+        assert!(!scan.next().unwrap());
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            (0..100).map(|cid| (cid / 10, cid)).collect::<Vec<_>>()
+        );
+
+        // 一致した100件が入っているブロックだけを読み、子テーブル全体は読まない
+        let records_per_block = 400 / child_layout.slot_size();
+        assert_eq!(
+            scan.inner_table.blocks.len(),
+            100usize.div_ceil(records_per_block)
+        );
+        assert!((scan.inner_table.blocks.len() as i32) < child_blocks);
+
+        // 先頭からやり直しても同じ件数になる
+        scan.before_first().unwrap();
+        let mut count = 0;
+        while scan.next().unwrap() {
+            count += 1;
+        }
+        assert_eq!(count, 100);
+        scan.close().unwrap();
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+use crate::index::Index;
+
+use super::constant::Constant;
+use super::scan::{Scan, UpdateScan};
+use super::table_scan::TableScan;
+
+// 索引でsearch_keyと等しいレコードのRIDを引き、テーブルのそのレコードだけを読む
+pub struct IndexSelectScan<I: Index> {
+    index: I,
+    table_scan: TableScan,
+    search_key: Constant,
+}
+
+impl<I: Index> IndexSelectScan<I> {
+    pub fn new(index: I, table_scan: TableScan, search_key: Constant) -> anyhow::Result<Self> {
+        let mut scan = Self {
+            index,
+            table_scan,
+            search_key,
+        };
+        scan.before_first()?;
+        Ok(scan)
+    }
+}
+
+impl<I: Index> Scan for IndexSelectScan<I> {
+    fn before_first(&mut self) -> anyhow::Result<()> {
+        self.index.before_first(self.search_key.clone())
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        if !self.index.next()? {
+            return Ok(false);
+        }
+        let rid = self.index.get_data_rid()?;
+        self.table_scan.move_to_rid(rid)?;
+        Ok(true)
+    }
+
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+        self.table_scan.get_int(field_name)
+    }
+
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+        self.table_scan.get_string(field_name)
+    }
+
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+        self.table_scan.get_bool(field_name)
+    }
+
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+        self.table_scan.get_float(field_name)
+    }
+
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+        self.table_scan.get_long(field_name)
+    }
+
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+        self.table_scan.get_val(field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.table_scan.has_field(field_name)
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.index.close()?;
+        self.table_scan.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::record_manager::predicate::{Expression, Operator, Predicate, Term};
+    use crate::record_manager::schema::Schema;
+    use crate::record_manager::select_scan::SelectScan;
+
+    #[test]
+    fn index_select_scan() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            block_size: 400,
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_int_field("dept".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 10).unwrap();
+        let table_manager = db.table_manager();
+        table_manager
+            .create_table("emp", &schema, &transaction)
+            .unwrap();
+        let layout = Arc::new(table_manager.get_layout("emp", &transaction).unwrap());
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), "emp", layout.clone()).unwrap();
+        for n in 0..1000 {
+            table_scan.insert().unwrap();
+            table_scan.set_int("id", n).unwrap();
+            table_scan.set_int("dept", n % 100).unwrap();
+            table_scan.set_string("name", format!("emp{}", n)).unwrap();
+        }
+        table_scan.close().unwrap();
+        let num_blocks = transaction
+            .lock()
+            .unwrap()
+            .size("emp.tbl".to_string())
+            .unwrap();
+
+        let index_manager = db.index_manager();
+        index_manager
+            .create_index("emp_dept", "emp", "dept", &transaction)
+            .unwrap();
+        let index_info = index_manager
+            .get_index_info("emp", &transaction)
+            .unwrap()
+            .remove("dept")
+            .unwrap();
+
+        let buffer_manager = db.buffer_manager();
+        buffer_manager.lock().unwrap().reset_stats();
+        let index = index_info.open(&transaction).unwrap();
+        let table_scan = TableScan::new(Arc::clone(&transaction), "emp", layout.clone()).unwrap();
+        let mut scan = IndexSelectScan::new(index, table_scan, Constant::Int(42)).unwrap();
+        let mut ids = vec![];
+        while scan.next().unwrap() {
+            assert_eq!(scan.get_int("dept").unwrap(), 42);
+            let id = scan.get_int("id").unwrap();
+            assert_eq!(scan.get_string("name").unwrap(), format!("emp{}", id));
+            ids.push(id);
+        }
+        scan.close().unwrap();
+        ids.sort();
+        assert_eq!(ids, (0..10).map(|n| n * 100 + 42).collect::<Vec<_>>());
+
+        // 一致するレコードのブロックと索引しか読まないので、全件を読むよりずっと少ない
+        let stats = buffer_manager.lock().unwrap().stats();
+        let index_pins = stats.hits + stats.misses;
+        buffer_manager.lock().unwrap().reset_stats();
+        let table_scan = TableScan::new(Arc::clone(&transaction), "emp", layout).unwrap();
+        let predicate = Predicate::new().with_term(Term::new(
+            Expression::field("dept"),
+            Operator::Eq,
+            Expression::constant(42),
+        ));
+        let mut scan = SelectScan::new(table_scan, predicate);
+        let mut count = 0;
+        while scan.next().unwrap() {
+            count += 1;
+        }
+        scan.close().unwrap();
+        assert_eq!(count, 10);
+        let stats = buffer_manager.lock().unwrap().stats();
+        assert!(stats.hits + stats.misses >= num_blocks as u64);
+        assert!(index_pins < num_blocks as u64 / 2);
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::file_manager::{Page, INTGER_BYTES};
+
+use super::schema::Schema;
+
+// スロットの先頭からNULLビットマップまでのオフセット
+pub const NULL_BITMAP_OFFSET: usize = INTGER_BYTES;
+
+// 文字列フィールドの符号化。文字列の最大長は文字数で、何バイト確保するかが変わる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    // 1文字1バイト。ASCII以外の文字は書けない
+    #[default]
+    Ascii,
+    // 1文字を最大4バイトとして確保する
+    Utf8,
+}
+
+impl Encoding {
+    // 最大length文字の文字列が占めるバイト数(長さのintを含む)
+    pub fn max_length(&self, length: usize) -> usize {
+        match self {
+            Encoding::Ascii => Page::max_length(length),
+            Encoding::Utf8 => Page::max_length(4 * length),
+        }
+    }
+
+    // 書けない文字や、最大長を超える文字列はInvalidInputにする
+    pub fn check(&self, value: &str, length: usize) -> io::Result<()> {
+        if *self == Encoding::Ascii && !value.is_ascii() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not ascii", value),
+            ));
+        }
+        if value.chars().count() > length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is longer than {} characters", value, length),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// スロットの先頭には使用中かどうかのフラグ(int)が入り、
+// NULLを許すフィールドがあればその数だけのビットをバイト単位に切り上げたNULLビットマップが続き、
+// その後ろにフィールドが並ぶ
+#[derive(Debug, Clone)]
+pub struct Layout {
+    schema: Schema,
+    offsets: HashMap<String, usize>,
+    // NULLを許すフィールドの、NULLビットマップでのビット位置
+    null_bits: HashMap<String, usize>,
+    slot_size: usize,
+    encoding: Encoding,
+}
+
+impl From<Schema> for Layout {
+    fn from(schema: Schema) -> Self {
+        Self::with_encoding(schema, Encoding::default())
+    }
+}
+
+impl Layout {
+    pub fn with_encoding(schema: Schema, encoding: Encoding) -> Self {
+        let mut null_bits = HashMap::new();
+        for name in schema.fields() {
+            if schema.is_optional(name) {
+                null_bits.insert(name.clone(), null_bits.len());
+            }
+        }
+
+        let mut offsets = HashMap::new();
+        let mut pos = NULL_BITMAP_OFFSET + null_bits.len().div_ceil(8);
+        for name in schema.fields() {
+            offsets.insert(name.clone(), pos);
+            pos += schema.info(name).unwrap().bytes_length(encoding);
+        }
+        Self {
+            schema,
+            offsets,
+            null_bits,
+            slot_size: pos,
+            encoding,
+        }
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn offset(&self, name: &str) -> Option<usize> {
+        self.offsets.get(name).copied()
+    }
+
+    pub fn null_bit(&self, name: &str) -> Option<usize> {
+        self.null_bits.get(name).copied()
+    }
+
+    pub fn null_bitmap_size(&self) -> usize {
+        self.null_bits.len().div_ceil(8)
+    }
+
+    pub fn slot_size(&self) -> usize {
+        self.slot_size
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record_manager::schema::FieldInfo;
+
+    #[test]
+    fn layout() {
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 9).unwrap();
+        schema.add_bool_field("active".to_string()).unwrap();
+        schema.add_float_field("score".to_string()).unwrap();
+        schema.add_long_field("total".to_string()).unwrap();
+        let layout = Layout::from(schema.clone());
+
+        assert_eq!(layout.encoding(), Encoding::Ascii);
+        assert_eq!(layout.offset("id"), Some(4));
+        assert_eq!(layout.offset("name"), Some(8));
+        assert_eq!(layout.offset("active"), Some(21));
+        assert_eq!(layout.offset("score"), Some(22));
+        assert_eq!(layout.offset("total"), Some(30));
+        assert_eq!(layout.offset("missing"), None);
+        assert_eq!(layout.slot_size(), 38);
+        assert_eq!(layout.null_bit("id"), None);
+        assert_eq!(layout.null_bitmap_size(), 0);
+
+        // Utf8では文字列に1文字4バイトを確保する
+        let layout = Layout::with_encoding(schema, Encoding::Utf8);
+        assert_eq!(layout.encoding(), Encoding::Utf8);
+        assert_eq!(layout.offset("name"), Some(8));
+        assert_eq!(layout.offset("active"), Some(48));
+        assert_eq!(layout.slot_size(), 65);
+    }
+
+    #[test]
+    fn null_bitmap() {
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        for n in 0..9 {
+            schema
+                .add_optional_field(format!("f{}", n), FieldInfo::Int)
+                .unwrap();
+        }
+        let layout = Layout::from(schema);
+
+        // 9ビットは2バイトに切り上げる
+        assert_eq!(layout.null_bitmap_size(), 2);
+        assert_eq!(layout.offset("id"), Some(6));
+        assert_eq!(layout.offset("f0"), Some(10));
+        assert_eq!(layout.offset("f8"), Some(42));
+        assert_eq!(layout.slot_size(), 46);
+        assert_eq!(layout.null_bit("id"), None);
+        assert_eq!(layout.null_bit("f0"), Some(0));
+        assert_eq!(layout.null_bit("f8"), Some(8));
+    }
+}
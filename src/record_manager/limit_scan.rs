@@ -0,0 +1,156 @@
+use super::constant::Constant;
+use super::scan::Scan;
+
+// innerの先頭からoffset件を飛ばし、続くlimit件だけを返す
+pub struct LimitScan<S: Scan> {
+    inner: S,
+    limit: usize,
+    offset: usize,
+    // before_firstから数えて返したレコード数
+    count: usize,
+}
+
+impl<S: Scan> LimitScan<S> {
+    pub fn new(inner: S, limit: usize, offset: usize) -> anyhow::Result<Self> {
+        let mut limit_scan = Self {
+            inner,
+            limit,
+            offset,
+            count: 0,
+        };
+        limit_scan.before_first()?;
+        Ok(limit_scan)
+    }
+
+    pub fn rows_returned(&self) -> usize {
+        self.count
+    }
+}
+
+impl<S: Scan> Scan for LimitScan<S> {
+    fn before_first(&mut self) -> anyhow::Result<()> {
+        self.count = 0;
+        self.inner.before_first()?;
+        for _ in 0..self.offset {
+            if !self.inner.next()? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        if self.count >= self.limit || !self.inner.next()? {
+            return Ok(false);
+        }
+        self.count += 1;
+        Ok(true)
+    }
+
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+        self.inner.get_int(field_name)
+    }
+
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+        self.inner.get_string(field_name)
+    }
+
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+        self.inner.get_bool(field_name)
+    }
+
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+        self.inner.get_float(field_name)
+    }
+
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+        self.inner.get_long(field_name)
+    }
+
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+        self.inner.get_val(field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.inner.has_field(field_name)
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.inner.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::record_manager::layout::Layout;
+    use crate::record_manager::scan::{assert_scan_exhausts, UpdateScan};
+    use crate::record_manager::schema::Schema;
+    use crate::record_manager::sort_scan::{SortKey, SortScan};
+    use crate::record_manager::table_scan::TableScan;
+
+    #[test]
+    fn limit_scan() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), "T", Arc::clone(&layout)).unwrap();
+        for n in 0..100 {
+            table_scan.insert().unwrap();
+            table_scan.set_int("id", n).unwrap();
+        }
+        table_scan.close().unwrap();
+        let open = || TableScan::new(Arc::clone(&transaction), "T", Arc::clone(&layout)).unwrap();
+
+        let mut scan = LimitScan::new(open(), 10, 20).unwrap();
+        let mut ids = vec![];
+        while scan.next().unwrap() {
+            ids.push(scan.get_int("id").unwrap());
+        }
+        assert_eq!(ids, (20..30).collect::<Vec<_>>());
+        assert_eq!(scan.rows_returned(), 10);
+        assert_scan_exhausts(&mut scan, 10);
+        scan.close().unwrap();
+
+        // 末尾を越える範囲はある分だけ返す
+        let mut scan = LimitScan::new(open(), 10, 95).unwrap();
+        assert_scan_exhausts(&mut scan, 5);
+        assert_eq!(scan.rows_returned(), 5);
+        scan.close().unwrap();
+        let mut scan = LimitScan::new(open(), 10, 200).unwrap();
+        assert_scan_exhausts(&mut scan, 0);
+        scan.close().unwrap();
+
+        // ORDER BY id DESC LIMIT 10 OFFSET 20
+        let sort_scan = SortScan::new(
+            open(),
+            layout.schema(),
+            vec![SortKey::desc("id")],
+            Arc::clone(&transaction),
+        )
+        .unwrap();
+        let mut scan = LimitScan::new(sort_scan, 10, 20).unwrap();
+        let mut ids = vec![];
+        while scan.next().unwrap() {
+            ids.push(scan.get_int("id").unwrap());
+        }
+        assert_eq!(ids, (70..80).rev().collect::<Vec<_>>());
+        scan.close().unwrap();
+
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
@@ -0,0 +1,473 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::planner::plan::Plan;
+
+use super::constant::Constant;
+use super::scan::Scan;
+use super::schema::Schema;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Constant(Constant),
+    FieldName(String),
+}
+
+impl Expression {
+    pub fn field(name: &str) -> Self {
+        Expression::FieldName(name.to_string())
+    }
+
+    pub fn constant(value: impl Into<Constant>) -> Self {
+        Expression::Constant(value.into())
+    }
+
+    pub fn applies_to(&self, schema: &Schema) -> bool {
+        match self {
+            Expression::Constant(_) => true,
+            Expression::FieldName(name) => schema.has_field(name),
+        }
+    }
+
+    pub fn evaluate(&self, scan: &mut dyn Scan) -> anyhow::Result<Constant> {
+        match self {
+            Expression::Constant(value) => Ok(value.clone()),
+            Expression::FieldName(name) => scan.get_val(name),
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Constant(value) => write!(f, "{}", value),
+            Expression::FieldName(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Operator::Eq => "=",
+            Operator::Ne => "<>",
+            Operator::Lt => "<",
+            Operator::Le => "<=",
+            Operator::Gt => ">",
+            Operator::Ge => ">=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+// lhs op rhs の形の比較
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub lhs: Expression,
+    pub op: Operator,
+    pub rhs: Expression,
+}
+
+impl Term {
+    pub fn new(lhs: Expression, op: Operator, rhs: Expression) -> Self {
+        Self { lhs, op, rhs }
+    }
+
+    // 型の違う値どうしは比較できないので、どの演算子でも満たさないものとする
+    pub fn is_satisfied(&self, scan: &mut dyn Scan) -> anyhow::Result<bool> {
+        let lhs = self.lhs.evaluate(scan)?;
+        let rhs = self.rhs.evaluate(scan)?;
+        let Some(ordering) = lhs.partial_cmp(&rhs) else {
+            return Ok(false);
+        };
+        Ok(match self.op {
+            Operator::Eq => ordering == Ordering::Equal,
+            Operator::Ne => ordering != Ordering::Equal,
+            Operator::Lt => ordering == Ordering::Less,
+            Operator::Le => ordering != Ordering::Greater,
+            Operator::Gt => ordering == Ordering::Greater,
+            Operator::Ge => ordering != Ordering::Less,
+        })
+    }
+
+    // schemaのフィールドだけで評価できるか
+    pub fn applies_to(&self, schema: &Schema) -> bool {
+        self.lhs.applies_to(schema) && self.rhs.applies_to(schema)
+    }
+
+    // このTermで何件に1件まで絞り込まれるかの見積もり
+    pub fn reduction_factor(&self, plan: &dyn Plan) -> i32 {
+        if self.op != Operator::Eq {
+            // 範囲の比較はおよそ1/3が残るとみなす
+            return 3;
+        }
+        match (&self.lhs, &self.rhs) {
+            (Expression::FieldName(lhs), Expression::FieldName(rhs)) => {
+                plan.distinct_values(lhs).max(plan.distinct_values(rhs))
+            }
+            (Expression::FieldName(name), Expression::Constant(_))
+            | (Expression::Constant(_), Expression::FieldName(name)) => plan.distinct_values(name),
+            (Expression::Constant(lhs), Expression::Constant(rhs)) => {
+                if lhs == rhs {
+                    1
+                } else {
+                    i32::MAX
+                }
+            }
+        }
+    }
+
+    // field_name = 定数 の形なら、その定数
+    pub fn equates_with_constant(&self, field_name: &str) -> Option<&Constant> {
+        if self.op != Operator::Eq {
+            return None;
+        }
+        match (&self.lhs, &self.rhs) {
+            (Expression::FieldName(name), Expression::Constant(value))
+            | (Expression::Constant(value), Expression::FieldName(name))
+                if name == field_name =>
+            {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    // field_name = 別のフィールド の形なら、そのフィールド名
+    pub fn equates_with_field(&self, field_name: &str) -> Option<&str> {
+        if self.op != Operator::Eq {
+            return None;
+        }
+        match (&self.lhs, &self.rhs) {
+            (Expression::FieldName(lhs), Expression::FieldName(rhs)) if lhs == field_name => {
+                Some(rhs)
+            }
+            (Expression::FieldName(lhs), Expression::FieldName(rhs)) if rhs == field_name => {
+                Some(lhs)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.lhs, self.op, self.rhs)
+    }
+}
+
+// すべてのTermを満たすレコードだけを選ぶ。Termがなければすべて選ぶ
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Predicate {
+    terms: Vec<Term>,
+}
+
+impl Predicate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_term(mut self, term: Term) -> Self {
+        self.terms.push(term);
+        self
+    }
+
+    // 別のWHERE句から作ったPredicateのTermを加えて、両方を満たすものだけを選ぶようにする
+    pub fn conjoin_with(&mut self, other: Predicate) {
+        self.terms.extend(other.terms);
+    }
+
+    pub fn terms(&self) -> &Vec<Term> {
+        &self.terms
+    }
+
+    pub fn reduction_factor(&self, plan: &dyn Plan) -> i32 {
+        self.terms.iter().fold(1, |factor, term| {
+            factor.saturating_mul(term.reduction_factor(plan))
+        })
+    }
+
+    // schemaのフィールドだけで評価できるTerm
+    pub fn select_sub_pred(&self, schema: &Schema) -> Predicate {
+        let terms = self
+            .terms
+            .iter()
+            .filter(|term| term.applies_to(schema))
+            .cloned()
+            .collect();
+        Predicate { terms }
+    }
+
+    // 2つのschemaを合わせて初めて評価できるTerm。どちらか片方で評価できるものは含めない
+    pub fn join_sub_pred(&self, schema1: &Schema, schema2: &Schema) -> anyhow::Result<Predicate> {
+        // 同じ名前のフィールドはschema1のものを使う
+        let mut schema = Schema::new();
+        schema.add_all(schema1)?;
+        for name in schema2.difference(schema1) {
+            schema.add(name, schema2)?;
+        }
+        let terms = self
+            .terms
+            .iter()
+            .filter(|term| {
+                !term.applies_to(schema1) && !term.applies_to(schema2) && term.applies_to(&schema)
+            })
+            .cloned()
+            .collect();
+        Ok(Predicate { terms })
+    }
+
+    pub fn equates_with_constant(&self, field_name: &str) -> Option<&Constant> {
+        self.terms
+            .iter()
+            .find_map(|term| term.equates_with_constant(field_name))
+    }
+
+    pub fn equates_with_field(&self, field_name: &str) -> Option<&str> {
+        self.terms
+            .iter()
+            .find_map(|term| term.equates_with_field(field_name))
+    }
+
+    pub fn is_satisfied(&self, scan: &mut dyn Scan) -> anyhow::Result<bool> {
+        for term in &self.terms {
+            if !term.is_satisfied(scan)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+// Termをandでつないだ形。Termがなければ空文字列
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, term) in self.terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, " and ")?;
+            }
+            write!(f, "{}", term)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    // フィールドの値を1レコード分だけ持つScan
+    struct RecordScan {
+        values: HashMap<String, Constant>,
+    }
+
+    impl RecordScan {
+        fn new(values: Vec<(&str, Constant)>) -> Self {
+            Self {
+                values: values
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value))
+                    .collect(),
+            }
+        }
+    }
+
+    impl Scan for RecordScan {
+        fn before_first(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn next(&mut self) -> anyhow::Result<bool> {
+            Ok(false)
+        }
+
+        fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+            match self.get_val(field_name)? {
+                Constant::Int(value) => Ok(value),
+                value => Err(anyhow::anyhow!("{} is not an int", value)),
+            }
+        }
+
+        fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+            match self.get_val(field_name)? {
+                Constant::String(value) => Ok(value),
+                value => Err(anyhow::anyhow!("{} is not a string", value)),
+            }
+        }
+
+        fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+            match self.get_val(field_name)? {
+                Constant::Bool(value) => Ok(value),
+                value => Err(anyhow::anyhow!("{} is not a bool", value)),
+            }
+        }
+
+        fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+            match self.get_val(field_name)? {
+                Constant::Float(value) => Ok(value),
+                value => Err(anyhow::anyhow!("{} is not a float", value)),
+            }
+        }
+
+        fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+            match self.get_val(field_name)? {
+                Constant::Long(value) | Constant::Timestamp(value) => Ok(value),
+                value => Err(anyhow::anyhow!("{} is not a long", value)),
+            }
+        }
+
+        fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+            self.values
+                .get(field_name)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("field {} not found", field_name))
+        }
+
+        fn has_field(&self, field_name: &str) -> bool {
+            self.values.contains_key(field_name)
+        }
+
+        fn close(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn predicate() {
+        let mut scan = RecordScan::new(vec![
+            ("id", Constant::Int(3)),
+            ("age", Constant::Int(3)),
+            ("name", Constant::from("joe")),
+            ("nickname", Constant::from("joe")),
+        ]);
+
+        // field = constant
+        let id_is_3 = Term::new(
+            Expression::field("id"),
+            Operator::Eq,
+            Expression::constant(3),
+        );
+        assert!(id_is_3.is_satisfied(&mut scan).unwrap());
+        let name_is_amy = Term::new(
+            Expression::field("name"),
+            Operator::Eq,
+            Expression::constant("amy"),
+        );
+        assert!(!name_is_amy.is_satisfied(&mut scan).unwrap());
+
+        // field = field
+        let name_is_nickname = Term::new(
+            Expression::field("name"),
+            Operator::Eq,
+            Expression::field("nickname"),
+        );
+        assert!(name_is_nickname.is_satisfied(&mut scan).unwrap());
+        let id_is_age = Term::new(
+            Expression::field("id"),
+            Operator::Eq,
+            Expression::field("age"),
+        );
+        assert!(id_is_age.is_satisfied(&mut scan).unwrap());
+
+        // 複数のTermはすべて満たす必要がある
+        let mut predicate = Predicate::new().with_term(id_is_3.clone());
+        assert!(predicate.is_satisfied(&mut scan).unwrap());
+        predicate.conjoin_with(Predicate::new().with_term(name_is_nickname));
+        assert_eq!(predicate.terms().len(), 2);
+        assert!(predicate.is_satisfied(&mut scan).unwrap());
+        predicate.conjoin_with(Predicate::new().with_term(name_is_amy));
+        assert!(!predicate.is_satisfied(&mut scan).unwrap());
+        assert!(Predicate::new().is_satisfied(&mut scan).unwrap());
+        assert_eq!(
+            predicate.to_string(),
+            "id = 3 and name = nickname and name = 'amy'"
+        );
+
+        // 型が違えばどの演算子でも満たさない
+        for op in [Operator::Eq, Operator::Ne, Operator::Lt, Operator::Ge] {
+            let mismatched = Term::new(Expression::field("id"), op, Expression::field("name"));
+            assert!(!mismatched.is_satisfied(&mut scan).unwrap());
+        }
+
+        // 存在しないフィールドはエラー
+        let missing = Term::new(
+            Expression::field("missing"),
+            Operator::Eq,
+            Expression::constant(3),
+        );
+        assert!(missing.is_satisfied(&mut scan).is_err());
+    }
+    #[test]
+    fn sub_predicates() {
+        let mut student = Schema::new();
+        student.add_int_field("sid".to_string()).unwrap();
+        student.add_int_field("majorid".to_string()).unwrap();
+        let mut dept = Schema::new();
+        dept.add_int_field("did".to_string()).unwrap();
+        dept.add_string_field("dname".to_string(), 10).unwrap();
+
+        let sid_is_1 = Term::new(
+            Expression::field("sid"),
+            Operator::Eq,
+            Expression::constant(1),
+        );
+        let major_is_dept = Term::new(
+            Expression::field("majorid"),
+            Operator::Eq,
+            Expression::field("did"),
+        );
+        let dname_is_math = Term::new(
+            Expression::constant("math"),
+            Operator::Eq,
+            Expression::field("dname"),
+        );
+        let predicate = Predicate::new()
+            .with_term(sid_is_1.clone())
+            .with_term(major_is_dept.clone())
+            .with_term(dname_is_math.clone());
+
+        assert_eq!(
+            predicate.select_sub_pred(&student),
+            Predicate::new().with_term(sid_is_1)
+        );
+        assert_eq!(
+            predicate.select_sub_pred(&dept),
+            Predicate::new().with_term(dname_is_math)
+        );
+        assert_eq!(
+            predicate.join_sub_pred(&student, &dept).unwrap(),
+            Predicate::new().with_term(major_is_dept.clone())
+        );
+        assert!(predicate
+            .join_sub_pred(&student, &Schema::new())
+            .unwrap()
+            .terms()
+            .is_empty());
+        // 同じ名前のフィールドがあっても結合のTermを取り出せる
+        let mut enroll = Schema::new();
+        enroll.add_int_field("sid".to_string()).unwrap();
+        enroll.add_int_field("did".to_string()).unwrap();
+        assert_eq!(
+            predicate.join_sub_pred(&student, &enroll).unwrap(),
+            Predicate::new().with_term(major_is_dept)
+        );
+        assert_eq!(
+            predicate.equates_with_constant("dname"),
+            Some(&Constant::from("math"))
+        );
+        assert_eq!(predicate.equates_with_field("did"), Some("majorid"));
+        assert_eq!(predicate.equates_with_field("sid"), None);
+    }
+}
@@ -0,0 +1,181 @@
+use super::constant::Constant;
+use super::scan::Scan;
+
+// scan1とscan2の直積を返す。scan1の各レコードに対してscan2を先頭から走査し直す
+pub struct ProductScan<S1: Scan, S2: Scan> {
+    scan1: S1,
+    scan2: S2,
+    // scan1が現在のレコードを指しているか
+    has_outer: bool,
+}
+
+impl<S1: Scan, S2: Scan> ProductScan<S1, S2> {
+    pub fn new(scan1: S1, scan2: S2) -> anyhow::Result<Self> {
+        let mut product_scan = Self {
+            scan1,
+            scan2,
+            has_outer: false,
+        };
+        product_scan.before_first()?;
+        Ok(product_scan)
+    }
+}
+
+impl<S1: Scan, S2: Scan> Scan for ProductScan<S1, S2> {
+    fn before_first(&mut self) -> anyhow::Result<()> {
+        self.scan1.before_first()?;
+        self.has_outer = self.scan1.next()?;
+        self.scan2.before_first()
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        while self.has_outer {
+            if self.scan2.next()? {
+                return Ok(true);
+            }
+            self.has_outer = self.scan1.next()?;
+            self.scan2.before_first()?;
+        }
+        Ok(false)
+    }
+
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+        if self.scan1.has_field(field_name) {
+            self.scan1.get_int(field_name)
+        } else {
+            self.scan2.get_int(field_name)
+        }
+    }
+
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+        if self.scan1.has_field(field_name) {
+            self.scan1.get_string(field_name)
+        } else {
+            self.scan2.get_string(field_name)
+        }
+    }
+
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+        if self.scan1.has_field(field_name) {
+            self.scan1.get_bool(field_name)
+        } else {
+            self.scan2.get_bool(field_name)
+        }
+    }
+
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+        if self.scan1.has_field(field_name) {
+            self.scan1.get_float(field_name)
+        } else {
+            self.scan2.get_float(field_name)
+        }
+    }
+
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+        if self.scan1.has_field(field_name) {
+            self.scan1.get_long(field_name)
+        } else {
+            self.scan2.get_long(field_name)
+        }
+    }
+
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+        if self.scan1.has_field(field_name) {
+            self.scan1.get_val(field_name)
+        } else {
+            self.scan2.get_val(field_name)
+        }
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.scan1.has_field(field_name) || self.scan2.has_field(field_name)
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.scan1.close()?;
+        self.scan2.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::buffer_manager::BufferManager;
+    use crate::file_manager::{FileManagerTrait, MemoryFileManager, PAGE_SIZE};
+    use crate::log_manager::LogManager;
+    use crate::record_manager::layout::Layout;
+    use crate::record_manager::scan::{assert_scan_exhausts, UpdateScan};
+    use crate::record_manager::schema::Schema;
+    use crate::record_manager::table_scan::TableScan;
+    use crate::transaction::lock_table::LockTable;
+    use crate::transaction::transaction::Transaction;
+
+    #[test]
+    fn product_scan() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            8,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let mut schema1 = Schema::new();
+        schema1.add_int_field("A".to_string()).unwrap();
+        schema1.add_string_field("B".to_string(), 9).unwrap();
+        let layout1 = Arc::new(Layout::from(schema1));
+        let mut schema2 = Schema::new();
+        schema2.add_int_field("C".to_string()).unwrap();
+        schema2.add_string_field("D".to_string(), 9).unwrap();
+        let layout2 = Arc::new(Layout::from(schema2));
+
+        let transaction = Arc::new(Mutex::new(Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        )));
+
+        let mut table_scan1 =
+            TableScan::new(Arc::clone(&transaction), "T1", Arc::clone(&layout1)).unwrap();
+        let mut table_scan2 =
+            TableScan::new(Arc::clone(&transaction), "T2", Arc::clone(&layout2)).unwrap();
+        for n in 0..3 {
+            table_scan1.insert().unwrap();
+            table_scan1.set_int("A", n).unwrap();
+            table_scan1.set_string("B", format!("b{}", n)).unwrap();
+            table_scan2.insert().unwrap();
+            table_scan2.set_int("C", n * 10).unwrap();
+            table_scan2.set_string("D", format!("d{}", n * 10)).unwrap();
+        }
+
+        let mut product_scan = ProductScan::new(table_scan1, table_scan2).unwrap();
+        assert!(product_scan.has_field("A"));
+        assert!(product_scan.has_field("D"));
+        assert!(!product_scan.has_field("E"));
+
+        let mut found = HashSet::new();
+        while product_scan.next().unwrap() {
+            let a = product_scan.get_int("A").unwrap();
+            let c = product_scan.get_int("C").unwrap();
+            assert_eq!(product_scan.get_string("B").unwrap(), format!("b{}", a));
+            assert_eq!(product_scan.get_string("D").unwrap(), format!("d{}", c));
+            assert!(found.insert((a, c)));
+        }
+        let expected: HashSet<_> = (0..3)
+            .flat_map(|a| (0..3).map(move |c| (a, c * 10)))
+            .collect();
+        assert_eq!(found, expected);
+        assert_scan_exhausts(&mut product_scan, 9);
+
+        product_scan.close().unwrap();
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
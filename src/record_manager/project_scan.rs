@@ -0,0 +1,147 @@
+use super::constant::Constant;
+use super::scan::Scan;
+
+// 内側のScanのうち、fieldsに含まれるフィールドだけを見せる。読み取り専用
+pub struct ProjectScan<S: Scan> {
+    inner: S,
+    fields: Vec<String>,
+}
+
+impl<S: Scan> ProjectScan<S> {
+    pub fn new(inner: S, fields: Vec<String>) -> Self {
+        Self { inner, fields }
+    }
+
+    fn check_field(&self, field_name: &str) -> anyhow::Result<()> {
+        if self.has_field(field_name) {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "field {} is not in the projection",
+                field_name
+            ))
+        }
+    }
+}
+
+impl<S: Scan> Scan for ProjectScan<S> {
+    fn before_first(&mut self) -> anyhow::Result<()> {
+        self.inner.before_first()
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        self.inner.next()
+    }
+
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+        self.check_field(field_name)?;
+        self.inner.get_int(field_name)
+    }
+
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+        self.check_field(field_name)?;
+        self.inner.get_string(field_name)
+    }
+
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+        self.check_field(field_name)?;
+        self.inner.get_bool(field_name)
+    }
+
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+        self.check_field(field_name)?;
+        self.inner.get_float(field_name)
+    }
+
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+        self.check_field(field_name)?;
+        self.inner.get_long(field_name)
+    }
+
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+        self.check_field(field_name)?;
+        self.inner.get_val(field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.fields.iter().any(|field| field == field_name)
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.inner.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::buffer_manager::BufferManager;
+    use crate::file_manager::{FileManagerTrait, MemoryFileManager, PAGE_SIZE};
+    use crate::log_manager::LogManager;
+    use crate::record_manager::layout::Layout;
+    use crate::record_manager::scan::{assert_scan_exhausts, UpdateScan};
+    use crate::record_manager::schema::Schema;
+    use crate::record_manager::table_scan::TableScan;
+    use crate::transaction::lock_table::LockTable;
+    use crate::transaction::transaction::Transaction;
+
+    #[test]
+    fn project_scan() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            8,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let table_name = "T".to_string();
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 10).unwrap();
+        schema.add_int_field("age".to_string()).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+
+        let transaction = Arc::new(Mutex::new(Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        )));
+
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), &table_name, Arc::clone(&layout)).unwrap();
+        for i in 0..10 {
+            table_scan.insert().unwrap();
+            table_scan.set_int("id", i).unwrap();
+            table_scan.set_string("name", format!("n{}", i)).unwrap();
+            table_scan.set_int("age", 20 + i).unwrap();
+        }
+
+        let mut project_scan =
+            ProjectScan::new(table_scan, vec!["id".to_string(), "name".to_string()]);
+        assert!(project_scan.has_field("id"));
+        assert!(project_scan.has_field("name"));
+        assert!(!project_scan.has_field("age"));
+
+        project_scan.before_first().unwrap();
+        while project_scan.next().unwrap() {
+            let id = project_scan.get_int("id").unwrap();
+            assert_eq!(project_scan.get_string("name").unwrap(), format!("n{}", id));
+            // 射影に含まれないフィールドは内側にあっても読めない
+            assert!(project_scan.get_int("age").is_err());
+            assert!(project_scan.get_val("age").is_err());
+        }
+        assert_scan_exhausts(&mut project_scan, 10);
+
+        project_scan.close().unwrap();
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
@@ -0,0 +1,725 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context};
+
+use crate::file_manager::BlockId;
+use crate::record_manager::schema::FieldInfo;
+use crate::transaction::transaction::{LogMode, Transaction};
+
+use super::constant::Constant;
+use super::layout::{Layout, NULL_BITMAP_OFFSET};
+
+const EMPTY: i32 = 0;
+const USED: i32 = 1;
+
+pub struct RecordPage {
+    transaction: Arc<Mutex<Transaction>>,
+    block_id: BlockId,
+    layout: Arc<Layout>,
+}
+
+impl RecordPage {
+    pub fn new(
+        transaction: Arc<Mutex<Transaction>>,
+        block_id: BlockId,
+        layout: Arc<Layout>,
+    ) -> anyhow::Result<Self> {
+        transaction.lock().unwrap().pin(&block_id)?;
+        Ok(Self {
+            transaction,
+            block_id,
+            layout,
+        })
+    }
+
+    pub fn block_id(&self) -> &BlockId {
+        &self.block_id
+    }
+
+    pub fn get_int(&self, slot: usize, field_name: &str) -> anyhow::Result<i32> {
+        let offset = self.field_offset(slot, field_name)?;
+        self.transaction
+            .lock()
+            .unwrap()
+            .get_int(&self.block_id, offset)
+    }
+
+    pub fn get_string(&self, slot: usize, field_name: &str) -> anyhow::Result<String> {
+        let offset = self.field_offset(slot, field_name)?;
+        self.transaction
+            .lock()
+            .unwrap()
+            .get_string(&self.block_id, offset)
+    }
+
+    pub fn get_bool(&self, slot: usize, field_name: &str) -> anyhow::Result<bool> {
+        let offset = self.field_offset(slot, field_name)?;
+        self.transaction
+            .lock()
+            .unwrap()
+            .get_bool(&self.block_id, offset)
+    }
+
+    pub fn get_float(&self, slot: usize, field_name: &str) -> anyhow::Result<f64> {
+        let offset = self.field_offset(slot, field_name)?;
+        self.transaction
+            .lock()
+            .unwrap()
+            .get_float(&self.block_id, offset)
+    }
+
+    // フィールドの型に合わせて読む
+    pub fn get_val(&self, slot: usize, field_name: &str) -> anyhow::Result<Constant> {
+        let offset = self.field_offset(slot, field_name)?;
+        let info = self.field_info(field_name)?;
+        self.transaction.lock().unwrap().get_val(
+            &self.block_id,
+            offset,
+            info,
+            self.layout.encoding(),
+        )
+    }
+
+    pub fn get_long(&self, slot: usize, field_name: &str) -> anyhow::Result<i64> {
+        let offset = self.field_offset(slot, field_name)?;
+        self.transaction
+            .lock()
+            .unwrap()
+            .get_long(&self.block_id, offset)
+    }
+
+    // タイムスタンプはlongと同じ8バイトで持つ
+    pub fn get_timestamp(&self, slot: usize, field_name: &str) -> anyhow::Result<i64> {
+        self.get_long(slot, field_name)
+    }
+
+    // NULLならNoneを返す。NULLを許さないフィールドは常にSome
+    pub fn get_optional_int(&self, slot: usize, field_name: &str) -> anyhow::Result<Option<i32>> {
+        let offset = self.field_offset(slot, field_name)?;
+        let mut transaction = self.transaction.lock().unwrap();
+        match self.layout.null_bit(field_name) {
+            Some(bit) => transaction.get_optional_int(
+                &self.block_id,
+                offset,
+                self.null_bitmap_offset(slot),
+                bit,
+            ),
+            None => transaction.get_int(&self.block_id, offset).map(Some),
+        }
+    }
+
+    pub fn get_optional_string(
+        &self,
+        slot: usize,
+        field_name: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let offset = self.field_offset(slot, field_name)?;
+        let mut transaction = self.transaction.lock().unwrap();
+        match self.layout.null_bit(field_name) {
+            Some(bit) => transaction.get_optional_string(
+                &self.block_id,
+                offset,
+                self.null_bitmap_offset(slot),
+                bit,
+            ),
+            None => transaction.get_string(&self.block_id, offset).map(Some),
+        }
+    }
+
+    pub fn is_null(&self, slot: usize, field_name: &str) -> anyhow::Result<bool> {
+        self.field_offset(slot, field_name)?;
+        match self.layout.null_bit(field_name) {
+            Some(bit) => self.transaction.lock().unwrap().is_null(
+                &self.block_id,
+                self.null_bitmap_offset(slot),
+                bit,
+            ),
+            None => Ok(false),
+        }
+    }
+
+    // 値はそのまま残し、NULLビットだけを立てる。次にset_*すればNULLでなくなる
+    pub fn set_null(&self, slot: usize, field_name: &str) -> anyhow::Result<()> {
+        self.field_offset(slot, field_name)?;
+        let bit = match self.layout.null_bit(field_name) {
+            Some(bit) => bit,
+            None => bail!("field {} is not nullable", field_name),
+        };
+        self.transaction.lock().unwrap().set_null(
+            &self.block_id,
+            self.null_bitmap_offset(slot),
+            bit,
+            true,
+            LogMode::Log,
+        )
+    }
+
+    pub fn set_int(&self, slot: usize, field_name: &str, val: i32) -> anyhow::Result<()> {
+        let offset = self.field_offset(slot, field_name)?;
+        self.transaction
+            .lock()
+            .unwrap()
+            .set_int(&self.block_id, offset, val, LogMode::Log)?;
+        self.clear_null(slot, field_name)
+    }
+
+    // 長さとLayoutのencodingを確かめてから書く
+    pub fn set_string(&self, slot: usize, field_name: &str, val: String) -> anyhow::Result<()> {
+        self.set_val(slot, field_name, Constant::String(val))
+    }
+
+    pub fn set_bool(&self, slot: usize, field_name: &str, val: bool) -> anyhow::Result<()> {
+        let offset = self.field_offset(slot, field_name)?;
+        self.transaction
+            .lock()
+            .unwrap()
+            .set_bool(&self.block_id, offset, val, LogMode::Log)?;
+        self.clear_null(slot, field_name)
+    }
+
+    pub fn set_float(&self, slot: usize, field_name: &str, val: f64) -> anyhow::Result<()> {
+        let offset = self.field_offset(slot, field_name)?;
+        self.transaction
+            .lock()
+            .unwrap()
+            .set_float(&self.block_id, offset, val, LogMode::Log)?;
+        self.clear_null(slot, field_name)
+    }
+
+    // フィールドの型に合わない値はエラーにする
+    pub fn set_val(&self, slot: usize, field_name: &str, val: Constant) -> anyhow::Result<()> {
+        let offset = self.field_offset(slot, field_name)?;
+        let info = self.field_info(field_name)?;
+        self.transaction.lock().unwrap().set_val(
+            &self.block_id,
+            offset,
+            info,
+            self.layout.encoding(),
+            val,
+            LogMode::Log,
+        )?;
+        self.clear_null(slot, field_name)
+    }
+
+    pub fn set_long(&self, slot: usize, field_name: &str, val: i64) -> anyhow::Result<()> {
+        let offset = self.field_offset(slot, field_name)?;
+        self.transaction
+            .lock()
+            .unwrap()
+            .set_long(&self.block_id, offset, val, LogMode::Log)?;
+        self.clear_null(slot, field_name)
+    }
+
+    // undoはSetLongのログで戻す
+    pub fn set_timestamp(&self, slot: usize, field_name: &str, val: i64) -> anyhow::Result<()> {
+        self.set_long(slot, field_name, val)
+    }
+
+    pub fn delete(&self, slot: usize) -> anyhow::Result<()> {
+        self.set_flag(slot, EMPTY)
+    }
+
+    // 新しいブロックの全スロットを空にして、各フィールドを初期値で埋める
+    pub fn format(&self) -> anyhow::Result<()> {
+        let mut slot = 0;
+        while self.is_valid_slot(slot) {
+            {
+                let mut transaction = self.transaction.lock().unwrap();
+                let slot_offset = self.offset(slot) as i32;
+                transaction.set_int(&self.block_id, slot_offset, EMPTY, LogMode::NoLog)?;
+                for i in 0..self.layout.null_bitmap_size() {
+                    let offset = self.null_bitmap_offset(slot) + i as i32;
+                    transaction.set_byte(&self.block_id, offset, 0, LogMode::NoLog)?;
+                }
+            }
+            for field_name in self.layout.schema().fields() {
+                self.format_field(slot, field_name)?;
+            }
+            slot += 1;
+        }
+        Ok(())
+    }
+
+    // スキーマに既定値があればそれを、なければ型の初期値(0や空文字列)をログなしで書く
+    pub fn format_field(&self, slot: usize, field_name: &str) -> anyhow::Result<()> {
+        let offset = self.field_offset(slot, field_name)?;
+        let info = self.field_info(field_name)?;
+        let value = match self.layout.schema().default_of(field_name) {
+            Some(value) => value.clone(),
+            None => info.default_value(),
+        };
+        self.transaction.lock().unwrap().set_val(
+            &self.block_id,
+            offset,
+            info,
+            self.layout.encoding(),
+            value,
+            LogMode::NoLog,
+        )
+    }
+
+    // フィールドを読まずに、使われているスロットの数だけを数える
+    pub fn used_slot_count(&self) -> anyhow::Result<usize> {
+        let mut count = 0;
+        for slot in 0..self.slot_count() {
+            if self.flag(slot)? == USED {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    pub fn first_used(&self) -> anyhow::Result<Option<usize>> {
+        self.search_after(None, USED)
+    }
+
+    // slotより後ろで最初に使われているスロット
+    pub fn next_used_after(&self, slot: usize) -> anyhow::Result<Option<usize>> {
+        self.search_after(Some(slot), USED)
+    }
+
+    // slotより前で最も近い使われているスロット。後ろから走査するときに使う
+    pub fn prev_used_before(&self, slot: usize) -> anyhow::Result<Option<usize>> {
+        for slot in (0..slot.min(self.slot_count())).rev() {
+            if self.flag(slot)? == USED {
+                return Ok(Some(slot));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn insert_after(&self, slot: Option<usize>) -> anyhow::Result<Option<usize>> {
+        let new_slot = self.search_after(slot, EMPTY)?;
+        if let Some(new_slot) = new_slot {
+            self.set_flag(new_slot, USED)?;
+        }
+        Ok(new_slot)
+    }
+
+    pub fn close(&self) -> anyhow::Result<()> {
+        self.transaction.lock().unwrap().unpin(&self.block_id)
+    }
+
+    fn set_flag(&self, slot: usize, flag: i32) -> anyhow::Result<()> {
+        self.transaction.lock().unwrap().set_int(
+            &self.block_id,
+            self.offset(slot) as i32,
+            flag,
+            LogMode::Log,
+        )
+    }
+
+    fn clear_null(&self, slot: usize, field_name: &str) -> anyhow::Result<()> {
+        match self.layout.null_bit(field_name) {
+            Some(bit) => self.transaction.lock().unwrap().set_null(
+                &self.block_id,
+                self.null_bitmap_offset(slot),
+                bit,
+                false,
+                LogMode::Log,
+            ),
+            None => Ok(()),
+        }
+    }
+
+    fn search_after(&self, slot: Option<usize>, flag: i32) -> anyhow::Result<Option<usize>> {
+        let mut slot = slot.map_or(0, |slot| slot + 1);
+        while self.is_valid_slot(slot) {
+            if self.flag(slot)? == flag {
+                return Ok(Some(slot));
+            }
+            slot += 1;
+        }
+        Ok(None)
+    }
+
+    fn flag(&self, slot: usize) -> anyhow::Result<i32> {
+        self.transaction
+            .lock()
+            .unwrap()
+            .get_int(&self.block_id, self.offset(slot) as i32)
+    }
+
+    fn is_valid_slot(&self, slot: usize) -> bool {
+        slot < self.slot_count()
+    }
+
+    fn slot_count(&self) -> usize {
+        self.transaction.lock().unwrap().block_size() / self.layout.slot_size()
+    }
+
+    fn offset(&self, slot: usize) -> usize {
+        slot * self.layout.slot_size()
+    }
+
+    fn null_bitmap_offset(&self, slot: usize) -> i32 {
+        (self.offset(slot) + NULL_BITMAP_OFFSET) as i32
+    }
+
+    fn field_info(&self, field_name: &str) -> anyhow::Result<&FieldInfo> {
+        self.layout
+            .schema()
+            .info(field_name)
+            .with_context(|| format!("unknown field {}", field_name))
+    }
+
+    fn field_offset(&self, slot: usize, field_name: &str) -> anyhow::Result<i32> {
+        let offset = self
+            .layout
+            .offset(field_name)
+            .with_context(|| format!("unknown field {}", field_name))?;
+        Ok((self.offset(slot) + offset) as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::buffer_manager::BufferManager;
+    use crate::file_manager::{FileManager, FileManagerOptions, FileManagerTrait};
+    use crate::log_manager::LogManager;
+    use crate::mydb::{MyDb, MyDbConfig};
+    use crate::record_manager::schema::Schema;
+    use crate::transaction::lock_table::LockTable;
+
+    #[test]
+    fn record_page() {
+        let mut schema = Schema::new();
+        schema.add_int_field("A".to_string()).unwrap();
+        schema.add_string_field("B".to_string(), 9).unwrap();
+        schema.add_bool_field("C".to_string()).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+
+        // ちょうど3スロット分のブロック
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let log_file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(log_file_manager, log_filename.to_string()).unwrap(),
+        ));
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new_with_options(
+                directory.to_string(),
+                FileManagerOptions {
+                    block_size: 3 * layout.slot_size(),
+                    ..Default::default()
+                },
+            )));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let transaction = Arc::new(Mutex::new(Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        )));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let block_id = transaction.lock().unwrap().append(filename).unwrap();
+        let record_page =
+            RecordPage::new(Arc::clone(&transaction), block_id, Arc::clone(&layout)).unwrap();
+        record_page.format().unwrap();
+        assert_eq!(record_page.first_used().unwrap(), None);
+
+        let mut slot = None;
+        for n in 0..3 {
+            slot = record_page.insert_after(slot).unwrap();
+            assert_eq!(slot, Some(n));
+            record_page.set_int(n, "A", n as i32).unwrap();
+            record_page.set_string(n, "B", format!("rec{}", n)).unwrap();
+            record_page.set_bool(n, "C", n % 2 == 0).unwrap();
+        }
+        assert_eq!(record_page.insert_after(slot).unwrap(), None);
+        assert_eq!(record_page.insert_after(None).unwrap(), None);
+        assert!(record_page.get_int(0, "D").is_err());
+
+        record_page.delete(1).unwrap();
+        assert_eq!(record_page.next_used_after(0).unwrap(), Some(2));
+        assert_eq!(record_page.prev_used_before(2).unwrap(), Some(0));
+        assert_eq!(record_page.get_int(2, "A").unwrap(), 2);
+        assert_eq!(record_page.get_string(2, "B").unwrap(), "rec2");
+        assert!(record_page.get_bool(2, "C").unwrap());
+        assert_eq!(record_page.insert_after(None).unwrap(), Some(1));
+
+        record_page.close().unwrap();
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn used_slot_gaps() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut schema = Schema::new();
+        schema.add_int_field("A".to_string()).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        let block_id = transaction.lock().unwrap().append("T.tbl").unwrap();
+        let slot_count = transaction.lock().unwrap().block_size() / layout.slot_size();
+        let record_page =
+            RecordPage::new(Arc::clone(&transaction), block_id, Arc::clone(&layout)).unwrap();
+        record_page.format().unwrap();
+
+        // 1, 2, 5と最後のスロットだけを残す
+        let last = slot_count - 1;
+        let used = vec![1, 2, 5, last];
+        let mut slot = None;
+        while let Some(new_slot) = record_page.insert_after(slot).unwrap() {
+            slot = Some(new_slot);
+        }
+        for slot in 0..slot_count {
+            if !used.contains(&slot) {
+                record_page.delete(slot).unwrap();
+            }
+        }
+
+        let mut forward = vec![];
+        let mut slot = record_page.first_used().unwrap();
+        while let Some(current) = slot {
+            forward.push(current);
+            slot = record_page.next_used_after(current).unwrap();
+        }
+        assert_eq!(forward, used);
+
+        let mut backward = vec![];
+        let mut slot = record_page.prev_used_before(slot_count).unwrap();
+        while let Some(current) = slot {
+            backward.push(current);
+            slot = record_page.prev_used_before(current).unwrap();
+        }
+        assert_eq!(backward, used.iter().rev().copied().collect::<Vec<_>>());
+
+        // ページの外から始めても範囲内のスロットだけを見る
+        assert_eq!(
+            record_page.prev_used_before(usize::MAX).unwrap(),
+            Some(last)
+        );
+        assert_eq!(record_page.next_used_after(last).unwrap(), None);
+        assert_eq!(record_page.used_slot_count().unwrap(), used.len());
+        assert_eq!(record_page.next_used_after(2).unwrap(), Some(5));
+        assert_eq!(record_page.prev_used_before(5).unwrap(), Some(2));
+        assert_eq!(record_page.prev_used_before(1).unwrap(), None);
+        assert_eq!(record_page.prev_used_before(0).unwrap(), None);
+
+        record_page.close().unwrap();
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn null_field() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema
+            .add_optional_field("age".to_string(), FieldInfo::Int)
+            .unwrap();
+        schema
+            .add_optional_field("name".to_string(), FieldInfo::String(9))
+            .unwrap();
+        let layout = Arc::new(Layout::from(schema));
+        let open = |transaction: &Arc<Mutex<Transaction>>, block_id: &BlockId| {
+            RecordPage::new(
+                Arc::clone(transaction),
+                block_id.clone(),
+                Arc::clone(&layout),
+            )
+            .unwrap()
+        };
+
+        let tx1 = Arc::new(Mutex::new(db.new_transaction()));
+        let block_id = tx1.lock().unwrap().append("T.tbl").unwrap();
+        let record_page = open(&tx1, &block_id);
+        record_page.format().unwrap();
+        let slot = record_page.insert_after(None).unwrap().unwrap();
+        record_page.set_int(slot, "id", 1).unwrap();
+        record_page.set_int(slot, "age", 20).unwrap();
+        record_page
+            .set_string(slot, "name", "alice".to_string())
+            .unwrap();
+        assert_eq!(record_page.get_optional_int(slot, "age").unwrap(), Some(20));
+        record_page.set_null(slot, "age").unwrap();
+        assert_eq!(record_page.get_optional_int(slot, "age").unwrap(), None);
+        assert!(record_page.is_null(slot, "age").unwrap());
+        assert!(!record_page.is_null(slot, "name").unwrap());
+        assert_eq!(
+            record_page.get_optional_string(slot, "name").unwrap(),
+            Some("alice".to_string())
+        );
+        // NULLを許さないフィールドはNULLにできない
+        assert!(record_page.set_null(slot, "id").is_err());
+        assert_eq!(record_page.get_optional_int(slot, "id").unwrap(), Some(1));
+        assert!(record_page.set_null(slot, "missing").is_err());
+        record_page.close().unwrap();
+        tx1.lock().unwrap().commit().unwrap();
+
+        // コミットしたNULLは次のトランザクションからも見える
+        let tx2 = Arc::new(Mutex::new(db.new_transaction()));
+        let record_page = open(&tx2, &block_id);
+        assert_eq!(record_page.get_optional_int(slot, "age").unwrap(), None);
+        record_page.set_int(slot, "age", 30).unwrap();
+        record_page.set_null(slot, "name").unwrap();
+        assert_eq!(record_page.get_optional_int(slot, "age").unwrap(), Some(30));
+        assert_eq!(record_page.get_optional_string(slot, "name").unwrap(), None);
+        record_page.close().unwrap();
+        tx2.lock().unwrap().rollback().unwrap();
+
+        // ロールバックでNULLビットも元に戻る
+        let tx3 = Arc::new(Mutex::new(db.new_transaction()));
+        let record_page = open(&tx3, &block_id);
+        assert_eq!(record_page.get_optional_int(slot, "age").unwrap(), None);
+        assert_eq!(
+            record_page.get_optional_string(slot, "name").unwrap(),
+            Some("alice".to_string())
+        );
+        record_page.close().unwrap();
+        tx3.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn timestamp_field() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema
+            .add_timestamp_field("created_at".to_string())
+            .unwrap();
+        let layout = Arc::new(Layout::from(schema));
+        let open = |transaction: &Arc<Mutex<Transaction>>, block_id: &BlockId| {
+            RecordPage::new(
+                Arc::clone(transaction),
+                block_id.clone(),
+                Arc::clone(&layout),
+            )
+            .unwrap()
+        };
+        // 2024-01-01T00:00:00Zのマイクロ秒
+        let created_at = 1_704_067_200_000_000;
+
+        let tx1 = Arc::new(Mutex::new(db.new_transaction()));
+        let block_id = tx1.lock().unwrap().append("T.tbl").unwrap();
+        let record_page = open(&tx1, &block_id);
+        record_page.format().unwrap();
+        let slot = record_page.insert_after(None).unwrap().unwrap();
+        assert_eq!(record_page.get_timestamp(slot, "created_at").unwrap(), 0);
+        record_page.set_int(slot, "id", 1).unwrap();
+        record_page
+            .set_timestamp(slot, "created_at", created_at)
+            .unwrap();
+        assert_eq!(
+            record_page.get_timestamp(slot, "created_at").unwrap(),
+            created_at
+        );
+        record_page.close().unwrap();
+        tx1.lock().unwrap().commit().unwrap();
+
+        // エポックより前の時刻も書けて、ロールバックで元に戻る
+        let tx2 = Arc::new(Mutex::new(db.new_transaction()));
+        let record_page = open(&tx2, &block_id);
+        record_page.set_timestamp(slot, "created_at", -1).unwrap();
+        assert_eq!(record_page.get_timestamp(slot, "created_at").unwrap(), -1);
+        record_page.close().unwrap();
+        tx2.lock().unwrap().rollback().unwrap();
+
+        let tx3 = Arc::new(Mutex::new(db.new_transaction()));
+        let record_page = open(&tx3, &block_id);
+        assert_eq!(
+            record_page.get_timestamp(slot, "created_at").unwrap(),
+            created_at
+        );
+        record_page.close().unwrap();
+        tx3.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn format_field() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let db = MyDb::open(MyDbConfig {
+            dir: tempdir.path().join("db").to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let fields = [
+            ("i", FieldInfo::Int, Constant::Int(7)),
+            ("s", FieldInfo::String(9), Constant::from("none")),
+            ("b", FieldInfo::Bool, Constant::Bool(true)),
+            ("f", FieldInfo::Float, Constant::Float(1.5)),
+            ("l", FieldInfo::Long, Constant::Long(-1)),
+            ("t", FieldInfo::Timestamp, Constant::Timestamp(1)),
+        ];
+        let mut schema = Schema::new();
+        for (name, info, _) in &fields {
+            schema.add_field(name.to_string(), *info).unwrap();
+        }
+        for (name, info, default) in &fields {
+            let name = format!("{}_default", name);
+            schema.add_field(name.clone(), *info).unwrap();
+            schema.set_default(&name, default.clone()).unwrap();
+        }
+        let layout = Arc::new(Layout::from(schema));
+
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+        let block_id = transaction.lock().unwrap().append("T.tbl").unwrap();
+        let record_page =
+            RecordPage::new(Arc::clone(&transaction), block_id, Arc::clone(&layout)).unwrap();
+        record_page.format().unwrap();
+
+        // 既定値のないフィールドは0や空文字列、既定値のあるフィールドはその値になる
+        for slot in [0, record_page.slot_count() - 1] {
+            for (name, info, default) in &fields {
+                assert_eq!(
+                    record_page.get_val(slot, name).unwrap(),
+                    info.default_value()
+                );
+                assert_eq!(
+                    record_page
+                        .get_val(slot, &format!("{}_default", name))
+                        .unwrap(),
+                    *default
+                );
+            }
+        }
+        assert_eq!(record_page.get_val(0, "i").unwrap(), Constant::Int(0));
+        assert_eq!(record_page.get_val(0, "s").unwrap(), Constant::from(""));
+        assert_eq!(record_page.get_val(0, "b").unwrap(), Constant::Bool(false));
+        assert_eq!(record_page.get_val(0, "f").unwrap(), Constant::Float(0.0));
+        assert_eq!(record_page.get_val(0, "l").unwrap(), Constant::Long(0));
+        assert_eq!(record_page.get_val(0, "t").unwrap(), Constant::Timestamp(0));
+
+        // 1つのフィールドだけを書き直せる
+        record_page.set_int(0, "i_default", 100).unwrap();
+        record_page.format_field(0, "i_default").unwrap();
+        assert_eq!(
+            record_page.get_val(0, "i_default").unwrap(),
+            Constant::Int(7)
+        );
+        assert!(record_page.format_field(0, "missing").is_err());
+        record_page.close().unwrap();
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
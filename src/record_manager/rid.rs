@@ -0,0 +1,14 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rid {
+    pub block_number: i32,
+    pub slot_id: usize,
+}
+
+impl Rid {
+    pub fn new(block_number: i32, slot_id: usize) -> Self {
+        Self {
+            block_number,
+            slot_id,
+        }
+    }
+}
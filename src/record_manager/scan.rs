@@ -0,0 +1,95 @@
+use super::constant::Constant;
+use super::rid::Rid;
+
+// レコードを順に読むための共通のインターフェース
+pub trait Scan {
+    fn before_first(&mut self) -> anyhow::Result<()>;
+    fn next(&mut self) -> anyhow::Result<bool>;
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32>;
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String>;
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool>;
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64>;
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64>;
+    // フィールドの型に応じた値を返す
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant>;
+    fn has_field(&self, field_name: &str) -> bool;
+    fn close(&mut self) -> anyhow::Result<()>;
+}
+
+// プランが返すBox<dyn Scan>も、SelectScanなどの中身にできるようにする
+impl<S: Scan + ?Sized> Scan for Box<S> {
+    fn before_first(&mut self) -> anyhow::Result<()> {
+        (**self).before_first()
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        (**self).next()
+    }
+
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+        (**self).get_int(field_name)
+    }
+
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+        (**self).get_string(field_name)
+    }
+
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+        (**self).get_bool(field_name)
+    }
+
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+        (**self).get_float(field_name)
+    }
+
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+        (**self).get_long(field_name)
+    }
+
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+        (**self).get_val(field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        (**self).has_field(field_name)
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        (**self).close()
+    }
+}
+
+// 現在のレコードを書き換えられるScan
+pub trait UpdateScan: Scan {
+    fn set_int(&mut self, field_name: &str, val: i32) -> anyhow::Result<()>;
+    fn set_string(&mut self, field_name: &str, val: String) -> anyhow::Result<()>;
+    fn set_bool(&mut self, field_name: &str, val: bool) -> anyhow::Result<()>;
+    fn set_float(&mut self, field_name: &str, val: f64) -> anyhow::Result<()>;
+    fn set_long(&mut self, field_name: &str, val: i64) -> anyhow::Result<()>;
+    // 値の型に応じたset_*を呼ぶ。フィールドの型と合っているかは呼び出し側で確かめる
+    fn set_val(&mut self, field_name: &str, val: Constant) -> anyhow::Result<()> {
+        match val {
+            Constant::Int(val) => self.set_int(field_name, val),
+            Constant::String(val) => self.set_string(field_name, val),
+            Constant::Bool(val) => self.set_bool(field_name, val),
+            Constant::Float(val) => self.set_float(field_name, val),
+            Constant::Long(val) | Constant::Timestamp(val) => self.set_long(field_name, val),
+        }
+    }
+    fn insert(&mut self) -> anyhow::Result<Rid>;
+    fn delete(&mut self) -> anyhow::Result<()>;
+    fn get_rid(&self) -> anyhow::Result<Rid>;
+    fn move_to_rid(&mut self, rid: Rid) -> anyhow::Result<()>;
+}
+
+// 先頭から最後まで読み、レコード数を確かめる。読み終えた後のnextもfalseを返し続ける
+#[cfg(test)]
+pub fn assert_scan_exhausts<S: Scan + ?Sized>(scan: &mut S, expected_count: usize) {
+    scan.before_first().unwrap();
+    let mut count = 0;
+    while scan.next().unwrap() {
+        count += 1;
+    }
+    assert_eq!(count, expected_count);
+    assert!(!scan.next().unwrap());
+}
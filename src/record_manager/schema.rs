@@ -0,0 +1,764 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Cursor, Read};
+
+use anyhow::{bail, Context};
+
+use crate::error::SchemaError;
+use crate::file_manager::{Page, BOOL_BYTES, FLOAT_BYTES, INTGER_BYTES, LONG_BYTES};
+
+use super::constant::Constant;
+use super::layout::Encoding;
+
+// 型コードにこのビットを立てて、NULLを許すフィールドであることを表す
+pub const OPTIONAL_FLAG: u8 = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldInfo {
+    Int,
+    // 文字列の最大長
+    String(usize),
+    Bool,
+    Float,
+    Long,
+    // Unixエポックからのマイクロ秒
+    Timestamp,
+}
+
+impl FieldInfo {
+    pub fn type_code(&self) -> u8 {
+        match self {
+            FieldInfo::Int => 0,
+            FieldInfo::String(_) => 1,
+            FieldInfo::Bool => 2,
+            FieldInfo::Float => 3,
+            FieldInfo::Long => 4,
+            FieldInfo::Timestamp => 5,
+        }
+    }
+
+    // type_codeの逆。lengthは文字列のときだけ使う
+    pub fn from_type_code(code: u8, length: usize) -> anyhow::Result<FieldInfo> {
+        Ok(match code {
+            0 => FieldInfo::Int,
+            1 => FieldInfo::String(length),
+            2 => FieldInfo::Bool,
+            3 => FieldInfo::Float,
+            4 => FieldInfo::Long,
+            5 => FieldInfo::Timestamp,
+            v => bail!("unknown field type {}", v),
+        })
+    }
+
+    pub fn bytes_length(&self, encoding: Encoding) -> usize {
+        match self {
+            FieldInfo::Int => INTGER_BYTES,
+            FieldInfo::String(length) => encoding.max_length(*length),
+            FieldInfo::Bool => BOOL_BYTES,
+            FieldInfo::Float => FLOAT_BYTES,
+            FieldInfo::Long | FieldInfo::Timestamp => LONG_BYTES,
+        }
+    }
+
+    // フォーマットしたばかりのスロットに入っている値
+    pub fn default_value(&self) -> Constant {
+        match self {
+            FieldInfo::Int => Constant::Int(0),
+            FieldInfo::String(_) => Constant::String(String::new()),
+            FieldInfo::Bool => Constant::Bool(false),
+            FieldInfo::Float => Constant::Float(0.0),
+            FieldInfo::Long => Constant::Long(0),
+            FieldInfo::Timestamp => Constant::Timestamp(0),
+        }
+    }
+
+    // 型が合わない値や、encodingで書けない文字列はInvalidInputにする
+    pub fn check_value(&self, value: &Constant, encoding: Encoding) -> io::Result<()> {
+        match (self, value) {
+            (FieldInfo::Int, Constant::Int(_))
+            | (FieldInfo::Bool, Constant::Bool(_))
+            | (FieldInfo::Float, Constant::Float(_))
+            | (FieldInfo::Long, Constant::Long(_))
+            | (FieldInfo::Timestamp, Constant::Timestamp(_)) => Ok(()),
+            (FieldInfo::String(length), Constant::String(value)) => encoding.check(value, *length),
+            (info, value) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} cannot be stored in {:?}", value, info),
+            )),
+        }
+    }
+
+    pub fn serialize_value(
+        &self,
+        page: &mut Page,
+        offset: usize,
+        value: &Constant,
+        encoding: Encoding,
+    ) -> io::Result<()> {
+        self.check_value(value, encoding)?;
+        match value {
+            Constant::Int(v) => page.set_int(offset, *v),
+            Constant::String(v) => page.set_string(offset, v.clone()),
+            Constant::Bool(v) => page.set_bool(offset, *v),
+            Constant::Float(v) => page.set_float(offset, *v),
+            Constant::Long(v) | Constant::Timestamp(v) => page.set_long(offset, *v),
+        }
+    }
+
+    // encodingで書けないはずのバイト列はInvalidDataにする
+    pub fn deserialize_value(
+        &self,
+        page: &mut Page,
+        offset: usize,
+        encoding: Encoding,
+    ) -> io::Result<Constant> {
+        Ok(match self {
+            FieldInfo::Int => Constant::Int(page.get_int(offset)?),
+            FieldInfo::String(_) => {
+                let value = page.get_string(offset)?;
+                if encoding == Encoding::Ascii && !value.is_ascii() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} is not ascii", value),
+                    ));
+                }
+                Constant::String(value)
+            }
+            FieldInfo::Bool => Constant::Bool(page.get_bool(offset)?),
+            FieldInfo::Float => Constant::Float(page.get_float(offset)?),
+            FieldInfo::Long => Constant::Long(page.get_long(offset)?),
+            FieldInfo::Timestamp => Constant::Timestamp(page.get_long(offset)?),
+        })
+    }
+}
+
+// schema!やfrom_field_listでフィールドの型を書くためのもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldDefinition {
+    Int,
+    // 文字列の最大長
+    String(usize),
+    Bool,
+    Long,
+    Float,
+    Timestamp,
+}
+
+impl From<FieldDefinition> for FieldInfo {
+    fn from(definition: FieldDefinition) -> Self {
+        match definition {
+            FieldDefinition::Int => FieldInfo::Int,
+            FieldDefinition::String(length) => FieldInfo::String(length),
+            FieldDefinition::Bool => FieldInfo::Bool,
+            FieldDefinition::Long => FieldInfo::Long,
+            FieldDefinition::Float => FieldInfo::Float,
+            FieldDefinition::Timestamp => FieldInfo::Timestamp,
+        }
+    }
+}
+
+// schema!["id" => Int, "name" => String(16)] と書くと、その順にフィールドを持つSchemaを作る
+macro_rules! schema {
+    ($($name:expr => $kind:ident $(($length:expr))?),* $(,)?) => {
+        $crate::record_manager::schema::Schema::from_field_list(&[$((
+            $name,
+            $crate::record_manager::schema::FieldDefinition::$kind $(($length))?,
+        )),*])
+    };
+}
+pub(crate) use schema;
+
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    fields: Vec<String>,
+    info: HashMap<String, FieldInfo>,
+    // NULLを許すフィールド
+    optional: HashSet<String>,
+    // 値を与えずに挿入したときに入れる値
+    defaults: HashMap<String, Constant>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 並べた順にフィールドを追加する。同じ名前が2度出てきたらエラー
+    pub fn from_field_list(fields: &[(&str, FieldDefinition)]) -> Result<Schema, SchemaError> {
+        let mut schema = Schema::new();
+        for (name, definition) in fields {
+            schema.add_field(name.to_string(), (*definition).into())?;
+        }
+        Ok(schema)
+    }
+
+    pub fn add_field(&mut self, name: String, info: FieldInfo) -> Result<(), SchemaError> {
+        if self.has_field(&name) {
+            return Err(SchemaError::DuplicateField(name));
+        }
+        self.fields.push(name.clone());
+        self.info.insert(name, info);
+        Ok(())
+    }
+
+    pub fn add_optional_field(&mut self, name: String, info: FieldInfo) -> Result<(), SchemaError> {
+        self.add_field(name.clone(), info)?;
+        self.optional.insert(name);
+        Ok(())
+    }
+
+    pub fn add_int_field(&mut self, name: String) -> Result<(), SchemaError> {
+        self.add_field(name, FieldInfo::Int)
+    }
+
+    pub fn add_string_field(&mut self, name: String, length: usize) -> Result<(), SchemaError> {
+        self.add_field(name, FieldInfo::String(length))
+    }
+
+    pub fn add_bool_field(&mut self, name: String) -> Result<(), SchemaError> {
+        self.add_field(name, FieldInfo::Bool)
+    }
+
+    pub fn add_float_field(&mut self, name: String) -> Result<(), SchemaError> {
+        self.add_field(name, FieldInfo::Float)
+    }
+
+    pub fn add_long_field(&mut self, name: String) -> Result<(), SchemaError> {
+        self.add_field(name, FieldInfo::Long)
+    }
+
+    pub fn add_timestamp_field(&mut self, name: String) -> Result<(), SchemaError> {
+        self.add_field(name, FieldInfo::Timestamp)
+    }
+
+    // 位置も型もそのままで名前だけを変える。レコードの中身は書き換えなくてよい
+    pub fn rename_field(&mut self, old_name: &str, new_name: &str) -> Result<(), SchemaError> {
+        let Some(position) = self.fields.iter().position(|name| name == old_name) else {
+            return Err(SchemaError::UnknownField(old_name.to_string()));
+        };
+        if self.has_field(new_name) {
+            return Err(SchemaError::DuplicateField(new_name.to_string()));
+        }
+        self.fields[position] = new_name.to_string();
+        let info = self.info.remove(old_name).unwrap();
+        self.info.insert(new_name.to_string(), info);
+        if self.optional.remove(old_name) {
+            self.optional.insert(new_name.to_string());
+        }
+        if let Some(value) = self.defaults.remove(old_name) {
+            self.defaults.insert(new_name.to_string(), value);
+        }
+        Ok(())
+    }
+
+    // フィールドの既定値を決める。型の合わない値は受け付けない
+    pub fn set_default(&mut self, name: &str, value: Constant) -> Result<(), SchemaError> {
+        let Some(info) = self.info(name) else {
+            return Err(SchemaError::UnknownField(name.to_string()));
+        };
+        // どのLayoutで使われるかはわからないので、文字数と型だけを確かめる
+        if info.check_value(&value, Encoding::Utf8).is_err() {
+            return Err(SchemaError::InvalidDefault(name.to_string()));
+        }
+        self.defaults.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    pub fn default_of(&self, name: &str) -> Option<&Constant> {
+        self.defaults.get(name)
+    }
+
+    pub fn add(&mut self, name: String, schema: &Schema) -> Result<(), SchemaError> {
+        match schema.info(&name) {
+            Some(info) if schema.is_optional(&name) => {
+                self.add_optional_field(name.clone(), *info)?
+            }
+            Some(info) => self.add_field(name.clone(), *info)?,
+            None => return Ok(()),
+        }
+        if let Some(value) = schema.default_of(&name) {
+            self.defaults.insert(name, value.clone());
+        }
+        Ok(())
+    }
+
+    pub fn add_all(&mut self, schema: &Schema) -> Result<(), SchemaError> {
+        for name in schema.fields() {
+            self.add(name.clone(), schema)?;
+        }
+        Ok(())
+    }
+
+    pub fn has_field(&self, name: &str) -> bool {
+        self.info.contains_key(name)
+    }
+
+    pub fn fields(&self) -> &Vec<String> {
+        &self.fields
+    }
+
+    pub fn info(&self, name: &str) -> Option<&FieldInfo> {
+        self.info.get(name)
+    }
+
+    pub fn is_optional(&self, name: &str) -> bool {
+        self.optional.contains(name)
+    }
+
+    // 型コードに、NULLを許すならOPTIONAL_FLAGを立てたもの
+    pub fn type_code(&self, name: &str) -> Option<u8> {
+        let code = self.info(name)?.type_code();
+        Some(if self.is_optional(name) {
+            code | OPTIONAL_FLAG
+        } else {
+            code
+        })
+    }
+
+    // 両方にあるフィールド名をselfの順に返す
+    pub fn intersection(&self, other: &Schema) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter(|name| other.has_field(name))
+            .cloned()
+            .collect()
+    }
+
+    // selfにだけあるフィールド名をselfの順に返す
+    pub fn difference(&self, other: &Schema) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter(|name| !other.has_field(name))
+            .cloned()
+            .collect()
+    }
+
+    // [フィールド数(4)] + フィールドごとに [名前の長さ(4)][名前][型(1)] (文字列なら [最大長(4)])
+    // NULLを許すフィールドは型の最上位ビットが立つ
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend((self.fields.len() as u32).to_be_bytes());
+        for name in &self.fields {
+            let info = self.info[name];
+            bytes.extend((name.len() as u32).to_be_bytes());
+            bytes.extend(name.as_bytes());
+            bytes.push(self.type_code(name).unwrap());
+            if let FieldInfo::String(length) = info {
+                bytes.extend((length as u32).to_be_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Schema> {
+        let mut cursor = Cursor::new(data);
+        let mut schema = Schema::new();
+        let count = read_u32(&mut cursor).context("read field count")?;
+        for i in 0..count {
+            let name_len = read_u32(&mut cursor)
+                .with_context(|| format!("read name length of field {}", i))?;
+            let mut name = vec![0; name_len as usize];
+            cursor
+                .read_exact(&mut name)
+                .with_context(|| format!("read name of field {}", i))?;
+            let name =
+                String::from_utf8(name).with_context(|| format!("decode name of field {}", i))?;
+
+            let mut code = [0; 1];
+            cursor
+                .read_exact(&mut code)
+                .with_context(|| format!("read type of field {}", name))?;
+            let info = match code[0] & !OPTIONAL_FLAG {
+                0 => FieldInfo::Int,
+                1 => {
+                    let length = read_u32(&mut cursor)
+                        .with_context(|| format!("read length of field {}", name))?;
+                    FieldInfo::String(length as usize)
+                }
+                2 => FieldInfo::Bool,
+                3 => FieldInfo::Float,
+                4 => FieldInfo::Long,
+                5 => FieldInfo::Timestamp,
+                v => bail!("unknown field type {} for field {}", v, name),
+            };
+            if code[0] & OPTIONAL_FLAG != 0 {
+                schema.add_optional_field(name, info)?;
+            } else {
+                schema.add_field(name, info)?;
+            }
+        }
+        Ok(schema)
+    }
+}
+
+fn read_u32(cursor: &mut Cursor<&[u8]>) -> anyhow::Result<u32> {
+    let mut bytes = [0; 4];
+    cursor.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record_manager::layout::Layout;
+
+    #[test]
+    fn schema() {
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 9).unwrap();
+        schema.add_bool_field("active".to_string()).unwrap();
+        schema.add_float_field("score".to_string()).unwrap();
+
+        assert_eq!(schema.fields(), &vec!["id", "name", "active", "score"]);
+        assert_eq!(schema.info("name"), Some(&FieldInfo::String(9)));
+        let name = schema.info("name").unwrap();
+        assert_eq!(name.bytes_length(Encoding::Ascii), 13);
+        assert_eq!(name.bytes_length(Encoding::Utf8), 40);
+        assert_eq!(
+            schema.info("active").unwrap().bytes_length(Encoding::Ascii),
+            1
+        );
+        assert_eq!(
+            schema.info("score").unwrap().bytes_length(Encoding::Utf8),
+            8
+        );
+        schema
+            .add_timestamp_field("created_at".to_string())
+            .unwrap();
+        assert_eq!(schema.info("created_at"), Some(&FieldInfo::Timestamp));
+        assert_eq!(
+            schema
+                .info("created_at")
+                .unwrap()
+                .bytes_length(Encoding::Ascii),
+            8
+        );
+        assert_eq!(schema.info("missing"), None);
+
+        let mut projected = Schema::new();
+        projected.add("name".to_string(), &schema).unwrap();
+        projected.add("missing".to_string(), &schema).unwrap();
+        assert_eq!(projected.fields(), &vec!["name"]);
+
+        let mut copied = Schema::new();
+        copied.add_all(&schema).unwrap();
+        assert_eq!(copied.fields(), schema.fields());
+    }
+
+    #[test]
+    fn optional_field() {
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema
+            .add_optional_field("name".to_string(), FieldInfo::String(9))
+            .unwrap();
+        assert!(!schema.is_optional("id"));
+        assert!(schema.is_optional("name"));
+        assert!(!schema.is_optional("missing"));
+        assert_eq!(schema.type_code("id"), Some(0));
+        assert_eq!(schema.type_code("name"), Some(1 | OPTIONAL_FLAG));
+        assert_eq!(schema.type_code("missing"), None);
+        assert_eq!(
+            schema.add_optional_field("id".to_string(), FieldInfo::Int),
+            Err(SchemaError::DuplicateField("id".to_string()))
+        );
+        assert!(!schema.is_optional("id"));
+
+        // 別のスキーマへ写してもNULLを許すかどうかは変わらない
+        let mut copied = Schema::new();
+        copied.add_all(&schema).unwrap();
+        assert!(!copied.is_optional("id"));
+        assert!(copied.is_optional("name"));
+    }
+
+    #[test]
+    fn intersection_and_difference() {
+        let mut student = Schema::new();
+        student.add_int_field("sid".to_string()).unwrap();
+        student.add_string_field("name".to_string(), 9).unwrap();
+        student.add_int_field("did".to_string()).unwrap();
+        let mut dept = Schema::new();
+        dept.add_int_field("did".to_string()).unwrap();
+        dept.add_string_field("dname".to_string(), 9).unwrap();
+        dept.add_string_field("name".to_string(), 9).unwrap();
+
+        // 順番はselfのフィールドの順になる
+        assert_eq!(student.intersection(&dept), vec!["name", "did"]);
+        assert_eq!(dept.intersection(&student), vec!["did", "name"]);
+        assert_eq!(student.difference(&dept), vec!["sid"]);
+        assert_eq!(dept.difference(&student), vec!["dname"]);
+
+        let mut course = Schema::new();
+        course.add_int_field("cid".to_string()).unwrap();
+        assert!(student.intersection(&course).is_empty());
+        assert_eq!(student.difference(&course), student.fields().clone());
+        assert!(student.difference(&student).is_empty());
+    }
+
+    #[test]
+    fn serialize_value() {
+        let cases = [
+            (FieldInfo::Int, Constant::Int(-7)),
+            (FieldInfo::String(5), Constant::from("hello")),
+            (FieldInfo::Bool, Constant::Bool(true)),
+            (FieldInfo::Float, Constant::Float(1.5)),
+            (FieldInfo::Long, Constant::Long(i64::MIN)),
+            (
+                FieldInfo::Timestamp,
+                Constant::Timestamp(1_704_067_200_000_000),
+            ),
+        ];
+        for encoding in [Encoding::Ascii, Encoding::Utf8] {
+            for (info, value) in &cases {
+                let mut page = Page::new(64);
+                assert_eq!(
+                    info.deserialize_value(&mut page, 4, encoding).unwrap(),
+                    info.default_value()
+                );
+                info.serialize_value(&mut page, 4, value, encoding).unwrap();
+                assert_eq!(
+                    &info.deserialize_value(&mut page, 4, encoding).unwrap(),
+                    value
+                );
+            }
+        }
+
+        // 型の違う値や長すぎる文字列は書かない
+        let mut page = Page::new(64);
+        let error = FieldInfo::Int
+            .serialize_value(&mut page, 4, &Constant::Long(1), Encoding::Ascii)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(FieldInfo::Long
+            .serialize_value(&mut page, 4, &Constant::Timestamp(1), Encoding::Ascii)
+            .is_err());
+        let error = FieldInfo::String(3)
+            .serialize_value(&mut page, 4, &Constant::from("abcd"), Encoding::Utf8)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(page.get_int(4).unwrap(), 0);
+        assert!(FieldInfo::Long
+            .deserialize_value(&mut page, 60, Encoding::Ascii)
+            .is_err());
+    }
+
+    #[test]
+    fn serialize_value_encoding() {
+        let info = FieldInfo::String(5);
+
+        // Utf8なら1文字4バイトまで確保しているので、ASCII以外の文字も最大長まで書ける
+        let value = Constant::from("héllö");
+        let mut page = Page::new(64);
+        info.serialize_value(&mut page, 4, &value, Encoding::Utf8)
+            .unwrap();
+        assert_eq!(
+            info.deserialize_value(&mut page, 4, Encoding::Utf8)
+                .unwrap(),
+            value
+        );
+        assert!(
+            page.get_int(4).unwrap() as usize + INTGER_BYTES <= info.bytes_length(Encoding::Utf8)
+        );
+
+        // Asciiでは1文字1バイトで、ASCII以外の文字は書けないし読めない
+        let value = Constant::from("hello");
+        let mut page = Page::new(64);
+        info.serialize_value(&mut page, 4, &value, Encoding::Ascii)
+            .unwrap();
+        assert_eq!(
+            info.deserialize_value(&mut page, 4, Encoding::Ascii)
+                .unwrap(),
+            value
+        );
+        assert_eq!(
+            page.get_int(4).unwrap() as usize + INTGER_BYTES,
+            info.bytes_length(Encoding::Ascii)
+        );
+        let error = info
+            .serialize_value(&mut page, 4, &Constant::from("héllo"), Encoding::Ascii)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        page.set_string(4, "héllo".to_string()).unwrap();
+        let error = info
+            .deserialize_value(&mut page, 4, Encoding::Ascii)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn from_field_list() {
+        let schema = Schema::from_field_list(&[
+            ("id", FieldDefinition::Int),
+            ("name", FieldDefinition::String(9)),
+            ("active", FieldDefinition::Bool),
+            ("total", FieldDefinition::Long),
+            ("score", FieldDefinition::Float),
+            ("created_at", FieldDefinition::Timestamp),
+        ])
+        .unwrap();
+        assert_eq!(
+            schema.fields(),
+            &vec!["id", "name", "active", "total", "score", "created_at"]
+        );
+        assert_eq!(schema.info("id"), Some(&FieldInfo::Int));
+        assert_eq!(schema.info("name"), Some(&FieldInfo::String(9)));
+        assert_eq!(schema.info("active"), Some(&FieldInfo::Bool));
+        assert_eq!(schema.info("total"), Some(&FieldInfo::Long));
+        assert_eq!(schema.info("score"), Some(&FieldInfo::Float));
+        assert_eq!(schema.info("created_at"), Some(&FieldInfo::Timestamp));
+        assert!(!schema.is_optional("id"));
+
+        // マクロでも同じスキーマになる
+        let from_macro = schema![
+            "id" => Int,
+            "name" => String(9),
+            "active" => Bool,
+            "total" => Long,
+            "score" => Float,
+            "created_at" => Timestamp,
+        ]
+        .unwrap();
+        assert_eq!(from_macro.fields(), schema.fields());
+        for name in schema.fields() {
+            assert_eq!(from_macro.info(name), schema.info(name));
+        }
+        assert!(schema![].unwrap().fields().is_empty());
+
+        assert_eq!(
+            schema!["id" => Int, "id" => String(9)].unwrap_err(),
+            SchemaError::DuplicateField("id".to_string())
+        );
+    }
+
+    #[test]
+    fn rename_field() {
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema
+            .add_optional_field("name".to_string(), FieldInfo::String(9))
+            .unwrap();
+        schema.add_bool_field("active".to_string()).unwrap();
+        let before = Layout::from(schema.clone());
+
+        schema.rename_field("name", "full_name").unwrap();
+        assert_eq!(schema.fields(), &vec!["id", "full_name", "active"]);
+        assert_eq!(schema.info("full_name"), Some(&FieldInfo::String(9)));
+        assert!(schema.is_optional("full_name"));
+        assert!(!schema.has_field("name"));
+        assert!(!schema.is_optional("name"));
+        // 名前以外は変わらないので、同じ位置のまま読める
+        let after = Layout::from(schema.clone());
+        assert_eq!(after.offset("full_name"), before.offset("name"));
+        assert_eq!(after.slot_size(), before.slot_size());
+
+        assert_eq!(
+            schema.rename_field("missing", "other"),
+            Err(SchemaError::UnknownField("missing".to_string()))
+        );
+        assert_eq!(
+            schema.rename_field("id", "active"),
+            Err(SchemaError::DuplicateField("active".to_string()))
+        );
+        assert_eq!(schema.fields(), &vec!["id", "full_name", "active"]);
+        assert_eq!(schema.info("id"), Some(&FieldInfo::Int));
+    }
+
+    #[test]
+    fn set_default() {
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 4).unwrap();
+        schema.set_default("id", Constant::Int(7)).unwrap();
+        assert_eq!(schema.default_of("id"), Some(&Constant::Int(7)));
+        assert_eq!(schema.default_of("name"), None);
+
+        assert_eq!(
+            schema.set_default("id", Constant::Bool(true)),
+            Err(SchemaError::InvalidDefault("id".to_string()))
+        );
+        assert_eq!(
+            schema.set_default("name", Constant::String("too long".to_string())),
+            Err(SchemaError::InvalidDefault("name".to_string()))
+        );
+        assert_eq!(
+            schema.set_default("missing", Constant::Int(0)),
+            Err(SchemaError::UnknownField("missing".to_string()))
+        );
+
+        // 名前を変えても、別のスキーマに写しても既定値はついてくる
+        schema.rename_field("id", "key").unwrap();
+        let mut copied = Schema::new();
+        copied.add_all(&schema).unwrap();
+        assert_eq!(copied.default_of("key"), Some(&Constant::Int(7)));
+    }
+
+    #[test]
+    fn duplicate_field() {
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        assert!(schema.has_field("id"));
+        assert!(!schema.has_field("name"));
+
+        assert_eq!(
+            schema.add_string_field("id".to_string(), 9),
+            Err(SchemaError::DuplicateField("id".to_string()))
+        );
+        assert_eq!(
+            schema.add_all(&schema.clone()),
+            Err(SchemaError::DuplicateField("id".to_string()))
+        );
+        assert_eq!(schema.fields(), &vec!["id"]);
+        assert_eq!(schema.info("id"), Some(&FieldInfo::Int));
+    }
+
+    #[test]
+    fn bytes() {
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 9).unwrap();
+        schema.add_bool_field("active".to_string()).unwrap();
+        schema.add_float_field("score".to_string()).unwrap();
+        schema
+            .add_timestamp_field("created_at".to_string())
+            .unwrap();
+
+        let bytes = schema.to_bytes();
+        let decoded = Schema::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.fields(), schema.fields());
+        for name in schema.fields() {
+            assert_eq!(decoded.info(name), schema.info(name));
+        }
+
+        assert_eq!(
+            Schema::from_bytes(&Schema::new().to_bytes())
+                .unwrap()
+                .fields()
+                .len(),
+            0
+        );
+
+        // 途中で切れたデータはpanicせずにエラーになる
+        for len in 0..bytes.len() {
+            assert!(Schema::from_bytes(&bytes[..len]).is_err());
+        }
+        let error = Schema::from_bytes(&bytes[..22]).unwrap_err();
+        assert_eq!(error.to_string(), "read length of field name");
+        let error = Schema::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(error.to_string(), "read type of field created_at");
+
+        let mut optional = Schema::new();
+        optional
+            .add_optional_field("name".to_string(), FieldInfo::String(9))
+            .unwrap();
+        optional.add_int_field("id".to_string()).unwrap();
+        let decoded = Schema::from_bytes(&optional.to_bytes()).unwrap();
+        assert!(decoded.is_optional("name"));
+        assert!(!decoded.is_optional("id"));
+        assert_eq!(decoded.info("name"), Some(&FieldInfo::String(9)));
+
+        let mut unknown = bytes.clone();
+        unknown[4 + 4 + 2] = 9;
+        let error = Schema::from_bytes(&unknown).unwrap_err();
+        assert_eq!(error.to_string(), "unknown field type 9 for field id");
+    }
+}
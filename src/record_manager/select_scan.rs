@@ -0,0 +1,251 @@
+use super::constant::Constant;
+use super::predicate::Predicate;
+use super::rid::Rid;
+use super::scan::{Scan, UpdateScan};
+
+// 内側のScanのうち、predicateを満たすレコードだけを返す
+pub struct SelectScan<S: Scan> {
+    inner: S,
+    predicate: Predicate,
+}
+
+impl<S: Scan> SelectScan<S> {
+    pub fn new(inner: S, predicate: Predicate) -> Self {
+        Self { inner, predicate }
+    }
+}
+
+impl<S: Scan> Scan for SelectScan<S> {
+    fn before_first(&mut self) -> anyhow::Result<()> {
+        self.inner.before_first()
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        while self.inner.next()? {
+            if self.predicate.is_satisfied(&mut self.inner)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+        self.inner.get_int(field_name)
+    }
+
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+        self.inner.get_string(field_name)
+    }
+
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+        self.inner.get_bool(field_name)
+    }
+
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+        self.inner.get_float(field_name)
+    }
+
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+        self.inner.get_long(field_name)
+    }
+
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+        self.inner.get_val(field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.inner.has_field(field_name)
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.inner.close()
+    }
+}
+
+impl<S: UpdateScan> UpdateScan for SelectScan<S> {
+    fn set_int(&mut self, field_name: &str, val: i32) -> anyhow::Result<()> {
+        self.inner.set_int(field_name, val)
+    }
+
+    fn set_string(&mut self, field_name: &str, val: String) -> anyhow::Result<()> {
+        self.inner.set_string(field_name, val)
+    }
+
+    fn set_bool(&mut self, field_name: &str, val: bool) -> anyhow::Result<()> {
+        self.inner.set_bool(field_name, val)
+    }
+
+    fn set_float(&mut self, field_name: &str, val: f64) -> anyhow::Result<()> {
+        self.inner.set_float(field_name, val)
+    }
+
+    fn set_long(&mut self, field_name: &str, val: i64) -> anyhow::Result<()> {
+        self.inner.set_long(field_name, val)
+    }
+
+    fn insert(&mut self) -> anyhow::Result<Rid> {
+        self.inner.insert()
+    }
+
+    fn delete(&mut self) -> anyhow::Result<()> {
+        self.inner.delete()
+    }
+
+    fn get_rid(&self) -> anyhow::Result<Rid> {
+        self.inner.get_rid()
+    }
+
+    fn move_to_rid(&mut self, rid: Rid) -> anyhow::Result<()> {
+        self.inner.move_to_rid(rid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::buffer_manager::BufferManager;
+    use crate::file_manager::{FileManagerTrait, MemoryFileManager, PAGE_SIZE};
+    use crate::log_manager::LogManager;
+    use crate::record_manager::layout::Layout;
+    use crate::record_manager::predicate::{Expression, Operator, Term};
+    use crate::record_manager::scan::assert_scan_exhausts;
+    use crate::record_manager::schema::Schema;
+    use crate::record_manager::table_scan::TableScan;
+    use crate::transaction::lock_table::LockTable;
+    use crate::transaction::transaction::Transaction;
+
+    #[test]
+    fn select_scan() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            8,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let table_name = "T".to_string();
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 10).unwrap();
+        schema.add_int_field("age".to_string()).unwrap();
+        schema.add_string_field("nickname".to_string(), 10).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+
+        let transaction = Arc::new(Mutex::new(Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        )));
+
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), &table_name, Arc::clone(&layout)).unwrap();
+        for i in 0..100 {
+            let name = format!("n{}", i % 10);
+            table_scan.insert().unwrap();
+            table_scan.set_int("id", i).unwrap();
+            table_scan.set_string("name", name.clone()).unwrap();
+            table_scan.set_int("age", i).unwrap();
+            let nickname = if i % 4 == 0 { name } else { "x".to_string() };
+            table_scan.set_string("nickname", nickname).unwrap();
+        }
+
+        let age_over_50 = Term::new(
+            Expression::field("age"),
+            Operator::Gt,
+            Expression::constant(50),
+        );
+        let mut select_scan =
+            SelectScan::new(table_scan, Predicate::new().with_term(age_over_50.clone()));
+        select_scan.before_first().unwrap();
+        while select_scan.next().unwrap() {
+            assert!(select_scan.get_int("age").unwrap() > 50);
+        }
+        assert_scan_exhausts(&mut select_scan, 49);
+        let mut table_scan = select_scan.inner;
+
+        let count = |table_scan: TableScan, predicate: Predicate| {
+            let mut select_scan = SelectScan::new(table_scan, predicate);
+            let mut count = 0;
+            select_scan.before_first().unwrap();
+            while select_scan.next().unwrap() {
+                count += 1;
+            }
+            (select_scan.inner, count)
+        };
+
+        // field = constant (文字列)
+        let name_is_n3 = Term::new(
+            Expression::field("name"),
+            Operator::Eq,
+            Expression::constant("n3"),
+        );
+        let (scan, n) = count(table_scan, Predicate::new().with_term(name_is_n3.clone()));
+        assert_eq!(n, 10);
+        table_scan = scan;
+
+        // field = field (文字列と整数)
+        let name_is_nickname = Term::new(
+            Expression::field("name"),
+            Operator::Eq,
+            Expression::field("nickname"),
+        );
+        let (scan, n) = count(table_scan, Predicate::new().with_term(name_is_nickname));
+        assert_eq!(n, 25);
+        table_scan = scan;
+        let id_is_age = Term::new(
+            Expression::field("id"),
+            Operator::Eq,
+            Expression::field("age"),
+        );
+        let (scan, n) = count(table_scan, Predicate::new().with_term(id_is_age));
+        assert_eq!(n, 100);
+        table_scan = scan;
+
+        // 複数のTermはすべて満たす必要がある
+        let (scan, n) = count(
+            table_scan,
+            Predicate::new()
+                .with_term(age_over_50.clone())
+                .with_term(name_is_n3),
+        );
+        assert_eq!(n, 5);
+        table_scan = scan;
+
+        // SelectScan越しに書き換える
+        let age_over_90 = Term::new(
+            Expression::field("age"),
+            Operator::Ge,
+            Expression::constant(91),
+        );
+        let mut select_scan = SelectScan::new(table_scan, Predicate::new().with_term(age_over_90));
+        select_scan.before_first().unwrap();
+        while select_scan.next().unwrap() {
+            select_scan.set_int("age", 0).unwrap();
+        }
+        let (scan, n) = count(select_scan.inner, Predicate::new().with_term(age_over_50));
+        assert_eq!(n, 40);
+        table_scan = scan;
+
+        // 型の違う値と比べるTermはどのレコードも満たさない
+        let mismatched = Term::new(
+            Expression::field("age"),
+            Operator::Eq,
+            Expression::constant("0"),
+        );
+        let mut select_scan = SelectScan::new(table_scan, Predicate::new().with_term(mismatched));
+        select_scan.before_first().unwrap();
+        assert!(!select_scan.next().unwrap());
+
+        select_scan.close().unwrap();
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
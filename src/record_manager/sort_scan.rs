@@ -0,0 +1,514 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context};
+
+use crate::transaction::transaction::Transaction;
+
+use super::constant::Constant;
+use super::layout::Layout;
+use super::scan::{Scan, UpdateScan};
+use super::schema::Schema;
+use super::table_scan::TableScan;
+
+// 1つのランにメモリ上で並べ替えるレコード数を、何ブロック分にするか
+const RUN_BLOCKS: usize = 2;
+// 一度にマージするランの数。ランごとにバッファを1つ固定する
+const MERGE_WIDTH: usize = 4;
+
+// 一時テーブルの名前に付ける番号
+static NEXT_TEMP_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub field_name: String,
+    pub direction: SortDirection,
+}
+
+impl SortKey {
+    pub fn asc(field_name: &str) -> Self {
+        Self {
+            field_name: field_name.to_string(),
+            direction: SortDirection::Asc,
+        }
+    }
+
+    pub fn desc(field_name: &str) -> Self {
+        Self {
+            field_name: field_name.to_string(),
+            direction: SortDirection::Desc,
+        }
+    }
+}
+
+// innerのレコードを外部マージソートで並べ替えて返す。
+// 最初のbefore_firstでinnerを読み切り、並べ替えたランを一時テーブルに書き出す
+pub struct SortScan<S: Scan> {
+    inner: S,
+    transaction: Arc<Mutex<Transaction>>,
+    layout: Arc<Layout>,
+    sort_keys: Vec<SortKey>,
+    // 並べ替え済みのランを入れた一時テーブル。Noneならまだ並べ替えていない
+    runs: Option<Vec<String>>,
+    merger: Option<Merger>,
+}
+
+impl<S: Scan> SortScan<S> {
+    // schemaはinnerから書き出すフィールド
+    pub fn new(
+        inner: S,
+        schema: &Schema,
+        sort_keys: Vec<SortKey>,
+        transaction: Arc<Mutex<Transaction>>,
+    ) -> anyhow::Result<Self> {
+        for sort_key in &sort_keys {
+            if !schema.has_field(&sort_key.field_name) {
+                bail!("sort field {} is not in the schema", sort_key.field_name);
+            }
+        }
+        Ok(Self {
+            inner,
+            transaction,
+            layout: Arc::new(Layout::from(schema.clone())),
+            sort_keys,
+            runs: None,
+            merger: None,
+        })
+    }
+
+    // innerをRUN_BLOCKSブロック分ずつメモリ上で並べ替え、ランとして書き出す
+    fn create_runs(&mut self) -> anyhow::Result<Vec<String>> {
+        let block_size = self.transaction.lock().unwrap().block_size();
+        let run_size = (block_size / self.layout.slot_size()).max(1) * RUN_BLOCKS;
+        let fields = self.layout.schema().fields().clone();
+
+        let mut runs = vec![];
+        self.inner.before_first()?;
+        let mut has_next = self.inner.next()?;
+        while has_next {
+            let mut rows = vec![];
+            while has_next && rows.len() < run_size {
+                let mut row = vec![];
+                for field in &fields {
+                    row.push(self.inner.get_val(field)?);
+                }
+                rows.push((sort_key(&self.sort_keys, &fields, &row), row));
+                has_next = self.inner.next()?;
+            }
+            rows.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            let (run, mut temp_table) = self.new_temp_table()?;
+            for (_, row) in rows {
+                temp_table.insert()?;
+                for (field, value) in fields.iter().zip(row) {
+                    temp_table.set_val(field, value)?;
+                }
+            }
+            temp_table.close()?;
+            runs.push(run);
+        }
+        // ランを作り終えたら、innerを読み直すことはない
+        self.inner.close()?;
+        Ok(runs)
+    }
+
+    // ランがMERGE_WIDTH個以下になるまで、MERGE_WIDTH個ずつ1つのランにまとめる
+    fn merge_runs(&mut self, mut runs: Vec<String>) -> anyhow::Result<Vec<String>> {
+        let fields = self.layout.schema().fields().clone();
+        while runs.len() > MERGE_WIDTH {
+            let mut merged_runs = vec![];
+            for chunk in runs.chunks(MERGE_WIDTH) {
+                let mut merger = Merger::new(
+                    &self.transaction,
+                    &self.layout,
+                    chunk,
+                    self.sort_keys.clone(),
+                )?;
+                let (merged_run, mut temp_table) = self.new_temp_table()?;
+                while merger.next()? {
+                    let scan = merger.current()?;
+                    temp_table.insert()?;
+                    for field in &fields {
+                        temp_table.set_val(field, scan.get_val(field)?)?;
+                    }
+                }
+                merger.close()?;
+                temp_table.close()?;
+                merged_runs.push(merged_run);
+                for run in chunk {
+                    self.delete_temp_table(run)?;
+                }
+            }
+            runs = merged_runs;
+        }
+        Ok(runs)
+    }
+
+    fn new_temp_table(&self) -> anyhow::Result<(String, TableScan)> {
+        let name = format!("_sort{}", NEXT_TEMP_ID.fetch_add(1, AtomicOrdering::SeqCst));
+        // 以前のプロセスが消し損ねたファイルが残っていれば、中身を引き継がないように消しておく
+        self.delete_temp_table(&name)?;
+        let temp_table = TableScan::new(
+            Arc::clone(&self.transaction),
+            &name,
+            Arc::clone(&self.layout),
+        )?;
+        Ok((name, temp_table))
+    }
+
+    fn delete_temp_table(&self, name: &str) -> anyhow::Result<()> {
+        self.transaction
+            .lock()
+            .unwrap()
+            .delete_file(&format!("{}.tbl", name))
+    }
+
+    fn merger(&mut self) -> anyhow::Result<&mut Merger> {
+        self.merger.as_mut().context("sort scan is not positioned")
+    }
+}
+
+impl<S: Scan> Scan for SortScan<S> {
+    fn before_first(&mut self) -> anyhow::Result<()> {
+        if let Some(mut merger) = self.merger.take() {
+            merger.close()?;
+        }
+        let runs = match self.runs.take() {
+            Some(runs) => runs,
+            None => {
+                let runs = self.create_runs()?;
+                self.merge_runs(runs)?
+            }
+        };
+        self.merger = Some(Merger::new(
+            &self.transaction,
+            &self.layout,
+            &runs,
+            self.sort_keys.clone(),
+        )?);
+        self.runs = Some(runs);
+        Ok(())
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        if self.merger.is_none() {
+            self.before_first()?;
+        }
+        self.merger()?.next()
+    }
+
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+        self.merger()?.current()?.get_int(field_name)
+    }
+
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+        self.merger()?.current()?.get_string(field_name)
+    }
+
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+        self.merger()?.current()?.get_bool(field_name)
+    }
+
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+        self.merger()?.current()?.get_float(field_name)
+    }
+
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+        self.merger()?.current()?.get_long(field_name)
+    }
+
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+        self.merger()?.current()?.get_val(field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.layout.schema().has_field(field_name)
+    }
+
+    // 一時テーブルのファイルも消す
+    fn close(&mut self) -> anyhow::Result<()> {
+        if let Some(mut merger) = self.merger.take() {
+            merger.close()?;
+        }
+        for run in self.runs.take().unwrap_or_default() {
+            self.delete_temp_table(&run)?;
+        }
+        self.inner.close()
+    }
+}
+
+// 並べ替えの向きを含めた比較のための値
+#[derive(Debug, Clone)]
+struct SortValue {
+    value: Constant,
+    direction: SortDirection,
+}
+
+impl Ord for SortValue {
+    // 型の違う値は比較できないが、同じフィールドどうしなので等しいとみなしてよい
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ordering = self
+            .value
+            .partial_cmp(&other.value)
+            .unwrap_or(Ordering::Equal);
+        match self.direction {
+            SortDirection::Asc => ordering,
+            SortDirection::Desc => ordering.reverse(),
+        }
+    }
+}
+
+impl PartialOrd for SortValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortValue {}
+
+fn sort_key(sort_keys: &[SortKey], fields: &[String], row: &[Constant]) -> Vec<SortValue> {
+    sort_keys
+        .iter()
+        .map(|sort_key| {
+            let i = fields
+                .iter()
+                .position(|field| *field == sort_key.field_name)
+                .unwrap();
+            SortValue {
+                value: row[i].clone(),
+                direction: sort_key.direction,
+            }
+        })
+        .collect()
+}
+
+// マージ中の各ランの先頭のレコード
+#[derive(PartialEq, Eq)]
+struct MergeEntry {
+    key: Vec<SortValue>,
+    run: usize,
+}
+
+impl Ord for MergeEntry {
+    // BinaryHeapは最大のものから取り出すので、逆順にして最小のものから取り出す。
+    // キーが等しければ先のランを優先し、並べ替えを安定にする
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then(self.run.cmp(&other.run))
+            .reverse()
+    }
+}
+
+impl PartialOrd for MergeEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// 並べ替え済みのランを同時に読み、小さいものから順に返す
+struct Merger {
+    scans: Vec<TableScan>,
+    sort_keys: Vec<SortKey>,
+    heap: BinaryHeap<MergeEntry>,
+    // 現在のレコードを指しているラン
+    current: Option<usize>,
+}
+
+impl Merger {
+    fn new(
+        transaction: &Arc<Mutex<Transaction>>,
+        layout: &Arc<Layout>,
+        runs: &[String],
+        sort_keys: Vec<SortKey>,
+    ) -> anyhow::Result<Self> {
+        let mut merger = Self {
+            scans: vec![],
+            sort_keys,
+            heap: BinaryHeap::new(),
+            current: None,
+        };
+        for (i, run) in runs.iter().enumerate() {
+            merger.scans.push(TableScan::new(
+                Arc::clone(transaction),
+                run,
+                Arc::clone(layout),
+            )?);
+            merger.advance(i)?;
+        }
+        Ok(merger)
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        if let Some(current) = self.current.take() {
+            self.advance(current)?;
+        }
+        match self.heap.pop() {
+            Some(entry) => {
+                self.current = Some(entry.run);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn current(&mut self) -> anyhow::Result<&mut TableScan> {
+        let current = self.current.context("sort scan has no current record")?;
+        Ok(&mut self.scans[current])
+    }
+
+    // runを次のレコードへ進め、残っていればヒープに入れる。読み終えたランのバッファは解放する
+    fn advance(&mut self, run: usize) -> anyhow::Result<()> {
+        let scan = &mut self.scans[run];
+        if !scan.next()? {
+            return scan.close();
+        }
+        let mut key = vec![];
+        for sort_key in &self.sort_keys {
+            key.push(SortValue {
+                value: scan.get_val(&sort_key.field_name)?,
+                direction: sort_key.direction,
+            });
+        }
+        self.heap.push(MergeEntry { key, run });
+        Ok(())
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        for scan in &mut self.scans {
+            scan.close()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::mydb::{MyDb, MyDbConfig};
+
+    #[test]
+    fn sort_scan() {
+        let tempdir = Builder::new().tempdir_in("./data").unwrap();
+        let dir = tempdir.path().join("db");
+        // ブロックを小さくして、ランが多段にマージされるようにする
+        let db = MyDb::open(MyDbConfig {
+            dir: dir.to_str().unwrap().to_string(),
+            block_size: 400,
+            ..Default::default()
+        })
+        .unwrap();
+        let transaction = Arc::new(Mutex::new(db.new_transaction()));
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema.add_int_field("key".to_string()).unwrap();
+        schema.add_string_field("name".to_string(), 8).unwrap();
+        let layout = Arc::new(Layout::from(schema.clone()));
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), "T", Arc::clone(&layout)).unwrap();
+        let mut seed: u32 = 2463534242;
+        for n in 0..500 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            table_scan.insert().unwrap();
+            table_scan.set_int("id", n).unwrap();
+            table_scan.set_int("key", (seed % 1000) as i32).unwrap();
+            table_scan
+                .set_string("name", format!("n{}", seed % 7))
+                .unwrap();
+        }
+        table_scan.close().unwrap();
+
+        let inner = TableScan::new(Arc::clone(&transaction), "T", Arc::clone(&layout)).unwrap();
+        let mut sort_scan = SortScan::new(
+            inner,
+            &schema,
+            vec![SortKey::asc("key")],
+            Arc::clone(&transaction),
+        )
+        .unwrap();
+        // 2回目のbefore_firstでも同じ順に読める
+        for _ in 0..2 {
+            sort_scan.before_first().unwrap();
+            let mut previous = i32::MIN;
+            let mut ids = vec![];
+            while sort_scan.next().unwrap() {
+                let key = sort_scan.get_int("key").unwrap();
+                assert!(key >= previous);
+                previous = key;
+                ids.push(sort_scan.get_int("id").unwrap());
+            }
+            ids.sort();
+            assert_eq!(ids, (0..500).collect::<Vec<_>>());
+        }
+        sort_scan.close().unwrap();
+
+        // 文字列の降順、同じ文字列の中ではキーの昇順
+        let inner = TableScan::new(Arc::clone(&transaction), "T", Arc::clone(&layout)).unwrap();
+        let mut sort_scan = SortScan::new(
+            inner,
+            &schema,
+            vec![SortKey::desc("name"), SortKey::asc("key")],
+            Arc::clone(&transaction),
+        )
+        .unwrap();
+        let mut previous: Option<(String, i32)> = None;
+        let mut count = 0;
+        while sort_scan.next().unwrap() {
+            let current = (
+                sort_scan.get_string("name").unwrap(),
+                sort_scan.get_int("key").unwrap(),
+            );
+            if let Some((name, key)) = &previous {
+                assert!(*name > current.0 || (*name == current.0 && *key <= current.1));
+            }
+            previous = Some(current);
+            count += 1;
+        }
+        assert_eq!(count, 500);
+        sort_scan.close().unwrap();
+
+        // 一時テーブルのファイルは残らない
+        let temp_files = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_str()
+                    .unwrap()
+                    .starts_with("_sort")
+            })
+            .count();
+        assert_eq!(temp_files, 0);
+
+        let inner = TableScan::new(Arc::clone(&transaction), "T", layout).unwrap();
+        assert!(SortScan::new(
+            inner,
+            &schema,
+            vec![SortKey::asc("missing")],
+            Arc::clone(&transaction)
+        )
+        .is_err());
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
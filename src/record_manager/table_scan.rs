@@ -0,0 +1,608 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::bail;
+
+use crate::file_manager::BlockId;
+use crate::transaction::transaction::Transaction;
+
+use super::constant::Constant;
+use super::layout::Layout;
+use super::record_page::RecordPage;
+use super::rid::Rid;
+use super::scan::{Scan, UpdateScan};
+
+pub struct TableScan {
+    transaction: Arc<Mutex<Transaction>>,
+    layout: Arc<Layout>,
+    filename: String,
+    record_page: Option<RecordPage>,
+    current_slot: Option<usize>,
+}
+
+impl TableScan {
+    pub fn new(
+        transaction: Arc<Mutex<Transaction>>,
+        table_name: &str,
+        layout: Arc<Layout>,
+    ) -> anyhow::Result<Self> {
+        Self::new_with_filename(transaction, format!("{}.tbl", table_name), layout)
+    }
+
+    // 拡張子が.tblでないファイルをテーブルとして読み書きする。索引のバケットなどに使う
+    pub fn new_with_filename(
+        transaction: Arc<Mutex<Transaction>>,
+        filename: String,
+        layout: Arc<Layout>,
+    ) -> anyhow::Result<Self> {
+        let mut table_scan = Self {
+            transaction,
+            layout,
+            filename,
+            record_page: None,
+            current_slot: None,
+        };
+        if table_scan.size()? == 0 {
+            table_scan.move_to_new_block()?;
+        } else {
+            table_scan.move_to_block(0)?;
+        }
+        Ok(table_scan)
+    }
+
+    // レコードを1件ずつ読まずに、ブロックごとの使用中スロット数を足し合わせる。
+    // 走査中の位置は変えない
+    pub fn count(&self) -> anyhow::Result<i64> {
+        let mut count = 0;
+        for block_number in 0..self.size()? {
+            let block_id = BlockId {
+                filename: self.filename.clone(),
+                block_number,
+            };
+            let record_page = RecordPage::new(
+                Arc::clone(&self.transaction),
+                block_id,
+                Arc::clone(&self.layout),
+            )?;
+            let used = record_page.used_slot_count();
+            record_page.close()?;
+            count += used? as i64;
+        }
+        Ok(count)
+    }
+
+    // フィールド名と値の組から1件挿入する。与えられなかったフィールドには、
+    // 既定値があればそれを、NULLを許すならNULLを入れる。
+    // 知らないフィールドや型の合わない値、必須フィールドの欠けは、挿入する前にエラーにする
+    pub fn insert_record(&mut self, values: &HashMap<String, Constant>) -> anyhow::Result<Rid> {
+        let schema = self.layout.schema();
+        for (name, value) in values {
+            match schema.info(name) {
+                Some(info) => info.check_value(value, self.layout.encoding())?,
+                None => bail!("field {} is not in table {}", name, self.filename),
+            }
+        }
+        if let Some(name) = schema.fields().iter().find(|name| {
+            !values.contains_key(*name)
+                && schema.default_of(name).is_none()
+                && !schema.is_optional(name)
+        }) {
+            bail!("required field {} is missing", name);
+        }
+
+        let rid = self.insert()?;
+        let slot = self.current_slot()?;
+        for name in self.layout.schema().fields() {
+            match values
+                .get(name)
+                .or_else(|| self.layout.schema().default_of(name))
+            {
+                Some(value) => self.record_page()?.set_val(slot, name, value.clone())?,
+                None => self.record_page()?.set_null(slot, name)?,
+            }
+        }
+        Ok(rid)
+    }
+
+    fn move_to_block(&mut self, block_number: i32) -> anyhow::Result<()> {
+        self.close()?;
+        let block_id = BlockId {
+            filename: self.filename.clone(),
+            block_number,
+        };
+        self.record_page = Some(RecordPage::new(
+            Arc::clone(&self.transaction),
+            block_id,
+            Arc::clone(&self.layout),
+        )?);
+        self.current_slot = None;
+        Ok(())
+    }
+
+    fn move_to_new_block(&mut self) -> anyhow::Result<()> {
+        self.close()?;
+        let block_id = self.transaction.lock().unwrap().append(&self.filename)?;
+        let record_page = RecordPage::new(
+            Arc::clone(&self.transaction),
+            block_id,
+            Arc::clone(&self.layout),
+        )?;
+        record_page.format()?;
+        self.record_page = Some(record_page);
+        self.current_slot = None;
+        Ok(())
+    }
+
+    fn at_last_block(&self) -> anyhow::Result<bool> {
+        Ok(self.record_page()?.block_id().block_number == self.size()? - 1)
+    }
+
+    fn size(&self) -> anyhow::Result<i32> {
+        self.transaction.lock().unwrap().size(self.filename.clone())
+    }
+
+    fn record_page(&self) -> anyhow::Result<&RecordPage> {
+        self.record_page
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("table scan on {} is closed", self.filename))
+    }
+
+    fn current_slot(&self) -> anyhow::Result<usize> {
+        self.current_slot
+            .ok_or_else(|| anyhow::anyhow!("table scan on {} has no current record", self.filename))
+    }
+}
+
+impl Scan for TableScan {
+    fn before_first(&mut self) -> anyhow::Result<()> {
+        self.move_to_block(0)
+    }
+
+    fn next(&mut self) -> anyhow::Result<bool> {
+        loop {
+            let record_page = self.record_page()?;
+            let next_slot = match self.current_slot {
+                Some(slot) => record_page.next_used_after(slot)?,
+                None => record_page.first_used()?,
+            };
+            if next_slot.is_some() {
+                self.current_slot = next_slot;
+                return Ok(true);
+            }
+            // 最後のレコードの位置に留まり、続けてnextを呼んでもfalseを返す
+            if self.at_last_block()? {
+                return Ok(false);
+            }
+            let next_block = self.record_page()?.block_id().block_number + 1;
+            self.move_to_block(next_block)?;
+        }
+    }
+
+    fn get_int(&mut self, field_name: &str) -> anyhow::Result<i32> {
+        self.record_page()?
+            .get_int(self.current_slot()?, field_name)
+    }
+
+    fn get_string(&mut self, field_name: &str) -> anyhow::Result<String> {
+        self.record_page()?
+            .get_string(self.current_slot()?, field_name)
+    }
+
+    fn get_bool(&mut self, field_name: &str) -> anyhow::Result<bool> {
+        self.record_page()?
+            .get_bool(self.current_slot()?, field_name)
+    }
+
+    fn get_float(&mut self, field_name: &str) -> anyhow::Result<f64> {
+        self.record_page()?
+            .get_float(self.current_slot()?, field_name)
+    }
+
+    fn get_long(&mut self, field_name: &str) -> anyhow::Result<i64> {
+        self.record_page()?
+            .get_long(self.current_slot()?, field_name)
+    }
+
+    fn get_val(&mut self, field_name: &str) -> anyhow::Result<Constant> {
+        self.record_page()?
+            .get_val(self.current_slot()?, field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.layout.schema().has_field(field_name)
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        if let Some(record_page) = self.record_page.take() {
+            record_page.close()?;
+        }
+        Ok(())
+    }
+}
+
+impl UpdateScan for TableScan {
+    fn set_int(&mut self, field_name: &str, val: i32) -> anyhow::Result<()> {
+        self.record_page()?
+            .set_int(self.current_slot()?, field_name, val)
+    }
+
+    fn set_string(&mut self, field_name: &str, val: String) -> anyhow::Result<()> {
+        self.record_page()?
+            .set_string(self.current_slot()?, field_name, val)
+    }
+
+    fn set_bool(&mut self, field_name: &str, val: bool) -> anyhow::Result<()> {
+        self.record_page()?
+            .set_bool(self.current_slot()?, field_name, val)
+    }
+
+    fn set_float(&mut self, field_name: &str, val: f64) -> anyhow::Result<()> {
+        self.record_page()?
+            .set_float(self.current_slot()?, field_name, val)
+    }
+
+    fn set_long(&mut self, field_name: &str, val: i64) -> anyhow::Result<()> {
+        self.record_page()?
+            .set_long(self.current_slot()?, field_name, val)
+    }
+
+    fn set_val(&mut self, field_name: &str, val: Constant) -> anyhow::Result<()> {
+        self.record_page()?
+            .set_val(self.current_slot()?, field_name, val)
+    }
+
+    // 現在位置より後ろの空きスロットを探し、どのブロックにもなければ新しいブロックを追加する
+    fn insert(&mut self) -> anyhow::Result<Rid> {
+        loop {
+            self.current_slot = self.record_page()?.insert_after(self.current_slot)?;
+            if self.current_slot.is_some() {
+                return self.get_rid();
+            }
+            if self.at_last_block()? {
+                self.move_to_new_block()?;
+            } else {
+                let next_block = self.record_page()?.block_id().block_number + 1;
+                self.move_to_block(next_block)?;
+            }
+        }
+    }
+
+    fn get_rid(&self) -> anyhow::Result<Rid> {
+        Ok(Rid::new(
+            self.record_page()?.block_id().block_number,
+            self.current_slot()?,
+        ))
+    }
+
+    fn move_to_rid(&mut self, rid: Rid) -> anyhow::Result<()> {
+        self.move_to_block(rid.block_number)?;
+        self.current_slot = Some(rid.slot_id);
+        Ok(())
+    }
+
+    fn delete(&mut self) -> anyhow::Result<()> {
+        self.record_page()?.delete(self.current_slot()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::buffer_manager::BufferManager;
+    use crate::file_manager::{FileManagerTrait, MemoryFileManager, PAGE_SIZE};
+    use crate::log_manager::LogManager;
+    use crate::record_manager::scan::assert_scan_exhausts;
+    use crate::record_manager::schema::{FieldInfo, Schema};
+    use crate::transaction::lock_table::LockTable;
+
+    #[test]
+    fn table_scan() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            8,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let table_name = "T".to_string();
+
+        let mut schema = Schema::new();
+        schema.add_int_field("A".to_string()).unwrap();
+        schema.add_string_field("B".to_string(), 9).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+
+        let transaction = Arc::new(Mutex::new(Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        )));
+
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), &table_name, Arc::clone(&layout)).unwrap();
+        for n in 0..500 {
+            table_scan.insert().unwrap();
+            table_scan.set_int("A", n).unwrap();
+            table_scan.set_string("B", format!("rec{}", n)).unwrap();
+        }
+        assert!(table_scan.size().unwrap() > 1);
+
+        table_scan.before_first().unwrap();
+        let mut found = HashSet::new();
+        while table_scan.next().unwrap() {
+            let a = table_scan.get_int("A").unwrap();
+            assert_eq!(table_scan.get_string("B").unwrap(), format!("rec{}", a));
+            assert!(found.insert(a));
+        }
+        assert_eq!(found, (0..500).collect());
+        assert_scan_exhausts(&mut table_scan, 500);
+        assert_eq!(table_scan.count().unwrap(), 500);
+
+        // 偶数のレコードを消すと、走査には奇数のレコードだけが残る
+        table_scan.before_first().unwrap();
+        while table_scan.next().unwrap() {
+            if table_scan.get_int("A").unwrap() % 2 == 0 {
+                table_scan.delete().unwrap();
+            }
+        }
+        table_scan.before_first().unwrap();
+        let mut remaining = 0;
+        while table_scan.next().unwrap() {
+            assert_eq!(table_scan.get_int("A").unwrap() % 2, 1);
+            remaining += 1;
+        }
+        assert_eq!(remaining, 250);
+        assert_eq!(table_scan.count().unwrap(), 250);
+
+        // トレイトオブジェクトとしても同じように扱える
+        let scan: &mut dyn UpdateScan = &mut table_scan;
+        assert!(scan.has_field("A"));
+        assert!(!scan.has_field("C"));
+        assert_scan_exhausts(scan, 250);
+
+        table_scan.close().unwrap();
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn move_to_rid() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            8,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let table_name = "T".to_string();
+
+        let mut schema = Schema::new();
+        schema.add_int_field("A".to_string()).unwrap();
+        schema.add_string_field("B".to_string(), 9).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+
+        let transaction = Arc::new(Mutex::new(Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        )));
+
+        let mut table_scan =
+            TableScan::new(Arc::clone(&transaction), &table_name, Arc::clone(&layout)).unwrap();
+        let mut rids = vec![];
+        for n in 0..300 {
+            let rid = table_scan.insert().unwrap();
+            assert_eq!(table_scan.get_rid().unwrap(), rid);
+            table_scan.set_int("A", n).unwrap();
+            table_scan.set_string("B", format!("rec{}", n)).unwrap();
+            rids.push(rid);
+        }
+        assert_ne!(rids[0].block_number, rids[299].block_number);
+
+        // 別のレコードまで進めてから、保存したRIDで戻る
+        table_scan.before_first().unwrap();
+        table_scan.next().unwrap();
+        for n in [0, 150, 299] {
+            table_scan.move_to_rid(rids[n]).unwrap();
+            assert_eq!(table_scan.get_int("A").unwrap(), n as i32);
+            assert_eq!(table_scan.get_string("B").unwrap(), format!("rec{}", n));
+        }
+
+        table_scan.close().unwrap();
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn count() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            8,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let transaction = Arc::new(Mutex::new(Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        )));
+
+        let mut schema = Schema::new();
+        schema.add_int_field("A".to_string()).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+        let mut table_scan = TableScan::new(Arc::clone(&transaction), "T", layout).unwrap();
+        assert_eq!(table_scan.count().unwrap(), 0);
+
+        for n in 0..1200 {
+            table_scan.insert().unwrap();
+            table_scan.set_int("A", n).unwrap();
+        }
+        assert!(table_scan.size().unwrap() > 1);
+        assert_eq!(table_scan.count().unwrap(), 1200);
+
+        // 3の倍数の400件を消す。途中で数えても走査の位置は変わらない
+        table_scan.before_first().unwrap();
+        let mut seen = 0;
+        while table_scan.next().unwrap() {
+            let a = table_scan.get_int("A").unwrap();
+            if a % 3 == 0 {
+                table_scan.delete().unwrap();
+            }
+            table_scan.count().unwrap();
+            seen += 1;
+        }
+        assert_eq!(seen, 1200);
+        assert_eq!(table_scan.count().unwrap(), 800);
+        assert_scan_exhausts(&mut table_scan, 800);
+
+        table_scan.close().unwrap();
+        transaction.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn long_field() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            8,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let new_transaction = || {
+            Arc::new(Mutex::new(Transaction::new(
+                Arc::clone(&file_manager),
+                Arc::clone(&log_manager),
+                Arc::clone(&buffer_manager),
+                Arc::clone(&lock_table),
+            )))
+        };
+
+        let mut schema = Schema::new();
+        schema.add_int_field("A".to_string()).unwrap();
+        schema.add_long_field("L".to_string()).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+        let values = [i64::MIN, 0, i64::MAX];
+
+        let tx1 = new_transaction();
+        let mut table_scan = TableScan::new(Arc::clone(&tx1), "T", Arc::clone(&layout)).unwrap();
+        for (n, value) in values.iter().enumerate() {
+            table_scan.insert().unwrap();
+            table_scan.set_int("A", n as i32).unwrap();
+            table_scan.set_long("L", *value).unwrap();
+        }
+        table_scan.close().unwrap();
+        tx1.lock().unwrap().commit().unwrap();
+
+        // ロールバックするとlongの値も元に戻る
+        let tx2 = new_transaction();
+        let mut table_scan = TableScan::new(Arc::clone(&tx2), "T", Arc::clone(&layout)).unwrap();
+        while table_scan.next().unwrap() {
+            table_scan.set_val("L", Constant::Long(42)).unwrap();
+            // 型の違う値は書かない
+            assert!(table_scan.set_val("L", Constant::Int(42)).is_err());
+        }
+        table_scan.close().unwrap();
+        tx2.lock().unwrap().rollback().unwrap();
+
+        let tx3 = new_transaction();
+        let mut table_scan = TableScan::new(Arc::clone(&tx3), "T", Arc::clone(&layout)).unwrap();
+        while table_scan.next().unwrap() {
+            let expected = values[table_scan.get_int("A").unwrap() as usize];
+            assert_eq!(table_scan.get_long("L").unwrap(), expected);
+            assert_eq!(table_scan.get_val("L").unwrap(), Constant::Long(expected));
+        }
+        assert_scan_exhausts(&mut table_scan, values.len());
+        table_scan.close().unwrap();
+        tx3.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn insert_record() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            8,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let transaction = Arc::new(Mutex::new(Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        )));
+
+        let mut schema = Schema::new();
+        schema.add_int_field("id".to_string()).unwrap();
+        schema
+            .add_optional_field("name".to_string(), FieldInfo::String(9))
+            .unwrap();
+        schema.add_bool_field("active".to_string()).unwrap();
+        schema.set_default("active", Constant::Bool(true)).unwrap();
+        let layout = Arc::new(Layout::from(schema));
+        let mut table_scan = TableScan::new(Arc::clone(&transaction), "T", layout).unwrap();
+
+        let values = HashMap::from([
+            ("id".to_string(), Constant::Int(1)),
+            ("name".to_string(), Constant::String("joe".to_string())),
+            ("active".to_string(), Constant::Bool(false)),
+        ]);
+        let first = table_scan.insert_record(&values).unwrap();
+        // 省いたフィールドは既定値かNULLになる
+        let values = HashMap::from([("id".to_string(), Constant::Int(2))]);
+        let second = table_scan.insert_record(&values).unwrap();
+
+        table_scan.move_to_rid(first).unwrap();
+        assert_eq!(table_scan.get_int("id").unwrap(), 1);
+        assert_eq!(table_scan.get_string("name").unwrap(), "joe");
+        assert!(!table_scan.get_bool("active").unwrap());
+        table_scan.move_to_rid(second).unwrap();
+        assert_eq!(table_scan.get_int("id").unwrap(), 2);
+        assert!(table_scan
+            .record_page()
+            .unwrap()
+            .is_null(second.slot_id, "name")
+            .unwrap());
+        assert!(table_scan.get_bool("active").unwrap());
+
+        // 必須フィールドが欠けていたり、知らないフィールドや型の違う値があれば何も挿入しない
+        let missing = HashMap::from([("name".to_string(), Constant::String("amy".to_string()))]);
+        assert!(table_scan.insert_record(&missing).is_err());
+        let unknown = HashMap::from([
+            ("id".to_string(), Constant::Int(3)),
+            ("age".to_string(), Constant::Int(20)),
+        ]);
+        assert!(table_scan.insert_record(&unknown).is_err());
+        let mismatched = HashMap::from([("id".to_string(), Constant::Bool(true))]);
+        assert!(table_scan.insert_record(&mismatched).is_err());
+        assert_eq!(table_scan.count().unwrap(), 2);
+
+        table_scan.close().unwrap();
+        transaction.lock().unwrap().commit().unwrap();
+    }
+}
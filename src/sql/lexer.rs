@@ -0,0 +1,232 @@
+use std::fmt;
+
+use crate::error::ParseError;
+
+const KEYWORDS: &[&str] = &[
+    "select", "from", "where", "and", "insert", "into", "values", "delete", "update", "set",
+    "create", "table", "view", "as", "index", "on", "int", "varchar", "bool", "float", "true",
+    "false",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    // キーワードと識別子は小文字にそろえる
+    Keyword(String),
+    Id(String),
+    IntLiteral(i32),
+    StrLiteral(String),
+    Comma,
+    Equals,
+    LParen,
+    RParen,
+    Star,
+    Dot,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Keyword(word) | Token::Id(word) => write!(f, "{}", word),
+            Token::IntLiteral(value) => write!(f, "{}", value),
+            Token::StrLiteral(value) => write!(f, "'{}'", value),
+            Token::Comma => write!(f, ","),
+            Token::Equals => write!(f, "="),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::Star => write!(f, "*"),
+            Token::Dot => write!(f, "."),
+        }
+    }
+}
+
+// トークンが始まる位置。どちらも1から数える
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+pub struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    position: Position,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(sql: &'a str) -> Self {
+        Self {
+            chars: sql.chars().peekable(),
+            position: Position { line: 1, column: 1 },
+        }
+    }
+
+    // 入力の最後までのトークンと、入力の終わりの位置
+    pub fn tokenize(mut self) -> Result<(Vec<(Token, Position)>, Position), ParseError> {
+        let mut tokens = vec![];
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
+        }
+        Ok((tokens, self.position))
+    }
+
+    fn next_token(&mut self) -> Result<Option<(Token, Position)>, ParseError> {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+        let start = self.position;
+        let Some(&c) = self.chars.peek() else {
+            return Ok(None);
+        };
+        let token = match c {
+            ',' => self.single(Token::Comma),
+            '=' => self.single(Token::Equals),
+            '(' => self.single(Token::LParen),
+            ')' => self.single(Token::RParen),
+            '*' => self.single(Token::Star),
+            '.' => self.single(Token::Dot),
+            '\'' => self.string_literal(start)?,
+            '-' | '0'..='9' => self.int_literal(start)?,
+            c if c.is_alphabetic() || c == '_' => self.word(),
+            c => return Err(error(start, format!("unexpected character '{}'", c))),
+        };
+        Ok(Some((token, start)))
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+
+    fn single(&mut self, token: Token) -> Token {
+        self.bump();
+        token
+    }
+
+    // 'で囲む。中の''は'1文字を表す
+    fn string_literal(&mut self, start: Position) -> Result<Token, ParseError> {
+        self.bump();
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('\'') if self.chars.peek() == Some(&'\'') => {
+                    self.bump();
+                    value.push('\'');
+                }
+                Some('\'') => return Ok(Token::StrLiteral(value)),
+                Some(c) => value.push(c),
+                None => return Err(error(start, "unterminated string literal".to_string())),
+            }
+        }
+    }
+
+    fn int_literal(&mut self, start: Position) -> Result<Token, ParseError> {
+        let mut text = String::new();
+        if self.chars.peek() == Some(&'-') {
+            text.push('-');
+            self.bump();
+        }
+        while let Some(&c) = self.chars.peek().filter(|c| c.is_ascii_digit()) {
+            text.push(c);
+            self.bump();
+        }
+        text.parse()
+            .map(Token::IntLiteral)
+            .map_err(|_| error(start, format!("invalid integer '{}'", text)))
+    }
+
+    fn word(&mut self) -> Token {
+        let mut word = String::new();
+        while let Some(&c) = self
+            .chars
+            .peek()
+            .filter(|c| c.is_alphanumeric() || **c == '_')
+        {
+            word.extend(c.to_lowercase());
+            self.bump();
+        }
+        if KEYWORDS.contains(&word.as_str()) {
+            Token::Keyword(word)
+        } else {
+            Token::Id(word)
+        }
+    }
+}
+
+fn error(position: Position, message: String) -> ParseError {
+    ParseError {
+        line: position.line,
+        column: position.column,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(sql: &str) -> Vec<Token> {
+        let (tokens, _) = Lexer::new(sql).tokenize().unwrap();
+        tokens.into_iter().map(|(token, _)| token).collect()
+    }
+
+    #[test]
+    fn lexer() {
+        assert_eq!(
+            tokens("SELECT s.Name, * FROM Student WHERE id = -12 AND name='it''s'"),
+            vec![
+                Token::Keyword("select".to_string()),
+                Token::Id("s".to_string()),
+                Token::Dot,
+                Token::Id("name".to_string()),
+                Token::Comma,
+                Token::Star,
+                Token::Keyword("from".to_string()),
+                Token::Id("student".to_string()),
+                Token::Keyword("where".to_string()),
+                Token::Id("id".to_string()),
+                Token::Equals,
+                Token::IntLiteral(-12),
+                Token::Keyword("and".to_string()),
+                Token::Id("name".to_string()),
+                Token::Equals,
+                Token::StrLiteral("it's".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokens("create table t (a varchar(10))"),
+            vec![
+                Token::Keyword("create".to_string()),
+                Token::Keyword("table".to_string()),
+                Token::Id("t".to_string()),
+                Token::LParen,
+                Token::Id("a".to_string()),
+                Token::Keyword("varchar".to_string()),
+                Token::LParen,
+                Token::IntLiteral(10),
+                Token::RParen,
+                Token::RParen,
+            ]
+        );
+
+        let (tokens, end) = Lexer::new("select a\n  from t").tokenize().unwrap();
+        assert_eq!(tokens[2].1, Position { line: 2, column: 3 });
+        assert_eq!(end, Position { line: 2, column: 9 });
+    }
+
+    #[test]
+    fn lexer_errors() {
+        let err = Lexer::new("select a\nfrom t where a = 'abc")
+            .tokenize()
+            .unwrap_err();
+        assert_eq!((err.line, err.column), (2, 18));
+        let err = Lexer::new("select a ; from t").tokenize().unwrap_err();
+        assert_eq!((err.line, err.column), (1, 10));
+        assert!(Lexer::new("select 99999999999").tokenize().is_err());
+        assert!(Lexer::new("select -").tokenize().is_err());
+    }
+}
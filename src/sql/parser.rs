@@ -0,0 +1,692 @@
+use std::fmt;
+
+use crate::error::ParseError;
+use crate::record_manager::constant::Constant;
+use crate::record_manager::predicate::{Expression, Operator, Predicate, Term};
+use crate::record_manager::schema::{FieldInfo, Schema};
+
+use super::lexer::{Lexer, Position, Token};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryData {
+    // Noneならselect *
+    pub fields: Option<Vec<String>>,
+    pub tables: Vec<String>,
+    pub predicate: Predicate,
+}
+
+// ビューの定義として保存できるSQL
+impl fmt::Display for QueryData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.fields {
+            Some(fields) => write!(f, "select {}", fields.join(", "))?,
+            None => write!(f, "select *")?,
+        }
+        write!(f, " from {}", self.tables.join(", "))?;
+        if !self.predicate.terms().is_empty() {
+            write!(f, " where {}", self.predicate)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertData {
+    pub table_name: String,
+    // Noneならテーブルのすべてのフィールドを定義した順に
+    pub fields: Option<Vec<String>>,
+    pub values: Vec<Constant>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteData {
+    pub table_name: String,
+    pub predicate: Predicate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateData {
+    pub table_name: String,
+    pub field_name: String,
+    pub new_value: Expression,
+    pub predicate: Predicate,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateTableData {
+    pub table_name: String,
+    pub schema: Schema,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateViewData {
+    pub view_name: String,
+    pub query: QueryData,
+}
+
+impl CreateViewData {
+    pub fn view_def(&self) -> String {
+        self.query.to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateIndexData {
+    pub index_name: String,
+    pub table_name: String,
+    pub field_name: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Statement {
+    Query(QueryData),
+    Insert(InsertData),
+    Delete(DeleteData),
+    Update(UpdateData),
+    CreateTable(CreateTableData),
+    CreateView(CreateViewData),
+    CreateIndex(CreateIndexData),
+}
+
+// 再帰下降でSQLを読む。各文のメソッドは入力の最後まで読み切る
+pub struct Parser {
+    tokens: Vec<(Token, Position)>,
+    current: usize,
+    end: Position,
+}
+
+impl Parser {
+    pub fn new(sql: &str) -> Result<Self, ParseError> {
+        let (tokens, end) = Lexer::new(sql).tokenize()?;
+        Ok(Self {
+            tokens,
+            current: 0,
+            end,
+        })
+    }
+
+    // 先頭のキーワードで文の種類を決める
+    pub fn statement(&mut self) -> Result<Statement, ParseError> {
+        if self.is_keyword("select") {
+            return self.query().map(Statement::Query);
+        }
+        if self.is_keyword("insert") {
+            return self.insert().map(Statement::Insert);
+        }
+        if self.is_keyword("delete") {
+            return self.delete().map(Statement::Delete);
+        }
+        if self.is_keyword("update") {
+            return self.update().map(Statement::Update);
+        }
+        if self.is_keyword("create") {
+            return match self.tokens.get(self.current + 1) {
+                Some((Token::Keyword(keyword), _)) if keyword == "table" => {
+                    self.create_table().map(Statement::CreateTable)
+                }
+                Some((Token::Keyword(keyword), _)) if keyword == "view" => {
+                    self.create_view().map(Statement::CreateView)
+                }
+                Some((Token::Keyword(keyword), _)) if keyword == "index" => {
+                    self.create_index().map(Statement::CreateIndex)
+                }
+                _ => {
+                    self.current += 1;
+                    Err(self.error("table, view or index"))
+                }
+            };
+        }
+        Err(self.error("a statement"))
+    }
+
+    // select <field>, ... | * from <table>, ... [where <predicate>]
+    pub fn query(&mut self) -> Result<QueryData, ParseError> {
+        let query = self.parse_query()?;
+        self.eat_end()?;
+        Ok(query)
+    }
+
+    // insert into <table> [(<field>, ...)] values (<constant>, ...)
+    pub fn insert(&mut self) -> Result<InsertData, ParseError> {
+        self.eat_keyword("insert")?;
+        self.eat_keyword("into")?;
+        let table_name = self.eat_id()?;
+        let fields = if self.is(&Token::LParen) {
+            self.current += 1;
+            let fields = self.list(Self::field)?;
+            self.eat(Token::RParen)?;
+            Some(fields)
+        } else {
+            None
+        };
+        self.eat_keyword("values")?;
+        self.eat(Token::LParen)?;
+        let values_position = self.position();
+        let values = self.list(Self::constant)?;
+        self.eat(Token::RParen)?;
+        // フィールドを省いたときの数はテーブルの定義を知るまで確かめられない
+        if let Some(fields) = fields
+            .as_ref()
+            .filter(|fields| fields.len() != values.len())
+        {
+            return Err(ParseError {
+                line: values_position.line,
+                column: values_position.column,
+                message: format!("expected {} values, found {}", fields.len(), values.len()),
+            });
+        }
+        self.eat_end()?;
+        Ok(InsertData {
+            table_name,
+            fields,
+            values,
+        })
+    }
+
+    // delete from <table> [where <predicate>]
+    pub fn delete(&mut self) -> Result<DeleteData, ParseError> {
+        self.eat_keyword("delete")?;
+        self.eat_keyword("from")?;
+        let table_name = self.eat_id()?;
+        let predicate = self.where_clause()?;
+        self.eat_end()?;
+        Ok(DeleteData {
+            table_name,
+            predicate,
+        })
+    }
+
+    // update <table> set <field> = <expression> [where <predicate>]
+    pub fn update(&mut self) -> Result<UpdateData, ParseError> {
+        self.eat_keyword("update")?;
+        let table_name = self.eat_id()?;
+        self.eat_keyword("set")?;
+        let field_name = self.field()?;
+        self.eat(Token::Equals)?;
+        let new_value = self.expression()?;
+        let predicate = self.where_clause()?;
+        self.eat_end()?;
+        Ok(UpdateData {
+            table_name,
+            field_name,
+            new_value,
+            predicate,
+        })
+    }
+
+    // create table <table> (<field> <type>, ...)
+    pub fn create_table(&mut self) -> Result<CreateTableData, ParseError> {
+        self.eat_keyword("create")?;
+        self.eat_keyword("table")?;
+        let table_name = self.eat_id()?;
+        self.eat(Token::LParen)?;
+        let mut schema = Schema::new();
+        loop {
+            let position = self.position();
+            let field_name = self.eat_id()?;
+            let info = self.field_type()?;
+            schema.add_field(field_name, info).map_err(|e| ParseError {
+                line: position.line,
+                column: position.column,
+                message: e.to_string(),
+            })?;
+            if !self.is(&Token::Comma) {
+                break;
+            }
+            self.current += 1;
+        }
+        self.eat(Token::RParen)?;
+        self.eat_end()?;
+        Ok(CreateTableData { table_name, schema })
+    }
+
+    // create view <view> as <query>
+    pub fn create_view(&mut self) -> Result<CreateViewData, ParseError> {
+        self.eat_keyword("create")?;
+        self.eat_keyword("view")?;
+        let view_name = self.eat_id()?;
+        self.eat_keyword("as")?;
+        let query = self.parse_query()?;
+        self.eat_end()?;
+        Ok(CreateViewData { view_name, query })
+    }
+
+    // create index <index> on <table> (<field>)
+    pub fn create_index(&mut self) -> Result<CreateIndexData, ParseError> {
+        self.eat_keyword("create")?;
+        self.eat_keyword("index")?;
+        let index_name = self.eat_id()?;
+        self.eat_keyword("on")?;
+        let table_name = self.eat_id()?;
+        self.eat(Token::LParen)?;
+        let field_name = self.field()?;
+        self.eat(Token::RParen)?;
+        self.eat_end()?;
+        Ok(CreateIndexData {
+            index_name,
+            table_name,
+            field_name,
+        })
+    }
+
+    fn parse_query(&mut self) -> Result<QueryData, ParseError> {
+        self.eat_keyword("select")?;
+        let fields = if self.is(&Token::Star) {
+            self.current += 1;
+            None
+        } else {
+            Some(self.list(Self::field)?)
+        };
+        self.eat_keyword("from")?;
+        let tables = self.list(Self::eat_id)?;
+        let predicate = self.where_clause()?;
+        Ok(QueryData {
+            fields,
+            tables,
+            predicate,
+        })
+    }
+
+    fn where_clause(&mut self) -> Result<Predicate, ParseError> {
+        if !self.is_keyword("where") {
+            return Ok(Predicate::new());
+        }
+        self.current += 1;
+        let mut predicate = Predicate::new().with_term(self.term()?);
+        while self.is_keyword("and") {
+            self.current += 1;
+            predicate = predicate.with_term(self.term()?);
+        }
+        Ok(predicate)
+    }
+
+    fn term(&mut self) -> Result<Term, ParseError> {
+        let lhs = self.expression()?;
+        self.eat(Token::Equals)?;
+        let rhs = self.expression()?;
+        Ok(Term::new(lhs, Operator::Eq, rhs))
+    }
+
+    fn expression(&mut self) -> Result<Expression, ParseError> {
+        if matches!(self.peek(), Some(Token::Id(_))) {
+            return self.field().map(Expression::FieldName);
+        }
+        self.constant().map(Expression::Constant)
+    }
+
+    // table.fieldと書いてもよい。フィールドは名前だけで引くので、テーブル名は読み捨てる
+    fn field(&mut self) -> Result<String, ParseError> {
+        let name = self.eat_id()?;
+        if !self.is(&Token::Dot) {
+            return Ok(name);
+        }
+        self.current += 1;
+        self.eat_id()
+    }
+
+    fn constant(&mut self) -> Result<Constant, ParseError> {
+        let constant = match self.peek() {
+            Some(Token::IntLiteral(value)) => Constant::Int(*value),
+            Some(Token::StrLiteral(value)) => Constant::String(value.clone()),
+            Some(Token::Keyword(keyword)) if keyword == "true" => Constant::Bool(true),
+            Some(Token::Keyword(keyword)) if keyword == "false" => Constant::Bool(false),
+            _ => return Err(self.error("a constant")),
+        };
+        self.current += 1;
+        Ok(constant)
+    }
+
+    // int | varchar(<length>) | bool | float
+    fn field_type(&mut self) -> Result<FieldInfo, ParseError> {
+        let info = match self.peek() {
+            Some(Token::Keyword(keyword)) if keyword == "int" => FieldInfo::Int,
+            Some(Token::Keyword(keyword)) if keyword == "bool" => FieldInfo::Bool,
+            Some(Token::Keyword(keyword)) if keyword == "float" => FieldInfo::Float,
+            Some(Token::Keyword(keyword)) if keyword == "varchar" => {
+                self.current += 1;
+                self.eat(Token::LParen)?;
+                let length = match self.peek() {
+                    Some(Token::IntLiteral(length)) if *length > 0 => *length as usize,
+                    _ => return Err(self.error("a positive length")),
+                };
+                self.current += 1;
+                self.eat(Token::RParen)?;
+                return Ok(FieldInfo::String(length));
+            }
+            _ => return Err(self.error("a field type")),
+        };
+        self.current += 1;
+        Ok(info)
+    }
+
+    // カンマ区切りで1つ以上
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![item(self)?];
+        while self.is(&Token::Comma) {
+            self.current += 1;
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.current).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> Position {
+        self.tokens
+            .get(self.current)
+            .map_or(self.end, |(_, position)| *position)
+    }
+
+    fn is(&self, token: &Token) -> bool {
+        self.peek() == Some(token)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Keyword(k)) if k == keyword)
+    }
+
+    fn eat(&mut self, token: Token) -> Result<(), ParseError> {
+        if !self.is(&token) {
+            return Err(self.error(&format!("'{}'", token)));
+        }
+        self.current += 1;
+        Ok(())
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if !self.is_keyword(keyword) {
+            return Err(self.error(keyword));
+        }
+        self.current += 1;
+        Ok(())
+    }
+
+    fn eat_id(&mut self) -> Result<String, ParseError> {
+        let Some(Token::Id(id)) = self.peek() else {
+            return Err(self.error("an identifier"));
+        };
+        let id = id.clone();
+        self.current += 1;
+        Ok(id)
+    }
+
+    fn eat_end(&mut self) -> Result<(), ParseError> {
+        if self.peek().is_some() {
+            return Err(self.error("end of input"));
+        }
+        Ok(())
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        let position = self.position();
+        let found = self
+            .peek()
+            .map_or("end of input".to_string(), |token| format!("'{}'", token));
+        ParseError {
+            line: position.line,
+            column: position.column,
+            message: format!("expected {}, found {}", expected, found),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str) -> Expression {
+        Expression::field(name)
+    }
+
+    fn eq(lhs: Expression, rhs: Expression) -> Term {
+        Term::new(lhs, Operator::Eq, rhs)
+    }
+
+    fn query(sql: &str) -> QueryData {
+        Parser::new(sql).unwrap().query().unwrap()
+    }
+
+    #[test]
+    fn queries() {
+        assert_eq!(
+            query("select a from t"),
+            QueryData {
+                fields: Some(vec!["a".to_string()]),
+                tables: vec!["t".to_string()],
+                predicate: Predicate::new(),
+            }
+        );
+        assert_eq!(query("SELECT * FROM T").fields, None);
+        assert_eq!(
+            query("select sname, gradyear from student, dept"),
+            QueryData {
+                fields: Some(vec!["sname".to_string(), "gradyear".to_string()]),
+                tables: vec!["student".to_string(), "dept".to_string()],
+                predicate: Predicate::new(),
+            }
+        );
+        assert_eq!(
+            query("select sname from student where majorid = did and did = 10").predicate,
+            Predicate::new()
+                .with_term(eq(field("majorid"), field("did")))
+                .with_term(eq(field("did"), Expression::constant(10)))
+        );
+        assert_eq!(
+            query("select a from t where b = 'it''s' and 'x' = c and d = true").predicate,
+            Predicate::new()
+                .with_term(eq(field("b"), Expression::constant("it's")))
+                .with_term(eq(Expression::constant("x"), field("c")))
+                .with_term(eq(field("d"), Expression::constant(true)))
+        );
+        assert_eq!(
+            query("select t.a from t where t.b = -3").predicate,
+            Predicate::new().with_term(eq(field("b"), Expression::constant(-3)))
+        );
+        assert_eq!(
+            query("select a\nfrom t\nwhere a = 1").fields,
+            Some(vec!["a".to_string()])
+        );
+    }
+
+    #[test]
+    fn updates() {
+        assert_eq!(
+            Parser::new("insert into student (sid, sname, active) values (1, 'joe', false)")
+                .unwrap()
+                .insert()
+                .unwrap(),
+            InsertData {
+                table_name: "student".to_string(),
+                fields: Some(vec![
+                    "sid".to_string(),
+                    "sname".to_string(),
+                    "active".to_string()
+                ]),
+                values: vec![
+                    Constant::Int(1),
+                    Constant::from("joe"),
+                    Constant::Bool(false)
+                ],
+            }
+        );
+        assert_eq!(
+            Parser::new("insert into student values (2, 'amy')")
+                .unwrap()
+                .insert()
+                .unwrap(),
+            InsertData {
+                table_name: "student".to_string(),
+                fields: None,
+                values: vec![Constant::Int(2), Constant::from("amy")],
+            }
+        );
+        assert_eq!(
+            Parser::new("delete from student")
+                .unwrap()
+                .delete()
+                .unwrap(),
+            DeleteData {
+                table_name: "student".to_string(),
+                predicate: Predicate::new(),
+            }
+        );
+        assert_eq!(
+            Parser::new("delete from student where gradyear = 2020")
+                .unwrap()
+                .delete()
+                .unwrap()
+                .predicate,
+            Predicate::new().with_term(eq(field("gradyear"), Expression::constant(2020)))
+        );
+        assert_eq!(
+            Parser::new("update student set majorid = 20 where sid = 1")
+                .unwrap()
+                .update()
+                .unwrap(),
+            UpdateData {
+                table_name: "student".to_string(),
+                field_name: "majorid".to_string(),
+                new_value: Expression::constant(20),
+                predicate: Predicate::new().with_term(eq(field("sid"), Expression::constant(1))),
+            }
+        );
+        assert_eq!(
+            Parser::new("update t set a = b")
+                .unwrap()
+                .update()
+                .unwrap()
+                .new_value,
+            field("b")
+        );
+    }
+
+    #[test]
+    fn creates() {
+        let data = Parser::new(
+            "create table student (sid int, sname varchar(10), active bool, gpa float)",
+        )
+        .unwrap()
+        .create_table()
+        .unwrap();
+        assert_eq!(data.table_name, "student");
+        assert_eq!(data.schema.fields(), &vec!["sid", "sname", "active", "gpa"]);
+        assert_eq!(data.schema.info("sname"), Some(&FieldInfo::String(10)));
+        assert_eq!(data.schema.info("gpa"), Some(&FieldInfo::Float));
+
+        let data =
+            Parser::new("create view seniors as select sname from student where gradyear = 2020")
+                .unwrap()
+                .create_view()
+                .unwrap();
+        assert_eq!(data.view_name, "seniors");
+        assert_eq!(
+            data.view_def(),
+            "select sname from student where gradyear = 2020"
+        );
+        // 保存した定義を読み直すと同じクエリになる
+        assert_eq!(query(&data.view_def()), data.query);
+        let data = Parser::new("create view v as select * from t where a = 'x''y'")
+            .unwrap()
+            .create_view()
+            .unwrap();
+        assert_eq!(query(&data.view_def()), data.query);
+
+        assert_eq!(
+            Parser::new("create index sid_idx on student (sid)")
+                .unwrap()
+                .create_index()
+                .unwrap(),
+            CreateIndexData {
+                index_name: "sid_idx".to_string(),
+                table_name: "student".to_string(),
+                field_name: "sid".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn statements() {
+        let statement = |sql: &str| Parser::new(sql).unwrap().statement().unwrap();
+        assert!(matches!(statement("select a from t"), Statement::Query(_)));
+        assert!(matches!(
+            statement("insert into t (a) values (1)"),
+            Statement::Insert(_)
+        ));
+        assert!(matches!(statement("delete from t"), Statement::Delete(_)));
+        assert!(matches!(
+            statement("update t set a = 1"),
+            Statement::Update(_)
+        ));
+        assert!(matches!(
+            statement("create table t (a int)"),
+            Statement::CreateTable(_)
+        ));
+        assert!(matches!(
+            statement("create view v as select a from t"),
+            Statement::CreateView(_)
+        ));
+        assert!(matches!(
+            statement("create index i on t (a)"),
+            Statement::CreateIndex(_)
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |sql: &str| {
+            let err = Parser::new(sql)
+                .and_then(|mut parser| parser.statement())
+                .unwrap_err();
+            (err.line, err.column, err.message)
+        };
+        assert_eq!(
+            error("select from t"),
+            (1, 8, "expected an identifier, found 'from'".to_string())
+        );
+        assert_eq!(
+            error("select a\nfrom t\nwhere a ="),
+            (3, 10, "expected a constant, found end of input".to_string())
+        );
+        assert_eq!(
+            error("select a from t b"),
+            (1, 17, "expected end of input, found 'b'".to_string())
+        );
+        assert_eq!(
+            error("insert into t (a, b) values (1)"),
+            (1, 30, "expected 2 values, found 1".to_string())
+        );
+        assert_eq!(
+            error("create table t (a int, a int)"),
+            (1, 24, "field a is already defined".to_string())
+        );
+        assert_eq!(
+            error("create table t (a varchar(0))"),
+            (1, 27, "expected a positive length, found '0'".to_string())
+        );
+        assert_eq!(
+            error("create trigger x"),
+            (
+                1,
+                8,
+                "expected table, view or index, found 'trigger'".to_string()
+            )
+        );
+        assert_eq!(
+            error("drop table t"),
+            (1, 1, "expected a statement, found 'drop'".to_string())
+        );
+        assert_eq!(
+            error("update t set a = 1 where"),
+            (1, 25, "expected a constant, found end of input".to_string())
+        );
+        assert_eq!(
+            error("create index i on t a"),
+            (1, 21, "expected '(', found 'a'".to_string())
+        );
+    }
+}
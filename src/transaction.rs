@@ -1,5 +1,6 @@
 mod buffer_list;
-mod lock_table;
+pub mod lock_table;
 mod log_record;
 mod recovery_manager;
 pub mod transaction;
+mod wait_for_graph;
@@ -24,9 +24,12 @@ impl BufferList {
         return self.buffers.get(block_id);
     }
 
+    pub fn pinned_buffers(&self) -> Vec<Arc<RwLock<Buffer>>> {
+        self.buffers.values().map(Arc::clone).collect()
+    }
+
     pub fn pin(&mut self, block_id: &BlockId) -> anyhow::Result<()> {
-        let mut locked_buffer_manager = self.buffer_manager.lock().unwrap();
-        let buffer = locked_buffer_manager.pin(block_id)?;
+        let buffer = BufferManager::pin(&self.buffer_manager, block_id)?;
         self.buffers.insert(block_id.clone(), Arc::clone(&buffer));
         let val = match self.pins.get(block_id) {
             Some(v) => *v,
@@ -47,7 +50,7 @@ impl BufferList {
             None => return Err(anyhow::anyhow!("Unpin Error").into()),
         };
 
-        if val != 1 {
+        if val <= 1 {
             self.buffers.remove(block_id);
             self.pins.remove(block_id);
         } else {
@@ -72,3 +75,40 @@ impl BufferList {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::{FileManagerTrait, MemoryFileManager, PAGE_SIZE};
+    use crate::log_manager::LogManager;
+    #[test]
+    fn unpin() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            3,
+        )));
+        let mut buffer_list = BufferList::new(Arc::clone(&buffer_manager));
+
+        let block_id = BlockId {
+            filename: "T.tbl".to_string(),
+            block_number: 0,
+        };
+
+        buffer_list.pin(&block_id).unwrap();
+        buffer_list.pin(&block_id).unwrap();
+
+        buffer_list.unpin(&block_id).unwrap();
+        assert!(buffer_list.get_buffer(&block_id).is_some());
+        assert_eq!(buffer_manager.lock().unwrap().available(), 2);
+
+        buffer_list.unpin(&block_id).unwrap();
+        assert!(buffer_list.get_buffer(&block_id).is_none());
+        assert_eq!(buffer_manager.lock().unwrap().available(), 3);
+    }
+}
@@ -1,19 +1,47 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
 use std::time::{Duration, Instant};
 
 use crate::{error::LockAbortError, file_manager::BlockId};
 
+use super::wait_for_graph::WaitForGraph;
+
 const MAX_TIME: Duration = Duration::from_secs(10);
 
-pub struct LockTable {
+#[derive(Default)]
+struct Locks {
+    // -1: X-lock, n: n個のS-lock
     table: HashMap<BlockId, i32>,
+    holders: HashMap<BlockId, HashSet<i32>>,
+    wait_for: WaitForGraph,
+}
+
+impl Locks {
+    fn lock_val(&self, block_id: &BlockId) -> i32 {
+        match self.table.get(block_id) {
+            Some(v) => *v,
+            None => 0,
+        }
+    }
+
+    fn other_holders(&self, block_id: &BlockId, txnum: i32) -> HashSet<i32> {
+        match self.holders.get(block_id) {
+            Some(holders) => holders.iter().copied().filter(|h| *h != txnum).collect(),
+            None => HashSet::new(),
+        }
+    }
+}
+
+pub struct LockTable {
+    locks: Mutex<Locks>,
+    condvar: Condvar,
 }
 
 impl Default for LockTable {
     fn default() -> Self {
         Self {
-            table: HashMap::new(),
+            locks: Mutex::new(Locks::default()),
+            condvar: Condvar::new(),
         }
     }
 }
@@ -23,39 +51,104 @@ impl LockTable {
         Self::default()
     }
 
-    fn slock(&mut self, block_id: &BlockId) -> Result<(), LockAbortError> {
-        let start = Instant::now();
-        while self.has_xlock(block_id) {
-            if start.elapsed() > MAX_TIME {
-                return Err(anyhow::anyhow!(format!("{:?}はlockされています", block_id)).into());
-            }
-        }
+    fn slock(&self, block_id: &BlockId, txnum: i32) -> Result<(), LockAbortError> {
+        let mut locks = self.wait_while(block_id, txnum, |locks| locks.lock_val(block_id) < 0)?;
+
+        let val = locks.lock_val(block_id);
+        locks.table.insert(block_id.clone(), val + 1);
+        locks
+            .holders
+            .entry(block_id.clone())
+            .or_default()
+            .insert(txnum);
+        Ok(())
+    }
 
-        let val = self.get_lock_val(block_id);
+    fn xlock(&self, block_id: &BlockId, txnum: i32) -> Result<(), LockAbortError> {
+        let mut locks = self.wait_while(block_id, txnum, |locks| locks.lock_val(block_id) > 1)?;
 
-        self.table.insert(block_id.clone(), val + 1);
+        locks.table.insert(block_id.clone(), -1);
+        locks
+            .holders
+            .entry(block_id.clone())
+            .or_default()
+            .insert(txnum);
         Ok(())
     }
 
-    fn xlock(&mut self, block_id: &BlockId) -> Result<(), LockAbortError> {
+    // 待つ間はwait-forグラフに辺を張り、閉路ができたら待たずにabortする
+    fn wait_while<F>(
+        &self,
+        block_id: &BlockId,
+        txnum: i32,
+        conflicts: F,
+    ) -> Result<MutexGuard<'_, Locks>, LockAbortError>
+    where
+        F: Fn(&Locks) -> bool,
+    {
         let start = Instant::now();
-        while self.has_other_slocks(block_id) {
-            if start.elapsed() > MAX_TIME {
+        let mut locks = self.locks.lock().unwrap();
+        while conflicts(&locks) {
+            let holders = locks.other_holders(block_id, txnum);
+            locks.wait_for.set_waits(txnum, holders);
+            if locks.wait_for.has_cycle_from(txnum) {
+                locks.wait_for.remove(txnum);
+                return Err(anyhow::anyhow!(format!(
+                    "{:?}を待つとデッドロックになります (transaction {})",
+                    block_id, txnum
+                ))
+                .into());
+            }
+            let elapsed = start.elapsed();
+            if elapsed > MAX_TIME {
+                locks.wait_for.remove(txnum);
                 return Err(anyhow::anyhow!(format!("{:?}はlockされています", block_id)).into());
             }
+            locks = self
+                .condvar
+                .wait_timeout(locks, MAX_TIME - elapsed)
+                .unwrap()
+                .0;
         }
-
-        self.table.insert(block_id.clone(), -1);
-        Ok(())
+        locks.wait_for.remove(txnum);
+        Ok(locks)
     }
 
-    fn unlock(&mut self, block_id: &BlockId) {
-        let ival = self.get_lock_val(block_id);
+    fn unlock(&self, block_id: &BlockId, txnum: i32) {
+        let mut locks = self.locks.lock().unwrap();
+        let ival = locks.lock_val(block_id);
         if ival > 1 {
-            self.table.insert(block_id.clone(), ival - 1);
+            locks.table.insert(block_id.clone(), ival - 1);
         } else {
-            self.table.remove(block_id);
+            locks.table.remove(block_id);
+        }
+        if let Some(holders) = locks.holders.get_mut(block_id) {
+            holders.remove(&txnum);
+            if holders.is_empty() {
+                locks.holders.remove(block_id);
+            }
         }
+        drop(locks);
+        self.condvar.notify_all();
+    }
+
+    pub fn lock_count_for_block(&self, block_id: &BlockId) -> i32 {
+        self.get_lock_val(block_id)
+    }
+
+    pub fn most_contended_blocks(&self, n: usize) -> Vec<(BlockId, i32)> {
+        let mut blocks: Vec<(BlockId, i32)> = self
+            .locks
+            .lock()
+            .unwrap()
+            .table
+            .iter()
+            .filter(|(block_id, _)| !block_id.is_dummy())
+            .map(|(block_id, val)| (block_id.clone(), *val))
+            .collect();
+        blocks.sort_by_key(|b| std::cmp::Reverse(b.1));
+        blocks.truncate(n);
+        blocks
     }
 
     fn has_xlock(&self, block_id: &BlockId) -> bool {
@@ -67,53 +160,321 @@ impl LockTable {
     }
 
     fn get_lock_val(&self, block_id: &BlockId) -> i32 {
-        match self.table.get(block_id) {
-            Some(v) => *v,
-            None => 0,
-        }
+        self.locks.lock().unwrap().lock_val(block_id)
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
 pub struct ConcurrentManager {
-    lock_table: Arc<Mutex<LockTable>>,
-    table: HashMap<BlockId, String>,
+    lock_table: Arc<LockTable>,
+    txnum: i32,
+    table: HashMap<BlockId, LockMode>,
 }
 
 impl ConcurrentManager {
-    pub fn new(lock_table: Arc<Mutex<LockTable>>) -> Self {
+    pub fn new(lock_table: Arc<LockTable>, txnum: i32) -> Self {
         let table = HashMap::new();
-        Self { lock_table, table }
+        Self {
+            lock_table,
+            txnum,
+            table,
+        }
     }
 
     pub fn slock(&mut self, block_id: &BlockId) -> anyhow::Result<()> {
-        if self.table.get(block_id) != None {
-            self.lock_table.lock().unwrap().slock(block_id)?;
-            self.table.insert(block_id.clone(), "S".to_string());
+        if !self.has_any_lock(block_id) {
+            self.lock_table.slock(block_id, self.txnum)?;
+            self.table.insert(block_id.clone(), LockMode::Shared);
         }
         Ok(())
     }
 
     pub fn xlock(&mut self, block_id: &BlockId) -> anyhow::Result<()> {
-        if self.has_lock(block_id) {
-            self.slock(block_id)?;
-            self.lock_table.lock().unwrap().xlock(block_id)?;
+        if !self.has_xlock(block_id) {
+            if !self.has_any_lock(block_id) {
+                self.slock(block_id)?;
+            }
+            self.lock_table.xlock(block_id, self.txnum)?;
 
-            self.table.insert(block_id.clone(), "X".to_string());
+            self.table.insert(block_id.clone(), LockMode::Exclusive);
         }
         Ok(())
     }
 
     pub fn release(&mut self) {
         for block_id in self.table.keys() {
-            self.lock_table.lock().unwrap().unlock(block_id);
+            self.lock_table.unlock(block_id, self.txnum);
         }
         self.table.clear();
     }
 
-    fn has_lock(&self, block_id: &BlockId) -> bool {
-        match self.table.get(block_id) {
-            Some(v) => v == "X",
-            None => false,
+    pub fn locked_blocks(&self) -> HashSet<BlockId> {
+        self.table.keys().cloned().collect()
+    }
+
+    // keepに含まれないブロックのS-lockだけを解放する。X-lockはcommitかrollbackまで持ち続ける
+    pub fn release_shared_except(&mut self, keep: &HashSet<BlockId>) {
+        let lock_table = &self.lock_table;
+        let txnum = self.txnum;
+        self.table.retain(|block_id, lock_mode| {
+            if keep.contains(block_id) || *lock_mode == LockMode::Exclusive {
+                return true;
+            }
+            lock_table.unlock(block_id, txnum);
+            false
+        });
+    }
+
+    pub fn has_any_lock(&self, block_id: &BlockId) -> bool {
+        self.table.contains_key(block_id)
+    }
+
+    // S-lockだけを持っている場合はfalse
+    fn has_xlock(&self, block_id: &BlockId) -> bool {
+        self.table.get(block_id) == Some(&LockMode::Exclusive)
+    }
+
+    pub fn lock_mode(&self, block_id: &BlockId) -> Option<LockMode> {
+        self.table.get(block_id).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn lock_count_for_block() {
+        let lock_table = LockTable::new();
+        let block_ids: Vec<BlockId> = (0..3)
+            .map(|i| BlockId {
+                filename: "testfile".to_string(),
+                block_number: i,
+            })
+            .collect();
+
+        assert_eq!(lock_table.lock_count_for_block(&block_ids[0]), 0);
+
+        for txnum in 0..3 {
+            lock_table.slock(&block_ids[0], txnum).unwrap();
+        }
+        lock_table.slock(&block_ids[1], 0).unwrap();
+        lock_table.xlock(&block_ids[2], 0).unwrap();
+        for txnum in 0..5 {
+            lock_table
+                .slock(&BlockId::dummy_for_file("testfile"), txnum)
+                .unwrap();
+        }
+
+        assert_eq!(lock_table.lock_count_for_block(&block_ids[0]), 3);
+        assert_eq!(lock_table.lock_count_for_block(&block_ids[1]), 1);
+        assert_eq!(lock_table.lock_count_for_block(&block_ids[2]), -1);
+
+        assert_eq!(
+            lock_table.most_contended_blocks(2),
+            vec![(block_ids[0].clone(), 3), (block_ids[1].clone(), 1)]
+        );
+
+        lock_table.unlock(&block_ids[0], 0);
+        assert_eq!(lock_table.lock_count_for_block(&block_ids[0]), 2);
+    }
+
+    #[test]
+    fn slock() {
+        let lock_table = Arc::new(LockTable::new());
+        let mut reader = ConcurrentManager::new(Arc::clone(&lock_table), 1);
+        let mut writer = ConcurrentManager::new(Arc::clone(&lock_table), 2);
+        let block_id = BlockId {
+            filename: "testfile".to_string(),
+            block_number: 1,
+        };
+
+        reader.slock(&block_id).unwrap();
+        reader.slock(&block_id).unwrap();
+        assert_eq!(lock_table.lock_count_for_block(&block_id), 1);
+
+        // readerのS-lockがある間、writerはX-lockを取れない
+        writer.slock(&block_id).unwrap();
+        assert!(lock_table.has_other_slocks(&block_id));
+
+        reader.release();
+        writer.xlock(&block_id).unwrap();
+        assert_eq!(lock_table.lock_count_for_block(&block_id), -1);
+    }
+
+    #[test]
+    fn has_any_lock() {
+        let lock_table = Arc::new(LockTable::new());
+        let mut concurrent_manager = ConcurrentManager::new(Arc::clone(&lock_table), 1);
+        let block_id = BlockId {
+            filename: "testfile".to_string(),
+            block_number: 1,
+        };
+
+        assert!(!concurrent_manager.has_any_lock(&block_id));
+
+        concurrent_manager.slock(&block_id).unwrap();
+        assert!(concurrent_manager.has_any_lock(&block_id));
+        assert!(!concurrent_manager.has_xlock(&block_id));
+
+        concurrent_manager.xlock(&block_id).unwrap();
+        assert!(concurrent_manager.has_any_lock(&block_id));
+        assert!(concurrent_manager.has_xlock(&block_id));
+        assert!(lock_table.has_xlock(&block_id));
+
+        concurrent_manager.release();
+        assert!(!concurrent_manager.has_any_lock(&block_id));
+        assert!(!lock_table.has_xlock(&block_id));
+    }
+
+    #[test]
+    fn lock_mode() {
+        let lock_table = Arc::new(LockTable::new());
+        let mut concurrent_manager = ConcurrentManager::new(Arc::clone(&lock_table), 1);
+        let block_ids: Vec<BlockId> = (0..2)
+            .map(|i| BlockId {
+                filename: "testfile".to_string(),
+                block_number: i,
+            })
+            .collect();
+
+        assert_eq!(concurrent_manager.lock_mode(&block_ids[0]), None);
+
+        concurrent_manager.slock(&block_ids[0]).unwrap();
+        concurrent_manager.slock(&block_ids[0]).unwrap();
+        assert_eq!(
+            concurrent_manager.lock_mode(&block_ids[0]),
+            Some(LockMode::Shared)
+        );
+        assert_eq!(lock_table.lock_count_for_block(&block_ids[0]), 1);
+
+        // S-lockからX-lockへの昇格
+        concurrent_manager.xlock(&block_ids[0]).unwrap();
+        assert_eq!(
+            concurrent_manager.lock_mode(&block_ids[0]),
+            Some(LockMode::Exclusive)
+        );
+        assert_eq!(lock_table.lock_count_for_block(&block_ids[0]), -1);
+
+        // X-lockを持っていればslockは何もしない
+        concurrent_manager.slock(&block_ids[0]).unwrap();
+        assert_eq!(
+            concurrent_manager.lock_mode(&block_ids[0]),
+            Some(LockMode::Exclusive)
+        );
+
+        concurrent_manager.xlock(&block_ids[1]).unwrap();
+        assert_eq!(
+            concurrent_manager.lock_mode(&block_ids[1]),
+            Some(LockMode::Exclusive)
+        );
+
+        concurrent_manager.release();
+        for block_id in &block_ids {
+            assert_eq!(concurrent_manager.lock_mode(block_id), None);
+            assert_eq!(lock_table.lock_count_for_block(block_id), 0);
+        }
+    }
+
+    #[test]
+    fn contention() {
+        let lock_table = Arc::new(LockTable::new());
+        let block_id = BlockId {
+            filename: "testfile".to_string(),
+            block_number: 1,
+        };
+        let readers = Arc::new(AtomicUsize::new(0));
+        let writing = Arc::new(AtomicBool::new(false));
+        let commits = Arc::new(AtomicUsize::new(0));
+
+        // 1スレッドの書き込みと7スレッドの読み込みが同じブロックを奪い合う。
+        // 待ちはCondvarで眠るので、所要時間はロックを保持している時間の合計程度に収まる
+        let start = Instant::now();
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let lock_table = Arc::clone(&lock_table);
+                let block_id = block_id.clone();
+                let readers = Arc::clone(&readers);
+                let writing = Arc::clone(&writing);
+                let commits = Arc::clone(&commits);
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        let mut concurrent_manager =
+                            ConcurrentManager::new(Arc::clone(&lock_table), i);
+                        if i == 0 {
+                            concurrent_manager.xlock(&block_id).unwrap();
+                            writing.store(true, Ordering::SeqCst);
+                            assert_eq!(readers.load(Ordering::SeqCst), 0);
+                            thread::sleep(Duration::from_millis(1));
+                            writing.store(false, Ordering::SeqCst);
+                        } else {
+                            concurrent_manager.slock(&block_id).unwrap();
+                            readers.fetch_add(1, Ordering::SeqCst);
+                            assert!(!writing.load(Ordering::SeqCst));
+                            thread::sleep(Duration::from_millis(1));
+                            readers.fetch_sub(1, Ordering::SeqCst);
+                        }
+                        commits.fetch_add(1, Ordering::SeqCst);
+                        concurrent_manager.release();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(commits.load(Ordering::SeqCst), 80);
+        assert_eq!(lock_table.lock_count_for_block(&block_id), 0);
+        assert!(start.elapsed() < MAX_TIME);
+    }
+
+    #[test]
+    fn deadlock() {
+        let lock_table = Arc::new(LockTable::new());
+        let block_ids: Vec<BlockId> = (0..2)
+            .map(|i| BlockId {
+                filename: "testfile".to_string(),
+                block_number: i,
+            })
+            .collect();
+        let barrier = Arc::new(Barrier::new(2));
+
+        // 2つのトランザクションが逆順にブロックをX-lockする。
+        // 閉路を作った側だけがすぐにabortされ、もう一方はその解放後にlockを取れる
+        let start = Instant::now();
+        let handles: Vec<_> = (0..2)
+            .map(|i| {
+                let lock_table = Arc::clone(&lock_table);
+                let first = block_ids[i].clone();
+                let second = block_ids[1 - i].clone();
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    let mut concurrent_manager =
+                        ConcurrentManager::new(Arc::clone(&lock_table), i as i32);
+                    concurrent_manager.xlock(&first).unwrap();
+                    barrier.wait();
+                    let result = concurrent_manager.xlock(&second);
+                    concurrent_manager.release();
+                    result.is_ok()
+                })
+            })
+            .collect();
+        let results: Vec<bool> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        assert_eq!(results.iter().filter(|ok| **ok).count(), 1);
+        assert!(start.elapsed() < MAX_TIME);
+        for block_id in &block_ids {
+            assert_eq!(lock_table.lock_count_for_block(block_id), 0);
         }
     }
 }
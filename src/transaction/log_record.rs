@@ -1,12 +1,15 @@
 use std::sync::{Arc, Mutex};
 
 use crate::{
-    file_manager::{BlockId, Page, INTGER_BYTES},
+    file_manager::{BlockId, Page, BOOL_BYTES, FLOAT_BYTES, INTGER_BYTES, LONG_BYTES},
     log_manager::LogManager,
 };
 
 use super::transaction::Transaction;
 
+// checkpoint recordのget_txnumが返す値
+pub const CHECKPOINT_TXNUM: i32 = -1;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogRecordType {
     CheckPoint = 0,
@@ -15,18 +18,31 @@ pub enum LogRecordType {
     Rollback,
     SetInt,
     SetString,
+    Comment,
+    SetBool,
+    SetLong,
+    SetFloat,
+    SetByte,
+    RenameFile,
 }
 
-impl From<i32> for LogRecordType {
-    fn from(v: i32) -> Self {
+impl TryFrom<i32> for LogRecordType {
+    type Error = anyhow::Error;
+    fn try_from(v: i32) -> anyhow::Result<Self> {
         match v {
-            0 => LogRecordType::CheckPoint,
-            1 => LogRecordType::Start,
-            2 => LogRecordType::Commit,
-            3 => LogRecordType::Rollback,
-            4 => LogRecordType::SetInt,
-            5 => LogRecordType::SetString,
-            _ => todo!(),
+            0 => Ok(LogRecordType::CheckPoint),
+            1 => Ok(LogRecordType::Start),
+            2 => Ok(LogRecordType::Commit),
+            3 => Ok(LogRecordType::Rollback),
+            4 => Ok(LogRecordType::SetInt),
+            5 => Ok(LogRecordType::SetString),
+            6 => Ok(LogRecordType::Comment),
+            7 => Ok(LogRecordType::SetBool),
+            8 => Ok(LogRecordType::SetLong),
+            9 => Ok(LogRecordType::SetFloat),
+            10 => Ok(LogRecordType::SetByte),
+            11 => Ok(LogRecordType::RenameFile),
+            _ => Err(anyhow::anyhow!("unknown log record type {v}")),
         }
     }
 }
@@ -40,31 +56,43 @@ impl From<LogRecordType> for i32 {
             LogRecordType::Rollback => 3,
             LogRecordType::SetInt => 4,
             LogRecordType::SetString => 5,
+            LogRecordType::Comment => 6,
+            LogRecordType::SetBool => 7,
+            LogRecordType::SetLong => 8,
+            LogRecordType::SetFloat => 9,
+            LogRecordType::SetByte => 10,
+            LogRecordType::RenameFile => 11,
         }
     }
 }
 
 pub enum LogRecord {
-    CheckPoint(TransactionRecord),
-    Start(TransactionRecord),
+    CheckPoint(CheckPointRecord),
+    Start(StartRecord),
     Commit(TransactionRecord),
     Rollback(TransactionRecord),
     SetInt(UpdateRecord<i32>),
     SetString(UpdateRecord<String>),
+    Comment(CommentRecord),
+    SetBool(UpdateRecord<bool>),
+    SetLong(UpdateRecord<i64>),
+    SetFloat(UpdateRecord<f64>),
+    SetByte(UpdateRecord<u8>),
+    RenameFile(RenameFileRecord),
 }
 
 impl LogRecord {
-    pub fn create_checkpoint_record(txnum: i32) -> Self {
-        LogRecord::CheckPoint(TransactionRecord {
+    pub fn create_checkpoint_record() -> Self {
+        LogRecord::CheckPoint(CheckPointRecord {
             record_type: LogRecordType::CheckPoint,
-            txnum,
         })
     }
 
-    pub fn create_start_record(txnum: i32) -> Self {
-        LogRecord::Start(TransactionRecord {
+    pub fn create_start_record(txnum: i32, name: String) -> Self {
+        LogRecord::Start(StartRecord {
             record_type: LogRecordType::Start,
             txnum,
+            name,
         })
     }
 
@@ -82,12 +110,19 @@ impl LogRecord {
         })
     }
 
-    pub fn create_set_int_record(txnum: i32, offset: i32, value: i32, block_id: BlockId) -> Self {
+    pub fn create_set_int_record(
+        txnum: i32,
+        offset: i32,
+        value: i32,
+        new_value: i32,
+        block_id: BlockId,
+    ) -> Self {
         LogRecord::SetInt(UpdateRecord {
             record_type: LogRecordType::SetInt,
             txnum,
             offset,
             value,
+            new_value,
             block_id,
         })
     }
@@ -96,6 +131,7 @@ impl LogRecord {
         txnum: i32,
         offset: i32,
         value: String,
+        new_value: String,
         block_id: BlockId,
     ) -> Self {
         LogRecord::SetString(UpdateRecord {
@@ -103,20 +139,128 @@ impl LogRecord {
             txnum,
             offset,
             value,
+            new_value,
+            block_id,
+        })
+    }
+
+    pub fn create_set_bool_record(
+        txnum: i32,
+        offset: i32,
+        value: bool,
+        new_value: bool,
+        block_id: BlockId,
+    ) -> Self {
+        LogRecord::SetBool(UpdateRecord {
+            record_type: LogRecordType::SetBool,
+            txnum,
+            offset,
+            value,
+            new_value,
+            block_id,
+        })
+    }
+
+    pub fn create_set_long_record(
+        txnum: i32,
+        offset: i32,
+        value: i64,
+        new_value: i64,
+        block_id: BlockId,
+    ) -> Self {
+        LogRecord::SetLong(UpdateRecord {
+            record_type: LogRecordType::SetLong,
+            txnum,
+            offset,
+            value,
+            new_value,
+            block_id,
+        })
+    }
+
+    pub fn create_set_float_record(
+        txnum: i32,
+        offset: i32,
+        value: f64,
+        new_value: f64,
+        block_id: BlockId,
+    ) -> Self {
+        LogRecord::SetFloat(UpdateRecord {
+            record_type: LogRecordType::SetFloat,
+            txnum,
+            offset,
+            value,
+            new_value,
             block_id,
         })
     }
+
+    pub fn create_set_byte_record(
+        txnum: i32,
+        offset: i32,
+        value: u8,
+        new_value: u8,
+        block_id: BlockId,
+    ) -> Self {
+        LogRecord::SetByte(UpdateRecord {
+            record_type: LogRecordType::SetByte,
+            txnum,
+            offset,
+            value,
+            new_value,
+            block_id,
+        })
+    }
+
+    pub fn create_rename_file_record(
+        txnum: i32,
+        old_filename: String,
+        new_filename: String,
+    ) -> Self {
+        LogRecord::RenameFile(RenameFileRecord {
+            record_type: LogRecordType::RenameFile,
+            txnum,
+            old_filename,
+            new_filename,
+        })
+    }
+
+    // 更新レコードなら対象のブロックを返す
+    pub fn block_id(&self) -> Option<&BlockId> {
+        match self {
+            Self::SetInt(record) => Some(&record.block_id),
+            Self::SetString(record) => Some(&record.block_id),
+            Self::SetBool(record) => Some(&record.block_id),
+            Self::SetLong(record) => Some(&record.block_id),
+            Self::SetFloat(record) => Some(&record.block_id),
+            Self::SetByte(record) => Some(&record.block_id),
+            _ => None,
+        }
+    }
+
+    pub fn create_comment_record(txnum: i32, text: String) -> Self {
+        LogRecord::Comment(CommentRecord {
+            record_type: LogRecordType::Comment,
+            txnum,
+            text,
+        })
+    }
 }
 
 impl LogRecordTrait for LogRecord {
     fn get_txnum(&self) -> i32 {
         match self {
-            Self::CheckPoint(record)
-            | Self::Commit(record)
-            | Self::Start(record)
-            | Self::Rollback(record) => record.txnum,
+            Self::CheckPoint(_) => CHECKPOINT_TXNUM,
+            Self::Commit(record) | Self::Rollback(record) => record.txnum,
+            Self::Start(record) => record.txnum,
             Self::SetInt(record) => record.txnum,
             Self::SetString(record) => record.txnum,
+            Self::Comment(record) => record.txnum,
+            Self::SetBool(record) => record.txnum,
+            Self::SetLong(record) => record.txnum,
+            Self::SetFloat(record) => record.txnum,
+            Self::SetByte(record) => record.txnum,
+            Self::RenameFile(record) => record.txnum,
         }
     }
 }
@@ -125,35 +269,60 @@ pub trait LogRecordTrait {
     fn get_txnum(&self) -> i32;
 }
 
+// checkpointはどのトランザクションにも属さない
+pub struct CheckPointRecord {
+    record_type: LogRecordType,
+}
+
 pub struct TransactionRecord {
     record_type: LogRecordType,
     txnum: i32,
 }
 
+pub struct StartRecord {
+    record_type: LogRecordType,
+    pub txnum: i32,
+    pub name: String,
+}
+
+pub struct CommentRecord {
+    record_type: LogRecordType,
+    pub txnum: i32,
+    pub text: String,
+}
+
+// ファイル名の変更。undoでは新しい名前から元の名前に戻す
+pub struct RenameFileRecord {
+    record_type: LogRecordType,
+    pub txnum: i32,
+    pub old_filename: String,
+    pub new_filename: String,
+}
+
 pub struct UpdateRecord<T> {
     record_type: LogRecordType,
     pub txnum: i32,
     pub offset: i32,
+    // 更新前の値(undo用)と更新後の値(redo用)
     pub value: T,
+    pub new_value: T,
     pub block_id: BlockId,
 }
 
 impl TryFrom<&mut Page> for LogRecord {
     type Error = anyhow::Error;
     fn try_from(page: &mut Page) -> anyhow::Result<Self> {
-        let record_type: LogRecordType = page.get_int(0)?.into();
+        let record_type = LogRecordType::try_from(page.get_int(0)?)?;
         match record_type {
-            LogRecordType::CheckPoint => {
-                let tpos = INTGER_BYTES;
-                let txnum = page.get_int(tpos)?;
-
-                Ok(LogRecord::create_checkpoint_record(txnum))
-            }
+            LogRecordType::CheckPoint => Ok(LogRecord::create_checkpoint_record()),
             LogRecordType::Start => {
                 let tpos = INTGER_BYTES;
                 let txnum = page.get_int(tpos)?;
 
-                Ok(LogRecord::create_start_record(txnum))
+                let npos = tpos + INTGER_BYTES;
+                let name = page.get_string(npos)?;
+
+                Ok(LogRecord::create_start_record(txnum, name))
             }
             LogRecordType::Commit => {
                 let tpos = INTGER_BYTES;
@@ -183,10 +352,14 @@ impl TryFrom<&mut Page> for LogRecord {
                 let vpos = opos + INTGER_BYTES;
                 let value = page.get_int(vpos)?;
 
+                let npos = vpos + INTGER_BYTES;
+                let new_value = page.get_int(npos)?;
+
                 Ok(LogRecord::create_set_int_record(
                     txnum,
                     offset,
                     value,
+                    new_value,
                     BlockId {
                         filename,
                         block_number,
@@ -207,32 +380,190 @@ impl TryFrom<&mut Page> for LogRecord {
                 let offset = page.get_int(opos)?;
 
                 let vpos = opos + INTGER_BYTES;
-                let value = page.get_string(vpos).unwrap();
+                let value = page.get_string(vpos)?;
+
+                let npos = vpos + Page::max_length(value.len());
+                let new_value = page.get_string(npos)?;
 
                 Ok(LogRecord::create_set_string_record(
                     txnum,
                     offset,
                     value,
+                    new_value,
                     BlockId {
                         filename,
                         block_number,
                     },
                 ))
             }
-            _ => {
-                todo!()
+            LogRecordType::Comment => {
+                let tpos = INTGER_BYTES;
+                let txnum = page.get_int(tpos)?;
+
+                let cpos = tpos + INTGER_BYTES;
+                let text = page.get_string(cpos)?;
+
+                Ok(LogRecord::create_comment_record(txnum, text))
+            }
+            LogRecordType::SetBool => {
+                let tpos = INTGER_BYTES;
+                let txnum = page.get_int(tpos)?;
+
+                let fpos = tpos + INTGER_BYTES;
+                let filename = page.get_string(fpos)?;
+
+                let bpos = fpos + Page::max_length(filename.len());
+                let block_number = page.get_int(bpos)?;
+
+                let opos = bpos + INTGER_BYTES;
+                let offset = page.get_int(opos)?;
+
+                let vpos = opos + INTGER_BYTES;
+                let value = page.get_bool(vpos)?;
+
+                let npos = vpos + BOOL_BYTES;
+                let new_value = page.get_bool(npos)?;
+
+                Ok(LogRecord::create_set_bool_record(
+                    txnum,
+                    offset,
+                    value,
+                    new_value,
+                    BlockId {
+                        filename,
+                        block_number,
+                    },
+                ))
+            }
+            LogRecordType::SetLong => {
+                let tpos = INTGER_BYTES;
+                let txnum = page.get_int(tpos)?;
+
+                let fpos = tpos + INTGER_BYTES;
+                let filename = page.get_string(fpos)?;
+
+                let bpos = fpos + Page::max_length(filename.len());
+                let block_number = page.get_int(bpos)?;
+
+                let opos = bpos + INTGER_BYTES;
+                let offset = page.get_int(opos)?;
+
+                let vpos = opos + INTGER_BYTES;
+                let value = page.get_long(vpos)?;
+
+                let npos = vpos + LONG_BYTES;
+                let new_value = page.get_long(npos)?;
+
+                Ok(LogRecord::create_set_long_record(
+                    txnum,
+                    offset,
+                    value,
+                    new_value,
+                    BlockId {
+                        filename,
+                        block_number,
+                    },
+                ))
+            }
+            LogRecordType::SetFloat => {
+                let tpos = INTGER_BYTES;
+                let txnum = page.get_int(tpos)?;
+
+                let fpos = tpos + INTGER_BYTES;
+                let filename = page.get_string(fpos)?;
+
+                let bpos = fpos + Page::max_length(filename.len());
+                let block_number = page.get_int(bpos)?;
+
+                let opos = bpos + INTGER_BYTES;
+                let offset = page.get_int(opos)?;
+
+                let vpos = opos + INTGER_BYTES;
+                let value = page.get_float(vpos)?;
+
+                let npos = vpos + FLOAT_BYTES;
+                let new_value = page.get_float(npos)?;
+
+                Ok(LogRecord::create_set_float_record(
+                    txnum,
+                    offset,
+                    value,
+                    new_value,
+                    BlockId {
+                        filename,
+                        block_number,
+                    },
+                ))
+            }
+            LogRecordType::SetByte => {
+                let tpos = INTGER_BYTES;
+                let txnum = page.get_int(tpos)?;
+
+                let fpos = tpos + INTGER_BYTES;
+                let filename = page.get_string(fpos)?;
+
+                let bpos = fpos + Page::max_length(filename.len());
+                let block_number = page.get_int(bpos)?;
+
+                let opos = bpos + INTGER_BYTES;
+                let offset = page.get_int(opos)?;
+
+                let vpos = opos + INTGER_BYTES;
+                let value = page.get_byte(vpos)?;
+
+                let npos = vpos + 1;
+                let new_value = page.get_byte(npos)?;
+
+                Ok(LogRecord::create_set_byte_record(
+                    txnum,
+                    offset,
+                    value,
+                    new_value,
+                    BlockId {
+                        filename,
+                        block_number,
+                    },
+                ))
+            }
+            LogRecordType::RenameFile => {
+                let tpos = INTGER_BYTES;
+                let txnum = page.get_int(tpos)?;
+
+                let opos = tpos + INTGER_BYTES;
+                let old_filename = page.get_string(opos)?;
+
+                let npos = opos + Page::max_length(old_filename.len());
+                let new_filename = page.get_string(npos)?;
+
+                Ok(LogRecord::create_rename_file_record(
+                    txnum,
+                    old_filename,
+                    new_filename,
+                ))
             }
         }
     }
 }
 
+// Pageの読み出しはカーソルを動かすので、複製してから読む
+impl TryFrom<&Page> for LogRecord {
+    type Error = anyhow::Error;
+    fn try_from(page: &Page) -> anyhow::Result<Self> {
+        LogRecord::try_from(&mut page.clone())
+    }
+}
+
 impl From<LogRecord> for Page {
     fn from(log_record: LogRecord) -> Page {
         match log_record {
-            LogRecord::CheckPoint(record)
-            | LogRecord::Commit(record)
-            | LogRecord::Start(record)
-            | LogRecord::Rollback(record) => {
+            LogRecord::CheckPoint(record) => {
+                let buf = Vec::with_capacity(INTGER_BYTES);
+                let mut page = Page::from(Box::from(buf));
+
+                page.set_int(0, record.record_type.into()).unwrap();
+                page
+            }
+            LogRecord::Commit(record) | LogRecord::Rollback(record) => {
                 let tpos = INTGER_BYTES;
                 let record_len = tpos + INTGER_BYTES;
 
@@ -243,22 +574,38 @@ impl From<LogRecord> for Page {
                 page.set_int(tpos, record.txnum).unwrap();
                 page
             }
+            LogRecord::Start(record) => {
+                let tpos = INTGER_BYTES;
+                let npos = tpos + INTGER_BYTES;
+                let record_len = npos + Page::max_length(record.name.len());
+
+                let buf = Vec::with_capacity(record_len);
+                let mut page = Page::from(Box::from(buf));
+
+                page.set_int(0, record.record_type.into()).unwrap();
+                page.set_int(tpos, record.txnum).unwrap();
+                page.set_string(npos, record.name).unwrap();
+                page
+            }
             LogRecord::SetInt(record) => {
                 let tpos = INTGER_BYTES;
                 let fpos = tpos + INTGER_BYTES;
                 let bpos = fpos + Page::max_length(record.block_id.filename.len());
                 let opos = bpos + INTGER_BYTES;
                 let vpos = opos + INTGER_BYTES;
-                let reclen = vpos + INTGER_BYTES;
+                let npos = vpos + INTGER_BYTES;
+                let reclen = npos + INTGER_BYTES;
 
                 let buf = Vec::with_capacity(reclen);
                 let mut page = Page::from(Box::from(buf));
-                page.set_int(0, LogRecordType::SetString.into()).unwrap();
+                page.set_int(0, LogRecordType::SetInt.into()).unwrap();
                 page.set_int(tpos, record.txnum).unwrap();
                 page.set_string(fpos, record.block_id.filename.to_owned())
                     .unwrap();
-                page.set_int(bpos, record.offset).unwrap();
+                page.set_int(bpos, record.block_id.block_number).unwrap();
+                page.set_int(opos, record.offset).unwrap();
                 page.set_int(vpos, record.value).unwrap();
+                page.set_int(npos, record.new_value).unwrap();
                 page
             }
             LogRecord::SetString(record) => {
@@ -267,7 +614,8 @@ impl From<LogRecord> for Page {
                 let bpos = fpos + Page::max_length(record.block_id.filename.len());
                 let opos = bpos + INTGER_BYTES;
                 let vpos = opos + INTGER_BYTES;
-                let reclen = vpos + Page::max_length(record.value.len());
+                let npos = vpos + Page::max_length(record.value.len());
+                let reclen = npos + Page::max_length(record.new_value.len());
 
                 let buf = Vec::with_capacity(reclen);
                 let mut page = Page::from(Box::from(buf));
@@ -278,9 +626,119 @@ impl From<LogRecord> for Page {
                 page.set_int(bpos, record.block_id.block_number).unwrap();
                 page.set_int(opos, record.offset).unwrap();
                 page.set_string(vpos, record.value.to_owned()).unwrap();
+                page.set_string(npos, record.new_value).unwrap();
+                page
+            }
+            LogRecord::Comment(record) => {
+                let tpos = INTGER_BYTES;
+                let cpos = tpos + INTGER_BYTES;
+                let reclen = cpos + Page::max_length(record.text.len());
+
+                let buf = Vec::with_capacity(reclen);
+                let mut page = Page::from(Box::from(buf));
+                page.set_int(0, record.record_type.into()).unwrap();
+                page.set_int(tpos, record.txnum).unwrap();
+                page.set_string(cpos, record.text).unwrap();
+                page
+            }
+            LogRecord::SetBool(record) => {
+                let tpos = INTGER_BYTES;
+                let fpos = tpos + INTGER_BYTES;
+                let bpos = fpos + Page::max_length(record.block_id.filename.len());
+                let opos = bpos + INTGER_BYTES;
+                let vpos = opos + INTGER_BYTES;
+                let npos = vpos + BOOL_BYTES;
+                let reclen = npos + BOOL_BYTES;
+
+                let buf = Vec::with_capacity(reclen);
+                let mut page = Page::from(Box::from(buf));
+                page.set_int(0, LogRecordType::SetBool.into()).unwrap();
+                page.set_int(tpos, record.txnum).unwrap();
+                page.set_string(fpos, record.block_id.filename.to_owned())
+                    .unwrap();
+                page.set_int(bpos, record.block_id.block_number).unwrap();
+                page.set_int(opos, record.offset).unwrap();
+                page.set_bool(vpos, record.value).unwrap();
+                page.set_bool(npos, record.new_value).unwrap();
+                page
+            }
+            LogRecord::SetLong(record) => {
+                let tpos = INTGER_BYTES;
+                let fpos = tpos + INTGER_BYTES;
+                let bpos = fpos + Page::max_length(record.block_id.filename.len());
+                let opos = bpos + INTGER_BYTES;
+                let vpos = opos + INTGER_BYTES;
+                let npos = vpos + LONG_BYTES;
+                let reclen = npos + LONG_BYTES;
+
+                let buf = Vec::with_capacity(reclen);
+                let mut page = Page::from(Box::from(buf));
+                page.set_int(0, LogRecordType::SetLong.into()).unwrap();
+                page.set_int(tpos, record.txnum).unwrap();
+                page.set_string(fpos, record.block_id.filename.to_owned())
+                    .unwrap();
+                page.set_int(bpos, record.block_id.block_number).unwrap();
+                page.set_int(opos, record.offset).unwrap();
+                page.set_long(vpos, record.value).unwrap();
+                page.set_long(npos, record.new_value).unwrap();
+                page
+            }
+            LogRecord::SetFloat(record) => {
+                let tpos = INTGER_BYTES;
+                let fpos = tpos + INTGER_BYTES;
+                let bpos = fpos + Page::max_length(record.block_id.filename.len());
+                let opos = bpos + INTGER_BYTES;
+                let vpos = opos + INTGER_BYTES;
+                let npos = vpos + FLOAT_BYTES;
+                let reclen = npos + FLOAT_BYTES;
+
+                let buf = Vec::with_capacity(reclen);
+                let mut page = Page::from(Box::from(buf));
+                page.set_int(0, LogRecordType::SetFloat.into()).unwrap();
+                page.set_int(tpos, record.txnum).unwrap();
+                page.set_string(fpos, record.block_id.filename.to_owned())
+                    .unwrap();
+                page.set_int(bpos, record.block_id.block_number).unwrap();
+                page.set_int(opos, record.offset).unwrap();
+                page.set_float(vpos, record.value).unwrap();
+                page.set_float(npos, record.new_value).unwrap();
+                page
+            }
+            LogRecord::SetByte(record) => {
+                let tpos = INTGER_BYTES;
+                let fpos = tpos + INTGER_BYTES;
+                let bpos = fpos + Page::max_length(record.block_id.filename.len());
+                let opos = bpos + INTGER_BYTES;
+                let vpos = opos + INTGER_BYTES;
+                let npos = vpos + 1;
+                let reclen = npos + 1;
+
+                let buf = Vec::with_capacity(reclen);
+                let mut page = Page::from(Box::from(buf));
+                page.set_int(0, LogRecordType::SetByte.into()).unwrap();
+                page.set_int(tpos, record.txnum).unwrap();
+                page.set_string(fpos, record.block_id.filename.to_owned())
+                    .unwrap();
+                page.set_int(bpos, record.block_id.block_number).unwrap();
+                page.set_int(opos, record.offset).unwrap();
+                page.set_byte(vpos, record.value).unwrap();
+                page.set_byte(npos, record.new_value).unwrap();
+                page
+            }
+            LogRecord::RenameFile(record) => {
+                let tpos = INTGER_BYTES;
+                let opos = tpos + INTGER_BYTES;
+                let npos = opos + Page::max_length(record.old_filename.len());
+                let reclen = npos + Page::max_length(record.new_filename.len());
+
+                let buf = Vec::with_capacity(reclen);
+                let mut page = Page::from(Box::from(buf));
+                page.set_int(0, record.record_type.into()).unwrap();
+                page.set_int(tpos, record.txnum).unwrap();
+                page.set_string(opos, record.old_filename).unwrap();
+                page.set_string(npos, record.new_filename).unwrap();
                 page
             }
-            _ => todo!(),
         }
     }
 }
@@ -295,8 +753,13 @@ mod tests {
             filename: "test.txt".to_string(),
             block_number: 1,
         };
-        let record =
-            LogRecord::create_set_string_record(1, 0, "test".to_string(), block_id.clone());
+        let record = LogRecord::create_set_string_record(
+            1,
+            0,
+            "test".to_string(),
+            "updated".to_string(),
+            block_id.clone(),
+        );
         let log_record = LogRecord::try_from(&mut record.into()).unwrap();
 
         match log_record {
@@ -305,17 +768,51 @@ mod tests {
                 assert_eq!(update_record.block_id, block_id);
                 assert_eq!(update_record.offset, 0);
                 assert_eq!(update_record.value, "test".to_string());
+                assert_eq!(update_record.new_value, "updated".to_string());
+            }
+            _ => panic!("Invalid log record type."),
+        }
+    }
+
+    #[test]
+    fn test_start() {
+        let record = LogRecord::create_start_record(3, "import".to_string());
+        let log_record = LogRecord::try_from(&mut record.into()).unwrap();
+
+        match log_record {
+            LogRecord::Start(start_record) => {
+                assert_eq!(start_record.txnum, 3);
+                assert_eq!(start_record.name, "import".to_string());
             }
             _ => panic!("Invalid log record type."),
         }
     }
 
+    #[test]
+    fn test_from_shared_page() {
+        let page: Page = LogRecord::create_commit_record(7).into();
+        let log_record = LogRecord::try_from(&page).unwrap();
+        assert_eq!(log_record.get_txnum(), 7);
+        assert!(matches!(log_record, LogRecord::Commit(_)));
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let mut page: Page = LogRecord::create_checkpoint_record().into();
+        assert_eq!(page.contents().len(), INTGER_BYTES);
+        let log_record = LogRecord::try_from(&mut page).unwrap();
+
+        assert_eq!(log_record.get_txnum(), CHECKPOINT_TXNUM);
+        assert!(matches!(log_record, LogRecord::CheckPoint(_)));
+    }
+
+    #[test]
     fn test_set_int() {
         let block_id = BlockId {
             filename: "test.txt".to_string(),
             block_number: 1,
         };
-        let record = LogRecord::create_set_int_record(1, 0, 20, block_id.clone());
+        let record = LogRecord::create_set_int_record(1, 0, 20, -7, block_id.clone());
         let log_record = LogRecord::try_from(&mut record.into()).unwrap();
 
         match log_record {
@@ -324,8 +821,124 @@ mod tests {
                 assert_eq!(update_record.block_id, block_id);
                 assert_eq!(update_record.offset, 0);
                 assert_eq!(update_record.value, 20);
+                assert_eq!(update_record.new_value, -7);
+            }
+            _ => panic!("Invalid log record type."),
+        }
+    }
+
+    #[test]
+    fn test_set_bool() {
+        let block_id = BlockId {
+            filename: "test.txt".to_string(),
+            block_number: 2,
+        };
+        let record = LogRecord::create_set_bool_record(1, 12, true, false, block_id.clone());
+        let log_record = LogRecord::try_from(&mut record.into()).unwrap();
+
+        match log_record {
+            LogRecord::SetBool(update_record) => {
+                assert_eq!(update_record.txnum, 1);
+                assert_eq!(update_record.block_id, block_id);
+                assert_eq!(update_record.offset, 12);
+                assert!(update_record.value);
+                assert!(!update_record.new_value);
+            }
+            _ => panic!("Invalid log record type."),
+        }
+    }
+
+    #[test]
+    fn test_set_long() {
+        let block_id = BlockId {
+            filename: "test.txt".to_string(),
+            block_number: 3,
+        };
+        let record = LogRecord::create_set_long_record(1, 16, i64::MAX, i64::MIN, block_id.clone());
+        let log_record = LogRecord::try_from(&mut record.into()).unwrap();
+
+        match log_record {
+            LogRecord::SetLong(update_record) => {
+                assert_eq!(update_record.txnum, 1);
+                assert_eq!(update_record.block_id, block_id);
+                assert_eq!(update_record.offset, 16);
+                assert_eq!(update_record.value, i64::MAX);
+                assert_eq!(update_record.new_value, i64::MIN);
+            }
+            _ => panic!("Invalid log record type."),
+        }
+    }
+
+    #[test]
+    fn test_set_float() {
+        let block_id = BlockId {
+            filename: "test.txt".to_string(),
+            block_number: 4,
+        };
+        let record = LogRecord::create_set_float_record(1, 24, -2.5, 0.125, block_id.clone());
+        let log_record = LogRecord::try_from(&mut record.into()).unwrap();
+
+        match log_record {
+            LogRecord::SetFloat(update_record) => {
+                assert_eq!(update_record.txnum, 1);
+                assert_eq!(update_record.block_id, block_id);
+                assert_eq!(update_record.offset, 24);
+                assert_eq!(update_record.value, -2.5);
+                assert_eq!(update_record.new_value, 0.125);
             }
             _ => panic!("Invalid log record type."),
         }
     }
+
+    #[test]
+    fn test_set_byte() {
+        let block_id = BlockId {
+            filename: "test.txt".to_string(),
+            block_number: 5,
+        };
+        let record = LogRecord::create_set_byte_record(1, 4, 0b101, 0xff, block_id.clone());
+        let log_record = LogRecord::try_from(&mut record.into()).unwrap();
+
+        match log_record {
+            LogRecord::SetByte(update_record) => {
+                assert_eq!(update_record.txnum, 1);
+                assert_eq!(update_record.block_id, block_id);
+                assert_eq!(update_record.offset, 4);
+                assert_eq!(update_record.value, 0b101);
+                assert_eq!(update_record.new_value, 0xff);
+            }
+            _ => panic!("Invalid log record type."),
+        }
+    }
+
+    #[test]
+    fn test_rename_file() {
+        let record = LogRecord::create_rename_file_record(
+            2,
+            "users.tbl".to_string(),
+            "members.tbl".to_string(),
+        );
+        let log_record = LogRecord::try_from(&mut record.into()).unwrap();
+
+        assert!(log_record.block_id().is_none());
+        match log_record {
+            LogRecord::RenameFile(rename_record) => {
+                assert_eq!(rename_record.txnum, 2);
+                assert_eq!(rename_record.old_filename, "users.tbl");
+                assert_eq!(rename_record.new_filename, "members.tbl");
+            }
+            _ => panic!("Invalid log record type."),
+        }
+    }
+
+    #[test]
+    fn unknown_record_type() {
+        assert!(LogRecordType::try_from(99).is_err());
+
+        let mut page = Page::new(8);
+        page.set_int(0, 99).unwrap();
+        page.set_int(INTGER_BYTES, 1).unwrap();
+        let err = LogRecord::try_from(&mut page).err().unwrap();
+        assert!(err.to_string().contains("unknown log record type 99"));
+    }
 }
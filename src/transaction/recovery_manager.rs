@@ -1,8 +1,9 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex, RwLock};
 
 use crate::{
     buffer_manager::{Buffer, BufferManager},
-    file_manager::Page,
+    file_manager::{BlockId, Page},
     log_manager::LogManager,
 };
 
@@ -16,6 +17,10 @@ pub struct RecoveryManager {
     buffer_manager: Arc<Mutex<BufferManager>>,
     //transaction: Transaction,
     txnum: i32,
+    // ブロック -> そのブロックを最初に更新したコミット済みレコードのLSN
+    dirty_page_table: HashMap<BlockId, i64>,
+    // checkpoint以降にcommitもrollbackもしていないトランザクション -> 最後のレコードのLSN
+    active_transaction_table: HashMap<i32, i64>,
 }
 
 impl RecoveryManager {
@@ -24,8 +29,9 @@ impl RecoveryManager {
         buffer_manager: Arc<Mutex<BufferManager>>,
         //transaction: Transaction,
         txnum: i32,
+        name: String,
     ) -> Self {
-        let record = LogRecord::create_start_record(txnum);
+        let record = LogRecord::create_start_record(txnum, name);
         let mut page: Page = record.into();
         log_manager
             .lock()
@@ -37,6 +43,23 @@ impl RecoveryManager {
             buffer_manager,
             //transaction,
             txnum,
+            dirty_page_table: HashMap::new(),
+            active_transaction_table: HashMap::new(),
+        }
+    }
+
+    // 読み込み専用のトランザクション用。startレコードを書かない
+    pub fn new_read_only(
+        log_manager: Arc<Mutex<LogManager>>,
+        buffer_manager: Arc<Mutex<BufferManager>>,
+        txnum: i32,
+    ) -> Self {
+        Self {
+            log_manager,
+            buffer_manager,
+            txnum,
+            dirty_page_table: HashMap::new(),
+            active_transaction_table: HashMap::new(),
         }
     }
 
@@ -50,7 +73,7 @@ impl RecoveryManager {
             .unwrap()
             .append_record(page.contents())
             .unwrap();
-        self.log_manager.lock().unwrap().flush_with(lsm).unwrap();
+        LogManager::flush_with_group(&self.log_manager, lsm).unwrap();
     }
 
     pub fn rollback(&self) {
@@ -66,28 +89,181 @@ impl RecoveryManager {
         self.log_manager.lock().unwrap().flush_with(lsm).unwrap();
     }
 
-    pub fn recover(&self) {
+    pub fn recover(&self) -> anyhow::Result<()> {
         self.buffer_manager.lock().unwrap().flush_all(self.txnum);
-        let record = LogRecord::create_checkpoint_record(self.txnum);
+        let record = LogRecord::create_checkpoint_record();
         let mut page: Page = record.into();
         let lsm = self
             .log_manager
             .lock()
             .unwrap()
-            .append_record(page.contents())
-            .unwrap();
-        self.log_manager.lock().unwrap().flush_with(lsm).unwrap();
+            .append_record(page.contents())?;
+        self.log_manager.lock().unwrap().flush_with(lsm)?;
+        Ok(())
     }
 
-    pub fn set_int(&self, buf: Arc<RwLock<Buffer>>, offset: i32) -> i32 {
+    // checkpoint以降のログを前から読み、コミット済みトランザクションの更新をディスクに書き直す。
+    // 同時に、UNDOで取り消すべき未完了のトランザクションをactive_transaction_tableに集める
+    pub fn redo(&mut self) -> anyhow::Result<()> {
+        // 間に他のトランザクションがログを追記しないよう、同じロックの中でLSNとイテレータを得る
+        let (latest_lsn, iter) = {
+            let mut locked_log_manager = self.log_manager.lock().unwrap();
+            (
+                locked_log_manager.latest_lsn(),
+                locked_log_manager.iterator()?,
+            )
+        };
+
+        // イテレータは最新のレコードから順にLSNを1ずつ遡る
+        let mut committed = HashSet::new();
+        // 後でコミット済みのrenameで名前が変わったファイル。rename前に書き出してあるのでやり直さない
+        let mut renamed_away = HashSet::new();
+        let mut records = vec![];
+        for (i, record) in iter.enumerate() {
+            let log_record = LogRecord::try_from(&mut Page::from(record))?;
+            match &log_record {
+                LogRecord::CheckPoint(_) => break,
+                LogRecord::Commit(_) => {
+                    committed.insert(log_record.get_txnum());
+                }
+                LogRecord::RenameFile(record) if committed.contains(&record.txnum) => {
+                    renamed_away.remove(&record.new_filename);
+                    renamed_away.insert(record.old_filename.clone());
+                }
+                _ => {}
+            }
+            if let Some(block_id) = log_record.block_id() {
+                if renamed_away.contains(&block_id.filename) {
+                    continue;
+                }
+            }
+            records.push((latest_lsn - i as i64, log_record));
+        }
+        records.reverse();
+
+        self.active_transaction_table.clear();
+        for (lsn, log_record) in &records {
+            match log_record {
+                LogRecord::Commit(_) | LogRecord::Rollback(_) => {
+                    self.active_transaction_table
+                        .remove(&log_record.get_txnum());
+                }
+                // recoverしている自分自身は除く
+                _ if log_record.get_txnum() != self.txnum => {
+                    self.active_transaction_table
+                        .insert(log_record.get_txnum(), *lsn);
+                }
+                _ => {}
+            }
+        }
+
+        self.dirty_page_table.clear();
+        for (lsn, log_record) in &records {
+            if !committed.contains(&log_record.get_txnum()) {
+                continue;
+            }
+            if let Some(block_id) = log_record.block_id() {
+                self.dirty_page_table
+                    .entry(block_id.clone())
+                    .or_insert(*lsn);
+            }
+        }
+
+        // recordsはcheckpoint以降のものだけなので、コミット済みの更新はすべてやり直す
+        if self.dirty_page_table.is_empty() {
+            return Ok(());
+        }
+        for (_, log_record) in records {
+            if committed.contains(&log_record.get_txnum()) {
+                self.redo_record(log_record)?;
+            }
+        }
+        self.buffer_manager.lock().unwrap().flush_all(self.txnum);
+        Ok(())
+    }
+
+    pub fn dirty_page_table(&self) -> &HashMap<BlockId, i64> {
+        &self.dirty_page_table
+    }
+
+    pub fn active_transaction_table(&self) -> &HashMap<i32, i64> {
+        &self.active_transaction_table
+    }
+
+    fn redo_record(&self, log_record: LogRecord) -> anyhow::Result<()> {
+        let block_id = match log_record.block_id() {
+            Some(block_id) => block_id.clone(),
+            None => return Ok(()),
+        };
+        let buffer = BufferManager::pin(&self.buffer_manager, &block_id)?;
+        {
+            let mut locked_buffer = buffer.write().unwrap();
+            match log_record {
+                LogRecord::SetInt(record) => {
+                    locked_buffer.set_int(record.offset as usize, record.new_value)?
+                }
+                LogRecord::SetString(record) => {
+                    locked_buffer.set_string(record.offset as usize, record.new_value)?
+                }
+                LogRecord::SetBool(record) => {
+                    locked_buffer.set_bool(record.offset as usize, record.new_value)?
+                }
+                LogRecord::SetLong(record) => {
+                    locked_buffer.set_long(record.offset as usize, record.new_value)?
+                }
+                LogRecord::SetFloat(record) => {
+                    locked_buffer.set_float(record.offset as usize, record.new_value)?
+                }
+                LogRecord::SetByte(record) => {
+                    locked_buffer.set_byte(record.offset as usize, record.new_value)?
+                }
+                _ => {}
+            }
+            locked_buffer.set_modified(self.txnum, -1);
+        }
+        self.buffer_manager.lock().unwrap().unpin(buffer);
+        Ok(())
+    }
+
+    // ファイル名を変える前にログをディスクまで書き、途中で落ちても元の名前に戻せるようにする
+    pub fn rename_file(&self, old_filename: &str, new_filename: &str) -> anyhow::Result<i64> {
+        let record = LogRecord::create_rename_file_record(
+            self.txnum,
+            old_filename.to_string(),
+            new_filename.to_string(),
+        );
+        let mut page: Page = record.into();
+        let mut locked_log_manager = self.log_manager.lock().unwrap();
+        let lsn = locked_log_manager.append_record(page.contents())?;
+        locked_log_manager.flush_with(lsn)?;
+        Ok(lsn)
+    }
+
+    pub fn comment(&self, text: String) -> anyhow::Result<i64> {
+        let record = LogRecord::create_comment_record(self.txnum, text);
+        let mut page: Page = record.into();
+        let lsn = self
+            .log_manager
+            .lock()
+            .unwrap()
+            .append_record(page.contents())?;
+        Ok(lsn)
+    }
+
+    pub fn set_int(&self, buf: Arc<RwLock<Buffer>>, offset: i32, new_value: i32) -> i64 {
         let mut locked_buffer = buf.write().unwrap();
         let old_value = locked_buffer.get_int(offset as usize).unwrap();
         let block_id = match locked_buffer.block_id() {
             Some(block_id) => block_id,
             None => panic!("block id not assigned"),
         };
-        let record =
-            LogRecord::create_set_int_record(self.txnum, offset, old_value, block_id.clone());
+        let record = LogRecord::create_set_int_record(
+            self.txnum,
+            offset,
+            old_value,
+            new_value,
+            block_id.clone(),
+        );
         let mut page: Page = record.into();
         self.log_manager
             .lock()
@@ -96,12 +272,93 @@ impl RecoveryManager {
             .unwrap()
     }
 
-    pub fn set_string(&self, buf: Arc<RwLock<Buffer>>, offset: i32) -> i32 {
+    pub fn set_string(&self, buf: Arc<RwLock<Buffer>>, offset: i32, new_value: String) -> i64 {
         let mut locked_buffer = buf.write().unwrap();
         let old_value = locked_buffer.get_string(offset as usize).unwrap();
         let block_id = locked_buffer.block_id().unwrap();
-        let record =
-            LogRecord::create_set_string_record(self.txnum, offset, old_value, block_id.clone());
+        let record = LogRecord::create_set_string_record(
+            self.txnum,
+            offset,
+            old_value,
+            new_value,
+            block_id.clone(),
+        );
+        let mut page: Page = record.into();
+        self.log_manager
+            .lock()
+            .unwrap()
+            .append_record(page.contents())
+            .unwrap()
+    }
+
+    pub fn set_bool(&self, buf: Arc<RwLock<Buffer>>, offset: i32, new_value: bool) -> i64 {
+        let mut locked_buffer = buf.write().unwrap();
+        let old_value = locked_buffer.get_bool(offset as usize).unwrap();
+        let block_id = locked_buffer.block_id().unwrap();
+        let record = LogRecord::create_set_bool_record(
+            self.txnum,
+            offset,
+            old_value,
+            new_value,
+            block_id.clone(),
+        );
+        let mut page: Page = record.into();
+        self.log_manager
+            .lock()
+            .unwrap()
+            .append_record(page.contents())
+            .unwrap()
+    }
+
+    pub fn set_long(&self, buf: Arc<RwLock<Buffer>>, offset: i32, new_value: i64) -> i64 {
+        let mut locked_buffer = buf.write().unwrap();
+        let old_value = locked_buffer.get_long(offset as usize).unwrap();
+        let block_id = locked_buffer.block_id().unwrap();
+        let record = LogRecord::create_set_long_record(
+            self.txnum,
+            offset,
+            old_value,
+            new_value,
+            block_id.clone(),
+        );
+        let mut page: Page = record.into();
+        self.log_manager
+            .lock()
+            .unwrap()
+            .append_record(page.contents())
+            .unwrap()
+    }
+
+    pub fn set_float(&self, buf: Arc<RwLock<Buffer>>, offset: i32, new_value: f64) -> i64 {
+        let mut locked_buffer = buf.write().unwrap();
+        let old_value = locked_buffer.get_float(offset as usize).unwrap();
+        let block_id = locked_buffer.block_id().unwrap();
+        let record = LogRecord::create_set_float_record(
+            self.txnum,
+            offset,
+            old_value,
+            new_value,
+            block_id.clone(),
+        );
+        let mut page: Page = record.into();
+        self.log_manager
+            .lock()
+            .unwrap()
+            .append_record(page.contents())
+            .unwrap()
+    }
+
+    pub fn set_byte(&self, buf: Arc<RwLock<Buffer>>, offset: i32, new_value: u8) -> i64 {
+        let mut locked_buffer = buf.write().unwrap();
+        let old_value = locked_buffer.get_byte(offset as usize).unwrap();
+        let block_id = locked_buffer.block_id().unwrap();
+        let record = LogRecord::create_set_byte_record(
+            self.txnum,
+            offset,
+            old_value,
+            new_value,
+            block_id.clone(),
+        );
         let mut page: Page = record.into();
         self.log_manager
             .lock()
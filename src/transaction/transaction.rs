@@ -1,11 +1,15 @@
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, Ok};
 
 use crate::buffer_manager::BufferManager;
-use crate::file_manager::{BlockId, FileManager, Page};
+use crate::file_manager::{BlockId, FileManagerTrait, Page};
 use crate::log_manager::LogManager;
+use crate::record_manager::constant::Constant;
+use crate::record_manager::layout::Encoding;
+use crate::record_manager::schema::FieldInfo;
 
 use super::buffer_list::BufferList;
 use super::lock_table::{ConcurrentManager, LockTable};
@@ -14,22 +18,81 @@ use super::recovery_manager::RecoveryManager;
 
 static TXMUN: AtomicUsize = AtomicUsize::new(0);
 
+pub fn total_transaction_count() -> usize {
+    TXMUN.load(Ordering::SeqCst)
+}
+
+// 生きているトランザクションの数を数える。作るときに1つ増やし、Dropで1つ減らす
+struct ActiveCount(Arc<AtomicUsize>);
+
+impl ActiveCount {
+    fn new(count: &Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::SeqCst);
+        Self(Arc::clone(count))
+    }
+}
+
+impl Drop for ActiveCount {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// savepointを作った時点のLSN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SavepointId(i64);
+
+// set_*で書き込むときに、更新をログに残すかどうか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogMode {
+    // rollbackで取り消せるように、更新前の値をログに残す
+    Log,
+    // ログに残さない。rollbackやrecoverでの取り消しと、まだ誰も読んでいない新しいブロックの初期化に使う
+    NoLog,
+    // savepointまでの取り消しの書き込み。後でコミットされうるので、REDOのためにLogと同じくログに残す
+    Compensating,
+}
+
 pub struct Transaction {
-    file_manager: Arc<Mutex<FileManager>>,
+    file_manager: Arc<Mutex<dyn FileManagerTrait>>,
     log_manager: Arc<Mutex<LogManager>>,
     buffer_manager: Arc<Mutex<BufferManager>>,
     recovery_manager: RecoveryManager,
     concurrent_manager: ConcurrentManager,
     buffer_list: BufferList,
     txnum: usize,
+    name: String,
+    // commitかrollbackが済んだかどうか
+    finished: bool,
+    savepoints: Vec<(SavepointId, HashSet<BlockId>)>,
+    // trueなら書き込みとログを一切行わない。読み込みは通常どおりS-lockを取る
+    read_only: bool,
+    // rollbackが済んでから減らすよう、Transaction::dropの後に破棄される
+    active_count: Option<ActiveCount>,
 }
 
 impl Transaction {
     pub fn new(
-        file_manager: Arc<Mutex<FileManager>>,
+        file_manager: Arc<Mutex<dyn FileManagerTrait>>,
+        log_manager: Arc<Mutex<LogManager>>,
+        buffer_manager: Arc<Mutex<BufferManager>>,
+        lock_table: Arc<LockTable>,
+    ) -> Self {
+        Self::begin_explicit(
+            String::new(),
+            file_manager,
+            log_manager,
+            buffer_manager,
+            lock_table,
+        )
+    }
+
+    pub fn begin_explicit(
+        name: String,
+        file_manager: Arc<Mutex<dyn FileManagerTrait>>,
         log_manager: Arc<Mutex<LogManager>>,
         buffer_manager: Arc<Mutex<BufferManager>>,
-        lock_table: Arc<Mutex<LockTable>>,
+        lock_table: Arc<LockTable>,
     ) -> Self {
         TXMUN.fetch_add(1, Ordering::SeqCst);
         let txnum = TXMUN.load(Ordering::SeqCst);
@@ -37,66 +100,213 @@ impl Transaction {
             Arc::clone(&log_manager),
             Arc::clone(&buffer_manager),
             txnum as i32,
+            name.clone(),
+        );
+        Self {
+            file_manager,
+            log_manager,
+            buffer_manager: Arc::clone(&buffer_manager),
+            recovery_manager,
+            concurrent_manager: ConcurrentManager::new(lock_table, txnum as i32),
+            buffer_list: BufferList::new(Arc::clone(&buffer_manager)),
+            txnum,
+            name,
+            finished: false,
+            savepoints: vec![],
+            read_only: false,
+            active_count: None,
+        }
+    }
+
+    // 読み込み専用のトランザクション。読むときはS-lockを取るので、
+    // 他のトランザクションが書き込み中のブロックはコミットかrollbackまで待ち、未コミットの更新は見えない
+    pub fn new_read_only(
+        file_manager: Arc<Mutex<dyn FileManagerTrait>>,
+        log_manager: Arc<Mutex<LogManager>>,
+        buffer_manager: Arc<Mutex<BufferManager>>,
+        lock_table: Arc<LockTable>,
+    ) -> Self {
+        TXMUN.fetch_add(1, Ordering::SeqCst);
+        let txnum = TXMUN.load(Ordering::SeqCst);
+        let recovery_manager = RecoveryManager::new_read_only(
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            txnum as i32,
         );
         Self {
             file_manager,
             log_manager,
             buffer_manager: Arc::clone(&buffer_manager),
             recovery_manager,
-            concurrent_manager: ConcurrentManager::new(lock_table),
+            concurrent_manager: ConcurrentManager::new(lock_table, txnum as i32),
             buffer_list: BufferList::new(Arc::clone(&buffer_manager)),
             txnum,
+            name: String::new(),
+            finished: false,
+            savepoints: vec![],
+            read_only: true,
+            active_count: None,
+        }
+    }
+
+    // countをこのトランザクションが破棄されるまで1つ増やしておく
+    pub fn with_active_count(mut self, count: &Arc<AtomicUsize>) -> Self {
+        self.active_count = Some(ActiveCount::new(count));
+        self
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn slock(&mut self, block_id: &BlockId) -> anyhow::Result<()> {
+        self.concurrent_manager
+            .slock(block_id)
+            .with_context(|| self.lock_context(block_id))
+    }
+
+    fn ensure_writable(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            anyhow::bail!("transaction {} is read-only", self.txnum);
         }
+        Ok(())
+    }
+
+    fn lock_context(&self, block_id: &BlockId) -> String {
+        format!(
+            "transaction {} ({}) could not lock {:?}",
+            self.txnum, self.name, block_id
+        )
     }
 
     pub fn commit(&mut self) -> anyhow::Result<()> {
-        self.recovery_manager.commit();
+        self.finished = true;
+        if !self.read_only {
+            self.recovery_manager.commit();
+        }
         self.concurrent_manager.release();
         self.buffer_list.unpin_all()?;
         Ok(())
     }
 
+    // 最後まで済んだときだけfinishedにするので、途中で失敗したらDropでやり直す
     pub fn rollback(&mut self) -> anyhow::Result<()> {
-        let iter = self.log_manager.lock().unwrap().iterator().unwrap();
+        if self.read_only {
+            self.concurrent_manager.release();
+            self.buffer_list.unpin_all()?;
+            self.finished = true;
+            return Ok(());
+        }
+        let iter = self
+            .log_manager
+            .lock()
+            .unwrap()
+            .iterator()
+            .context("read the log to roll back")?;
 
         for record in iter {
             let mut page = Page::from(record);
-            let log_record = LogRecord::try_from(&mut page).unwrap();
+            let log_record = LogRecord::try_from(&mut page)?;
             if log_record.get_txnum() == self.txnum as i32 {
                 match log_record {
-                    LogRecord::Start(_) => return Ok(()),
+                    LogRecord::Start(_) => break,
                     _ => {}
                 }
-                self.undo(log_record);
+                self.undo(log_record)?;
             }
         }
         self.recovery_manager.rollback();
         self.concurrent_manager.release();
         self.buffer_list.unpin_all()?;
+        self.finished = true;
+        Ok(())
+    }
+
+    pub fn savepoint(&mut self) -> SavepointId {
+        let savepoint = SavepointId(self.log_manager.lock().unwrap().latest_lsn());
+        self.savepoints
+            .push((savepoint, self.concurrent_manager.locked_blocks()));
+        savepoint
+    }
+
+    // savepoint以降の更新を取り消し、それ以降に取ったS-lockを解放する
+    pub fn rollback_to_savepoint(&mut self, savepoint: SavepointId) -> anyhow::Result<()> {
+        let index = self
+            .savepoints
+            .iter()
+            .position(|(id, _)| *id == savepoint)
+            .with_context(|| format!("unknown savepoint {:?}", savepoint))?;
+
+        // 間に他のトランザクションがログを追記しないよう、同じロックの中でLSNとイテレータを得る
+        let (latest_lsn, iter) = {
+            let mut locked_log_manager = self.log_manager.lock().unwrap();
+            (
+                locked_log_manager.latest_lsn(),
+                locked_log_manager.iterator()?,
+            )
+        };
+        // イテレータは最新のレコードから順にLSNを1ずつ遡る
+        let mut log_records = vec![];
+        for (i, record) in iter.enumerate() {
+            if latest_lsn - i as i64 <= savepoint.0 {
+                break;
+            }
+            let log_record = LogRecord::try_from(&mut Page::from(record))?;
+            if log_record.get_txnum() == self.txnum as i32 {
+                log_records.push(log_record);
+            }
+        }
+        // このトランザクションは後でコミットされうるので、REDOで取り消し前の値に戻らないよう
+        // 取り消しの書き込みもログに残す
+        for log_record in log_records {
+            self.undo_with_log(log_record, LogMode::Compensating)?;
+        }
+
+        let (_, locked_blocks) = &self.savepoints[index];
+        self.concurrent_manager.release_shared_except(locked_blocks);
+        self.savepoints.truncate(index + 1);
         Ok(())
     }
 
-    pub fn recover(&mut self) {
+    pub fn recover(&mut self) -> anyhow::Result<()> {
         {
             let mut locked_buffer_manager = self.buffer_manager.lock().unwrap();
             locked_buffer_manager.flush_all(self.txnum as i32);
         }
+        // REDO: コミット済みの更新をやり直してから、UNDO: 未完了の更新を取り消す
+        self.recovery_manager.redo()?;
 
-        let mut finished_transactions: Vec<i32> = vec![];
-        let iter = self.log_manager.lock().unwrap().iterator().unwrap();
+        // 取り消すのはactive_transaction_tableにあるトランザクションだけ。
+        // すべてのstartレコードまで遡ればそれより前を読む必要はない
+        let mut losers: HashSet<i32> = self
+            .recovery_manager
+            .active_transaction_table()
+            .keys()
+            .copied()
+            .collect();
+        let iter = self.log_manager.lock().unwrap().iterator()?;
         for record in iter {
+            if losers.is_empty() {
+                break;
+            }
             let mut page = Page::from(record);
-            let log_record = LogRecord::try_from(&mut page).unwrap();
+            let log_record = LogRecord::try_from(&mut page)?;
             let txnum = log_record.get_txnum();
             match log_record {
-                LogRecord::CheckPoint(_) => return,
-                LogRecord::Commit(_) | LogRecord::Rollback(_) => finished_transactions.push(txnum),
+                LogRecord::CheckPoint(_) => break,
+                LogRecord::Start(_) => {
+                    losers.remove(&txnum);
+                }
+                _ if losers.contains(&txnum) => self.undo(log_record)?,
                 _ => {}
             }
-            if !finished_transactions.contains(&txnum) {
-                self.undo(log_record);
-            }
         }
+        // ここより前のログは次回のrecoveryで読む必要がない
+        self.recovery_manager.recover()
     }
 
     pub fn pin(&mut self, block_id: &BlockId) -> anyhow::Result<()> {
@@ -108,42 +318,143 @@ impl Transaction {
     }
 
     pub fn get_int(&mut self, block_id: &BlockId, offset: i32) -> anyhow::Result<i32> {
-        self.concurrent_manager.slock(block_id)?;
-        let buffer = match self.buffer_list.get_buffer(block_id) {
-            Some(b) => Arc::clone(b),
-            None => todo!(),
-        };
+        self.slock(block_id)?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer not pinned")?;
         let mut locked_buffer = buffer.write().unwrap();
         locked_buffer.get_int(offset as usize).context("get int")
     }
 
     pub fn get_string(&mut self, block_id: &BlockId, offset: i32) -> anyhow::Result<String> {
-        self.concurrent_manager.slock(block_id)?;
-        let buffer = match self.buffer_list.get_buffer(block_id) {
-            Some(b) => b,
-            None => todo!(),
-        };
+        self.slock(block_id)?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer not pinned")?;
         let mut locked_buffer = buffer.write().unwrap();
         locked_buffer
             .get_string(offset as usize)
             .context("get string")
     }
 
+    pub fn get_bool(&mut self, block_id: &BlockId, offset: i32) -> anyhow::Result<bool> {
+        self.slock(block_id)?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer none")?;
+        let mut locked_buffer = buffer.write().unwrap();
+        locked_buffer.get_bool(offset as usize).context("get bool")
+    }
+
+    pub fn get_long(&mut self, block_id: &BlockId, offset: i32) -> anyhow::Result<i64> {
+        self.slock(block_id)?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer none")?;
+        let mut locked_buffer = buffer.write().unwrap();
+        locked_buffer.get_long(offset as usize).context("get long")
+    }
+
+    pub fn get_float(&mut self, block_id: &BlockId, offset: i32) -> anyhow::Result<f64> {
+        self.slock(block_id)?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer none")?;
+        let mut locked_buffer = buffer.write().unwrap();
+        locked_buffer
+            .get_float(offset as usize)
+            .context("get float")
+    }
+
+    pub fn get_val(
+        &mut self,
+        block_id: &BlockId,
+        offset: i32,
+        info: &FieldInfo,
+        encoding: Encoding,
+    ) -> anyhow::Result<Constant> {
+        self.slock(block_id)?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer not pinned")?;
+        let mut locked_buffer = buffer.write().unwrap();
+        info.deserialize_value(&mut locked_buffer, offset as usize, encoding)
+            .context("get value")
+    }
+
+    pub fn get_byte(&mut self, block_id: &BlockId, offset: i32) -> anyhow::Result<u8> {
+        self.slock(block_id)?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer none")?;
+        let mut locked_buffer = buffer.write().unwrap();
+        locked_buffer.get_byte(offset as usize).context("get byte")
+    }
+
+    // bitmap_offsetから始まるビット列のbit番目が立っていればNULL
+    pub fn is_null(
+        &mut self,
+        block_id: &BlockId,
+        bitmap_offset: i32,
+        bit: usize,
+    ) -> anyhow::Result<bool> {
+        let byte = self.get_byte(block_id, bitmap_offset + (bit / 8) as i32)?;
+        Ok(byte & (1 << (bit % 8)) != 0)
+    }
+
+    pub fn get_optional_int(
+        &mut self,
+        block_id: &BlockId,
+        offset: i32,
+        bitmap_offset: i32,
+        bit: usize,
+    ) -> anyhow::Result<Option<i32>> {
+        if self.is_null(block_id, bitmap_offset, bit)? {
+            return Ok(None);
+        }
+        self.get_int(block_id, offset).map(Some)
+    }
+
+    pub fn get_optional_string(
+        &mut self,
+        block_id: &BlockId,
+        offset: i32,
+        bitmap_offset: i32,
+        bit: usize,
+    ) -> anyhow::Result<Option<String>> {
+        if self.is_null(block_id, bitmap_offset, bit)? {
+            return Ok(None);
+        }
+        self.get_string(block_id, offset).map(Some)
+    }
+
     pub fn set_int(
         &mut self,
         block_id: &BlockId,
         offset: i32,
         val: i32,
-        ok_to_log: bool,
+        log_mode: LogMode,
     ) -> anyhow::Result<()> {
-        self.concurrent_manager.xlock(block_id)?;
+        self.ensure_writable()?;
+        self.concurrent_manager
+            .xlock(block_id)
+            .with_context(|| self.lock_context(block_id))?;
         let buffer = self
             .buffer_list
             .get_buffer(block_id)
             .context("buffer none")?;
         let mut lsn = -1;
-        if ok_to_log {
-            lsn = self.recovery_manager.set_int(Arc::clone(buffer), offset);
+        if log_mode != LogMode::NoLog {
+            lsn = self
+                .recovery_manager
+                .set_int(Arc::clone(buffer), offset, val);
         }
         let mut locked_buffer = buffer.write().unwrap();
         locked_buffer.set_int(offset as usize, val)?;
@@ -156,16 +467,21 @@ impl Transaction {
         block_id: &BlockId,
         offset: i32,
         val: String,
-        ok_to_log: bool,
+        log_mode: LogMode,
     ) -> anyhow::Result<()> {
-        self.concurrent_manager.xlock(block_id)?;
+        self.ensure_writable()?;
+        self.concurrent_manager
+            .xlock(block_id)
+            .with_context(|| self.lock_context(block_id))?;
         let buffer = self
             .buffer_list
             .get_buffer(block_id)
             .context("buffer none")?;
         let mut lsn = -1;
-        if ok_to_log {
-            lsn = self.recovery_manager.set_string(Arc::clone(buffer), offset);
+        if log_mode != LogMode::NoLog {
+            lsn = self
+                .recovery_manager
+                .set_string(Arc::clone(buffer), offset, val.clone());
         }
         let mut locked_buffer = buffer.write().unwrap();
         locked_buffer.set_string(offset as usize, val)?;
@@ -173,35 +489,340 @@ impl Transaction {
         Ok(())
     }
 
+    pub fn set_bool(
+        &mut self,
+        block_id: &BlockId,
+        offset: i32,
+        val: bool,
+        log_mode: LogMode,
+    ) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+        self.concurrent_manager
+            .xlock(block_id)
+            .with_context(|| self.lock_context(block_id))?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer none")?;
+        let mut lsn = -1;
+        if log_mode != LogMode::NoLog {
+            lsn = self
+                .recovery_manager
+                .set_bool(Arc::clone(buffer), offset, val);
+        }
+        let mut locked_buffer = buffer.write().unwrap();
+        locked_buffer.set_bool(offset as usize, val)?;
+        locked_buffer.set_modified(self.txnum as i32, lsn);
+        Ok(())
+    }
+
+    // ログは値の型ごとのレコードで書く。型の合わない値はログを書く前に弾く
+    pub fn set_val(
+        &mut self,
+        block_id: &BlockId,
+        offset: i32,
+        info: &FieldInfo,
+        encoding: Encoding,
+        val: Constant,
+        log_mode: LogMode,
+    ) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+        info.check_value(&val, encoding)?;
+        self.concurrent_manager
+            .xlock(block_id)
+            .with_context(|| self.lock_context(block_id))?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer none")?;
+        let mut lsn = -1;
+        if log_mode != LogMode::NoLog {
+            let buffer = Arc::clone(buffer);
+            lsn = match &val {
+                Constant::Int(v) => self.recovery_manager.set_int(buffer, offset, *v),
+                Constant::String(v) => self.recovery_manager.set_string(buffer, offset, v.clone()),
+                Constant::Bool(v) => self.recovery_manager.set_bool(buffer, offset, *v),
+                Constant::Float(v) => self.recovery_manager.set_float(buffer, offset, *v),
+                Constant::Long(v) | Constant::Timestamp(v) => {
+                    self.recovery_manager.set_long(buffer, offset, *v)
+                }
+            };
+        }
+        let mut locked_buffer = buffer.write().unwrap();
+        info.serialize_value(&mut locked_buffer, offset as usize, &val, encoding)?;
+        locked_buffer.set_modified(self.txnum as i32, lsn);
+        Ok(())
+    }
+
+    pub fn set_long(
+        &mut self,
+        block_id: &BlockId,
+        offset: i32,
+        val: i64,
+        log_mode: LogMode,
+    ) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+        self.concurrent_manager
+            .xlock(block_id)
+            .with_context(|| self.lock_context(block_id))?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer none")?;
+        let mut lsn = -1;
+        if log_mode != LogMode::NoLog {
+            lsn = self
+                .recovery_manager
+                .set_long(Arc::clone(buffer), offset, val);
+        }
+        let mut locked_buffer = buffer.write().unwrap();
+        locked_buffer.set_long(offset as usize, val)?;
+        locked_buffer.set_modified(self.txnum as i32, lsn);
+        Ok(())
+    }
+
+    pub fn set_float(
+        &mut self,
+        block_id: &BlockId,
+        offset: i32,
+        val: f64,
+        log_mode: LogMode,
+    ) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+        self.concurrent_manager
+            .xlock(block_id)
+            .with_context(|| self.lock_context(block_id))?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer none")?;
+        let mut lsn = -1;
+        if log_mode != LogMode::NoLog {
+            lsn = self
+                .recovery_manager
+                .set_float(Arc::clone(buffer), offset, val);
+        }
+        let mut locked_buffer = buffer.write().unwrap();
+        locked_buffer.set_float(offset as usize, val)?;
+        locked_buffer.set_modified(self.txnum as i32, lsn);
+        Ok(())
+    }
+
+    pub fn set_byte(
+        &mut self,
+        block_id: &BlockId,
+        offset: i32,
+        val: u8,
+        log_mode: LogMode,
+    ) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+        self.concurrent_manager
+            .xlock(block_id)
+            .with_context(|| self.lock_context(block_id))?;
+        let buffer = self
+            .buffer_list
+            .get_buffer(block_id)
+            .context("buffer none")?;
+        let mut lsn = -1;
+        if log_mode != LogMode::NoLog {
+            lsn = self
+                .recovery_manager
+                .set_byte(Arc::clone(buffer), offset, val);
+        }
+        let mut locked_buffer = buffer.write().unwrap();
+        locked_buffer.set_byte(offset as usize, val)?;
+        locked_buffer.set_modified(self.txnum as i32, lsn);
+        Ok(())
+    }
+
+    // ビットだけを書き換え、同じバイトに入っている他のフィールドのビットは残す
+    pub fn set_null(
+        &mut self,
+        block_id: &BlockId,
+        bitmap_offset: i32,
+        bit: usize,
+        null: bool,
+        log_mode: LogMode,
+    ) -> anyhow::Result<()> {
+        let offset = bitmap_offset + (bit / 8) as i32;
+        let byte = self.get_byte(block_id, offset)?;
+        let mask = 1 << (bit % 8);
+        let new_byte = if null { byte | mask } else { byte & !mask };
+        if new_byte == byte {
+            return Ok(());
+        }
+        self.set_byte(block_id, offset, new_byte, log_mode)
+    }
+
+    pub fn log_comment(&mut self, comment: &str) -> anyhow::Result<i64> {
+        self.recovery_manager.comment(comment.to_string())
+    }
+
     pub fn size(&mut self, filename: String) -> anyhow::Result<i32> {
-        let dummy = BlockId {
-            filename: filename.clone(),
-            block_number: -1,
-        };
-        self.concurrent_manager.slock(&dummy)?;
+        let dummy = BlockId::dummy_for_file(&filename);
+        self.concurrent_manager
+            .slock(&dummy)
+            .with_context(|| self.lock_context(&dummy))?;
         let mut locked_fm = self.file_manager.lock().unwrap();
         locked_fm.length(&filename)
     }
 
-    pub fn undo(&mut self, log_record: LogRecord) {
-        match log_record {
-            LogRecord::CheckPoint(record)
-            | LogRecord::Commit(record)
-            | LogRecord::Start(record)
-            | LogRecord::Rollback(record) => {
-                todo!()
+    pub fn block_size(&self) -> usize {
+        self.file_manager.lock().unwrap().block_size()
+    }
+
+    pub fn append(&mut self, filename: &str) -> anyhow::Result<BlockId> {
+        self.ensure_writable()?;
+        let dummy = BlockId::dummy_for_file(filename);
+        self.concurrent_manager
+            .xlock(&dummy)
+            .with_context(|| self.lock_context(&dummy))?;
+        let mut locked_fm = self.file_manager.lock().unwrap();
+        locked_fm
+            .append_new_block(filename)
+            .context("append new block")
+    }
+
+    // このトランザクションが変更したバッファを先に書き出してから、ファイルを削除する
+    pub fn delete_file(&mut self, filename: &str) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+        let dummy = BlockId::dummy_for_file(filename);
+        self.concurrent_manager
+            .xlock(&dummy)
+            .with_context(|| self.lock_context(&dummy))?;
+        self.buffer_manager
+            .lock()
+            .unwrap()
+            .flush_all(self.txnum as i32);
+        let mut locked_fm = self.file_manager.lock().unwrap();
+        locked_fm.delete_file(filename).context("delete file")
+    }
+
+    // ファイル名を変える。ログに残すので、rollbackやrecoverで元の名前に戻る
+    pub fn rename_file(&mut self, old_filename: &str, new_filename: &str) -> anyhow::Result<()> {
+        self.rename_file_with_log(old_filename, new_filename, LogMode::Log)
+    }
+
+    fn rename_file_with_log(
+        &mut self,
+        old_filename: &str,
+        new_filename: &str,
+        log_mode: LogMode,
+    ) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+        // 他のトランザクションが読み書きしているブロックがあれば、終わるまで待つ
+        let num_blocks = self.file_manager.lock().unwrap().length(old_filename)?;
+        let blocks = (-1..num_blocks)
+            .map(|block_number| BlockId {
+                filename: old_filename.to_string(),
+                block_number,
+            })
+            .chain([BlockId::dummy_for_file(new_filename)]);
+        for block_id in blocks {
+            self.concurrent_manager
+                .xlock(&block_id)
+                .with_context(|| self.lock_context(&block_id))?;
+        }
+        if log_mode == LogMode::NoLog && num_blocks == 0 {
+            // recoverで取り消すとき、名前を変える前に落ちていればold_filenameはまだない
+            return Ok(());
+        }
+        if log_mode != LogMode::NoLog {
+            self.recovery_manager
+                .rename_file(old_filename, new_filename)?;
+        }
+        self.buffer_manager
+            .lock()
+            .unwrap()
+            .rename_file(old_filename, new_filename)?;
+        let mut locked_fm = self.file_manager.lock().unwrap();
+        locked_fm
+            .rename_file(old_filename, new_filename)
+            .with_context(|| format!("rename {} to {}", old_filename, new_filename))
+    }
+
+    // 書き込み直前のバッファについて、ログが先にディスクへ書かれていることを確認する
+    pub fn assert_wal_invariant(&self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        let last_flushed_lsn = self.log_manager.lock().unwrap().last_flushed_lsn();
+        for buffer in self.buffer_list.pinned_buffers() {
+            let locked_buffer = buffer.read().unwrap();
+            if locked_buffer.modifying_tx() >= 0
+                && locked_buffer.last_save_number() > last_flushed_lsn
+            {
+                panic!(
+                    "write-ahead log invariant violated: {:?} was modified by transaction {} at lsn {}, but the log is only flushed up to lsn {}",
+                    locked_buffer.block_id(),
+                    locked_buffer.modifying_tx(),
+                    locked_buffer.last_save_number(),
+                    last_flushed_lsn
+                );
             }
+        }
+    }
+
+    pub fn undo(&mut self, log_record: LogRecord) -> anyhow::Result<()> {
+        self.undo_with_log(log_record, LogMode::NoLog)
+    }
+
+    fn undo_with_log(&mut self, log_record: LogRecord, log_mode: LogMode) -> anyhow::Result<()> {
+        match log_record {
+            LogRecord::CheckPoint(_)
+            | LogRecord::Commit(_)
+            | LogRecord::Start(_)
+            | LogRecord::Rollback(_)
+            | LogRecord::Comment(_) => {}
             LogRecord::SetInt(record) => {
-                self.pin(&record.block_id);
-                self.set_int(&record.block_id, record.offset, record.value, false);
-                self.unpin(&record.block_id);
+                self.pin(&record.block_id)?;
+                self.set_int(&record.block_id, record.offset, record.value, log_mode)?;
+                self.unpin(&record.block_id)?;
             }
             LogRecord::SetString(record) => {
-                self.pin(&record.block_id);
-                self.set_string(&record.block_id, record.offset, record.value, false);
-                self.unpin(&record.block_id);
+                self.pin(&record.block_id)?;
+                self.set_string(&record.block_id, record.offset, record.value, log_mode)?;
+                self.unpin(&record.block_id)?;
+            }
+            LogRecord::SetBool(record) => {
+                self.pin(&record.block_id)?;
+                self.set_bool(&record.block_id, record.offset, record.value, log_mode)?;
+                self.unpin(&record.block_id)?;
+            }
+            LogRecord::SetLong(record) => {
+                self.pin(&record.block_id)?;
+                self.set_long(&record.block_id, record.offset, record.value, log_mode)?;
+                self.unpin(&record.block_id)?;
+            }
+            LogRecord::SetFloat(record) => {
+                self.pin(&record.block_id)?;
+                self.set_float(&record.block_id, record.offset, record.value, log_mode)?;
+                self.unpin(&record.block_id)?;
+            }
+            LogRecord::SetByte(record) => {
+                self.pin(&record.block_id)?;
+                self.set_byte(&record.block_id, record.offset, record.value, log_mode)?;
+                self.unpin(&record.block_id)?;
+            }
+            LogRecord::RenameFile(record) => {
+                self.rename_file_with_log(&record.new_filename, &record.old_filename, log_mode)?;
             }
         }
+        Ok(())
+    }
+}
+
+// commitもrollbackもされずに破棄されたトランザクションはrollbackして、ロックとpinを解放する
+// commitもrollbackもされずに捨てられたらロールバックする。
+// パニック中はロックやバッファの状態がわからないので何もしない
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if self.finished || std::thread::panicking() {
+            return;
+        }
+        if let Err(e) = self.rollback() {
+            eprintln!("failed to roll back transaction {}: {:?}", self.txnum, e);
+        }
     }
 }
 
@@ -209,21 +830,25 @@ impl Transaction {
 mod tests {
     use tempfile::Builder;
 
+    use std::collections::HashMap;
+
     use super::*;
+    use crate::error::LockAbortError;
+    use crate::file_manager::{FileManager, MemoryFileManager, PAGE_SIZE};
 
     #[test]
     fn transaction() {
         let directory = "./data";
-        let log_tempfile = Builder::new().tempfile_in(directory.to_string()).unwrap();
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
         let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
-        let log_file_manager = FileManager::new(directory.to_string());
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
         let log_manager = Arc::new(Mutex::new(
-            LogManager::new(log_file_manager, log_filename.to_string()).unwrap(),
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
         ));
 
-        let tempfile = Builder::new().tempfile_in(directory.to_string()).unwrap();
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
         let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
-        let file_manager = Arc::new(Mutex::new(FileManager::new(directory.to_string())));
 
         let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
             file_manager.clone(),
@@ -231,7 +856,7 @@ mod tests {
             3,
         )));
 
-        let lock_table = Arc::new(Mutex::new(LockTable::new()));
+        let lock_table = Arc::new(LockTable::new());
 
         let mut tx1 = Transaction::new(
             Arc::clone(&file_manager),
@@ -241,14 +866,15 @@ mod tests {
         );
 
         let block = BlockId {
-            filename: filename.clone().to_string(),
+            filename: filename.to_string(),
             block_number: 1,
         };
 
-        tx1.pin(&block);
-        tx1.set_int(&block, 80, 1, false);
-        tx1.set_string(&block, 40, "one".to_string(), false);
-        tx1.commit();
+        tx1.pin(&block).unwrap();
+        tx1.set_int(&block, 80, 1, LogMode::NoLog).unwrap();
+        tx1.set_string(&block, 40, "one".to_string(), LogMode::NoLog)
+            .unwrap();
+        tx1.commit().unwrap();
 
         let mut tx2 = Transaction::new(
             Arc::clone(&file_manager),
@@ -256,15 +882,16 @@ mod tests {
             Arc::clone(&buffer_manager),
             Arc::clone(&lock_table),
         );
-        tx2.pin(&block);
+        tx2.pin(&block).unwrap();
         let ival = tx2.get_int(&block, 80).unwrap();
         let sval = tx2.get_string(&block, 40).unwrap();
 
         let new_ival = ival + 1;
         let new_sval = sval + "!";
-        tx2.set_int(&block, 80, new_ival, false);
-        tx2.set_string(&block, 40, new_sval.to_string(), false);
-        tx2.commit();
+        tx2.set_int(&block, 80, new_ival, LogMode::NoLog).unwrap();
+        tx2.set_string(&block, 40, new_sval.to_string(), LogMode::NoLog)
+            .unwrap();
+        tx2.commit().unwrap();
 
         let mut tx3 = Transaction::new(
             Arc::clone(&file_manager),
@@ -272,9 +899,9 @@ mod tests {
             Arc::clone(&buffer_manager),
             Arc::clone(&lock_table),
         );
-        tx3.pin(&block);
-        tx3.set_int(&block, 80, 9999, false);
-        tx3.rollback();
+        tx3.pin(&block).unwrap();
+        tx3.set_int(&block, 80, 9999, LogMode::Log).unwrap();
+        tx3.rollback().unwrap();
 
         let mut tx4 = Transaction::new(
             Arc::clone(&file_manager),
@@ -282,8 +909,1434 @@ mod tests {
             Arc::clone(&buffer_manager),
             Arc::clone(&lock_table),
         );
-        tx4.pin(&block);
-        let rval = tx4.get_int(&block, 80);
-        tx4.commit();
+        tx4.pin(&block).unwrap();
+        assert_eq!(tx4.get_int(&block, 80).unwrap(), 2);
+        // pinしていないブロックは読めない
+        tx4.unpin(&block).unwrap();
+        assert!(tx4.get_int(&block, 80).is_err());
+        assert!(tx4.get_string(&block, 40).is_err());
+        tx4.commit().unwrap();
+    }
+
+    #[test]
+    fn begin_explicit() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let mut tx = Transaction::begin_explicit(
+            "import".to_string(),
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        assert_eq!(tx.name(), "import");
+        assert!(total_transaction_count() >= tx.txnum);
+
+        let iter = log_manager.lock().unwrap().iterator().unwrap();
+        let names: Vec<String> = iter
+            .map(|record| LogRecord::try_from(&mut Page::from(record)).unwrap())
+            .filter_map(|log_record| match log_record {
+                LogRecord::Start(record) => Some(record.name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, vec!["import".to_string()]);
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let block = BlockId {
+            filename: filename.to_string(),
+            block_number: 1,
+        };
+        let mut writer = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        writer.pin(&block).unwrap();
+        writer.set_int(&block, 0, 1, LogMode::Log).unwrap();
+
+        // 他のトランザクションがX-lockを持っているので、待ちきれずにabortする
+        tx.pin(&block).unwrap();
+        let err = tx.get_int(&block, 0).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("import"));
+        assert!(message.contains(filename));
+        assert!(message.contains("block_number: 1"));
+        assert!(err.chain().any(|cause| cause.is::<LockAbortError>()));
+        tx.unpin(&block).unwrap();
+        writer.commit().unwrap();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "write-ahead log invariant violated")]
+    fn wal_invariant() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let mut tx = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        let block = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+        tx.pin(&block).unwrap();
+        tx.set_int(&block, 80, 0, LogMode::NoLog).unwrap();
+        tx.set_int(&block, 80, 1, LogMode::Log).unwrap();
+        log_manager.lock().unwrap().iterator().unwrap();
+        tx.assert_wal_invariant();
+
+        // ログをflushしないまま更新する
+        tx.set_int(&block, 80, 2, LogMode::Log).unwrap();
+        tx.assert_wal_invariant();
+    }
+
+    #[test]
+    fn log_comment() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let mut tx = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        let lsn = tx.log_comment("import from backup 2024-01-01").unwrap();
+        assert!(lsn > 0);
+
+        let iter = log_manager.lock().unwrap().iterator().unwrap();
+        let comments: Vec<(i32, String)> = iter
+            .map(|record| LogRecord::try_from(&mut Page::from(record)).unwrap())
+            .filter_map(|log_record| match log_record {
+                LogRecord::Comment(record) => Some((record.txnum, record.text)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            comments,
+            vec![(tx.txnum as i32, "import from backup 2024-01-01".to_string())]
+        );
+    }
+
+    #[test]
+    fn append() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let mut tx1 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        let mut tx2 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+
+        let block = tx1.append(filename).unwrap();
+        assert_eq!(block.block_number, 0);
+
+        // 末尾のダミーブロックはコミットまでtx1がXロックを保持する
+        let dummy = BlockId::dummy_for_file(filename);
+        assert_eq!(lock_table.lock_count_for_block(&dummy), -1);
+        tx1.commit().unwrap();
+        assert_eq!(lock_table.lock_count_for_block(&dummy), 0);
+
+        let block = tx2.append(filename).unwrap();
+        assert_eq!(block.block_number, 1);
+        tx2.commit().unwrap();
+    }
+
+    #[test]
+    fn bool_rollback() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let block = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+
+        let mut tx1 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx1.pin(&block).unwrap();
+        tx1.set_bool(&block, 10, true, LogMode::Log).unwrap();
+        tx1.commit().unwrap();
+
+        let mut tx2 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx2.pin(&block).unwrap();
+        assert!(tx2.get_bool(&block, 10).unwrap());
+        tx2.set_bool(&block, 10, false, LogMode::Log).unwrap();
+        assert!(!tx2.get_bool(&block, 10).unwrap());
+        tx2.rollback().unwrap();
+
+        let mut tx3 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx3.pin(&block).unwrap();
+        assert!(tx3.get_bool(&block, 10).unwrap());
+        tx3.commit().unwrap();
+    }
+
+    #[test]
+    fn long_rollback() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let block = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+
+        let mut tx1 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx1.pin(&block).unwrap();
+        tx1.set_long(&block, 10, i32::MAX as i64 + 1, LogMode::Log)
+            .unwrap();
+        tx1.commit().unwrap();
+
+        let mut tx2 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx2.pin(&block).unwrap();
+        assert_eq!(tx2.get_long(&block, 10).unwrap(), i32::MAX as i64 + 1);
+        tx2.set_long(&block, 10, -1, LogMode::Log).unwrap();
+        assert_eq!(tx2.get_long(&block, 10).unwrap(), -1);
+        tx2.rollback().unwrap();
+
+        let mut tx3 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx3.pin(&block).unwrap();
+        assert_eq!(tx3.get_long(&block, 10).unwrap(), i32::MAX as i64 + 1);
+        tx3.commit().unwrap();
+    }
+
+    #[test]
+    fn float_rollback() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let block = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+
+        let mut tx1 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx1.pin(&block).unwrap();
+        tx1.set_float(&block, 10, f64::INFINITY, LogMode::Log)
+            .unwrap();
+        tx1.commit().unwrap();
+
+        let mut tx2 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx2.pin(&block).unwrap();
+        assert_eq!(tx2.get_float(&block, 10).unwrap(), f64::INFINITY);
+        tx2.set_float(&block, 10, f64::NAN, LogMode::Log).unwrap();
+        assert!(tx2.get_float(&block, 10).unwrap().is_nan());
+        tx2.rollback().unwrap();
+
+        let mut tx3 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx3.pin(&block).unwrap();
+        assert_eq!(tx3.get_float(&block, 10).unwrap(), f64::INFINITY);
+        tx3.commit().unwrap();
+    }
+
+    #[test]
+    fn recover() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let block = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+
+        let mut tx1 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx1.pin(&block).unwrap();
+        tx1.set_int(&block, 80, 1, LogMode::Log).unwrap();
+        tx1.commit().unwrap();
+
+        let mut tx2 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx2.pin(&block).unwrap();
+        tx2.set_int(&block, 80, 2, LogMode::Log).unwrap();
+        // 未コミットの更新がディスクに書き出された後でクラッシュする
+        buffer_manager.lock().unwrap().flush_all(tx2.txnum as i32);
+        std::mem::forget(tx2);
+
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let mut tx3 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx3.recover().unwrap();
+        tx3.commit().unwrap();
+
+        let mut tx4 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx4.pin(&block).unwrap();
+        assert_eq!(tx4.get_int(&block, 80).unwrap(), 1);
+        tx4.commit().unwrap();
+    }
+
+    #[test]
+    fn recover_undoes_rename() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let new_transaction = |buffer_manager: &Arc<Mutex<BufferManager>>,
+                               lock_table: &Arc<LockTable>| {
+            Transaction::new(
+                Arc::clone(&file_manager),
+                Arc::clone(&log_manager),
+                Arc::clone(buffer_manager),
+                Arc::clone(lock_table),
+            )
+        };
+
+        let mut tx1 = new_transaction(&buffer_manager, &lock_table);
+        let block = tx1.append("T.tbl").unwrap();
+        tx1.pin(&block).unwrap();
+        tx1.set_int(&block, 80, 1, LogMode::Log).unwrap();
+        tx1.commit().unwrap();
+
+        // 名前を変えてから書き込み、コミットせずにクラッシュする
+        let mut tx2 = new_transaction(&buffer_manager, &lock_table);
+        tx2.rename_file("T.tbl", "U.tbl").unwrap();
+        let renamed = BlockId {
+            filename: "U.tbl".to_string(),
+            block_number: 0,
+        };
+        tx2.pin(&renamed).unwrap();
+        assert_eq!(tx2.get_int(&renamed, 80).unwrap(), 1);
+        tx2.set_int(&renamed, 80, 2, LogMode::Log).unwrap();
+        buffer_manager.lock().unwrap().flush_all(tx2.txnum as i32);
+        assert_eq!(file_manager.lock().unwrap().length("T.tbl").unwrap(), 0);
+        std::mem::forget(tx2);
+
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let mut tx3 = new_transaction(&buffer_manager, &lock_table);
+        tx3.recover().unwrap();
+        tx3.commit().unwrap();
+
+        assert_eq!(file_manager.lock().unwrap().length("U.tbl").unwrap(), 0);
+        let mut tx4 = new_transaction(&buffer_manager, &lock_table);
+        tx4.pin(&block).unwrap();
+        assert_eq!(tx4.get_int(&block, 80).unwrap(), 1);
+        tx4.commit().unwrap();
+    }
+
+    #[test]
+    fn recover_redoes_committed_updates() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let block = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+
+        let mut tx1 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx1.pin(&block).unwrap();
+        tx1.set_int(&block, 80, 7, LogMode::Log).unwrap();
+        let first_lsn = log_manager.lock().unwrap().latest_lsn();
+        tx1.set_string(&block, 100, "redo".to_string(), LogMode::Log)
+            .unwrap();
+        let savepoint = tx1.savepoint();
+        tx1.set_int(&block, 80, 8, LogMode::Log).unwrap();
+        tx1.rollback_to_savepoint(savepoint).unwrap();
+        tx1.commit().unwrap();
+
+        let mut tx2 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx2.pin(&block).unwrap();
+        tx2.set_int(&block, 80, 9, LogMode::Log).unwrap();
+        buffer_manager.lock().unwrap().flush_all(tx2.txnum as i32);
+        std::mem::forget(tx2);
+
+        // コミット済みの更新がディスクから失われた状態でクラッシュする
+        let block_size = file_manager.lock().unwrap().block_size();
+        file_manager
+            .lock()
+            .unwrap()
+            .write(&block, &mut Page::new(block_size))
+            .unwrap();
+
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let mut tx3 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx3.recover().unwrap();
+        assert_eq!(
+            tx3.recovery_manager.dirty_page_table().get(&block),
+            Some(&first_lsn)
+        );
+        tx3.commit().unwrap();
+
+        let mut tx4 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx4.pin(&block).unwrap();
+        assert_eq!(tx4.get_int(&block, 80).unwrap(), 7);
+        assert_eq!(tx4.get_string(&block, 100).unwrap(), "redo");
+        tx4.commit().unwrap();
+    }
+
+    #[test]
+    fn recover_undoes_only_active_transactions() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let new_transaction = |buffer_manager: &Arc<Mutex<BufferManager>>,
+                               lock_table: &Arc<LockTable>| {
+            Transaction::new(
+                Arc::clone(&file_manager),
+                Arc::clone(&log_manager),
+                Arc::clone(buffer_manager),
+                Arc::clone(lock_table),
+            )
+        };
+        let block0 = BlockId {
+            filename: "T.tbl".to_string(),
+            block_number: 0,
+        };
+        let block1 = BlockId {
+            filename: "T.tbl".to_string(),
+            block_number: 1,
+        };
+
+        // tx1とtx2は重なって動き、tx1だけがコミットしてからクラッシュする
+        let mut tx1 = new_transaction(&buffer_manager, &lock_table);
+        let mut tx2 = new_transaction(&buffer_manager, &lock_table);
+        tx1.pin(&block0).unwrap();
+        tx2.pin(&block1).unwrap();
+        tx1.set_int(&block0, 0, 1, LogMode::Log).unwrap();
+        tx2.set_int(&block1, 0, 2, LogMode::Log).unwrap();
+        tx1.set_int(&block0, 4, 3, LogMode::Log).unwrap();
+        tx2.set_int(&block1, 4, 4, LogMode::Log).unwrap();
+        let tx2_last_lsn = log_manager.lock().unwrap().latest_lsn();
+        tx1.commit().unwrap();
+        buffer_manager.lock().unwrap().flush_all(tx2.txnum as i32);
+        let tx2_txnum = tx2.txnum as i32;
+        std::mem::forget(tx2);
+
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let mut tx3 = new_transaction(&buffer_manager, &lock_table);
+        tx3.recover().unwrap();
+        assert_eq!(
+            tx3.recovery_manager.active_transaction_table(),
+            &HashMap::from([(tx2_txnum, tx2_last_lsn)])
+        );
+        tx3.commit().unwrap();
+
+        let mut tx4 = new_transaction(&buffer_manager, &lock_table);
+        tx4.pin(&block0).unwrap();
+        tx4.pin(&block1).unwrap();
+        assert_eq!(tx4.get_int(&block0, 0).unwrap(), 1);
+        assert_eq!(tx4.get_int(&block0, 4).unwrap(), 3);
+        assert_eq!(tx4.get_int(&block1, 0).unwrap(), 0);
+        assert_eq!(tx4.get_int(&block1, 4).unwrap(), 0);
+        tx4.commit().unwrap();
+    }
+
+    #[test]
+    fn recover_returns_error_on_corrupt_log() {
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(MemoryFileManager::new(PAGE_SIZE)));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), "logfile".to_string()).unwrap(),
+        ));
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        // 読めないレコードがあれば、panicせずにエラーを返す
+        let mut page = Page::new(8);
+        page.set_int(0, 99).unwrap();
+        log_manager
+            .lock()
+            .unwrap()
+            .append_record(page.contents())
+            .unwrap();
+        let mut tx = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        let err = tx.recover().unwrap_err();
+        assert!(err.to_string().contains("unknown log record type 99"));
+        tx.rollback().unwrap();
+    }
+
+    #[test]
+    fn recover_stops_at_checkpoint() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let block = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+
+        // クラッシュのたびにbuffer poolとlock tableを作り直す
+        let restart = || {
+            let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+                file_manager.clone(),
+                log_manager.clone(),
+                3,
+            )));
+            let lock_table = Arc::new(LockTable::new());
+            (buffer_manager, lock_table)
+        };
+        let new_transaction = |buffer_manager: &Arc<Mutex<BufferManager>>,
+                               lock_table: &Arc<LockTable>| {
+            Transaction::new(
+                Arc::clone(&file_manager),
+                Arc::clone(&log_manager),
+                Arc::clone(buffer_manager),
+                Arc::clone(lock_table),
+            )
+        };
+
+        let (buffer_manager, lock_table) = restart();
+        let mut tx1 = new_transaction(&buffer_manager, &lock_table);
+        tx1.pin(&block).unwrap();
+        tx1.set_int(&block, 80, 1, LogMode::Log).unwrap();
+        buffer_manager.lock().unwrap().flush_all(tx1.txnum as i32);
+        std::mem::forget(tx1);
+
+        // 1回目のrecoveryでtx1を取り消し、checkpointを書く
+        let (buffer_manager, lock_table) = restart();
+        let mut tx2 = new_transaction(&buffer_manager, &lock_table);
+        tx2.recover().unwrap();
+        tx2.commit().unwrap();
+
+        let mut tx3 = new_transaction(&buffer_manager, &lock_table);
+        tx3.pin(&block).unwrap();
+        assert_eq!(tx3.get_int(&block, 80).unwrap(), 0);
+        tx3.set_int(&block, 80, 5, LogMode::Log).unwrap();
+        tx3.commit().unwrap();
+
+        let mut tx4 = new_transaction(&buffer_manager, &lock_table);
+        tx4.pin(&block).unwrap();
+        tx4.set_int(&block, 80, 9, LogMode::Log).unwrap();
+        buffer_manager.lock().unwrap().flush_all(tx4.txnum as i32);
+        std::mem::forget(tx4);
+
+        // 2回目のrecoveryはcheckpointで止まるので、tx1の未完了の更新をもう一度取り消さない
+        let (buffer_manager, lock_table) = restart();
+        let mut tx5 = new_transaction(&buffer_manager, &lock_table);
+        tx5.recover().unwrap();
+        tx5.commit().unwrap();
+
+        let mut tx6 = new_transaction(&buffer_manager, &lock_table);
+        tx6.pin(&block).unwrap();
+        assert_eq!(tx6.get_int(&block, 80).unwrap(), 5);
+        tx6.commit().unwrap();
+
+        let iter = log_manager.lock().unwrap().iterator().unwrap();
+        let checkpoints = iter
+            .filter(|record| {
+                let mut page = Page::from(record.clone());
+                matches!(
+                    LogRecord::try_from(&mut page).unwrap(),
+                    LogRecord::CheckPoint(_)
+                )
+            })
+            .count();
+        assert_eq!(checkpoints, 2);
+    }
+
+    #[test]
+    fn drop_rolls_back() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let block = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+
+        let mut tx1 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx1.pin(&block).unwrap();
+        tx1.set_int(&block, 80, 1, LogMode::Log).unwrap();
+        tx1.commit().unwrap();
+
+        {
+            let mut tx2 = Transaction::new(
+                Arc::clone(&file_manager),
+                Arc::clone(&log_manager),
+                Arc::clone(&buffer_manager),
+                Arc::clone(&lock_table),
+            );
+            tx2.pin(&block).unwrap();
+            tx2.set_int(&block, 80, 2, LogMode::Log).unwrap();
+        }
+        assert_eq!(lock_table.lock_count_for_block(&block), 0);
+        assert_eq!(buffer_manager.lock().unwrap().available(), 3);
+
+        let mut tx3 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx3.pin(&block).unwrap();
+        assert_eq!(tx3.get_int(&block, 80).unwrap(), 1);
+        tx3.commit().unwrap();
+
+        // ロールバックが済んだトランザクションは、Dropでもう一度ロールバックしない
+        let mut tx4 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        let txnum = tx4.txnum as i32;
+        tx4.pin(&block).unwrap();
+        tx4.set_int(&block, 80, 4, LogMode::Log).unwrap();
+        tx4.rollback().unwrap();
+        drop(tx4);
+        let iter = log_manager.lock().unwrap().iterator().unwrap();
+        let rollbacks = iter
+            .filter(|record| {
+                let mut page = Page::from(record.clone());
+                let log_record = LogRecord::try_from(&mut page).unwrap();
+                log_record.get_txnum() == txnum && matches!(log_record, LogRecord::Rollback(_))
+            })
+            .count();
+        assert_eq!(rollbacks, 1);
+
+        // パニックで捨てられたトランザクションはロールバックしない
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut tx5 = Transaction::new(
+                Arc::clone(&file_manager),
+                Arc::clone(&log_manager),
+                Arc::clone(&buffer_manager),
+                Arc::clone(&lock_table),
+            );
+            tx5.pin(&block).unwrap();
+            tx5.set_int(&block, 80, 5, LogMode::Log).unwrap();
+            panic!("abort tx5");
+        }));
+        assert!(result.is_err());
+        assert_eq!(lock_table.lock_count_for_block(&block), -1);
+    }
+
+    #[test]
+    fn rollback_to_savepoint() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let block0 = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+        let block1 = BlockId {
+            filename: filename.to_string(),
+            block_number: 1,
+        };
+        let block2 = BlockId {
+            filename: filename.to_string(),
+            block_number: 2,
+        };
+
+        let mut tx = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx.pin(&block0).unwrap();
+        tx.pin(&block1).unwrap();
+        tx.pin(&block2).unwrap();
+        tx.set_int(&block0, 0, 1, LogMode::Log).unwrap();
+
+        let savepoint = tx.savepoint();
+        tx.set_int(&block0, 0, 2, LogMode::Log).unwrap();
+        tx.set_int(&block0, 4, 3, LogMode::Log).unwrap();
+        tx.set_int(&block1, 0, 4, LogMode::Log).unwrap();
+        assert_eq!(lock_table.lock_count_for_block(&block1), -1);
+
+        tx.get_int(&block2, 0).unwrap();
+        assert_eq!(lock_table.lock_count_for_block(&block2), 1);
+
+        tx.rollback_to_savepoint(savepoint).unwrap();
+        assert_eq!(tx.get_int(&block0, 0).unwrap(), 1);
+        assert_eq!(tx.get_int(&block0, 4).unwrap(), 0);
+        // X-lockはsavepoint後に取ったものもcommitまで残り、S-lockだけが解放される
+        assert_eq!(lock_table.lock_count_for_block(&block0), -1);
+        assert_eq!(lock_table.lock_count_for_block(&block1), -1);
+        assert_eq!(lock_table.lock_count_for_block(&block2), 0);
+
+        // 同じsavepointには何度でも戻れる
+        tx.set_int(&block0, 0, 5, LogMode::Log).unwrap();
+        tx.rollback_to_savepoint(savepoint).unwrap();
+        assert_eq!(tx.get_int(&block0, 0).unwrap(), 1);
+        tx.commit().unwrap();
+
+        let mut tx = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx.pin(&block0).unwrap();
+        tx.pin(&block1).unwrap();
+        assert_eq!(tx.get_int(&block0, 0).unwrap(), 1);
+        assert_eq!(tx.get_int(&block1, 0).unwrap(), 0);
+        assert!(tx.rollback_to_savepoint(savepoint).is_err());
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn read_only() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+
+        let block = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+
+        let mut tx1 = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        tx1.pin(&block).unwrap();
+        tx1.set_int(&block, 80, 1, LogMode::Log).unwrap();
+        tx1.commit().unwrap();
+
+        // 読み込み専用のトランザクションは書き込めず、ログも書かない
+        let latest_lsn = log_manager.lock().unwrap().latest_lsn();
+        let mut tx2 = Transaction::new_read_only(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        assert!(tx2.is_read_only());
+        tx2.pin(&block).unwrap();
+        assert_eq!(tx2.get_int(&block, 80).unwrap(), 1);
+        assert!(tx2.set_int(&block, 80, 2, LogMode::Log).is_err());
+        assert!(tx2
+            .set_string(&block, 100, "read only".to_string(), LogMode::Log)
+            .is_err());
+        assert!(tx2.append(filename).is_err());
+        assert_eq!(lock_table.lock_count_for_block(&block), 1);
+        tx2.commit().unwrap();
+        assert_eq!(lock_table.lock_count_for_block(&block), 0);
+        assert_eq!(log_manager.lock().unwrap().latest_lsn(), latest_lsn);
+
+        // 書き込み中のブロックを読む読み込み専用トランザクションはrollbackまで待ち、
+        // 未コミットの2ではなく元の1を読む
+        let mut writer = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        writer.pin(&block).unwrap();
+        writer.set_int(&block, 80, 2, LogMode::Log).unwrap();
+        assert_eq!(lock_table.lock_count_for_block(&block), -1);
+
+        let hold = std::time::Duration::from_millis(200);
+        let start = std::time::Instant::now();
+        let handles: Vec<_> = (0..10)
+            .map(|_| {
+                let file_manager = Arc::clone(&file_manager);
+                let log_manager = Arc::clone(&log_manager);
+                let buffer_manager = Arc::clone(&buffer_manager);
+                let lock_table = Arc::clone(&lock_table);
+                let block = block.clone();
+                std::thread::spawn(move || {
+                    let mut reader = Transaction::new_read_only(
+                        file_manager,
+                        log_manager,
+                        buffer_manager,
+                        lock_table,
+                    );
+                    reader.pin(&block).unwrap();
+                    let value = reader.get_int(&block, 80).unwrap();
+                    reader.commit().unwrap();
+                    value
+                })
+            })
+            .collect();
+        std::thread::sleep(hold);
+        writer.rollback().unwrap();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 1);
+        }
+        assert!(start.elapsed() >= hold);
+        assert_eq!(lock_table.lock_count_for_block(&block), 0);
+    }
+
+    #[test]
+    fn reader_waits_for_writer() {
+        let directory = "./data";
+        let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+        let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+            Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+        let log_manager = Arc::new(Mutex::new(
+            LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+        ));
+        let tempfile = Builder::new().tempfile_in(directory).unwrap();
+        let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+            file_manager.clone(),
+            log_manager.clone(),
+            3,
+        )));
+        let lock_table = Arc::new(LockTable::new());
+        let block = BlockId {
+            filename: filename.to_string(),
+            block_number: 0,
+        };
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let hold = std::time::Duration::from_millis(200);
+
+        // writerがX-lockを持ったまま未コミットの値を書き、しばらくしてrollbackする
+        let writer = {
+            let file_manager = Arc::clone(&file_manager);
+            let log_manager = Arc::clone(&log_manager);
+            let buffer_manager = Arc::clone(&buffer_manager);
+            let lock_table = Arc::clone(&lock_table);
+            let block = block.clone();
+            let barrier = Arc::clone(&barrier);
+            std::thread::spawn(move || {
+                let mut tx =
+                    Transaction::new(file_manager, log_manager, buffer_manager, lock_table);
+                tx.pin(&block).unwrap();
+                tx.set_int(&block, 0, 99, LogMode::Log).unwrap();
+                barrier.wait();
+                std::thread::sleep(hold);
+                tx.rollback().unwrap();
+            })
+        };
+
+        // readerはS-lockを取るまで待たされ、未コミットの99を読むことはない
+        barrier.wait();
+        let start = std::time::Instant::now();
+        let mut reader = Transaction::new(
+            Arc::clone(&file_manager),
+            Arc::clone(&log_manager),
+            Arc::clone(&buffer_manager),
+            Arc::clone(&lock_table),
+        );
+        reader.pin(&block).unwrap();
+        assert_eq!(reader.get_int(&block, 0).unwrap(), 0);
+        assert!(start.elapsed() >= hold);
+        reader.commit().unwrap();
+        writer.join().unwrap();
+        assert_eq!(lock_table.lock_count_for_block(&block), 0);
+    }
+
+    mod concurrent {
+        use std::sync::Barrier;
+        use std::thread;
+        use std::time::{Duration, Instant};
+
+        use super::*;
+
+        // 各スレッドが順に実行する操作。usizeはブロック番号
+        #[derive(Debug, Clone, Copy)]
+        enum Op {
+            GetInt(usize),
+            SetInt(usize, i32),
+            // 読んだ値にdeltaを足して書き戻す
+            AddInt(usize, i32),
+            // そのブロックのlock_count_for_blockを記録する
+            LockCount(usize),
+            // 全スレッドがここに来るまで待つ
+            Wait,
+            Commit,
+            Rollback,
+        }
+
+        #[derive(Debug, Default)]
+        struct Outcome {
+            commits: usize,
+            rollbacks: usize,
+            // ロックが取れずにabortされた回数
+            aborts: usize,
+            // GetIntとLockCountで得た値
+            values: Vec<i32>,
+        }
+
+        struct Harness {
+            file_manager: Arc<Mutex<dyn FileManagerTrait>>,
+            log_manager: Arc<Mutex<LogManager>>,
+            buffer_manager: Arc<Mutex<BufferManager>>,
+            lock_table: Arc<LockTable>,
+            blocks: Vec<BlockId>,
+            _files: Vec<tempfile::NamedTempFile>,
+        }
+
+        impl Harness {
+            fn new(block_count: usize) -> Self {
+                let directory = "./data";
+                let log_tempfile = Builder::new().tempfile_in(directory).unwrap();
+                let log_filename = log_tempfile.path().file_name().unwrap().to_str().unwrap();
+                let file_manager: Arc<Mutex<dyn FileManagerTrait>> =
+                    Arc::new(Mutex::new(FileManager::new(directory.to_string())));
+                let log_manager = Arc::new(Mutex::new(
+                    LogManager::new(Arc::clone(&file_manager), log_filename.to_string()).unwrap(),
+                ));
+                let tempfile = Builder::new().tempfile_in(directory).unwrap();
+                let filename = tempfile.path().file_name().unwrap().to_str().unwrap();
+                let blocks = (0..block_count)
+                    .map(|i| BlockId {
+                        filename: filename.to_string(),
+                        block_number: i as i32,
+                    })
+                    .collect();
+                let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
+                    Arc::clone(&file_manager),
+                    Arc::clone(&log_manager),
+                    8,
+                )));
+                Self {
+                    file_manager,
+                    log_manager,
+                    buffer_manager,
+                    lock_table: Arc::new(LockTable::new()),
+                    blocks,
+                    _files: vec![log_tempfile, tempfile],
+                }
+            }
+
+            fn new_transaction(&self) -> Transaction {
+                Transaction::new(
+                    Arc::clone(&self.file_manager),
+                    Arc::clone(&self.log_manager),
+                    Arc::clone(&self.buffer_manager),
+                    Arc::clone(&self.lock_table),
+                )
+            }
+
+            // scriptごとにスレッドを1つ立てて実行する。
+            // ロックが取れずにabortしたトランザクションはrollbackし、次のCommitかRollbackまでの操作を飛ばす
+            fn run(&self, scripts: Vec<Vec<Op>>) -> Vec<Outcome> {
+                let barrier = Arc::new(Barrier::new(scripts.len()));
+                let handles: Vec<_> = scripts
+                    .into_iter()
+                    .map(|script| {
+                        let lock_table = Arc::clone(&self.lock_table);
+                        let blocks = self.blocks.clone();
+                        let barrier = Arc::clone(&barrier);
+                        let begin = {
+                            let file_manager = Arc::clone(&self.file_manager);
+                            let log_manager = Arc::clone(&self.log_manager);
+                            let buffer_manager = Arc::clone(&self.buffer_manager);
+                            let lock_table = Arc::clone(&self.lock_table);
+                            move || {
+                                Transaction::new(
+                                    Arc::clone(&file_manager),
+                                    Arc::clone(&log_manager),
+                                    Arc::clone(&buffer_manager),
+                                    Arc::clone(&lock_table),
+                                )
+                            }
+                        };
+                        thread::spawn(move || {
+                            let mut tx = begin();
+                            let mut outcome = Outcome::default();
+                            let mut aborted = false;
+                            for op in script {
+                                if aborted {
+                                    match op {
+                                        // 他のスレッドを待たせないよう、Waitだけは飛ばさない
+                                        Op::Wait => {
+                                            barrier.wait();
+                                        }
+                                        Op::Commit | Op::Rollback => {
+                                            aborted = false;
+                                            tx = begin();
+                                        }
+                                        _ => {}
+                                    }
+                                    continue;
+                                }
+                                let result = match op {
+                                    Op::GetInt(i) => tx.pin(&blocks[i]).and_then(|_| {
+                                        outcome.values.push(tx.get_int(&blocks[i], 0)?);
+                                        Ok(())
+                                    }),
+                                    Op::SetInt(i, value) => tx.pin(&blocks[i]).and_then(|_| {
+                                        tx.set_int(&blocks[i], 0, value, LogMode::Log)
+                                    }),
+                                    Op::AddInt(i, delta) => tx.pin(&blocks[i]).and_then(|_| {
+                                        let value = tx.get_int(&blocks[i], 0)?;
+                                        tx.set_int(&blocks[i], 0, value + delta, LogMode::Log)
+                                    }),
+                                    Op::LockCount(i) => {
+                                        outcome
+                                            .values
+                                            .push(lock_table.lock_count_for_block(&blocks[i]));
+                                        Ok(())
+                                    }
+                                    Op::Wait => {
+                                        barrier.wait();
+                                        Ok(())
+                                    }
+                                    Op::Commit => {
+                                        tx.commit().unwrap();
+                                        outcome.commits += 1;
+                                        tx = begin();
+                                        Ok(())
+                                    }
+                                    Op::Rollback => {
+                                        tx.rollback().unwrap();
+                                        outcome.rollbacks += 1;
+                                        tx = begin();
+                                        Ok(())
+                                    }
+                                };
+                                if result.is_err() {
+                                    tx.rollback().unwrap();
+                                    outcome.aborts += 1;
+                                    aborted = true;
+                                }
+                            }
+                            tx.commit().unwrap();
+                            outcome
+                        })
+                    })
+                    .collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            }
+
+            // コミット済みの値を読む
+            fn values(&self) -> Vec<i32> {
+                let mut tx = self.new_transaction();
+                let values = self
+                    .blocks
+                    .iter()
+                    .map(|block| {
+                        tx.pin(block).unwrap();
+                        tx.get_int(block, 0).unwrap()
+                    })
+                    .collect();
+                tx.commit().unwrap();
+                values
+            }
+
+            fn assert_unlocked(&self) {
+                for block in &self.blocks {
+                    assert_eq!(self.lock_table.lock_count_for_block(block), 0);
+                }
+            }
+        }
+
+        #[test]
+        fn overlapping_blocks_are_isolated() {
+            let harness = Harness::new(3);
+
+            // スレッド0はブロック0と1を、スレッド1はブロック1と2を1ずつ増やし、5回に1回はrollbackする。
+            // 更新が失われず、rollbackした分も残らなければ、ブロック1はコミットされた回数の合計になる
+            let script = |a: usize, b: usize| -> Vec<Op> {
+                (0..20)
+                    .flat_map(|k| {
+                        let end = if k % 5 == 4 { Op::Rollback } else { Op::Commit };
+                        [Op::AddInt(a, 1), Op::AddInt(b, 1), end]
+                    })
+                    .collect()
+            };
+            let outcomes = harness.run(vec![script(0, 1), script(2, 1)]);
+
+            assert!(outcomes
+                .iter()
+                .all(|o| o.commits + o.rollbacks + o.aborts == 20));
+            assert!(outcomes.iter().any(|o| o.commits > 0));
+            assert_eq!(
+                harness.values(),
+                vec![
+                    outcomes[0].commits as i32,
+                    (outcomes[0].commits + outcomes[1].commits) as i32,
+                    outcomes[1].commits as i32,
+                ]
+            );
+            harness.assert_unlocked();
+        }
+
+        #[test]
+        fn cyclic_locks_are_aborted() {
+            let harness = Harness::new(3);
+
+            // スレッドiはブロックiを書いてから、全員がそろった後にブロック(i+1)%3を書く。
+            // 待ちが閉路になるので、タイムアウトを待たずに少なくとも1つがabortされる
+            let start = Instant::now();
+            let scripts = (0..3)
+                .map(|i| {
+                    let value = i as i32 + 1;
+                    vec![
+                        Op::SetInt(i, value),
+                        Op::Wait,
+                        Op::SetInt((i + 1) % 3, value),
+                        Op::Commit,
+                    ]
+                })
+                .collect();
+            let outcomes = harness.run(scripts);
+
+            assert!(start.elapsed() < Duration::from_secs(5));
+            assert!(outcomes.iter().any(|o| o.aborts == 1));
+            assert!(outcomes.iter().any(|o| o.commits == 1));
+            // ブロックbを書くのはスレッドbと(b+2)%3。abortされたスレッドの値は残らない
+            let values = harness.values();
+            for (b, value) in values.iter().enumerate() {
+                let writers = [b, (b + 2) % 3];
+                let committed: Vec<i32> = writers
+                    .iter()
+                    .filter(|i| outcomes[**i].commits == 1)
+                    .map(|i| *i as i32 + 1)
+                    .collect();
+                if committed.is_empty() {
+                    assert_eq!(*value, 0);
+                } else {
+                    assert!(committed.contains(value));
+                }
+            }
+            harness.assert_unlocked();
+        }
+
+        #[test]
+        fn read_heavy_shares_locks() {
+            let harness = Harness::new(2);
+            harness.run(vec![vec![Op::SetInt(0, 7), Op::SetInt(1, 11), Op::Commit]]);
+
+            // 10スレッドが同時にS-lockを持ち、その後も読み込みを繰り返す
+            let script: Vec<Op> = [
+                Op::GetInt(0),
+                Op::Wait,
+                Op::LockCount(0),
+                Op::Wait,
+                Op::Commit,
+            ]
+            .into_iter()
+            .chain((0..20).flat_map(|_| [Op::GetInt(0), Op::GetInt(1), Op::Commit]))
+            .collect();
+            let outcomes = harness.run(vec![script; 10]);
+
+            for outcome in &outcomes {
+                assert_eq!(outcome.aborts, 0);
+                assert_eq!(outcome.commits, 21);
+                assert_eq!(outcome.values[..2], [7, 10]);
+                assert!(outcome.values[2..].chunks(2).all(|pair| pair == [7, 11]));
+            }
+            assert_eq!(harness.values(), vec![7, 11]);
+            harness.assert_unlocked();
+        }
     }
 }
@@ -0,0 +1,63 @@
+use std::collections::{HashMap, HashSet};
+
+// トランザクション番号 -> そのトランザクションが待っているトランザクション番号
+#[derive(Debug, Default)]
+pub struct WaitForGraph {
+    edges: HashMap<i32, HashSet<i32>>,
+}
+
+impl WaitForGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_waits(&mut self, txnum: i32, holders: HashSet<i32>) {
+        self.edges.insert(txnum, holders);
+    }
+
+    pub fn remove(&mut self, txnum: i32) {
+        self.edges.remove(&txnum);
+    }
+
+    pub fn has_cycle_from(&self, txnum: i32) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack: Vec<i32> = match self.edges.get(&txnum) {
+            Some(holders) => holders.iter().copied().collect(),
+            None => return false,
+        };
+        while let Some(current) = stack.pop() {
+            if current == txnum {
+                return true;
+            }
+            if !visited.insert(current) {
+                continue;
+            }
+            if let Some(holders) = self.edges.get(&current) {
+                stack.extend(holders.iter().copied());
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn has_cycle_from() {
+        let mut graph = WaitForGraph::new();
+        graph.set_waits(1, HashSet::from([2]));
+        graph.set_waits(2, HashSet::from([3, 4]));
+        assert!(!graph.has_cycle_from(1));
+        assert!(!graph.has_cycle_from(5));
+
+        graph.set_waits(4, HashSet::from([1]));
+        assert!(graph.has_cycle_from(1));
+        assert!(graph.has_cycle_from(4));
+        assert!(!graph.has_cycle_from(3));
+
+        graph.remove(2);
+        assert!(!graph.has_cycle_from(1));
+    }
+}